@@ -0,0 +1,58 @@
+// full redraws of a large surface, on one thread and then in tiles on all of them
+// cargo bench --bench tiles
+
+use std::time::{Duration,Instant};
+
+use gui::{
+    Pixel,PixelIdx,PixelIndexSlice,Size,
+    draw::{Painter,Point},
+    guicell::GuiCell,
+    window::{SurfaceSlice,Tiling},
+};
+
+const SIZES: [Size; 3] = [
+    Size {width: 1280, height: 720},
+    Size {width: 1920, height: 1080},
+    Size {width: 3840, height: 2160},
+];
+const FRAMES: u32 = 10;
+
+// enough going on per pixel that drawing it isn't just a memory fill
+struct Scene;
+impl GuiCell for Scene {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let size = surface.size();
+        let mut p = Painter::new(surface);
+        p.clear(Pixel::rgb(0x20, 0x20, 0x28));
+        for i in 0..64 {
+            let (x, y) = ((i % 8) as f32 + 0.5, (i / 8) as f32 + 0.5);
+            let center = Point::new(x * size.width as f32 / 8.0, y * size.height as f32 / 8.0);
+            let radius = size.width.min(size.height) as f32 / 12.0;
+            p.fill_circle(center, radius, Pixel::rgba(0xFF, (i * 4) as u8, 0x80, 0xA0));
+        }
+        p.fill_rect(PixelIndexSlice {offset: PixelIdx::new(size.width / 4, size.height / 4), size: Size {width: size.width / 2, height: size.height / 2}}, Pixel::rgba(0x40, 0xC0, 0xFF, 0x60));
+    }
+}
+
+fn time(mut f: impl FnMut()) -> Duration {
+    f(); // warm up
+    let start = Instant::now();
+    for _ in 0..FRAMES { f(); }
+    start.elapsed() / FRAMES
+}
+
+fn main() {
+    let tiled = Tiling::new();
+    let single = tiled.with_threads(1);
+    println!("{} threads, {}x{} tiles", tiled.threads(), tiled.tile_size().width, tiled.tile_size().height);
+    for size in SIZES {
+        let mut pixels = vec![Pixel::default(); size.width * size.height];
+        let mut surface = SurfaceSlice::new(pixels.as_mut_ptr(), size);
+        let one = time(|| single.draw(&Scene, &mut surface));
+        let all = time(|| tiled.draw(&Scene, &mut surface));
+        println!(
+            "{:>4}x{:<4} one thread {:>9.2?}  tiled {:>9.2?}  {:.2}x",
+            size.width, size.height, one, all, one.as_secs_f64() / all.as_secs_f64(),
+        );
+    }
+}
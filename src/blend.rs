@@ -0,0 +1,258 @@
+use crate::Pixel;
+
+// how a source color is combined with the color already there (the destination)
+// Pixel always holds straight (non-premultiplied) alpha, where a = 0xFF is opaque;
+//  blending converts to premultiplied internally and back
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum BlendMode {
+    // porter-duff operators
+    Clear,
+    Src,
+    Dst,
+    #[default]
+    SrcOver,
+    DstOver,
+    SrcIn,
+    DstIn,
+    SrcOut,
+    DstOut,
+    SrcAtop,
+    DstAtop,
+    Xor,
+    Plus,
+    
+    // separable blend modes, composited src-over (w3c compositing spec)
+    Multiply,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+} impl BlendMode {
+    pub const ALL: [BlendMode; 24] = {
+        use BlendMode::*;
+        [
+            Clear, Src, Dst, SrcOver, DstOver, SrcIn, DstIn, SrcOut, DstOut, SrcAtop, DstAtop, Xor, Plus,
+            Multiply, Screen, Overlay, Darken, Lighten, ColorDodge, ColorBurn, HardLight, SoftLight, Difference, Exclusion,
+        ]
+    };
+    
+    // porter-duff (source factor,destination factor), given both alphas
+    fn factors(self, sa: f32, da: f32) -> Option<(f32,f32)> {
+        use BlendMode::*;
+        Some(match self {
+            Clear   => (0.0, 0.0),
+            Src     => (1.0, 0.0),
+            Dst     => (0.0, 1.0),
+            SrcOver => (1.0, 1.0 - sa),
+            DstOver => (1.0 - da, 1.0),
+            SrcIn   => (da, 0.0),
+            DstIn   => (0.0, sa),
+            SrcOut  => (1.0 - da, 0.0),
+            DstOut  => (0.0, 1.0 - sa),
+            SrcAtop => (da, 1.0 - sa),
+            DstAtop => (1.0 - da, sa),
+            Xor     => (1.0 - da, 1.0 - sa),
+            Plus    => (1.0, 1.0),
+            _ => return None,
+        })
+    }
+    
+    // blend function B(cb,cs) on straight color channels in [0,1]
+    fn mix(self, cb: f32, cs: f32) -> f32 {
+        use BlendMode::*;
+        match self {
+            Multiply   => cb * cs,
+            Screen     => cb + cs - cb * cs,
+            Overlay    => HardLight.mix(cs, cb),
+            Darken     => cb.min(cs),
+            Lighten    => cb.max(cs),
+            ColorDodge => if cb == 0.0 { 0.0 } else if cs >= 1.0 { 1.0 } else { (cb / (1.0 - cs)).min(1.0) },
+            ColorBurn  => if cb >= 1.0 { 1.0 } else if cs <= 0.0 { 0.0 } else { 1.0 - ((1.0 - cb) / cs).min(1.0) },
+            HardLight  => if cs <= 0.5 { Multiply.mix(cb, 2.0 * cs) } else { Screen.mix(cb, 2.0 * cs - 1.0) },
+            SoftLight  => {
+                if cs <= 0.5 {
+                    cb - (1.0 - 2.0 * cs) * cb * (1.0 - cb)
+                } else {
+                    let d = if cb <= 0.25 { ((16.0 * cb - 12.0) * cb + 4.0) * cb } else { cb.sqrt() };
+                    cb + (2.0 * cs - 1.0) * (d - cb)
+                }
+            },
+            Difference => (cb - cs).abs(),
+            Exclusion  => cb + cs - 2.0 * cb * cs,
+            _ => cs,
+        }
+    }
+}
+
+impl Pixel {
+    // self drawn onto dst
+    pub fn blend(self, dst: Pixel, mode: BlendMode) -> Pixel {
+        // cheap and very common cases
+        match mode {
+            BlendMode::Src => return self,
+            BlendMode::Dst => return dst,
+            BlendMode::SrcOver if self.a == 0xFF => return self,
+            BlendMode::SrcOver if self.a == 0x00 => return dst,
+            _ => (),
+        }
+        
+        let s = self.channels();
+        let d = dst.channels();
+        let (sa, da) = (s[3], d[3]);
+        
+        let mut out = [0.0f32; 4];
+        match mode.factors(sa, da) {
+            Some((fs, fd)) => {
+                for i in 0..3 {
+                    out[i] = s[i] * sa * fs + d[i] * da * fd;
+                }
+                out[3] = sa * fs + da * fd;
+            },
+            None => {
+                for i in 0..3 {
+                    out[i] = s[i] * sa * (1.0 - da) + d[i] * da * (1.0 - sa) + sa * da * mode.mix(d[i], s[i]);
+                }
+                out[3] = sa + da * (1.0 - sa);
+            },
+        }
+        
+        Pixel::from_premultiplied(out)
+    }
+    
+    // self drawn onto dst over only part of the pixel, e.g. along an anti-aliased edge
+    pub fn blend_coverage(self, dst: Pixel, mode: BlendMode, coverage: f32) -> Pixel {
+        if coverage <= 0.0 { return dst; }
+        let blended = self.blend(dst, mode);
+        if coverage >= 1.0 { blended } else { dst.lerp_premultiplied(blended, coverage) }
+    }
+    
+    pub fn premultiply(self) -> Pixel {
+        let m = |c: u8| ((c as u32 * self.a as u32 + 127) / 255) as u8;
+        Pixel {b: m(self.b), g: m(self.g), r: m(self.r), a: self.a}
+    }
+    
+    pub fn unpremultiply(self) -> Pixel {
+        if self.a == 0 { return Pixel::TRANSPARENT; }
+        let m = |c: u8| ((c as u32 * 255 + self.a as u32 / 2) / self.a as u32).min(255) as u8;
+        Pixel {b: m(self.b), g: m(self.g), r: m(self.r), a: self.a}
+    }
+    
+    // straight [r,g,b,a] in [0,1]
+    fn channels(self) -> [f32; 4] {
+        [self.r, self.g, self.b, self.a].map(|c| c as f32 / 255.0)
+    }
+    
+    // premultiplied [r,g,b,a] in [0,1] -> straight pixel
+    fn from_premultiplied(c: [f32; 4]) -> Pixel {
+        let a = c[3].clamp(0.0, 1.0);
+        if a <= 0.0 { return Pixel::TRANSPARENT; }
+        let q = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        Pixel {r: q(c[0] / a), g: q(c[1] / a), b: q(c[2] / a), a: q(a)}
+    }
+    
+    // like lerp, but weighting colors by their alpha so transparent colors don't bleed in
+    pub(crate) fn lerp_premultiplied(self, other: Pixel, t: f32) -> Pixel {
+        let (a, b) = (self.channels(), other.channels());
+        let mut out = [0.0f32; 4];
+        for i in 0..3 {
+            out[i] = a[i] * a[3] + (b[i] * b[3] - a[i] * a[3]) * t;
+        }
+        out[3] = a[3] + (b[3] - a[3]) * t;
+        Pixel::from_premultiplied(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        PixelIdx,PixelIndexSlice,Size,
+        draw::Painter,
+        image::Image,
+    };
+    
+    // src painted with mode over dst, through a fill and through an image, on a row long enough for the simd paths
+    fn painted(src: Pixel, dst: Pixel, mode: BlendMode) -> Vec<[u8; 4]> {
+        let size = Size {width: 9, height: 2};
+        let row = Image::filled(Size {width: 9, height: 1}, src);
+        let mut image = Image::filled(size, dst);
+        unsafe { image.with_slice(|s| {
+            let mut p = Painter::new(s);
+            p.set_blend_mode(mode);
+            p.fill_rect(PixelIndexSlice {offset: PixelIdx::new(0, 0), size: Size {width: 9, height: 1}}, src);
+            p.draw_image(&row, PixelIdx::new(0, 1));
+        }) };
+        image.pixels().iter().map(|p| [p.r, p.g, p.b, p.a]).collect()
+    }
+    
+    fn check(src: Pixel, dst: Pixel, expected: &[(BlendMode,[u8; 4])]) {
+        for &(mode, expected) in expected {
+            let blended = src.blend(dst, mode);
+            assert_eq!([blended.r, blended.g, blended.b, blended.a], expected, "{mode:?}");
+            assert!(painted(src, dst, mode).iter().all(|p| *p == expected), "{mode:?} painted");
+        }
+    }
+    
+    #[test]
+    fn porter_duff() {
+        use BlendMode::*;
+        // both translucent, so every operator's factors show
+        check(Pixel::rgba(0xFF, 0x80, 0x00, 0xC0), Pixel::rgba(0x00, 0x40, 0xFF, 0x80), &[
+            (Clear,       [0x00, 0x00, 0x00, 0x00]),
+            (Src,         [0xFF, 0x80, 0x00, 0xC0]),
+            (Dst,         [0x00, 0x40, 0xFF, 0x80]),
+            (SrcOver,     [0xDB, 0x77, 0x24, 0xE0]),
+            (DstOver,     [0x6D, 0x5B, 0x92, 0xE0]),
+            (SrcIn,       [0xFF, 0x80, 0x00, 0x60]),
+            (DstIn,       [0x00, 0x40, 0xFF, 0x60]),
+            (SrcOut,      [0xFF, 0x80, 0x00, 0x60]),
+            (DstOut,      [0x00, 0x40, 0xFF, 0x20]),
+            (SrcAtop,     [0xC0, 0x70, 0x3F, 0x80]),
+            (DstAtop,     [0x7F, 0x60, 0x80, 0xC0]),
+            (Xor,         [0xC0, 0x70, 0x3F, 0x7F]),
+            (Plus,        [0xC0, 0x81, 0x80, 0xFF]),
+        ]);
+        // clear pixels don't change anything under src-over, and opaque ones replace it
+        check(Pixel::TRANSPARENT, Pixel::rgba(0x00, 0x40, 0xFF, 0x80), &[(SrcOver, [0x00, 0x40, 0xFF, 0x80])]);
+        check(Pixel::rgb(0x12, 0x34, 0x56), Pixel::rgba(0x00, 0x40, 0xFF, 0x80), &[(SrcOver, [0x12, 0x34, 0x56, 0xFF])]);
+    }
+    
+    #[test]
+    fn separable() {
+        use BlendMode::*;
+        // opaque over opaque is the blend function on its own
+        check(Pixel::rgb(0x40, 0xC0, 0x90), Pixel::rgb(0xA0, 0x30, 0x60), &[
+            (Multiply,    [0x28, 0x24, 0x36, 0xFF]),
+            (Screen,      [0xB8, 0xCC, 0xBA, 0xFF]),
+            (Overlay,     [0x71, 0x48, 0x6C, 0xFF]),
+            (Darken,      [0x40, 0x30, 0x60, 0xFF]),
+            (Lighten,     [0xA0, 0xC0, 0x90, 0xFF]),
+            (ColorDodge,  [0xD6, 0xC2, 0xDD, 0xFF]),
+            (ColorBurn,   [0x00, 0x00, 0x00, 0xFF]),
+            (HardLight,   [0x50, 0x99, 0x75, 0xFF]),
+            (SoftLight,   [0x82, 0x50, 0x68, 0xFF]),
+            (Difference,  [0x60, 0x90, 0x30, 0xFF]),
+            (Exclusion,   [0x90, 0xA8, 0x84, 0xFF]),
+        ]);
+        // translucent ones are composited src-over, with the blend function where both are there
+        check(Pixel::rgba(0xFF, 0x80, 0x00, 0xC0), Pixel::rgba(0x00, 0x40, 0xFF, 0x80), &[
+            (Multiply,    [0x6D, 0x4E, 0x24, 0xE0]),
+            (Screen,      [0xDB, 0x85, 0x92, 0xE0]),
+            (Overlay,     [0x6D, 0x5B, 0x92, 0xE0]),
+            (Darken,      [0x6D, 0x5B, 0x24, 0xE0]),
+            (Lighten,     [0xDB, 0x77, 0x92, 0xE0]),
+            (ColorDodge,  [0x6D, 0x77, 0x92, 0xE0]),
+            (ColorBurn,   [0x6D, 0x40, 0x92, 0xE0]),
+            (HardLight,   [0xDB, 0x5C, 0x24, 0xE0]),
+            (SoftLight,   [0x6D, 0x5B, 0x92, 0xE0]),
+            (Difference,  [0xDB, 0x5B, 0x92, 0xE0]),
+            (Exclusion,   [0xDB, 0x77, 0x92, 0xE0]),
+        ]);
+    }
+}
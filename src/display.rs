@@ -0,0 +1,399 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::{
+    blend::BlendMode,
+    draw::{FillRule,Paint,Painter,Path,PathCmd,Point,Stroke,Transform},
+    guicell::GuiCell,
+    image::Image,
+    window::{Damage,SurfaceSlice},
+    PixelIdx,PixelIndexSlice,Size,
+};
+
+mod encode;
+
+pub use encode::DisplayListError;
+
+// a frame kept as what to draw rather than as pixels
+// cells record commands into one with GuiCell::record; it can then be compared against the last frame's
+//  to find what changed, and rasterized only there, or serialized and drawn somewhere else entirely
+
+// one thing to draw, in the coordinates of the cell that recorded it
+#[derive(Clone,Debug)]
+pub enum Command {
+    Clear(Paint), // everything within the clip, replaced regardless of blend mode
+    FillRect(PixelIndexSlice, Paint),
+    FillPath(Path, FillRule, Paint),
+    StrokePath(Path, Stroke, Paint),
+    Image(Arc<Image>, PixelIdx),
+}
+
+// where and how a command is drawn
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct State {
+    pub origin: (isize,isize), // window coordinates of the recording cell's (0,0)
+    pub size: Size,            // of the recording cell
+    pub clip: PixelIndexSlice, // in window coordinates, always within the cell
+    pub transform: Transform,
+    pub blend_mode: BlendMode,
+}
+
+#[derive(Clone,Debug)]
+pub struct Item {
+    pub state: State,
+    pub command: Command,
+} impl Item {
+    // as much of the window as the item could touch, in window coordinates
+    pub fn bounds(&self) -> PixelIndexSlice {
+        let State {origin, clip, transform, ..} = self.state;
+        let points = |path: &Path| path.commands().iter().flat_map(|cmd| match *cmd {
+            PathCmd::MoveTo(p) | PathCmd::LineTo(p) => vec![p],
+            PathCmd::QuadTo(c, p) => vec![c, p],
+            PathCmd::CubicTo(c1, c2, p) => vec![c1, c2, p],
+            PathCmd::Close => vec![],
+        }).collect::<Vec<_>>();
+        let corners = |rect: PixelIndexSlice| {
+            let (x, y) = (rect.offset.x as f32, rect.offset.y as f32);
+            let (w, h) = (rect.size.width as f32, rect.size.height as f32);
+            vec![Point::new(x, y), Point::new(x + w, y), Point::new(x, y + h), Point::new(x + w, y + h)]
+        };
+        // local points, and how far past them drawing can reach
+        let (local, reach) = match &self.command {
+            Command::Clear(_) => return clip,
+            Command::FillRect(rect, _) => (corners(*rect), 0.0),
+            Command::FillPath(path, _, _) => (points(path), 0.0),
+            // miters reach out furthest, up to the limit
+            Command::StrokePath(path, stroke, _) => (points(path), stroke.width * stroke.miter_limit.max(1.0) / 2.0),
+            Command::Image(image, at) => (corners(PixelIndexSlice {offset: *at, size: image.size()}), 0.0),
+        };
+        if local.is_empty() { return PixelIndexSlice::default(); }
+        
+        let (mut min, mut max) = (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN));
+        for p in local.into_iter().map(|p| transform.apply(p)) {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        // a pixel more on each side, for anti-aliasing
+        let reach = reach * transform.a.hypot(transform.b).max(transform.c.hypot(transform.d)) + 1.0;
+        let left   = (origin.0 as f32 + min.x - reach).floor().max(0.0) as usize;
+        let top    = (origin.1 as f32 + min.y - reach).floor().max(0.0) as usize;
+        let right  = (origin.0 as f32 + max.x + reach).ceil().max(0.0) as usize;
+        let bottom = (origin.1 as f32 + max.y + reach).ceil().max(0.0) as usize;
+        PixelIndexSlice {
+            offset: PixelIdx::new(left, top),
+            size: Size {width: right.saturating_sub(left), height: bottom.saturating_sub(top)},
+        }.intersect(&clip)
+    }
+}
+
+// an image Recorder::pixels drew, and who for, so the next frame can have it again without drawing it
+#[derive(Clone,Debug)]
+struct Pixels {
+    cell: usize, // address of the cell recording it
+    call: usize, // how many times that cell had called pixels before
+    state: State,
+    area: PixelIndexSlice, // in the cell's coordinates
+    image: Arc<Image>,
+} impl Pixels {
+    fn key(&self) -> (usize,usize,State,PixelIndexSlice) { (self.cell, self.call, self.state, self.area) }
+}
+
+#[derive(Clone,Debug,Default)]
+pub struct DisplayList {
+    size: Size,
+    items: Vec<Item>,
+    pixels: Vec<Pixels>,
+} impl DisplayList {
+    pub fn new(size: Size) -> Self { Self {size, items: Vec::new(), pixels: Vec::new()} }
+    
+    // everything cell draws when given a window of the given size
+    pub fn record(cell: &dyn GuiCell, size: Size) -> Self {
+        let mut list = Self::new(size);
+        let bounds = PixelIndexSlice {offset: PixelIdx::new(0,0), size};
+        Recorder::new(&mut list, None).cell(cell, bounds);
+        list
+    }
+    
+    // the same, except that whatever was drawn through Recorder::pixels for previous is taken from it,
+    //  rather than drawn again, for cells in the same place that damage doesn't reach
+    // like drawing straight to the window, this relies on cells invalidating whatever they change
+    pub fn record_since(cell: &dyn GuiCell, size: Size, previous: &DisplayList, damage: &Damage) -> Self {
+        let mut list = Self::new(size);
+        let bounds = PixelIndexSlice {offset: PixelIdx::new(0,0), size};
+        let previous = (previous.size == size).then_some((previous, damage));
+        Recorder::new(&mut list, previous).cell(cell, bounds);
+        list
+    }
+    
+    pub fn size(&self) -> Size { self.size }
+    pub fn items(&self) -> &[Item] { &self.items }
+    pub fn len(&self) -> usize { self.items.len() }
+    pub fn is_empty(&self) -> bool { self.items.is_empty() }
+    
+    pub fn push(&mut self, item: Item) { self.items.push(item); }
+    
+    // where drawing self would give different pixels than drawing previous did
+    // items are the same if they hash the same, with images going by which image rather than what's in it;
+    //  ones that were added, removed or drawn in a different order relative to the others damage where they were and where they are
+    pub fn diff(&self, previous: &DisplayList) -> Damage {
+        let mut damage = Damage::new();
+        if self.size != previous.size {
+            damage.add(PixelIndexSlice {offset: PixelIdx::new(0,0), size: self.size});
+            return damage;
+        }
+        
+        // where in previous each hash is, first first
+        let mut positions: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, item) in previous.items.iter().enumerate().rev() {
+            positions.entry(encode::hash(item)).or_default().push(i);
+        }
+        let mut kept = vec![false; previous.items.len()];
+        let mut last = None;
+        for item in &self.items {
+            let found = positions.get_mut(&encode::hash(item)).and_then(|found| found.pop());
+            match found {
+                // still in the same order as everything else that stayed
+                Some(i) if last.is_none_or(|last| i > last) => {
+                    kept[i] = true;
+                    last = Some(i);
+                },
+                _ => damage.add(item.bounds()),
+            }
+        }
+        for (item, _) in previous.items.iter().zip(kept).filter(|(_, kept)| !kept) {
+            damage.add(item.bounds());
+        }
+        damage
+    }
+    
+    // runs of consecutive items drawn with the same state, which share a painter
+    pub fn batches(&self) -> impl Iterator<Item = &[Item]> {
+        self.items.chunk_by(|a, b| a.state == b.state)
+    }
+    
+    // every item that touches area, in window coordinates, drawn onto surface, which should cover the window
+    // nothing outside of area is touched
+    pub fn rasterize(&self, surface: &mut SurfaceSlice, area: PixelIndexSlice) {
+        for batch in self.batches() {
+            let state = batch[0].state;
+            let clip = state.clip.intersect(&area);
+            if clip.is_empty() { continue; }
+            
+            let local = PixelIndexSlice {
+                offset: PixelIdx::new((clip.offset.x as isize - state.origin.0) as usize, (clip.offset.y as isize - state.origin.1) as usize),
+                size: clip.size,
+            };
+            let mut slice = surface.child(state.origin, state.size).clipped(local);
+            let mut painter = Painter::new(&mut slice);
+            painter.set_transform(state.transform);
+            painter.set_blend_mode(state.blend_mode);
+            for item in batch.iter().filter(|item| !item.bounds().intersect(&clip).is_empty()) {
+                match &item.command {
+                    Command::Clear(paint) => painter.clear(paint),
+                    Command::FillRect(rect, paint) => painter.fill_rect(*rect, paint),
+                    Command::FillPath(path, rule, paint) => painter.fill_path(path, *rule, paint),
+                    Command::StrokePath(path, stroke, paint) => painter.stroke_path(path, stroke, paint),
+                    Command::Image(image, at) => painter.draw_image(image, *at),
+                }
+            }
+        }
+    }
+    
+    // to send somewhere else to be drawn; images are included in full
+    pub fn encode(&self) -> Vec<u8> { encode::list(self) }
+    pub fn decode(data: &[u8]) -> Result<Self,DisplayListError> { encode::decode(data) }
+}
+
+// what a cell records its drawing with, in its own coordinates, like a Painter
+pub struct Recorder<'a> {
+    list: &'a mut DisplayList,
+    state: State,
+    previous: Option<(&'a DisplayList,&'a Damage)>, // where pixels can be taken from, and where they can't
+    cell: usize,  // address of the cell recording
+    calls: usize, // to pixels, by that cell
+} impl<'a> Recorder<'a> {
+    fn new(list: &'a mut DisplayList, previous: Option<(&'a DisplayList,&'a Damage)>) -> Self {
+        let bounds = PixelIndexSlice {offset: PixelIdx::new(0,0), size: list.size};
+        Self {
+            state: State {
+                origin: (0,0),
+                size: list.size,
+                clip: bounds,
+                transform: Transform::IDENTITY,
+                blend_mode: BlendMode::default(),
+            },
+            list,
+            previous,
+            cell: 0,
+            calls: 0,
+        }
+    }
+    
+    pub fn size(&self) -> Size { self.state.size }
+    
+    // the part of the cell that can be seen, in its coordinates
+    pub fn visible_area(&self) -> PixelIndexSlice {
+        let State {origin, clip, ..} = self.state;
+        PixelIndexSlice {
+            offset: PixelIdx::new((clip.offset.x as isize - origin.0) as usize, (clip.offset.y as isize - origin.1) as usize),
+            size: clip.size,
+        }
+    }
+    
+    pub fn transform(&self) -> Transform { self.state.transform }
+    pub fn set_transform(&mut self, transform: Transform) { self.state.transform = transform; }
+    
+    pub fn blend_mode(&self) -> BlendMode { self.state.blend_mode }
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.state.blend_mode = mode; }
+    
+    pub fn command(&mut self, command: Command) {
+        if self.state.clip.is_empty() { return; }
+        let item = Item {state: self.state, command};
+        if !item.bounds().is_empty() { self.list.push(item); }
+    }
+    
+    pub fn clear(&mut self, paint: impl Into<Paint>) { self.command(Command::Clear(paint.into())); }
+    pub fn fill_rect(&mut self, rect: PixelIndexSlice, paint: impl Into<Paint>) { self.command(Command::FillRect(rect, paint.into())); }
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule, paint: impl Into<Paint>) { self.command(Command::FillPath(path.clone(), rule, paint.into())); }
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke, paint: impl Into<Paint>) { self.command(Command::StrokePath(path.clone(), stroke.clone(), paint.into())); }
+    pub fn draw_image(&mut self, image: Arc<Image>, at: PixelIdx) { self.command(Command::Image(image, at)); }
+    
+    // drawing that's easier done pixel by pixel, kept as an image of whatever part of area can be seen
+    // draw is given a slice of the whole cell, as GuiCell::draw would be, that can only write within area
+    // when recording since an earlier list, draw is skipped if nothing invalidated area since
+    pub fn pixels(&mut self, area: PixelIndexSlice, draw: impl FnOnce(&mut SurfaceSlice)) {
+        let area = area.intersect(&self.visible_area());
+        if area.is_empty() { return; }
+        let state = self.state;
+        let key = (self.cell, self.calls, state, area);
+        self.calls += 1;
+        
+        let in_window = PixelIndexSlice {
+            offset: PixelIdx::new((state.origin.0 + area.offset.x as isize) as usize, (state.origin.1 + area.offset.y as isize) as usize),
+            size: area.size,
+        };
+        let kept = self.previous
+            .filter(|(_, damage)| damage.areas().iter().all(|d| d.intersect(&in_window).is_empty()))
+            .and_then(|(previous, _)| previous.pixels.iter().find(|p| p.key() == key));
+        let image = match kept {
+            Some(kept) => kept.image.clone(),
+            None => {
+                let size = self.size();
+                let mut image = Image::new(area.size);
+                // draw only paints through the child it's given
+                unsafe { image.with_slice(|slice| draw(&mut slice.child((-(area.offset.x as isize), -(area.offset.y as isize)), size))) };
+                Arc::new(image)
+            },
+        };
+        
+        self.state.transform = Transform::IDENTITY;
+        self.state.blend_mode = BlendMode::SrcOver;
+        self.draw_image(image.clone(), area.offset);
+        self.state = state;
+        self.list.pixels.push(Pixels {cell: key.0, call: key.1, state, area, image});
+    }
+    
+    // a child occupying area of this cell, clipped to it
+    pub fn cell(&mut self, cell: &dyn GuiCell, area: PixelIndexSlice) {
+        self.cell_at(cell, (area.offset.x as isize, area.offset.y as isize), area.size, area);
+    }
+    
+    // a child whose (0,0) is at origin in this cell, seen only through visible, e.g. scrolled content
+    pub fn cell_at(&mut self, cell: &dyn GuiCell, origin: (isize,isize), size: Size, visible: PixelIndexSlice) {
+        let parent = self.state;
+        let origin = (parent.origin.0 + origin.0, parent.origin.1 + origin.1);
+        let to_window = |area: PixelIndexSlice, origin: (isize,isize)| {
+            let left = (origin.0 + area.offset.x as isize).max(0) as usize;
+            let top = (origin.1 + area.offset.y as isize).max(0) as usize;
+            let right = (origin.0 + (area.offset.x + area.size.width) as isize).max(0) as usize;
+            let bottom = (origin.1 + (area.offset.y + area.size.height) as isize).max(0) as usize;
+            PixelIndexSlice {
+                offset: PixelIdx::new(left, top),
+                size: Size {width: right.saturating_sub(left), height: bottom.saturating_sub(top)},
+            }
+        };
+        let clip = parent.clip
+            .intersect(&to_window(visible, parent.origin))
+            .intersect(&to_window(PixelIndexSlice {offset: PixelIdx::new(0,0), size}, origin));
+        if clip.is_empty() { return; }
+        
+        let outer = (self.cell, self.calls);
+        self.state = State {origin, size, clip, transform: Transform::IDENTITY, blend_mode: BlendMode::default()};
+        (self.cell, self.calls) = (cell as *const dyn GuiCell as *const () as usize, 0);
+        if !cell.record(self) {
+            self.pixels(PixelIndexSlice {offset: PixelIdx::new(0,0), size}, |slice| cell.draw(slice));
+        }
+        self.state = parent;
+        (self.cell, self.calls) = outer;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Pixel,draw::Gradient};
+    
+    const SIZE: Size = Size {width: 64, height: 48};
+    
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> PixelIndexSlice {
+        PixelIndexSlice {offset: PixelIdx::new(x, y), size: Size {width, height}}
+    }
+    
+    fn item(command: Command) -> Item {
+        let state = State {origin: (0, 0), size: SIZE, clip: rect(0, 0, SIZE.width, SIZE.height), transform: Transform::IDENTITY, blend_mode: BlendMode::SrcOver};
+        Item {state, command}
+    }
+    
+    fn list() -> DisplayList {
+        let mut logo = Image::new(Size {width: 3, height: 2});
+        for (i, p) in logo.pixels_mut().iter_mut().enumerate() { *p = Pixel {r: i as u8 * 40, g: 90, b: 200, a: 128 + i as u8}; }
+        let mut triangle = Path::new();
+        triangle.move_to(Point::new(30.0, 4.0)).line_to(Point::new(60.0, 20.0)).quad_to(Point::new(40.0, 40.0), Point::new(32.0, 30.0)).close();
+        let gradient = Paint::linear(Point::new(0.0, 0.0), Point::new(20.0, 0.0), Gradient::even(&[Pixel::rgb(255, 0, 0), Pixel::rgb(0, 0, 255)]));
+        
+        let mut list = DisplayList::new(SIZE);
+        list.push(item(Command::Clear(Pixel::rgb(240, 240, 230).into())));
+        list.push(item(Command::FillRect(rect(2, 3, 10, 8), gradient)));
+        list.push(item(Command::FillPath(Path::circle(Point::new(15.0, 30.0), 6.5), FillRule::EvenOdd, Pixel::rgb(0, 120, 0).into())));
+        let mut stroked = item(Command::StrokePath(triangle, Stroke::new(2.5).with_dashes(vec![3.0, 1.0], 0.5), Pixel {r: 9, g: 8, b: 7, a: 200}.into()));
+        stroked.state.transform = Transform::rotate(0.1).then(Transform::translate(1.0, 2.0));
+        stroked.state.blend_mode = BlendMode::Multiply;
+        list.push(stroked);
+        list.push(item(Command::Image(Arc::new(logo), PixelIdx::new(50, 40))));
+        list
+    }
+    
+    fn rasterize(list: &DisplayList) -> Vec<[u8; 4]> {
+        let mut image = Image::new(SIZE);
+        unsafe { image.with_slice(|s| list.rasterize(s, rect(0, 0, SIZE.width, SIZE.height))) };
+        image.pixels().iter().map(|p| [p.r, p.g, p.b, p.a]).collect()
+    }
+    
+    #[test]
+    fn encode_round_trip() {
+        let list = list();
+        let bytes = list.encode();
+        let back = DisplayList::decode(&bytes).unwrap();
+        assert_eq!((back.size(), back.len()), (list.size(), list.len()));
+        for (a, b) in list.items().iter().zip(back.items()) {
+            assert_eq!(a.state, b.state);
+            assert_eq!(a.bounds(), b.bounds());
+        }
+        assert_eq!(back.encode(), bytes);
+        assert!(rasterize(&back) == rasterize(&list));
+        assert!(DisplayList::decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+    
+    #[test]
+    fn diff() {
+        let list = list();
+        assert!(list.clone().diff(&list).is_empty());
+        
+        let mut moved = list.clone();
+        moved.items[1].command = Command::FillRect(rect(20, 10, 10, 8), Pixel::rgb(1, 2, 3).into());
+        let damage = moved.diff(&list);
+        assert_eq!(damage.bounds(), list.items[1].bounds().union(&moved.items[1].bounds()));
+        // the image further off is left alone
+        assert!(damage.areas().iter().all(|area| area.intersect(&list.items[4].bounds()).is_empty()));
+    }
+}
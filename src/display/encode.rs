@@ -0,0 +1,344 @@
+use std::fmt;
+use std::hash::{DefaultHasher,Hasher};
+use std::sync::Arc;
+
+use crate::{
+    PixelIdx,PixelIndexSlice,Size,Pixel,
+    blend::BlendMode,
+    draw::{FillRule,Gradient,LineCap,LineJoin,Paint,Path,PathCmd,Point,Spread,Stroke,Transform},
+    image::Image,
+};
+
+use super::{Command,DisplayList,Item,State};
+
+// little-endian throughout; lengths and coordinates are u32, positions f32
+// the header, then the window size and the number of items, then each item: its state, then its command,
+//  each starting with a tag byte for which kind it is
+// pixels are r,g,b,a, as they are in most other formats
+
+const MAGIC: &[u8; 8] = b"GUIDL\x00\x00\x01";
+
+#[derive(Debug)]
+pub enum DisplayListError {
+    Malformed(&'static str), // what couldn't be read
+} impl fmt::Display for DisplayListError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DisplayListError::Malformed(what) => write!(f, "malformed display list: bad {what}"),
+        }
+    }
+} impl std::error::Error for DisplayListError {
+}
+
+pub(super) fn list(list: &DisplayList) -> Vec<u8> {
+    let mut out = Writer(MAGIC.to_vec());
+    out.size(list.size);
+    out.len(list.items.len());
+    for item in &list.items { out.item(item); }
+    out.0
+}
+
+// what diffs tell items apart by: everything that would be encoded, except that images go by address,
+//  since hashing every pixel of every image every frame would cost more than redrawing them
+pub(super) fn hash(item: &Item) -> u64 {
+    let mut out = Writer(Hashed(DefaultHasher::new()));
+    out.item(item);
+    out.0.0.finish()
+}
+
+// where a Writer's bytes go
+trait Sink {
+    fn write(&mut self, bytes: &[u8]);
+    fn image(&mut self, image: &Arc<Image>);
+}
+
+impl Sink for Vec<u8> {
+    fn write(&mut self, bytes: &[u8]) { self.extend_from_slice(bytes); }
+    fn image(&mut self, image: &Arc<Image>) {
+        for v in [image.width(), image.height()] { self.extend_from_slice(&(v as u32).to_le_bytes()); }
+        self.extend_from_slice(&image.to_rgba());
+    }
+}
+
+struct Hashed(DefaultHasher);
+impl Sink for Hashed {
+    fn write(&mut self, bytes: &[u8]) { self.0.write(bytes); }
+    fn image(&mut self, image: &Arc<Image>) { self.0.write_usize(Arc::as_ptr(image) as usize); }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<DisplayList,DisplayListError> {
+    let mut r = Reader {data, pos: 0};
+    if r.bytes(MAGIC.len(), "header")? != MAGIC { return Err(DisplayListError::Malformed("header")); }
+    let size = r.size()?;
+    let count = r.len()?;
+    let mut items = Vec::new();
+    for _ in 0..count { items.push(r.item()?); }
+    if r.pos != data.len() { return Err(DisplayListError::Malformed("length")); }
+    Ok(DisplayList {size, items, pixels: Vec::new()})
+}
+
+fn blend_index(mode: BlendMode) -> u8 {
+    BlendMode::ALL.iter().position(|m| *m == mode).unwrap() as u8
+}
+
+struct Writer<S: Sink>(S);
+impl<S: Sink> Writer<S> {
+    fn u8(&mut self, v: u8) { self.0.write(&[v]); }
+    fn u32(&mut self, v: u32) { self.0.write(&v.to_le_bytes()); }
+    fn i32(&mut self, v: i32) { self.0.write(&v.to_le_bytes()); }
+    fn f32(&mut self, v: f32) { self.0.write(&v.to_le_bytes()); }
+    fn len(&mut self, v: usize) { self.u32(v as u32); }
+    
+    fn pixel(&mut self, p: Pixel) { self.0.write(&[p.r, p.g, p.b, p.a]); }
+    fn point(&mut self, p: Point) { self.f32(p.x); self.f32(p.y); }
+    fn idx(&mut self, idx: PixelIdx) { self.len(idx.x); self.len(idx.y); }
+    fn size(&mut self, size: Size) { self.len(size.width); self.len(size.height); }
+    fn rect(&mut self, rect: PixelIndexSlice) { self.idx(rect.offset); self.size(rect.size); }
+    
+    fn item(&mut self, item: &Item) {
+        let State {origin, size, clip, transform, blend_mode} = item.state;
+        self.i32(origin.0 as i32);
+        self.i32(origin.1 as i32);
+        self.size(size);
+        self.rect(clip);
+        let Transform {a, b, c, d, e, f} = transform;
+        for v in [a, b, c, d, e, f] { self.f32(v); }
+        self.u8(blend_index(blend_mode));
+        
+        match &item.command {
+            Command::Clear(paint) => {
+                self.u8(0);
+                self.paint(paint);
+            },
+            Command::FillRect(rect, paint) => {
+                self.u8(1);
+                self.rect(*rect);
+                self.paint(paint);
+            },
+            Command::FillPath(path, rule, paint) => {
+                self.u8(2);
+                self.path(path);
+                self.u8(match rule { FillRule::NonZero => 0, FillRule::EvenOdd => 1 });
+                self.paint(paint);
+            },
+            Command::StrokePath(path, stroke, paint) => {
+                self.u8(3);
+                self.path(path);
+                self.stroke(stroke);
+                self.paint(paint);
+            },
+            Command::Image(image, at) => {
+                self.u8(4);
+                self.image(image);
+                self.idx(*at);
+            },
+        }
+    }
+    
+    fn paint(&mut self, paint: &Paint) {
+        match paint {
+            Paint::Solid(color) => {
+                self.u8(0);
+                self.pixel(*color);
+            },
+            Paint::Linear {start, end, gradient} => {
+                self.u8(1);
+                self.point(*start);
+                self.point(*end);
+                self.gradient(gradient);
+            },
+            Paint::Radial {center, radius, focal, gradient} => {
+                self.u8(2);
+                self.point(*center);
+                self.f32(*radius);
+                self.point(*focal);
+                self.gradient(gradient);
+            },
+            Paint::Conic {center, angle, gradient} => {
+                self.u8(3);
+                self.point(*center);
+                self.f32(*angle);
+                self.gradient(gradient);
+            },
+            Paint::Pattern {image, origin, spread} => {
+                self.u8(4);
+                self.image(image);
+                self.point(*origin);
+                self.spread(*spread);
+            },
+        }
+    }
+    
+    fn spread(&mut self, spread: Spread) {
+        self.u8(match spread { Spread::Pad => 0, Spread::Repeat => 1, Spread::Reflect => 2 });
+    }
+    
+    fn gradient(&mut self, gradient: &Gradient) {
+        self.spread(gradient.spread());
+        self.len(gradient.stops().len());
+        for (offset, color) in gradient.stops() {
+            self.f32(*offset);
+            self.pixel(*color);
+        }
+    }
+    
+    fn path(&mut self, path: &Path) {
+        self.len(path.commands().len());
+        for cmd in path.commands() {
+            match *cmd {
+                PathCmd::MoveTo(p) => { self.u8(0); self.point(p); },
+                PathCmd::LineTo(p) => { self.u8(1); self.point(p); },
+                PathCmd::QuadTo(c, p) => { self.u8(2); self.point(c); self.point(p); },
+                PathCmd::CubicTo(c1, c2, p) => { self.u8(3); self.point(c1); self.point(c2); self.point(p); },
+                PathCmd::Close => self.u8(4),
+            }
+        }
+    }
+    
+    fn stroke(&mut self, stroke: &Stroke) {
+        self.f32(stroke.width);
+        self.u8(match stroke.join { LineJoin::Miter => 0, LineJoin::Round => 1, LineJoin::Bevel => 2 });
+        self.f32(stroke.miter_limit);
+        self.u8(match stroke.cap { LineCap::Butt => 0, LineCap::Round => 1, LineCap::Square => 2 });
+        self.len(stroke.dashes.len());
+        for dash in &stroke.dashes { self.f32(*dash); }
+        self.f32(stroke.dash_offset);
+    }
+    
+    fn image(&mut self, image: &Arc<Image>) { self.0.image(image); }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+} impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize, what: &'static str) -> Result<&'a [u8],DisplayListError> {
+        let bytes = self.pos.checked_add(n)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or(DisplayListError::Malformed(what))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+    
+    fn u8(&mut self, what: &'static str) -> Result<u8,DisplayListError> { Ok(self.bytes(1, what)?[0]) }
+    fn u32(&mut self, what: &'static str) -> Result<u32,DisplayListError> {
+        self.bytes(4, what).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    fn i32(&mut self, what: &'static str) -> Result<i32,DisplayListError> { self.u32(what).map(|v| v as i32) }
+    fn f32(&mut self, what: &'static str) -> Result<f32,DisplayListError> { self.u32(what).map(f32::from_bits) }
+    fn len(&mut self) -> Result<usize,DisplayListError> { self.u32("length").map(|v| v as usize) }
+    
+    fn pixel(&mut self) -> Result<Pixel,DisplayListError> {
+        let b = self.bytes(4, "color")?;
+        Ok(Pixel {r: b[0], g: b[1], b: b[2], a: b[3]})
+    }
+    fn point(&mut self) -> Result<Point,DisplayListError> { Ok(Point::new(self.f32("point")?, self.f32("point")?)) }
+    fn idx(&mut self) -> Result<PixelIdx,DisplayListError> {
+        Ok(PixelIdx::new(self.u32("position")? as usize, self.u32("position")? as usize))
+    }
+    fn size(&mut self) -> Result<Size,DisplayListError> {
+        Ok(Size {width: self.u32("size")? as usize, height: self.u32("size")? as usize})
+    }
+    fn rect(&mut self) -> Result<PixelIndexSlice,DisplayListError> { Ok(PixelIndexSlice {offset: self.idx()?, size: self.size()?}) }
+    
+    fn item(&mut self) -> Result<Item,DisplayListError> {
+        let origin = (self.i32("origin")? as isize, self.i32("origin")? as isize);
+        let size = self.size()?;
+        let clip = self.rect()?;
+        let mut t = [0.0; 6];
+        for v in &mut t { *v = self.f32("transform")?; }
+        let [a, b, c, d, e, f] = t;
+        let blend_mode = *BlendMode::ALL.get(self.u8("blend mode")? as usize).ok_or(DisplayListError::Malformed("blend mode"))?;
+        let state = State {origin, size, clip, transform: Transform {a, b, c, d, e, f}, blend_mode};
+        
+        let command = match self.u8("command")? {
+            0 => Command::Clear(self.paint()?),
+            1 => Command::FillRect(self.rect()?, self.paint()?),
+            2 => {
+                let path = self.path()?;
+                let rule = match self.u8("fill rule")? {
+                    0 => FillRule::NonZero,
+                    1 => FillRule::EvenOdd,
+                    _ => return Err(DisplayListError::Malformed("fill rule")),
+                };
+                Command::FillPath(path, rule, self.paint()?)
+            },
+            3 => Command::StrokePath(self.path()?, self.stroke()?, self.paint()?),
+            4 => Command::Image(self.image()?, self.idx()?),
+            _ => return Err(DisplayListError::Malformed("command")),
+        };
+        Ok(Item {state, command})
+    }
+    
+    fn paint(&mut self) -> Result<Paint,DisplayListError> {
+        Ok(match self.u8("paint")? {
+            0 => Paint::Solid(self.pixel()?),
+            1 => Paint::Linear {start: self.point()?, end: self.point()?, gradient: self.gradient()?},
+            2 => Paint::Radial {center: self.point()?, radius: self.f32("radius")?, focal: self.point()?, gradient: self.gradient()?},
+            3 => Paint::Conic {center: self.point()?, angle: self.f32("angle")?, gradient: self.gradient()?},
+            4 => Paint::Pattern {image: self.image()?, origin: self.point()?, spread: self.spread()?},
+            _ => return Err(DisplayListError::Malformed("paint")),
+        })
+    }
+    
+    fn spread(&mut self) -> Result<Spread,DisplayListError> {
+        Ok(match self.u8("spread")? {
+            0 => Spread::Pad,
+            1 => Spread::Repeat,
+            2 => Spread::Reflect,
+            _ => return Err(DisplayListError::Malformed("spread")),
+        })
+    }
+    
+    fn gradient(&mut self) -> Result<Gradient,DisplayListError> {
+        let mut gradient = Gradient::new().with_spread(self.spread()?);
+        for _ in 0..self.len()? {
+            let offset = self.f32("gradient stop")?;
+            gradient.add_stop(offset, self.pixel()?);
+        }
+        Ok(gradient)
+    }
+    
+    // rebuilt through the builders, which give back the same commands for any path they made
+    fn path(&mut self) -> Result<Path,DisplayListError> {
+        let mut path = Path::new();
+        for _ in 0..self.len()? {
+            match self.u8("path command")? {
+                0 => path.move_to(self.point()?),
+                1 => path.line_to(self.point()?),
+                2 => path.quad_to(self.point()?, self.point()?),
+                3 => path.cubic_to(self.point()?, self.point()?, self.point()?),
+                4 => path.close(),
+                _ => return Err(DisplayListError::Malformed("path command")),
+            };
+        }
+        Ok(path)
+    }
+    
+    fn stroke(&mut self) -> Result<Stroke,DisplayListError> {
+        let width = self.f32("stroke width")?;
+        let join = match self.u8("line join")? {
+            0 => LineJoin::Miter,
+            1 => LineJoin::Round,
+            2 => LineJoin::Bevel,
+            _ => return Err(DisplayListError::Malformed("line join")),
+        };
+        let miter_limit = self.f32("miter limit")?;
+        let cap = match self.u8("line cap")? {
+            0 => LineCap::Butt,
+            1 => LineCap::Round,
+            2 => LineCap::Square,
+            _ => return Err(DisplayListError::Malformed("line cap")),
+        };
+        let mut dashes = Vec::new();
+        for _ in 0..self.len()? { dashes.push(self.f32("dash")?); }
+        let dash_offset = self.f32("dash offset")?;
+        Ok(Stroke {width, join, miter_limit, cap, dashes, dash_offset})
+    }
+    
+    fn image(&mut self) -> Result<Arc<Image>,DisplayListError> {
+        let size = self.size()?;
+        let len = size.width.checked_mul(size.height).and_then(|n| n.checked_mul(4)).ok_or(DisplayListError::Malformed("image size"))?;
+        Ok(Arc::new(Image::from_rgba(size, self.bytes(len, "image")?)))
+    }
+}
@@ -0,0 +1,911 @@
+mod paint;
+mod path;
+mod raster;
+mod stroke;
+mod transform;
+
+pub use paint::{Gradient,Paint,Spread};
+pub use path::{FillRule,Path,PathCmd};
+pub use raster::Rasterizer;
+pub use stroke::{LineCap,LineJoin,Stroke};
+pub use transform::Transform;
+
+use std::f32::consts::TAU;
+use std::rc::Rc;
+
+use crate::{
+    blend::BlendMode,
+    image::Image,
+    simd,
+    text::{Font,Glyph,GlyphId},
+    window::SurfaceSlice,
+    Pixel,PixelIdx,PixelIndexSlice,Size,
+};
+
+// a position in slice coordinates, for anything that can land between pixels
+// pixel (x,y) covers the square from (x,y) to (x+1,y+1), so its center is at (x+0.5,y+0.5)
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+} impl Point {
+    pub const fn new(x: f32, y: f32) -> Self { Self {x,y} }
+    
+    pub fn lerp(self, other: Point, t: f32) -> Point {
+        Point::new(self.x + (other.x - self.x) * t, self.y + (other.y - self.y) * t)
+    }
+    
+    pub fn distance(self, other: Point) -> f32 {
+        (self.x - other.x).hypot(self.y - other.y)
+    }
+} impl From<PixelIdx> for Point {
+    // center of the pixel
+    fn from(idx: PixelIdx) -> Self {
+        Point::new(idx.x as f32 + 0.5, idx.y as f32 + 0.5)
+    }
+}
+
+// coverage of each pixel in area, for clips that aren't whole-pixel rectangles
+struct Mask {
+    area: PixelIndexSlice,
+    coverage: Vec<f32>,
+} impl Mask {
+    fn at(&self, idx: PixelIdx) -> f32 {
+        if !self.area.contains(idx) { return 0.0; }
+        self.coverage[(idx.y - self.area.offset.y) * self.area.size.width + idx.x - self.area.offset.x]
+    }
+}
+
+// everything push saves and pop restores
+#[derive(Clone)]
+struct State {
+    transform: Transform,
+    inverse: Transform,
+    clip: PixelIndexSlice,  // nothing outside of this gets drawn
+    mask: Option<Rc<Mask>>, // and within it, only as much as the mask allows
+    blend_mode: BlendMode,
+}
+
+// drawing context handed to cells that want more than set_pixel
+// all coordinates are relative to the wrapped slice, and everything is clipped to it
+// shapes taking Points are anti-aliased; shapes taking PixelIdx/PixelIndexSlice are pixel-exact,
+//  as long as the transform only moves things by whole pixels
+// angles are in radians, clockwise from the positive x axis (since y points down)
+// every draw call blends with what's already there using the painter's blend mode (src-over by default)
+// fills and strokes take anything that converts into a Paint, so a plain Pixel works too
+// shapes and paints are in local coordinates, which the transform maps onto the slice
+pub struct Painter<'a> {
+    surface: &'a mut SurfaceSlice,
+    state: State,
+    stack: Vec<State>,
+} impl<'a> Painter<'a> {
+    
+    pub fn new(surface: &'a mut SurfaceSlice) -> Self {
+        let clip = surface.visible_area();
+        Self {
+            surface,
+            state: State {
+                transform: Transform::IDENTITY,
+                inverse: Transform::IDENTITY,
+                clip,
+                mask: None,
+                blend_mode: BlendMode::default(),
+            },
+            stack: Vec::new(),
+        }
+    }
+    
+    pub fn blend_mode(&self) -> BlendMode { self.state.blend_mode }
+    pub fn set_blend_mode(&mut self, mode: BlendMode) { self.state.blend_mode = mode; }
+    
+    // run f with a different blend mode, then switch back
+    pub fn with_blend_mode<R>(&mut self, mode: BlendMode, f: impl FnOnce(&mut Self) -> R) -> R {
+        let old = std::mem::replace(&mut self.state.blend_mode, mode);
+        let rt = f(self);
+        self.state.blend_mode = old;
+        rt
+    }
+    
+    // save the transform, clip and blend mode, to be restored by the matching pop
+    pub fn push(&mut self) {
+        self.stack.push(self.state.clone());
+    }
+    
+    // popping more than was pushed does nothing
+    pub fn pop(&mut self) {
+        if let Some(state) = self.stack.pop() { self.state = state; }
+    }
+    
+    // run f between a push and a pop
+    pub fn with_pushed<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.push();
+        let rt = f(self);
+        self.pop();
+        rt
+    }
+    
+    pub fn transform(&self) -> Transform { self.state.transform }
+    
+    // a transform that can't be inverted flattens everything, so shapes come out empty
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.state.transform = transform;
+        self.state.inverse = transform.invert().unwrap_or(Transform::scale(0.0, 0.0));
+    }
+    
+    // t gets applied to everything drawn from now on, before the current transform
+    pub fn transform_by(&mut self, t: Transform) {
+        self.set_transform(t.then(self.state.transform));
+    }
+    
+    pub fn translate(&mut self, x: f32, y: f32) { self.transform_by(Transform::translate(x, y)); }
+    pub fn scale(&mut self, x: f32, y: f32) { self.transform_by(Transform::scale(x, y)); }
+    pub fn rotate(&mut self, angle: f32) { self.transform_by(Transform::rotate(angle)); }
+    pub fn skew(&mut self, x: f32, y: f32) { self.transform_by(Transform::skew(x, y)); }
+    
+    // clips only ever shrink; use push and pop to get the old one back
+    pub fn clip_rect(&mut self, rect: PixelIndexSlice) {
+        match self.pixel_offset() {
+            Some((dx, dy)) => self.state.clip = self.device_rect(rect, dx, dy).unwrap_or_default(),
+            None => {
+                let (min, max) = corners(rect);
+                self.clip_path(&Path::rect(min, max), FillRule::NonZero);
+            },
+        }
+    }
+    
+    pub fn clip_rounded_rect(&mut self, min: Point, max: Point, radius: f32) {
+        self.clip_path(&Path::rounded_rect(min, max, radius), FillRule::NonZero);
+    }
+    
+    pub fn clip_path(&mut self, path: &Path, rule: FillRule) {
+        let contours = path.flatten(self.tolerance());
+        let contours: Vec<&[Point]> = contours.iter().map(|(points, _)| points.as_slice()).collect();
+        let Some((area, mut coverage)) = self.rasterize(&contours, rule) else {
+            self.state.clip = PixelIndexSlice::default();
+            self.state.mask = None;
+            return;
+        };
+        if let Some(old) = &self.state.mask {
+            for (i, c) in coverage.iter_mut().enumerate() {
+                *c *= old.at(PixelIdx::new(area.offset.x + i % area.size.width, area.offset.y + i / area.size.width));
+            }
+        }
+        self.state.clip = area;
+        self.state.mask = Some(Rc::new(Mask {area, coverage}));
+    }
+    
+    // escape hatch for anything the painter can't do
+    pub fn surface(&mut self) -> &mut SurfaceSlice { self.surface }
+    
+    pub fn size(&self) -> Size { self.surface.size() }
+    
+    // these go straight to the slice, ignoring the transform and clip
+    pub fn get_pixel(&self, idx: PixelIdx) -> Pixel { self.surface.get_pixel(idx) }
+    pub fn set_pixel(&mut self, idx: PixelIdx, color: Pixel) { self.surface.set_pixel(idx, color); }
+    
+    // draw color over a pixel, covering `coverage` (0 to 1) of it
+    // idx is in slice coordinates, so this ignores the transform, but not the clip
+    pub fn plot(&mut self, idx: PixelIdx, color: Pixel, coverage: f32) {
+        let coverage = coverage * self.clip_coverage(idx);
+        if coverage <= 0.0 { return; }
+        self.blend_at(idx, color, coverage);
+    }
+    
+    // plot with the paint's color for the pixel's center
+    fn shade(&mut self, idx: PixelIdx, paint: &Paint, coverage: f32) {
+        let coverage = coverage * self.clip_coverage(idx);
+        if coverage <= 0.0 { return; }
+        let color = paint.color_at(self.state.inverse.apply(idx.into()));
+        self.blend_at(idx, color, coverage);
+    }
+    
+    fn blend_at(&mut self, idx: PixelIdx, color: Pixel, coverage: f32) {
+        let dst = self.surface.get_pixel(idx);
+        self.surface.set_pixel(idx, color.blend_coverage(dst, self.state.blend_mode, coverage));
+    }
+    
+    fn clip_coverage(&self, idx: PixelIdx) -> f32 {
+        if !self.state.clip.contains(idx) { return 0.0; }
+        self.state.mask.as_ref().map_or(1.0, |mask| mask.at(idx))
+    }
+    
+    // whether drawing paint fully covering a pixel just replaces it
+    fn replaces(&self, paint: &Paint) -> bool {
+        match self.state.blend_mode {
+            BlendMode::Src => true,
+            BlendMode::SrcOver => paint.is_opaque(),
+            _ => false,
+        }
+    }
+    
+    // how far the transform moves things, if that's all it does
+    fn offset(&self) -> Option<Point> {
+        let t = self.state.transform;
+        if t.is_translation() { Some(Point::new(t.e, t.f)) } else { None }
+    }
+    
+    // same, but only for moves by whole pixels
+    fn pixel_offset(&self) -> Option<(isize,isize)> {
+        self.offset()
+            .filter(|o| o.x.fract() == 0.0 && o.y.fract() == 0.0)
+            .map(|o| (o.x as isize, o.y as isize))
+    }
+    
+    // rect moved by (dx,dy), then clipped; None if none of it is visible
+    fn device_rect(&self, rect: PixelIndexSlice, dx: isize, dy: isize) -> Option<PixelIndexSlice> {
+        let left   = (rect.offset.x as isize + dx).max(0);
+        let top    = (rect.offset.y as isize + dy).max(0);
+        let right  = rect.offset.x as isize + dx + rect.size.width  as isize;
+        let bottom = rect.offset.y as isize + dy + rect.size.height as isize;
+        if right <= left || bottom <= top { return None; }
+        self.visible(PixelIndexSlice {
+            offset: PixelIdx::new(left as usize, top as usize),
+            size: Size {width: (right - left) as usize, height: (bottom - top) as usize},
+        })
+    }
+    
+    // FLATTEN_TOLERANCE in local coordinates, so curves stay smooth when scaled up
+    fn tolerance(&self) -> f32 {
+        FLATTEN_TOLERANCE / self.state.transform.max_scale().max(1e-3)
+    }
+    
+    // replace everything within the clip, regardless of blend mode or transform
+    pub fn clear(&mut self, paint: impl Into<Paint>) {
+        let area = self.state.clip;
+        let paint = paint.into();
+        self.with_blend_mode(BlendMode::Src, |p| p.fill_device(area, &paint));
+    }
+    
+    // part of rect to actually touch, or None if none of it is visible
+    fn visible(&self, rect: PixelIndexSlice) -> Option<PixelIndexSlice> {
+        let rect = self.state.clip.intersect(&rect);
+        if rect.size.width == 0 || rect.size.height == 0 { None } else { Some(rect) }
+    }
+    
+    // visible pixels whose squares overlap the given bounds, in slice coordinates
+    fn visible_bounds(&self, min: Point, max: Point) -> Option<PixelIndexSlice> {
+        let visible = self.state.clip;
+        let left   = min.x.floor().max(visible.offset.x as f32);
+        let top    = min.y.floor().max(visible.offset.y as f32);
+        let right  = max.x.ceil().min((visible.offset.x + visible.size.width ) as f32);
+        let bottom = max.y.ceil().min((visible.offset.y + visible.size.height) as f32);
+        if !(left < right && top < bottom) { return None; }
+        Some(PixelIndexSlice {
+            offset: PixelIdx::new(left as usize, top as usize),
+            size: Size {width: (right - left) as usize, height: (bottom - top) as usize},
+        })
+    }
+    
+    // plot every pixel in bounds with the coverage given for its center
+    // bounds and coverage are in local coordinates, so the transform has to be a translation
+    fn fill_coverage(&mut self, min: Point, max: Point, paint: &Paint, coverage: impl Fn(Point) -> f32) {
+        let o = self.offset().unwrap_or_default();
+        let Some(area) = self.visible_bounds(Point::new(min.x + o.x, min.y + o.y), Point::new(max.x + o.x, max.y + o.y)) else { return };
+        for y in area.offset.y..area.offset.y + area.size.height {
+            for x in area.offset.x..area.offset.x + area.size.width {
+                let idx = PixelIdx::new(x,y);
+                let p = Point::from(idx);
+                self.shade(idx, paint, coverage(Point::new(p.x - o.x, p.y - o.y)));
+            }
+        }
+    }
+    
+    // fill a rect that's already in slice coordinates and within the clip
+    fn fill_device(&mut self, rect: PixelIndexSlice, paint: &Paint) {
+        if self.state.mask.is_some() {
+            for y in rect.offset.y..rect.offset.y + rect.size.height {
+                for x in rect.offset.x..rect.offset.x + rect.size.width {
+                    self.shade(PixelIdx::new(x,y), paint, 1.0);
+                }
+            }
+            return;
+        }
+        
+        let replaces = self.replaces(paint);
+        let mode = self.state.blend_mode;
+        let inverse = self.state.inverse;
+        for y in rect.offset.y..rect.offset.y + rect.size.height {
+            if let Some((x0, row)) = self.surface.row_mut(y) {
+                let start = rect.offset.x - x0;
+                let span = &mut row[start..start + rect.size.width];
+                match (paint.as_solid(), replaces) {
+                    (Some(color), true ) => simd::fill(span, color),
+                    (Some(color), false) => simd::blend(span, color, mode),
+                    (None, _) => for (i, px) in span.iter_mut().enumerate() {
+                        let color = paint.color_at(inverse.apply(PixelIdx::new(rect.offset.x + i, y).into()));
+                        *px = if replaces { color } else { color.blend(*px, mode) };
+                    },
+                }
+            }
+        }
+    }
+    
+    pub fn fill_rect(&mut self, rect: PixelIndexSlice, paint: impl Into<Paint>) {
+        match self.pixel_offset() {
+            Some((dx, dy)) => if let Some(rect) = self.device_rect(rect, dx, dy) {
+                self.fill_device(rect, &paint.into());
+            },
+            None => {
+                let (min, max) = corners(rect);
+                self.fill_path(&Path::rect(min, max), FillRule::NonZero, paint);
+            },
+        }
+    }
+    
+    // outline of the given width, drawn just inside rect
+    pub fn stroke_rect(&mut self, rect: PixelIndexSlice, width: usize, paint: impl Into<Paint>) {
+        if rect.size.width == 0 || rect.size.height == 0 || width == 0 { return; }
+        let paint = paint.into();
+        let PixelIdx {x, y} = rect.offset;
+        let Size {width: w, height: h} = rect.size;
+        let t = width.min(w).min(h);
+        
+        self.fill_rect(PixelIndexSlice {offset: PixelIdx::new(x, y        ), size: Size {width: w, height: t}}, &paint);
+        self.fill_rect(PixelIndexSlice {offset: PixelIdx::new(x, y + h - t), size: Size {width: w, height: t}}, &paint);
+        self.fill_rect(PixelIndexSlice {offset: PixelIdx::new(x,         y), size: Size {width: t, height: h}}, &paint);
+        self.fill_rect(PixelIndexSlice {offset: PixelIdx::new(x + w - t, y), size: Size {width: t, height: h}}, &paint);
+    }
+    
+    // bresenham, both endpoints inclusive
+    // the endpoints get transformed, but the line itself stays one pixel wide
+    pub fn line(&mut self, from: PixelIdx, to: PixelIdx, paint: impl Into<Paint>) {
+        let paint = paint.into();
+        let t = self.state.transform;
+        let to_device = |idx: PixelIdx| {
+            let p = t.apply(idx.into());
+            (p.x.floor() as isize, p.y.floor() as isize)
+        };
+        let (mut x, mut y) = to_device(from);
+        let (x1, y1) = to_device(to);
+        
+        let dx =  (x1 - x).abs();
+        let dy = -(y1 - y).abs();
+        let sx = if x < x1 { 1 } else { -1 };
+        let sy = if y < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        
+        loop {
+            if x >= 0 && y >= 0 {
+                self.shade(PixelIdx::new(x as usize, y as usize), &paint, 1.0);
+            }
+            if x == x1 && y == y1 { break; }
+            let e2 = 2 * err;
+            if e2 >= dy { err += dy; x += sx; }
+            if e2 <= dx { err += dx; y += sy; }
+        }
+    }
+    
+    // anti-aliased line with round ends
+    // widths of 1 or less use xiaolin wu's algorithm, which is cheaper
+    pub fn line_aa(&mut self, from: Point, to: Point, width: f32, paint: impl Into<Paint>) {
+        let paint = paint.into();
+        let Some(o) = self.offset() else {
+            let mut path = Path::new();
+            path.move_to(from).line_to(to);
+            self.stroke_path(&path, &Stroke::new(width).with_cap(LineCap::Round), paint);
+            return;
+        };
+        if width <= 1.0 {
+            let shift = |p: Point| Point::new(p.x + o.x, p.y + o.y);
+            self.wu_line(shift(from), shift(to), &paint, width.max(0.0));
+            return;
+        }
+        let r = width / 2.0;
+        self.fill_coverage(
+            Point::new(from.x.min(to.x) - r, from.y.min(to.y) - r),
+            Point::new(from.x.max(to.x) + r, from.y.max(to.y) + r),
+            &paint,
+            |p| r - segment_distance(p, from, to) + 0.5,
+        );
+    }
+    
+    // connected line segments; closing the shape is up to the caller
+    pub fn polyline(&mut self, points: &[Point], width: f32, paint: impl Into<Paint>) {
+        if points.len() < 2 { return; }
+        if self.offset().is_none() {
+            let mut path = Path::new();
+            points.iter().for_each(|p| { path.line_to(*p); });
+            let stroke = Stroke::new(width).with_join(LineJoin::Round).with_cap(LineCap::Round);
+            self.stroke_path(&path, &stroke, paint);
+            return;
+        }
+        let r = width.max(1.0) / 2.0;
+        let (min, max) = bounds(points.iter().copied());
+        let intensity = width.min(1.0);
+        self.fill_coverage(
+            Point::new(min.x - r, min.y - r),
+            Point::new(max.x + r, max.y + r),
+            &paint.into(),
+            // distance to the whole polyline, so joints don't get drawn twice
+            |p| {
+                let d = points.windows(2).map(|s| segment_distance(p, s[0], s[1])).fold(f32::MAX, f32::min);
+                (r - d + 0.5).min(1.0) * intensity
+            },
+        );
+    }
+    
+    // from and to are in slice coordinates
+    fn wu_line(&mut self, from: Point, to: Point, paint: &Paint, intensity: f32) {
+        // work in pixel-center coordinates
+        let (mut x0, mut y0) = (from.x - 0.5, from.y - 0.5);
+        let (mut x1, mut y1) = (to.x - 0.5, to.y - 0.5);
+        
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+        }
+        
+        let gradient = if x1 - x0 == 0.0 { 1.0 } else { (y1 - y0) / (x1 - x0) };
+        let mut plot = |x: f32, y: f32, c: f32| {
+            let (x, y) = if steep { (y, x) } else { (x, y) };
+            if x < 0.0 || y < 0.0 { return; }
+            self.shade(PixelIdx::new(x as usize, y as usize), paint, c * intensity);
+        };
+        
+        // endpoints get partial coverage along the line, depending on how far they reach into their pixel
+        let x_start = x0.round();
+        let y_start = y0 + gradient * (x_start - x0);
+        let gap = 1.0 - (x0 + 0.5).fract();
+        plot(x_start, y_start.floor(), (1.0 - y_start.fract()) * gap);
+        plot(x_start, y_start.floor() + 1.0, y_start.fract() * gap);
+        
+        let x_end = x1.round();
+        let y_end = y1 + gradient * (x_end - x1);
+        let gap = (x1 + 0.5).fract();
+        plot(x_end, y_end.floor(), (1.0 - y_end.fract()) * gap);
+        plot(x_end, y_end.floor() + 1.0, y_end.fract() * gap);
+        
+        let mut y = y_start + gradient;
+        let mut x = x_start + 1.0;
+        while x < x_end {
+            plot(x, y.floor(), 1.0 - y.fract());
+            plot(x, y.floor() + 1.0, y.fract());
+            y += gradient;
+            x += 1.0;
+        }
+    }
+    
+    pub fn fill_circle(&mut self, center: Point, radius: f32, paint: impl Into<Paint>) {
+        self.fill_ellipse(center, radius, radius, paint);
+    }
+    
+    // ring of the given width, centered on the circle
+    pub fn stroke_circle(&mut self, center: Point, radius: f32, width: f32, paint: impl Into<Paint>) {
+        if self.offset().is_none() {
+            self.stroke_path(&Path::circle(center, radius), &Stroke::new(width), paint);
+            return;
+        }
+        let r = width / 2.0;
+        let reach = radius + r + 1.0;
+        self.fill_coverage(
+            Point::new(center.x - reach, center.y - reach),
+            Point::new(center.x + reach, center.y + reach),
+            &paint.into(),
+            |p| (r - (p.distance(center) - radius).abs() + 0.5).min(1.0).min(width),
+        );
+    }
+    
+    pub fn fill_ellipse(&mut self, center: Point, rx: f32, ry: f32, paint: impl Into<Paint>) {
+        if rx <= 0.0 || ry <= 0.0 { return; }
+        if self.offset().is_none() {
+            self.fill_path(&Path::ellipse(center, rx, ry), FillRule::NonZero, paint);
+            return;
+        }
+        self.fill_coverage(
+            Point::new(center.x - rx - 1.0, center.y - ry - 1.0),
+            Point::new(center.x + rx + 1.0, center.y + ry + 1.0),
+            &paint.into(),
+            |p| 0.5 - ellipse_distance(p, center, rx, ry),
+        );
+    }
+    
+    pub fn stroke_ellipse(&mut self, center: Point, rx: f32, ry: f32, width: f32, paint: impl Into<Paint>) {
+        if rx <= 0.0 || ry <= 0.0 { return; }
+        if self.offset().is_none() {
+            self.stroke_path(&Path::ellipse(center, rx, ry), &Stroke::new(width), paint);
+            return;
+        }
+        let r = width / 2.0;
+        self.fill_coverage(
+            Point::new(center.x - rx - r - 1.0, center.y - ry - r - 1.0),
+            Point::new(center.x + rx + r + 1.0, center.y + ry + r + 1.0),
+            &paint.into(),
+            |p| (r - ellipse_distance(p, center, rx, ry).abs() + 0.5).min(1.0).min(width),
+        );
+    }
+    
+    // part of a circle's outline from `start` to `end`, going clockwise, with round ends
+    pub fn arc(&mut self, center: Point, radius: f32, start: f32, end: f32, width: f32, paint: impl Into<Paint>) {
+        let sweep = (end - start).rem_euclid(TAU);
+        let sweep = if sweep == 0.0 && end != start { TAU } else { sweep };
+        if self.offset().is_none() {
+            let mut path = Path::new();
+            path.arc(center, radius, start, start + sweep);
+            self.stroke_path(&path, &Stroke::new(width).with_cap(LineCap::Round), paint);
+            return;
+        }
+        let ends = [start, start + sweep].map(|a| Point::new(center.x + radius * a.cos(), center.y + radius * a.sin()));
+        let r = width / 2.0;
+        let reach = radius + r + 1.0;
+        
+        self.fill_coverage(
+            Point::new(center.x - reach, center.y - reach),
+            Point::new(center.x + reach, center.y + reach),
+            &paint.into(),
+            |p| {
+                let angle = (p.y - center.y).atan2(p.x - center.x);
+                let d = if (angle - start).rem_euclid(TAU) <= sweep {
+                    (p.distance(center) - radius).abs()
+                } else {
+                    p.distance(ends[0]).min(p.distance(ends[1]))
+                };
+                (r - d + 0.5).min(1.0).min(width)
+            },
+        );
+    }
+    
+    // closed polygon, filled using the non-zero winding rule
+    pub fn fill_polygon(&mut self, points: &[Point], paint: impl Into<Paint>) {
+        if points.len() < 3 { return; }
+        self.fill_contours(&[points], FillRule::NonZero, &paint.into());
+    }
+    
+    pub fn fill_path(&mut self, path: &Path, rule: FillRule, paint: impl Into<Paint>) {
+        let contours = path.flatten(self.tolerance());
+        let contours: Vec<&[Point]> = contours.iter().map(|(points, _)| points.as_slice()).collect();
+        self.fill_contours(&contours, rule, &paint.into());
+    }
+    
+    // the outline is worked out before transforming, so the stroke scales along with the path
+    pub fn stroke_path(&mut self, path: &Path, stroke: &Stroke, paint: impl Into<Paint>) {
+        let polygons: Vec<Vec<Point>> = path.flatten(self.tolerance()).iter()
+            .flat_map(|(points, closed)| stroke.outline(points, *closed))
+            .collect();
+        let polygons: Vec<&[Point]> = polygons.iter().map(|p| p.as_slice()).collect();
+        self.fill_contours(&polygons, FillRule::NonZero, &paint.into());
+    }
+    
+    fn fill_contours(&mut self, contours: &[&[Point]], rule: FillRule, paint: &Paint) {
+        let Some((area, coverage)) = self.rasterize(contours, rule) else { return };
+        for (i, c) in coverage.into_iter().enumerate() {
+            let idx = PixelIdx::new(area.offset.x + i % area.size.width, area.offset.y + i / area.size.width);
+            self.shade(idx, paint, c);
+        }
+    }
+    
+    // coverage of every visible pixel the transformed contours could touch, row by row
+    // every contour is implicitly closed
+    fn rasterize(&self, contours: &[&[Point]], rule: FillRule) -> Option<(PixelIndexSlice,Vec<f32>)> {
+        let t = self.state.transform;
+        let (min, max) = bounds(contours.iter().flat_map(|c| c.iter().map(|p| t.apply(*p))));
+        let area = self.visible_bounds(min, max)?;
+        
+        let origin = Point::new(area.offset.x as f32, area.offset.y as f32);
+        let local = |p: Point| {
+            let p = t.apply(p);
+            Point::new(p.x - origin.x, p.y - origin.y)
+        };
+        
+        let mut raster = Rasterizer::new(area.size.width, area.size.height);
+        for points in contours {
+            for (i, p) in points.iter().enumerate() {
+                raster.line(local(*p), local(points[(i + 1) % points.len()]));
+            }
+        }
+        Some((area, raster.coverage().map(|c| rule.apply(c)).collect()))
+    }
+    
+    // outline of a closed polygon
+    pub fn stroke_polygon(&mut self, points: &[Point], width: f32, paint: impl Into<Paint>) {
+        if points.len() < 2 { return; }
+        let mut closed = points.to_vec();
+        closed.push(points[0]);
+        self.polyline(&closed, width, paint);
+    }
+    
+    // single line of text along a baseline starting at origin, with size in pixels per em
+    // returns how far along the baseline it went
+    pub fn fill_text(&mut self, font: &Font, text: &str, origin: Point, size: f32, paint: impl Into<Paint>) -> f32 {
+        let mut x = origin.x;
+        let positions: Vec<(GlyphId,Point)> = font.shape(text, size).into_iter().map(|g| {
+            let p = Point::new(x + g.offset.x, origin.y + g.offset.y);
+            x += g.advance;
+            (g.glyph, p)
+        }).collect();
+        self.fill_glyphs(font, positions, size, paint);
+        x - origin.x
+    }
+    
+    // glyphs already laid out, each at its pen position on the baseline
+    pub fn fill_glyphs(&mut self, font: &Font, glyphs: impl IntoIterator<Item = (GlyphId,Point)>, size: f32, paint: impl Into<Paint>) {
+        let paint = paint.into();
+        match self.offset() {
+            // rasterized glyphs are cached, but only work if they come out the same shape
+            Some(o) => for (glyph, p) in glyphs {
+                let pen = Point::new(p.x + o.x, (p.y + o.y).round());
+                let bitmap = font.glyph(glyph, size, pen.x - pen.x.floor());
+                self.fill_glyph(&bitmap, pen.x.floor() as isize, pen.y as isize, &paint);
+            },
+            None => {
+                let scale = size / font.units_per_em() as f32;
+                for (glyph, pen) in glyphs {
+                    let Some(outline) = font.outline(glyph) else { continue };
+                    let outline = outline.map(|p| Point::new(pen.x + p.x * scale, pen.y - p.y * scale));
+                    self.fill_path(&outline, FillRule::NonZero, &paint);
+                }
+            },
+        }
+    }
+    
+    // glyph with its pen position at (x,y), in slice coordinates
+    fn fill_glyph(&mut self, glyph: &Glyph, x: isize, y: isize, paint: &Paint) {
+        for row in 0..glyph.size.height {
+            let py = y + glyph.top + row as isize;
+            if py < 0 { continue; }
+            for col in 0..glyph.size.width {
+                let px = x + glyph.left + col as isize;
+                let c = glyph.coverage[row * glyph.size.width + col];
+                if px < 0 || c == 0 { continue; }
+                self.shade(PixelIdx::new(px as usize, py as usize), paint, c as f32 / 255.0);
+            }
+        }
+    }
+    
+    // image at its own size, with its top-left corner at `at`
+    // anything more than moving it by whole pixels resamples it
+    pub fn draw_image(&mut self, image: &Image, at: PixelIdx) {
+        let bounds = PixelIndexSlice {offset: at, size: image.size()};
+        let Some((dx, dy)) = self.pixel_offset() else {
+            let (min, max) = corners(bounds);
+            let outline = [min, Point::new(max.x, min.y), max, Point::new(min.x, max.y)];
+            let Some((area, coverage)) = self.rasterize(&[&outline], FillRule::NonZero) else { return };
+            for (i, c) in coverage.into_iter().enumerate() {
+                let idx = PixelIdx::new(area.offset.x + i % area.size.width, area.offset.y + i / area.size.width);
+                let p = self.state.inverse.apply(idx.into());
+                self.plot(idx, sample(image, Point::new(p.x - min.x, p.y - min.y)), c);
+            }
+            return;
+        };
+        
+        let Some(rect) = self.device_rect(bounds, dx, dy) else { return };
+        // where the image's top-left corner ends up
+        let (ox, oy) = (at.x as isize + dx, at.y as isize + dy);
+        if self.state.mask.is_some() {
+            for y in rect.offset.y..rect.offset.y + rect.size.height {
+                for x in rect.offset.x..rect.offset.x + rect.size.width {
+                    let src = image.row((y as isize - oy) as usize)[(x as isize - ox) as usize];
+                    self.plot(PixelIdx::new(x,y), src, 1.0);
+                }
+            }
+            return;
+        }
+        
+        let mode = self.state.blend_mode;
+        for y in rect.offset.y..rect.offset.y + rect.size.height {
+            if let Some((x0, row)) = self.surface.row_mut(y) {
+                let src = &image.row((y as isize - oy) as usize)[(rect.offset.x as isize - ox) as usize..][..rect.size.width];
+                let start = rect.offset.x - x0;
+                let dst = &mut row[start..start + rect.size.width];
+                simd::blend_span(dst, src, mode);
+            }
+        }
+    }
+}
+
+// largest distance allowed between a curve and the lines drawn in its place, in pixels
+const FLATTEN_TOLERANCE: f32 = 0.1;
+
+fn bounds(points: impl Iterator<Item = Point>) -> (Point,Point) {
+    points.fold(
+        (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN)),
+        |(min, max), p| (Point::new(min.x.min(p.x), min.y.min(p.y)), Point::new(max.x.max(p.x), max.y.max(p.y))),
+    )
+}
+
+// top-left and bottom-right corners of a rect
+fn corners(rect: PixelIndexSlice) -> (Point,Point) {
+    let min = Point::new(rect.offset.x as f32, rect.offset.y as f32);
+    (min, Point::new(min.x + rect.size.width as f32, min.y + rect.size.height as f32))
+}
+
+// bilinear sample of image at p, with the image's top-left corner at (0,0)
+// samples past the edges take the nearest edge pixel
+fn sample(image: &Image, p: Point) -> Pixel {
+    let Size {width, height} = image.size();
+    if width == 0 || height == 0 { return Pixel::TRANSPARENT; }
+    let (x, y) = (p.x - 0.5, p.y - 0.5);
+    let (x0, y0) = (x.floor(), y.floor());
+    let at = |x: f32, y: f32| image.row((y.max(0.0) as usize).min(height - 1))[(x.max(0.0) as usize).min(width - 1)];
+    let top    = at(x0, y0      ).lerp_premultiplied(at(x0 + 1.0, y0      ), x - x0);
+    let bottom = at(x0, y0 + 1.0).lerp_premultiplied(at(x0 + 1.0, y0 + 1.0), x - x0);
+    top.lerp_premultiplied(bottom, y - y0)
+}
+
+fn segment_distance(p: Point, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let len2 = dx * dx + dy * dy;
+    if len2 == 0.0 { return p.distance(a); }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / len2).clamp(0.0, 1.0);
+    p.distance(a.lerp(b, t))
+}
+
+// approximate signed distance to an axis-aligned ellipse, negative inside
+fn ellipse_distance(p: Point, center: Point, rx: f32, ry: f32) -> f32 {
+    let (dx, dy) = (p.x - center.x, p.y - center.y);
+    let f = (dx * dx) / (rx * rx) + (dy * dy) / (ry * ry) - 1.0;
+    let gx = 2.0 * dx / (rx * rx);
+    let gy = 2.0 * dy / (ry * ry);
+    let g = gx.hypot(gy);
+    if g == 0.0 { -rx.min(ry) } else { f / g }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const RED: Pixel = Pixel::rgb(0xFF, 0, 0);
+    
+    // what f paints on a transparent image
+    fn paint(width: usize, height: usize, f: impl FnOnce(&mut Painter)) -> Image {
+        let mut image = Image::new(Size {width, height});
+        unsafe { image.with_slice(|s| f(&mut Painter::new(s))) };
+        image
+    }
+    
+    // a string per row: # where it's opaque, . where it's clear, and + for anything between
+    fn coverage(image: &Image) -> Vec<String> {
+        let Size {width, height} = image.size();
+        (0..height).map(|y| image.row(y)[..width].iter().map(|p| match p.a {
+            0x00 => '.',
+            0xFF => '#',
+            _ => '+',
+        }).collect()).collect()
+    }
+    
+    fn alpha(image: &Image, x: usize, y: usize) -> u8 { image.get_pixel(PixelIdx::new(x, y)).a }
+    
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> PixelIndexSlice {
+        PixelIndexSlice {offset: PixelIdx::new(x, y), size: Size {width, height}}
+    }
+    
+    #[test]
+    fn rects() {
+        let image = paint(6, 4, |p| p.fill_rect(rect(1, 1, 3, 2), Pixel::WHITE));
+        assert_eq!(coverage(&image), ["......", ".###..", ".###..", "......"]);
+        let image = paint(6, 4, |p| p.stroke_rect(rect(0, 0, 6, 4), 1, Pixel::WHITE));
+        assert_eq!(coverage(&image), ["######", "#....#", "#....#", "######"]);
+        // whatever hangs off of the surface is cut off
+        let image = paint(6, 4, |p| p.fill_rect(rect(4, 2, 5, 5), Pixel::WHITE));
+        assert_eq!(coverage(&image), ["......", "......", "....##", "....##"]);
+        // the color comes through exactly
+        let image = paint(2, 1, |p| p.fill_rect(rect(0, 0, 1, 1), Pixel::rgba(0x12, 0x34, 0x56, 0x78)));
+        let p = image.get_pixel(PixelIdx::new(0, 0));
+        assert_eq!([p.r, p.g, p.b, p.a], [0x12, 0x34, 0x56, 0x78]);
+    }
+    
+    #[test]
+    fn lines() {
+        let image = paint(6, 3, |p| p.line(PixelIdx::new(0, 0), PixelIdx::new(5, 2), Pixel::WHITE));
+        assert_eq!(coverage(&image), ["##....", "..##..", "....##"]);
+        let image = paint(3, 3, |p| p.line(PixelIdx::new(1, 2), PixelIdx::new(1, 0), Pixel::WHITE));
+        assert_eq!(coverage(&image), [".#.", ".#.", ".#."]);
+        // an anti-aliased line along pixel centers covers just the pixels it goes through
+        let image = paint(5, 3, |p| p.line_aa(Point::new(0.5, 1.5), Point::new(4.5, 1.5), 1.0, Pixel::WHITE));
+        assert_eq!(coverage(&image)[0], ".....");
+        assert_eq!(coverage(&image)[2], ".....");
+        assert_eq!(&coverage(&image)[1][1..4], "###");
+    }
+    
+    #[test]
+    fn shapes() {
+        // pixels the edge cuts in half are half covered
+        let image = paint(4, 4, |p| p.fill_polygon(&[Point::new(0.0, 0.0), Point::new(4.0, 0.0), Point::new(0.0, 4.0)], Pixel::WHITE));
+        assert_eq!(coverage(&image), ["###+", "##+.", "#+..", "+..."]);
+        assert_eq!(alpha(&image, 3, 0), 0x80);
+        assert_eq!(alpha(&image, 1, 2), 0x80);
+        
+        // circles are symmetric, and cover about as much as their area
+        let image = paint(10, 10, |p| p.fill_circle(Point::new(5.0, 5.0), 3.0, Pixel::WHITE));
+        for y in 0..10 {
+            for x in 0..10 {
+                assert_eq!(alpha(&image, x, y), alpha(&image, 9 - x, y), "{x},{y}");
+                assert_eq!(alpha(&image, x, y), alpha(&image, y, x), "{x},{y}");
+            }
+        }
+        let row = |y: usize| image.row(y)[..10].iter().map(|p| p.a).collect::<Vec<_>>();
+        assert_eq!(row(0), [0; 10]);
+        assert_eq!(row(2), [0, 0, 1, 149, 253, 253, 149, 1, 0, 0]);
+        assert_eq!(row(4), [0, 1, 253, 255, 255, 255, 255, 253, 1, 0]);
+        let area: f32 = image.pixels().iter().map(|p| p.a as f32 / 255.0).sum();
+        // to within half a pixel, since the distance to the edge is only approximate
+        assert!((area - 9.0 * std::f32::consts::PI).abs() < 0.5, "{area}");
+    }
+    
+    #[test]
+    fn transforms() {
+        let image = paint(4, 3, |p| {
+            p.push();
+            p.translate(2.0, 1.0);
+            p.fill_rect(rect(0, 0, 1, 1), Pixel::WHITE);
+            p.pop();
+            // popping more than was pushed does nothing
+            p.pop();
+            p.fill_rect(rect(0, 0, 1, 1), Pixel::WHITE);
+        });
+        assert_eq!(coverage(&image), ["#...", "..#.", "...."]);
+        
+        let image = paint(4, 3, |p| p.with_pushed(|p| {
+            p.scale(2.0, 2.0);
+            p.fill_rect(rect(1, 0, 1, 1), Pixel::WHITE);
+        }));
+        assert_eq!(coverage(&image), ["..##", "..##", "...."]);
+        
+        // moving by part of a pixel splits edges between the pixels on either side
+        let image = paint(4, 1, |p| {
+            p.translate(0.5, 0.0);
+            p.fill_rect(rect(0, 0, 2, 1), Pixel::WHITE);
+        });
+        assert_eq!(image.pixels().iter().map(|p| p.a).collect::<Vec<_>>(), [0x80, 0xFF, 0x80, 0x00]);
+    }
+    
+    #[test]
+    fn clips() {
+        let image = paint(6, 4, |p| {
+            p.push();
+            // clips only ever shrink
+            p.clip_rect(rect(2, 0, 3, 4));
+            p.clip_rect(rect(0, 1, 4, 2));
+            p.clear(Pixel::WHITE);
+            p.pop();
+            p.fill_rect(rect(0, 0, 1, 1), RED);
+        });
+        assert_eq!(coverage(&image), ["#.....", "..##..", "..##..", "......"]);
+        
+        // clips are in the coordinates of the transform at the time
+        let image = paint(4, 4, |p| {
+            p.translate(1.0, 1.0);
+            p.clip_rect(rect(0, 0, 2, 2));
+            p.translate(-1.0, -1.0);
+            p.fill_rect(rect(0, 0, 4, 4), Pixel::WHITE);
+        });
+        assert_eq!(coverage(&image), ["....", ".##.", ".##.", "...."]);
+    }
+    
+    #[test]
+    fn masks() {
+        let image = paint(12, 8, |p| {
+            p.clip_rounded_rect(Point::new(0.0, 0.0), Point::new(12.0, 8.0), 3.0);
+            p.clear(Pixel::WHITE);
+        });
+        for (x, y) in [(0, 0), (11, 0), (0, 7), (11, 7)] {
+            assert!(alpha(&image, x, y) < 0x20, "{x},{y}");
+        }
+        for (x, y) in [(3, 0), (8, 7), (0, 3), (11, 4), (6, 4)] {
+            assert_eq!(alpha(&image, x, y), 0xFF, "{x},{y}");
+        }
+        
+        // masks multiply, and go away with a pop
+        let image = paint(3, 2, |p| {
+            p.push();
+            p.clip_path(&Path::rect(Point::new(0.0, 0.0), Point::new(3.0, 0.5)), FillRule::NonZero);
+            p.clip_path(&Path::rect(Point::new(0.0, 0.0), Point::new(0.5, 2.0)), FillRule::NonZero);
+            p.fill_rect(rect(0, 0, 3, 2), Pixel::WHITE);
+            p.pop();
+            p.fill_rect(rect(2, 1, 1, 1), Pixel::WHITE);
+        });
+        assert_eq!(image.pixels().iter().map(|p| p.a).collect::<Vec<_>>(), [0x40, 0x00, 0x00, 0x00, 0x00, 0xFF]);
+        
+        // images are masked too
+        let white = Image::filled(Size {width: 2, height: 1}, Pixel::WHITE);
+        let image = paint(2, 1, |p| {
+            p.clip_path(&Path::rect(Point::new(0.5, 0.0), Point::new(2.0, 1.0)), FillRule::NonZero);
+            p.draw_image(&white, PixelIdx::new(0, 0));
+        });
+        assert_eq!(image.pixels().iter().map(|p| p.a).collect::<Vec<_>>(), [0x80, 0xFF]);
+    }
+}
@@ -0,0 +1,271 @@
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use crate::{
+    image::Image,
+    Pixel,
+};
+
+use super::Point;
+
+// what happens past the ends of a gradient, or the edges of a pattern
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Spread {
+    #[default]
+    Pad,     // keep the color at the edge
+    Repeat,  // start over from the other end
+    Reflect, // go back the way it came
+} impl Spread {
+    // map t onto 0..=1
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Spread::Pad => t.clamp(0.0, 1.0),
+            Spread::Repeat => t.rem_euclid(1.0),
+            Spread::Reflect => {
+                let t = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            },
+        }
+    }
+    
+    // map an index onto 0..len
+    fn apply_index(self, i: isize, len: usize) -> usize {
+        let len = len as isize;
+        let i = match self {
+            Spread::Pad => i.clamp(0, len - 1),
+            Spread::Repeat => i.rem_euclid(len),
+            Spread::Reflect => {
+                let i = i.rem_euclid(2 * len);
+                if i >= len { 2 * len - 1 - i } else { i }
+            },
+        };
+        i as usize
+    }
+}
+
+// colors along 0..=1, interpolated between stops
+// stops at the same offset make a hard edge, in the order they were added
+#[derive(Clone,Debug,Default)]
+pub struct Gradient {
+    stops: Vec<(f32,Pixel)>,
+    spread: Spread,
+} impl Gradient {
+    pub fn new() -> Self { Self::default() }
+    
+    // evenly spaced stops
+    pub fn even(colors: &[Pixel]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        colors.iter().enumerate().fold(Self::new(), |g, (i, c)| g.with_stop(i as f32 / last, *c))
+    }
+    
+    pub fn with_stop(mut self, offset: f32, color: Pixel) -> Self { self.add_stop(offset, color); self }
+    pub fn with_spread(mut self, spread: Spread) -> Self { self.spread = spread; self }
+    
+    pub fn add_stop(&mut self, offset: f32, color: Pixel) {
+        let offset = offset.clamp(0.0, 1.0);
+        let i = self.stops.partition_point(|s| s.0 <= offset);
+        self.stops.insert(i, (offset, color));
+    }
+    
+    pub fn stops(&self) -> &[(f32,Pixel)] { &self.stops }
+    pub fn spread(&self) -> Spread { self.spread }
+    
+    pub fn color_at(&self, t: f32) -> Pixel {
+        let t = self.spread.apply(t);
+        let i = self.stops.partition_point(|s| s.0 <= t);
+        match (i.checked_sub(1).map(|i| self.stops[i]), self.stops.get(i).copied()) {
+            (None, None) => Pixel::TRANSPARENT,
+            (Some((_, c)), None) | (None, Some((_, c))) => c,
+            (Some((a, from)), Some((b, to))) => from.lerp_premultiplied(to, (t - a) / (b - a)),
+        }
+    }
+    
+    fn is_opaque(&self) -> bool {
+        !self.stops.is_empty() && self.stops.iter().all(|s| s.1.a == 0xFF)
+    }
+}
+
+// anything that can be used to fill or stroke a shape
+// positions are in the same coordinates as the shape being drawn
+// angles are in radians, clockwise from the positive x axis, like everywhere else
+#[derive(Clone,Debug)]
+pub enum Paint {
+    Solid(Pixel),
+    // t goes from 0 at start to 1 at end, constant along lines perpendicular to it
+    Linear {start: Point, end: Point, gradient: Gradient},
+    // t goes from 0 at focal to 1 at the circle; focal is kept inside the circle
+    Radial {center: Point, radius: f32, focal: Point, gradient: Gradient},
+    // t goes from 0 to 1 clockwise around center, starting at angle
+    Conic {center: Point, angle: f32, gradient: Gradient},
+    // image tiled from origin, one image pixel per surface pixel
+    Pattern {image: Arc<Image>, origin: Point, spread: Spread},
+} impl Paint {
+    pub fn linear(start: Point, end: Point, gradient: Gradient) -> Self {
+        Paint::Linear {start, end, gradient}
+    }
+    
+    pub fn radial(center: Point, radius: f32, gradient: Gradient) -> Self {
+        Paint::Radial {center, radius, focal: center, gradient}
+    }
+    
+    pub fn focal_radial(center: Point, radius: f32, focal: Point, gradient: Gradient) -> Self {
+        Paint::Radial {center, radius, focal, gradient}
+    }
+    
+    pub fn conic(center: Point, angle: f32, gradient: Gradient) -> Self {
+        Paint::Conic {center, angle, gradient}
+    }
+    
+    pub fn pattern(image: Arc<Image>, origin: Point) -> Self {
+        Paint::Pattern {image, origin, spread: Spread::Repeat}
+    }
+    
+    pub fn as_solid(&self) -> Option<Pixel> {
+        if let Paint::Solid(color) = self { Some(*color) } else { None }
+    }
+    
+    // whether every pixel painted is fully opaque
+    pub fn is_opaque(&self) -> bool {
+        match self {
+            Paint::Solid(color) => color.a == 0xFF,
+            Paint::Linear {gradient, ..} | Paint::Radial {gradient, ..} | Paint::Conic {gradient, ..} => gradient.is_opaque(),
+            Paint::Pattern {image, ..} => !image.pixels().is_empty() && image.pixels().iter().all(|p| p.a == 0xFF),
+        }
+    }
+    
+    // color at a point, usually the center of the pixel being drawn
+    pub fn color_at(&self, p: Point) -> Pixel {
+        match self {
+            Paint::Solid(color) => *color,
+            Paint::Linear {start, end, gradient} => {
+                let (dx, dy) = (end.x - start.x, end.y - start.y);
+                let len2 = dx * dx + dy * dy;
+                let t = if len2 == 0.0 { 0.0 } else { ((p.x - start.x) * dx + (p.y - start.y) * dy) / len2 };
+                gradient.color_at(t)
+            },
+            Paint::Radial {center, radius, focal, gradient} => {
+                gradient.color_at(radial_t(p, *center, *radius, *focal))
+            },
+            Paint::Conic {center, angle, gradient} => {
+                let a = (p.y - center.y).atan2(p.x - center.x);
+                gradient.color_at((a - angle).rem_euclid(TAU) / TAU)
+            },
+            Paint::Pattern {image, origin, spread} => {
+                let size = image.size();
+                if size.width == 0 || size.height == 0 { return Pixel::TRANSPARENT; }
+                let x = spread.apply_index((p.x - origin.x).floor() as isize, size.width);
+                let y = spread.apply_index((p.y - origin.y).floor() as isize, size.height);
+                image.row(y)[x]
+            },
+        }
+    }
+} impl Default for Paint {
+    fn default() -> Self { Paint::Solid(Pixel::TRANSPARENT) }
+} impl From<Pixel> for Paint {
+    fn from(color: Pixel) -> Self { Paint::Solid(color) }
+} impl From<&Paint> for Paint {
+    fn from(paint: &Paint) -> Self { paint.clone() }
+}
+
+// how far p is along the ray from focal to the circle
+fn radial_t(p: Point, center: Point, radius: f32, focal: Point) -> f32 {
+    if radius <= 0.0 { return 1.0; }
+    
+    // pull focal just inside the circle, so every ray from it hits the circle exactly once
+    let (mut ex, mut ey) = (focal.x - center.x, focal.y - center.y);
+    let max = radius * 0.999;
+    let len = ex.hypot(ey);
+    if len > max {
+        ex *= max / len;
+        ey *= max / len;
+    }
+    let (dx, dy) = (p.x - center.x - ex, p.y - center.y - ey);
+    
+    // solve |e + s*d| = radius for s > 0; p is then 1/s of the way there
+    let a = dx * dx + dy * dy;
+    if a == 0.0 { return 0.0; }
+    let b = ex * dx + ey * dy;
+    let c = ex * ex + ey * ey - radius * radius;
+    let s = (-b + (b * b - a * c).sqrt()) / a;
+    1.0 / s
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        draw::Painter,
+        PixelIdx,PixelIndexSlice,Size,
+    };
+    
+    // one row of width pixels filled with paint, as [r,g,b,a]
+    fn row(width: usize, paint: Paint) -> Vec<[u8;4]> {
+        let mut image = Image::new(Size {width, height: 1});
+        let rect = PixelIndexSlice {offset: PixelIdx::new(0, 0), size: Size {width, height: 1}};
+        unsafe { image.with_slice(|s| Painter::new(s).fill_rect(rect, paint)) };
+        image.row(0)[..width].iter().map(|p| [p.r, p.g, p.b, p.a]).collect()
+    }
+    
+    // the red channel of a black to rgb(80,00,00) gradient spanning pixels 8..=16, over 32 pixels
+    // pixel centers land on t = (x - 8) / 8 exactly, so every value is a multiple of 0x10
+    fn linear(spread: Spread) -> Vec<u8> {
+        let gradient = Gradient::even(&[Pixel::BLACK, Pixel::rgb(0x80, 0, 0)]).with_spread(spread);
+        let pixels = row(32, Paint::linear(Point::new(8.5, 0.0), Point::new(16.5, 0.0), gradient));
+        assert!(pixels.iter().all(|p| p[1] == 0 && p[2] == 0 && p[3] == 0xFF));
+        pixels.iter().map(|p| p[0]).collect()
+    }
+    
+    #[test]
+    fn gradient_spreads() {
+        let ramp: Vec<u8> = (0..8).map(|i| i * 0x10).collect();
+        let reverse: Vec<u8> = (1..=8).rev().map(|i| i * 0x10).collect();
+        
+        let pad = linear(Spread::Pad);
+        assert_eq!(pad[..8], [0; 8]);
+        assert_eq!(pad[8..16], ramp[..]);
+        assert_eq!(pad[16..], [0x80; 16]);
+        
+        let repeat = linear(Spread::Repeat);
+        for i in 0..4 { assert_eq!(repeat[i * 8..i * 8 + 8], ramp[..]); }
+        
+        // back down from the end, and back up from the start
+        let reflect = linear(Spread::Reflect);
+        assert_eq!(reflect[..8], reverse[..]);
+        assert_eq!(reflect[8..16], ramp[..]);
+        assert_eq!(reflect[16..24], reverse[..]);
+        assert_eq!(reflect[24..], ramp[..]);
+    }
+    
+    #[test]
+    fn gradient_stops() {
+        // a hard edge takes the later stop at its offset
+        let gradient = Gradient::new()
+            .with_stop(0.0, Pixel::BLACK)
+            .with_stop(0.5, Pixel::BLACK)
+            .with_stop(0.5, Pixel::WHITE)
+            .with_stop(1.0, Pixel::WHITE);
+        let pixels = row(4, Paint::linear(Point::new(0.5, 0.0), Point::new(2.5, 0.0), gradient));
+        assert_eq!(pixels, [[0, 0, 0, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF], [0xFF, 0xFF, 0xFF, 0xFF]]);
+        
+        // transparent stops fade out without darkening what they fade from
+        let gradient = Gradient::even(&[Pixel::TRANSPARENT, Pixel::rgb(0, 0, 0xFF)]);
+        let pixels = row(3, Paint::linear(Point::new(0.5, 0.0), Point::new(2.5, 0.0), gradient));
+        assert_eq!(pixels, [[0, 0, 0, 0], [0, 0, 0xFF, 0x80], [0, 0, 0xFF, 0xFF]]);
+        
+        assert_eq!(Gradient::new().color_at(0.5).a, 0);
+    }
+    
+    #[test]
+    fn pattern_spreads() {
+        let mut image = Image::new(Size {width: 3, height: 1});
+        for x in 0..3 { image.set_pixel(PixelIdx::new(x, 0), Pixel::rgb(x as u8 + 1, 0, 0)); }
+        let image = Arc::new(image);
+        let reds = |spread| -> Vec<u8> {
+            let paint = Paint::Pattern {image: image.clone(), origin: Point::new(4.0, 0.0), spread};
+            row(12, paint).iter().map(|p| p[0]).collect()
+        };
+        assert_eq!(reds(Spread::Pad), [1, 1, 1, 1, 1, 2, 3, 3, 3, 3, 3, 3]);
+        assert_eq!(reds(Spread::Repeat), [3, 1, 2, 3, 1, 2, 3, 1, 2, 3, 1, 2]);
+        assert_eq!(reds(Spread::Reflect), [3, 3, 2, 1, 1, 2, 3, 3, 2, 1, 1, 2]);
+    }
+}
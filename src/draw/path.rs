@@ -0,0 +1,225 @@
+use std::f32::consts::FRAC_PI_2;
+
+use super::Point;
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum PathCmd {
+    MoveTo(Point),
+    LineTo(Point),
+    QuadTo(Point,Point),        // control, end
+    CubicTo(Point,Point,Point), // control, control, end
+    Close,
+}
+
+// which areas enclosed by a path count as inside
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum FillRule {
+    #[default]
+    NonZero,
+    EvenOdd,
+} impl FillRule {
+    // winding-weighted coverage from the rasterizer -> actual coverage
+    pub fn apply(self, coverage: f32) -> f32 {
+        match self {
+            FillRule::NonZero => coverage.abs().min(1.0),
+            FillRule::EvenOdd => {
+                let c = coverage.abs() % 2.0;
+                if c > 1.0 { 2.0 - c } else { c }
+            },
+        }
+    }
+}
+
+// a sequence of contours made of lines and bezier curves
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Path {
+    cmds: Vec<PathCmd>,
+    start: Point,   // start of the current contour
+    current: Option<Point>,
+} impl Path {
+    pub fn new() -> Self { Self::default() }
+    
+    pub fn commands(&self) -> &[PathCmd] { &self.cmds }
+    pub fn is_empty(&self) -> bool { self.cmds.is_empty() }
+    
+    pub fn move_to(&mut self, p: Point) -> &mut Self {
+        self.cmds.push(PathCmd::MoveTo(p));
+        self.start = p;
+        self.current = Some(p);
+        self
+    }
+    
+    // drawing commands without a current point start a new contour at their first point
+    fn ensure_start(&mut self, p: Point) {
+        if self.current.is_none() { self.move_to(p); }
+    }
+    
+    pub fn line_to(&mut self, p: Point) -> &mut Self {
+        self.ensure_start(p);
+        self.cmds.push(PathCmd::LineTo(p));
+        self.current = Some(p);
+        self
+    }
+    
+    pub fn quad_to(&mut self, control: Point, p: Point) -> &mut Self {
+        self.ensure_start(control);
+        self.cmds.push(PathCmd::QuadTo(control, p));
+        self.current = Some(p);
+        self
+    }
+    
+    pub fn cubic_to(&mut self, c1: Point, c2: Point, p: Point) -> &mut Self {
+        self.ensure_start(c1);
+        self.cmds.push(PathCmd::CubicTo(c1, c2, p));
+        self.current = Some(p);
+        self
+    }
+    
+    // circular arc from angle `start` to `end`; clockwise on screen if end > start
+    // connects to the current point with a line, like the html canvas
+    pub fn arc(&mut self, center: Point, radius: f32, start: f32, end: f32) -> &mut Self {
+        let at = |a: f32| Point::new(center.x + radius * a.cos(), center.y + radius * a.sin());
+        
+        match self.current {
+            None => { self.move_to(at(start)); },
+            Some(_) => { self.line_to(at(start)); },
+        }
+        
+        // one cubic per quarter turn at most keeps the error well below a pixel
+        let sweep = end - start;
+        let pieces = (sweep.abs() / FRAC_PI_2).ceil().max(1.0) as usize;
+        let step = sweep / pieces as f32;
+        let k = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+        
+        for i in 0..pieces {
+            let a0 = start + step * i as f32;
+            let a1 = a0 + step;
+            let (p0, p1) = (at(a0), at(a1));
+            self.cubic_to(
+                Point::new(p0.x - k * a0.sin(), p0.y + k * a0.cos()),
+                Point::new(p1.x + k * a1.sin(), p1.y - k * a1.cos()),
+                p1,
+            );
+        }
+        self
+    }
+    
+    pub fn close(&mut self) -> &mut Self {
+        if self.current.is_some() {
+            self.cmds.push(PathCmd::Close);
+            self.current = Some(self.start);
+        }
+        self
+    }
+    
+    pub fn rect(min: Point, max: Point) -> Self {
+        let mut path = Path::new();
+        path.move_to(min)
+            .line_to(Point::new(max.x, min.y))
+            .line_to(max)
+            .line_to(Point::new(min.x, max.y))
+            .close();
+        path
+    }
+    
+    pub fn rounded_rect(min: Point, max: Point, radius: f32) -> Self {
+        let r = radius.min((max.x - min.x) / 2.0).min((max.y - min.y) / 2.0).max(0.0);
+        if r == 0.0 { return Self::rect(min, max); }
+        
+        let mut path = Path::new();
+        path.arc(Point::new(max.x - r, min.y + r), r, -FRAC_PI_2, 0.0)
+            .arc(Point::new(max.x - r, max.y - r), r, 0.0, FRAC_PI_2)
+            .arc(Point::new(min.x + r, max.y - r), r, FRAC_PI_2, 2.0 * FRAC_PI_2)
+            .arc(Point::new(min.x + r, min.y + r), r, 2.0 * FRAC_PI_2, 3.0 * FRAC_PI_2)
+            .close();
+        path
+    }
+    
+    pub fn circle(center: Point, radius: f32) -> Self {
+        let mut path = Path::new();
+        path.arc(center, radius, 0.0, 4.0 * FRAC_PI_2).close();
+        path
+    }
+    
+    // axis-aligned ellipse with radii rx and ry
+    pub fn ellipse(center: Point, rx: f32, ry: f32) -> Self {
+        Self::circle(Point::default(), 1.0).map(|p| Point::new(center.x + p.x * rx, center.y + p.y * ry))
+    }
+    
+    // apply f to every point, e.g. to transform the path
+    pub fn map(&self, f: impl Fn(Point) -> Point) -> Self {
+        let cmds = self.cmds.iter().map(|cmd| match *cmd {
+            PathCmd::MoveTo(p)          => PathCmd::MoveTo(f(p)),
+            PathCmd::LineTo(p)          => PathCmd::LineTo(f(p)),
+            PathCmd::QuadTo(c, p)       => PathCmd::QuadTo(f(c), f(p)),
+            PathCmd::CubicTo(c1, c2, p) => PathCmd::CubicTo(f(c1), f(c2), f(p)),
+            PathCmd::Close              => PathCmd::Close,
+        }).collect();
+        Self {
+            cmds,
+            start: f(self.start),
+            current: self.current.map(f),
+        }
+    }
+    
+    // curves replaced by line segments that stray from them by no more than `tolerance`
+    // returns each contour's points, and whether it was closed
+    pub fn flatten(&self, tolerance: f32) -> Vec<(Vec<Point>,bool)> {
+        let mut contours: Vec<(Vec<Point>,bool)> = Vec::new();
+        let mut points: Vec<Point> = Vec::new();
+        let mut last = Point::default();
+        
+        // a lone point is just the start of a contour that never got drawn
+        let finish = |points: &mut Vec<Point>, closed: bool, contours: &mut Vec<(Vec<Point>,bool)>| {
+            if points.len() > 1 {
+                contours.push((std::mem::take(points), closed));
+            }
+            points.clear();
+        };
+        
+        for cmd in &self.cmds {
+            match *cmd {
+                PathCmd::MoveTo(p) => {
+                    finish(&mut points, false, &mut contours);
+                    points.push(p);
+                    last = p;
+                },
+                PathCmd::LineTo(p) => {
+                    points.push(p);
+                    last = p;
+                },
+                PathCmd::QuadTo(c, p) => {
+                    let dd = Point::new(last.x - 2.0 * c.x + p.x, last.y - 2.0 * c.y + p.y);
+                    let n = ((dd.x.hypot(dd.y) / (8.0 * tolerance)).sqrt().ceil() as usize).clamp(1, 100);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        points.push(last.lerp(c, t).lerp(c.lerp(p, t), t));
+                    }
+                    last = p;
+                },
+                PathCmd::CubicTo(c1, c2, p) => {
+                    let dd1 = Point::new(last.x - 2.0 * c1.x + c2.x, last.y - 2.0 * c1.y + c2.y);
+                    let dd2 = Point::new(c1.x - 2.0 * c2.x + p.x, c1.y - 2.0 * c2.y + p.y);
+                    let dd = dd1.x.hypot(dd1.y).max(dd2.x.hypot(dd2.y));
+                    let n = ((0.75 * dd / tolerance).sqrt().ceil() as usize).clamp(1, 100);
+                    for i in 1..=n {
+                        let t = i as f32 / n as f32;
+                        let (a, b, c) = (last.lerp(c1, t), c1.lerp(c2, t), c2.lerp(p, t));
+                        let (d, e) = (a.lerp(b, t), b.lerp(c, t));
+                        points.push(d.lerp(e, t));
+                    }
+                    last = p;
+                },
+                PathCmd::Close => {
+                    let start = points.first().copied().unwrap_or(last);
+                    finish(&mut points, true, &mut contours);
+                    // anything drawn after a close continues from the contour's start
+                    points.push(start);
+                    last = start;
+                },
+            }
+        }
+        finish(&mut points, false, &mut contours);
+        contours
+    }
+}
@@ -0,0 +1,120 @@
+use super::Point;
+
+// coverage rasterizer for polygons, based on accumulating signed area per pixel
+// (the same approach as font-rs and stb_truetype's v2 rasterizer)
+// edges are added one at a time in any order; once all edges of all contours are in, 
+//  coverage() yields the exact fraction of each pixel covered, weighted by winding number
+pub struct Rasterizer {
+    width: usize,
+    height: usize,
+    acc: Vec<f32>,
+} impl Rasterizer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            // the extra element absorbs contributions right of the last pixel of the last row
+            acc: vec![0.0; width * height + 1],
+        }
+    }
+    
+    pub fn width (&self) -> usize { self.width  }
+    pub fn height(&self) -> usize { self.height }
+    
+    pub fn clear(&mut self) {
+        self.acc.iter_mut().for_each(|a| *a = 0.0);
+    }
+    
+    // add an edge; contours must be closed for the result to make sense
+    // parts outside of the canvas are handled correctly (anything left of it still counts)
+    pub fn line(&mut self, p0: Point, p1: Point) {
+        let w = self.width as f32;
+        
+        // split at x = 0 and x = width, then flatten the outside pieces onto those borders
+        let mut cuts = [0.0, 1.0, 1.0, 1.0];
+        let mut n = 1;
+        for border in [0.0, w] {
+            let t = (border - p0.x) / (p1.x - p0.x);
+            if t > 0.0 && t < 1.0 {
+                cuts[n] = t;
+                n += 1;
+            }
+        }
+        cuts[n] = 1.0;
+        cuts[..=n].sort_by(|a, b| a.total_cmp(b));
+        
+        for i in 0..n {
+            let a = p0.lerp(p1, cuts[i]);
+            let b = p0.lerp(p1, cuts[i + 1]);
+            let clamp = |p: Point| Point::new(p.x.clamp(0.0, w), p.y);
+            self.clipped_line(clamp(a), clamp(b));
+        }
+    }
+    
+    // x must be within [0,width]
+    fn clipped_line(&mut self, p0: Point, p1: Point) {
+        if p0.y == p1.y { return; }
+        let (dir, p0, p1) = if p0.y < p1.y { (1.0, p0, p1) } else { (-1.0, p1, p0) };
+        if p1.y <= 0.0 || p0.y >= self.height as f32 { return; }
+        
+        let dxdy = (p1.x - p0.x) / (p1.y - p0.y);
+        let mut x = p0.x;
+        if p0.y < 0.0 { x -= p0.y * dxdy; }
+        
+        let y_start = p0.y.max(0.0) as usize;
+        let y_end = (p1.y.ceil() as usize).min(self.height);
+        let last = self.acc.len() - 1;
+        
+        for y in y_start..y_end {
+            let row = y * self.width;
+            let dy = ((y + 1) as f32).min(p1.y) - (y as f32).max(p0.y);
+            let x_next = x + dxdy * dy;
+            let d = dy * dir;
+            
+            let (x0, x1) = if x < x_next { (x, x_next) } else { (x_next, x) };
+            let x0_floor = x0.floor();
+            let x0i = x0_floor as usize;
+            let x1_ceil = x1.ceil();
+            let x1i = x1_ceil as usize;
+            
+            let mut add = |i: usize, v: f32| self.acc[(row + i).min(last)] += v;
+            
+            if x1i <= x0i + 1 {
+                // edge stays within one pixel column on this row
+                let xmf = 0.5 * (x + x_next) - x0_floor;
+                add(x0i, d - d * xmf);
+                add(x0i + 1, d * xmf);
+            } else {
+                let s = (x1 - x0).recip();
+                let x0f = x0 - x0_floor;
+                let a0 = 0.5 * s * (1.0 - x0f) * (1.0 - x0f);
+                let x1f = x1 - x1_ceil + 1.0;
+                let am = 0.5 * s * x1f * x1f;
+                
+                add(x0i, d * a0);
+                if x1i == x0i + 2 {
+                    add(x0i + 1, d * (1.0 - a0 - am));
+                } else {
+                    let a1 = s * (1.5 - x0f);
+                    add(x0i + 1, d * (a1 - a0));
+                    for xi in x0i + 2..x1i - 1 {
+                        add(xi, d * s);
+                    }
+                    let a2 = a1 + (x1i - x0i - 3) as f32 * s;
+                    add(x1i - 1, d * (1.0 - a2 - am));
+                }
+                add(x1i, d * am);
+            }
+            x = x_next;
+        }
+    }
+    
+    // signed, winding-weighted coverage of each pixel, row by row
+    // e.g. 1.0 is fully inside one clockwise contour, -2.0 fully inside two counter-clockwise ones
+    pub fn coverage(&self) -> impl Iterator<Item = f32> + '_ {
+        self.acc[..self.width * self.height].iter().scan(0.0, |sum, a| {
+            *sum += a;
+            Some(*sum)
+        })
+    }
+}
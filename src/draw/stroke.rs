@@ -0,0 +1,325 @@
+use std::f32::consts::PI;
+
+use super::Point;
+
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+#[derive(Clone,Debug,PartialEq)]
+pub struct Stroke {
+    pub width: f32,
+    pub join: LineJoin,
+    pub miter_limit: f32, // ratio of miter length to width past which miters become bevels, as in svg
+    pub cap: LineCap,
+    pub dashes: Vec<f32>, // alternating on/off lengths; empty for a solid line
+    pub dash_offset: f32,
+} impl Default for Stroke {
+    fn default() -> Self {
+        Self {
+            width: 1.0,
+            join: LineJoin::default(),
+            miter_limit: 4.0,
+            cap: LineCap::default(),
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+        }
+    }
+} impl Stroke {
+    pub fn new(width: f32) -> Self { Self {width, ..Self::default()} }
+    
+    pub fn with_join(mut self, join: LineJoin) -> Self { self.join = join; self }
+    pub fn with_miter_limit(mut self, limit: f32) -> Self { self.miter_limit = limit; self }
+    pub fn with_cap(mut self, cap: LineCap) -> Self { self.cap = cap; self }
+    pub fn with_dashes(mut self, dashes: Vec<f32>, offset: f32) -> Self {
+        self.dashes = dashes;
+        self.dash_offset = offset;
+        self
+    }
+    
+    // outline of a flattened contour, as polygons to be filled with the non-zero rule
+    // each piece of the line is one outline going around its caps and joins, rather than overlapping shapes,
+    //  since the rasterizer adds up coverage and translucent edges would come out darker where shapes overlap
+    // everything winds the same way, so pieces that cross each other merge instead of cancelling out
+    pub fn outline(&self, points: &[Point], closed: bool) -> Vec<Vec<Point>> {
+        let mut polygons = Vec::new();
+        if self.width <= 0.0 { return polygons; }
+        
+        let mut points: Vec<Point> = points.to_vec();
+        points.dedup();
+        
+        if self.dashes.iter().any(|d| *d > 0.0) && self.dashes.iter().all(|d| *d >= 0.0) {
+            if closed && points.len() > 1 { points.push(points[0]); }
+            for dash in self.dash(&points) {
+                self.outline_polyline(&dash, false, &mut polygons);
+            }
+        } else {
+            if closed && points.len() > 1 && points.first() == points.last() { points.pop(); }
+            self.outline_polyline(&points, closed, &mut polygons);
+        }
+        polygons
+    }
+    
+    // split a polyline into the pieces that are "on"
+    fn dash(&self, points: &[Point]) -> Vec<Vec<Point>> {
+        // odd-length patterns repeat twice, like svg
+        let pattern: Vec<f32> = if self.dashes.len() % 2 == 1 {
+            self.dashes.iter().chain(&self.dashes).copied().collect()
+        } else {
+            self.dashes.clone()
+        };
+        let total: f32 = pattern.iter().sum();
+        
+        // find where in the pattern we start
+        let mut phase = self.dash_offset.rem_euclid(total);
+        let mut idx = 0;
+        while phase >= pattern[idx] {
+            phase -= pattern[idx];
+            idx = (idx + 1) % pattern.len();
+        }
+        let mut left = pattern[idx] - phase; // length left in the current dash or gap
+        
+        let mut dashes = Vec::new();
+        let mut current: Vec<Point> = Vec::new();
+        if idx % 2 == 0 && !points.is_empty() { current.push(points[0]); }
+        
+        for seg in points.windows(2) {
+            let (a, b) = (seg[0], seg[1]);
+            let len = a.distance(b);
+            let mut pos = 0.0;
+            while len - pos > left {
+                pos += left;
+                let p = a.lerp(b, pos / len);
+                if idx % 2 == 0 {
+                    current.push(p);
+                    dashes.push(std::mem::take(&mut current));
+                } else {
+                    current.push(p);
+                }
+                idx = (idx + 1) % pattern.len();
+                left = pattern[idx];
+            }
+            left -= len - pos;
+            if idx % 2 == 0 { current.push(b); }
+        }
+        if idx % 2 == 0 && current.len() > 1 {
+            dashes.push(current);
+        }
+        dashes
+    }
+    
+    fn outline_polyline(&self, points: &[Point], closed: bool, polygons: &mut Vec<Vec<Point>>) {
+        let r = self.width / 2.0;
+        
+        if points.len() == 1 || (points.len() == 2 && points[0] == points[1]) {
+            // zero-length lines only show up with caps that extend them
+            let mut polygon = match self.cap {
+                LineCap::Butt => return,
+                LineCap::Round => circle(points[0], r),
+                LineCap::Square => square(points[0], Point::new(1.0, 0.0), r),
+            };
+            if signed_area(&polygon) < 0.0 { polygon.reverse(); }
+            polygons.push(polygon);
+            return;
+        }
+        if points.len() < 2 { return; }
+        
+        // walking forward, the right side then the left side back makes a positive area
+        let right = self.side(points, closed, -1.0);
+        let mut left = self.side(points, closed, 1.0);
+        left.reverse();
+        if closed {
+            // the line's a ring, with the left side going around the other way inside or outside of the right
+            polygons.push(right);
+            polygons.push(left);
+            return;
+        }
+        
+        let n = points.len();
+        let (start, end) = (points[0], points[n - 1]);
+        let (dir_start, dir_end) = (direction(points[1], start), direction(points[n - 2], end));
+        let mut polygon = right;
+        self.cap(end, dir_end, &mut polygon);
+        polygon.extend(left);
+        self.cap(start, dir_start, &mut polygon);
+        polygons.push(polygon);
+    }
+    
+    // points r to one side of the line, sign 1 being the left, with joins where that's the outside of a turn
+    fn side(&self, points: &[Point], closed: bool, sign: f32) -> Vec<Point> {
+        let r = self.width / 2.0 * sign;
+        let n = points.len();
+        let offset = |p: Point, normal: Point| Point::new(p.x + normal.x * r, p.y + normal.y * r);
+        
+        let mut side = Vec::with_capacity(n * 2);
+        if !closed { side.push(offset(points[0], normal(points[0], points[1]))); }
+        let joints = if closed { 0..n } else { 1..n - 1 };
+        for i in joints {
+            let (prev, p, next) = (points[(i + n - 1) % n], points[i], points[(i + 1) % n]);
+            self.join(prev, p, next, r, &mut side);
+        }
+        if !closed { side.push(offset(points[n - 1], normal(points[n - 2], points[n - 1]))); }
+        side
+    }
+    
+    // from the end of the offset segment into p to the start of the one out of it, r to the side of both
+    fn join(&self, prev: Point, p: Point, next: Point, r: f32, side: &mut Vec<Point>) {
+        let (n0, n1) = (normal(prev, p), normal(p, next));
+        let (d0, d1) = (direction(prev, p), direction(p, next));
+        let a = Point::new(p.x + n0.x * r, p.y + n0.y * r);
+        let b = Point::new(p.x + n1.x * r, p.y + n1.y * r);
+        let cross = d0.x * d1.y - d0.y * d1.x;
+        let dot = d0.x * d1.x + d0.y * d1.y;
+        if cross.abs() < 1e-6 && dot > 0.0 { return side.push(b); }
+        
+        let mid = Point::new(n0.x + n1.x, n0.y + n1.y);
+        let len = mid.x.hypot(mid.y);
+        // cos of half the angle between the offset lines
+        let cos_half = len / 2.0;
+        // where the offset lines cross, as far from p as a miter reaches
+        let corner = (len >= 1e-6).then(|| {
+            let scale = r / cos_half / len;
+            Point::new(p.x + mid.x * scale, p.y + mid.y * scale)
+        });
+        
+        // turning away from this side, so it's the outside; turning right back around goes around the left
+        let outside = if cross.abs() < 1e-6 { r > 0.0 } else { cross * r < 0.0 };
+        if outside {
+            side.push(a);
+            match self.join {
+                LineJoin::Bevel => (),
+                LineJoin::Miter => if let Some(corner) = corner.filter(|_| 1.0 / cos_half <= self.miter_limit) { side.push(corner); },
+                LineJoin::Round => side.extend(arc(p, a, b, d0, r.abs())),
+            }
+            side.push(b);
+            return;
+        }
+        
+        // the inside: the offset lines cross before either segment ends, unless they're too short,
+        //  in which case going through p covers the gap without leaving the line
+        let within = |c: Point| {
+            let (x, y) = (c.x - p.x, c.y - p.y);
+            -(x * d0.x + y * d0.y) <= prev.distance(p) && x * d1.x + y * d1.y <= p.distance(next)
+        };
+        match corner.filter(|c| within(*c)) {
+            Some(corner) => side.push(corner),
+            None => side.extend([a, p, b]),
+        }
+    }
+    
+    // what goes around the end of the line at p, heading dir, from the right side to the left
+    fn cap(&self, p: Point, dir: Point, polygon: &mut Vec<Point>) {
+        let r = self.width / 2.0;
+        let right = Point::new(p.x + dir.y * r, p.y - dir.x * r);
+        let left = Point::new(p.x - dir.y * r, p.y + dir.x * r);
+        match self.cap {
+            LineCap::Butt => (),
+            LineCap::Round => polygon.extend(arc(p, right, left, dir, r)),
+            LineCap::Square => polygon.extend([
+                Point::new(right.x + dir.x * r, right.y + dir.y * r),
+                Point::new(left.x + dir.x * r, left.y + dir.y * r),
+            ]),
+        }
+    }
+}
+
+// points strictly between from and to on the circle of radius r around center, going around the side dir points to
+fn arc(center: Point, from: Point, to: Point, dir: Point, r: f32) -> Vec<Point> {
+    let start = (from.y - center.y).atan2(from.x - center.x);
+    let end = (to.y - center.y).atan2(to.x - center.x);
+    // leaving from toward dir means turning whichever way dir is from it
+    let turning = (from.x - center.x) * dir.y - (from.y - center.y) * dir.x;
+    let sweep = if turning >= 0.0 { (end - start).rem_euclid(2.0 * PI) } else { -(start - end).rem_euclid(2.0 * PI) };
+    let steps = arc_steps(r, sweep.abs());
+    (1..steps).map(|i| {
+        let angle = start + sweep * i as f32 / steps as f32;
+        Point::new(center.x + r * angle.cos(), center.y + r * angle.sin())
+    }).collect()
+}
+
+// segments needed for an arc of radius r to stay within a tenth of a pixel of the real thing
+fn arc_steps(r: f32, sweep: f32) -> usize {
+    let step = 2.0 * (1.0 - 0.1 / r.max(0.1)).clamp(-1.0, 1.0).acos();
+    ((sweep / step.max(0.01)).ceil() as usize).clamp(1, 256)
+}
+
+fn circle(center: Point, r: f32) -> Vec<Point> {
+    let steps = arc_steps(r, 2.0 * PI).max(8);
+    (0..steps).map(|i| {
+        let angle = 2.0 * PI * i as f32 / steps as f32;
+        Point::new(center.x + r * angle.cos(), center.y + r * angle.sin())
+    }).collect()
+}
+
+// square of half-size r around p, aligned with dir
+fn square(p: Point, dir: Point, r: f32) -> Vec<Point> {
+    let (dx, dy) = (dir.x * r, dir.y * r);
+    let (nx, ny) = (-dy, dx);
+    vec![
+        Point::new(p.x + dx + nx, p.y + dy + ny),
+        Point::new(p.x + dx - nx, p.y + dy - ny),
+        Point::new(p.x - dx - nx, p.y - dy - ny),
+        Point::new(p.x - dx + nx, p.y - dy + ny),
+    ]
+}
+
+fn direction(a: Point, b: Point) -> Point {
+    let len = a.distance(b);
+    if len == 0.0 { Point::new(1.0, 0.0) } else { Point::new((b.x - a.x) / len, (b.y - a.y) / len) }
+}
+
+// unit normal of the segment a->b
+fn normal(a: Point, b: Point) -> Point {
+    let d = direction(a, b);
+    Point::new(-d.y, d.x)
+}
+
+fn signed_area(polygon: &[Point]) -> f32 {
+    let n = polygon.len();
+    (0..n).map(|i| {
+        let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+        a.x * b.y - b.x * a.y
+    }).sum::<f32>() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn area(polygons: &[Vec<Point>]) -> f32 { polygons.iter().map(|p| signed_area(p)).sum() }
+    
+    // outlines that don't overlap themselves have exactly the area of the stroke
+    #[test]
+    fn outline_area() {
+        let line = [Point::new(0.0, 0.0), Point::new(10.0, 0.0)];
+        let bent = [Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0)];
+        let square = [Point::new(0.0, 0.0), Point::new(10.0, 0.0), Point::new(10.0, 10.0), Point::new(0.0, 10.0)];
+        let stroke = Stroke::new(2.0);
+        
+        assert_eq!(stroke.outline(&line, false).len(), 1);
+        assert!((area(&stroke.outline(&line, false)) - 20.0).abs() < 1e-3);
+        assert!((area(&stroke.clone().with_cap(LineCap::Square).outline(&line, false)) - 24.0).abs() < 1e-3);
+        // round ends are polygons within a tenth of a pixel of the circle
+        let round = area(&stroke.clone().with_cap(LineCap::Round).outline(&line, false)) - 20.0;
+        assert!(round <= PI && round > PI * 0.9 * 0.9);
+        // the miter fills in the outside corner
+        assert!((area(&stroke.outline(&bent, false)) - 40.0).abs() < 1e-3);
+        assert!((area(&stroke.clone().with_join(LineJoin::Bevel).outline(&bent, false)) - 39.5).abs() < 1e-3);
+        // a ring, 12 across outside and 8 inside
+        assert!((area(&stroke.outline(&square, true)) - 80.0).abs() < 1e-3);
+        let dot = stroke.clone().with_cap(LineCap::Square).outline(&line[..1], false);
+        assert!((area(&dot) - 4.0).abs() < 1e-3);
+    }
+}
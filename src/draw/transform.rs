@@ -0,0 +1,91 @@
+use super::Point;
+
+// affine transform, mapping (x,y) to (a*x + c*y + e, b*x + d*y + f)
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Transform {
+    pub a: f32, pub b: f32,
+    pub c: f32, pub d: f32,
+    pub e: f32, pub f: f32,
+} impl Transform {
+    pub const IDENTITY: Transform = Transform {a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 0.0, f: 0.0};
+    
+    pub const fn translate(x: f32, y: f32) -> Self {
+        Self {e: x, f: y, ..Self::IDENTITY}
+    }
+    
+    pub const fn scale(x: f32, y: f32) -> Self {
+        Self {a: x, d: y, ..Self::IDENTITY}
+    }
+    
+    // clockwise on screen, since y points down
+    pub fn rotate(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {a: cos, b: sin, c: -sin, d: cos, e: 0.0, f: 0.0}
+    }
+    
+    // angles the y axis by x radians and the x axis by y radians
+    pub fn skew(x: f32, y: f32) -> Self {
+        Self {b: y.tan(), c: x.tan(), ..Self::IDENTITY}
+    }
+    
+    // self first, then other
+    pub fn then(self, other: Transform) -> Transform {
+        Transform {
+            a: other.a * self.a + other.c * self.b,
+            b: other.b * self.a + other.d * self.b,
+            c: other.a * self.c + other.c * self.d,
+            d: other.b * self.c + other.d * self.d,
+            e: other.a * self.e + other.c * self.f + other.e,
+            f: other.b * self.e + other.d * self.f + other.f,
+        }
+    }
+    
+    pub fn apply(&self, p: Point) -> Point {
+        Point::new(
+            self.a * p.x + self.c * p.y + self.e,
+            self.b * p.x + self.d * p.y + self.f,
+        )
+    }
+    
+    // None if the transform squashes everything onto a line or a point
+    // tiny scales still invert, since they're only small compared to what they're drawn at
+    pub fn invert(&self) -> Option<Transform> {
+        let det = self.a * self.d - self.b * self.c;
+        if det == 0.0 || !det.is_finite() { return None; }
+        let (a, b, c, d) = (self.d / det, -self.b / det, -self.c / det, self.a / det);
+        Some(Transform {
+            a, b, c, d,
+            e: -(a * self.e + c * self.f),
+            f: -(b * self.e + d * self.f),
+        })
+    }
+    
+    pub fn is_identity(&self) -> bool { *self == Self::IDENTITY }
+    
+    // whether this only moves things around, without scaling, rotating or skewing
+    pub fn is_translation(&self) -> bool {
+        self.a == 1.0 && self.b == 0.0 && self.c == 0.0 && self.d == 1.0
+    }
+    
+    // the most a unit of length can get stretched by
+    pub fn max_scale(&self) -> f32 {
+        self.a.hypot(self.b).max(self.c.hypot(self.d))
+    }
+} impl Default for Transform {
+    fn default() -> Self { Self::IDENTITY }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn invert() {
+        let small = Transform::scale(0.001, 0.002).then(Transform::translate(3.0, 4.0));
+        let p = small.invert().unwrap().apply(small.apply(Point::new(5.0, -7.0)));
+        assert!((p.x - 5.0).abs() < 1e-3 && (p.y + 7.0).abs() < 1e-3);
+        assert!(Transform::scale(0.0, 1.0).invert().is_none());
+        assert!(Transform::scale(f32::INFINITY, 1.0).invert().is_none());
+        assert!(Transform::scale(f32::NAN, 1.0).invert().is_none());
+    }
+}
@@ -1,7 +1,161 @@
-use crate::{Pixel,PixelIdx,window::SurfaceSlice};
+mod flex;
+mod freedraw;
+mod grid;
+mod image;
+mod scroll;
+mod styled;
+
+pub use flex::{FlexCell,FlexItem,Justify};
+pub use freedraw::FreeDrawCell;
+pub use grid::{GridCell,GridChild,Track};
+pub use image::{ImageCell,ScaleMode};
+pub use scroll::ScrollCell;
+pub use styled::{Border,Style,StyledCell};
+
+use std::cell::RefCell;
+
+use crate::{
+    CommonEvent,MousePos,Pixel,PixelIdx,PixelIndexSlice,Size,
+    display::Recorder,
+    draw::Painter,
+    window::{Damage,SurfaceSlice},
+};
 
 pub trait GuiCell {
     fn draw(&self, surface: &mut SurfaceSlice);
+    
+    // size is the size of the slice this cell was last given to draw in
+    // mouse positions are relative to the cell's top-left corner
+    // return true if the event was consumed
+    fn handle_event(&mut self, _e: &CommonEvent, _size: Size) -> bool { false }
+    
+    // size this cell would like to have, given how much room there is
+    // used by containers that size children by content; Size::ZERO means no preference
+    fn measure(&self, _available: Size) -> Size { Size::ZERO }
+    
+    // what draw would do, as commands for a display list instead of pixels
+    // return false to have draw's pixels kept instead, which is what cells that don't implement this get
+    fn record(&self, _recorder: &mut Recorder) -> bool { false }
+}
+
+// how containers hold their children: as any cell, or only as Sync ones, for containers that have to be Sync themselves
+//  to be the root of a tiled window
+pub trait ChildCell: GuiCell {
+    fn as_cell(&self) -> &dyn GuiCell;
+    fn as_cell_mut(&mut self) -> &mut dyn GuiCell;
+} impl ChildCell for dyn GuiCell {
+    fn as_cell(&self) -> &dyn GuiCell { self }
+    fn as_cell_mut(&mut self) -> &mut dyn GuiCell { self }
+} impl ChildCell for dyn GuiCell + Sync {
+    fn as_cell(&self) -> &dyn GuiCell { self }
+    fn as_cell_mut(&mut self) -> &mut dyn GuiCell { self }
+}
+
+// pass an event on to a child occupying area of its parent
+// targeted mouse events only reach the child if the cursor is inside area
+pub fn forward_event(child: &mut dyn GuiCell, e: &CommonEvent, area: PixelIndexSlice) -> bool {
+    if let Some(pos) = e.position() && e.is_targeted() {
+        match pos.to_idx() {
+            Some(idx) if area.contains(idx) => (),
+            _ => return false,
+        }
+    }
+    forward_to(child, &e.relative_to(area.offset.into()), area.size, area.offset.into(), area)
+}
+
+// for children that don't simply sit in an area of their parent, e.g. scrolled content
+// e is already relative to the child; origin is where the child's (0,0) is, and visible is what of it can be seen,
+//  both in the parent's coordinates
+// children that consume the event without invalidating anything are assumed to have changed all over
+pub fn forward_to(child: &mut dyn GuiCell, e: &CommonEvent, size: Size, origin: MousePos, visible: PixelIndexSlice) -> bool {
+    let Some(outer) = DISPATCH.with_borrow(|d| d.as_ref().map(|d| (d.origin, d.clip, d.invalidations))) else {
+        return child.handle_event(e, size);
+    };
+    let (outer_origin, outer_clip, before) = outer;
+    
+    let clip = to_window(outer_origin, visible).intersect(&outer_clip);
+    let set = |origin: MousePos, clip: PixelIndexSlice| DISPATCH.with_borrow_mut(|d| if let Some(d) = d {
+        d.origin = origin;
+        d.clip = clip;
+    });
+    set(MousePos::new(outer_origin.x + origin.x, outer_origin.y + origin.y), clip);
+    let consumed = child.handle_event(e, size);
+    set(outer_origin, outer_clip);
+    
+    if consumed && DISPATCH.with_borrow(|d| d.as_ref().is_some_and(|d| d.invalidations == before)) {
+        invalidate(visible);
+    }
+    consumed
+}
+
+// while a window dispatches an event: what's been invalidated so far, in window coordinates,
+//  where the cell handling it has its (0,0) in the window, and the part of the window it can be seen in
+struct Dispatch {
+    damage: Damage,
+    origin: MousePos,
+    clip: PixelIndexSlice,
+    invalidations: usize,
+}
+thread_local! {
+    static DISPATCH: RefCell<Option<Dispatch>> = const { RefCell::new(None) };
+}
+
+// marks area of the cell handling the current event as needing a redraw, in that cell's coordinates
+// only means something while a window is dispatching an event; anywhere else it does nothing
+pub fn invalidate(area: PixelIndexSlice) {
+    DISPATCH.with_borrow_mut(|d| if let Some(d) = d {
+        d.invalidations += 1;
+        d.damage.add(to_window(d.origin, area).intersect(&d.clip));
+    });
+}
+
+// runs f as a window of the given size dispatching an event, and returns what it invalidated
+pub(crate) fn collect_damage(size: Size, f: impl FnOnce()) -> Damage {
+    let dispatch = Dispatch {
+        damage: Damage::new(),
+        origin: MousePos::default(),
+        clip: PixelIndexSlice {offset: PixelIdx::new(0,0), size},
+        invalidations: 0,
+    };
+    // dispatches nest when handling an event makes another window handle one
+    let outer = DISPATCH.replace(Some(dispatch));
+    f();
+    DISPATCH.replace(outer).map(|d| d.damage).unwrap_or_default()
+}
+
+// area relative to origin, in window coordinates; whatever's left of or above the window is cut off
+fn to_window(origin: MousePos, area: PixelIndexSlice) -> PixelIndexSlice {
+    let left   = origin.x + area.offset.x as isize;
+    let top    = origin.y + area.offset.y as isize;
+    let right  = left + area.size.width  as isize;
+    let bottom = top  + area.size.height as isize;
+    let (left, top) = (left.max(0), top.max(0));
+    if right <= left || bottom <= top { return PixelIndexSlice::default(); }
+    PixelIndexSlice {
+        offset: PixelIdx::new(left as usize, top as usize),
+        size: Size {width: (right - left) as usize, height: (bottom - top) as usize},
+    }
+}
+
+// placement of a child along an axis with more room than the child needs
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    #[default]
+    Stretch,
+} impl Align {
+    // (offset,length) of a child of length `wanted` within `available`
+    pub fn place(self, wanted: usize, available: usize) -> (usize,usize) {
+        let len = wanted.min(available);
+        match self {
+            Align::Start   => (0, len),
+            Align::Center  => ((available - len) / 2, len),
+            Align::End     => (available - len, len),
+            Align::Stretch => (0, available),
+        }
+    }
 }
 
 pub struct SolidCell {
@@ -12,12 +166,12 @@ pub struct SolidCell {
     }
 } impl GuiCell for SolidCell {
     fn draw(&self, surface: &mut SurfaceSlice) {
-        let size = surface.size();
-        for y in 0..size.height() {
-            for x in 0..size.width() {
-                surface.set_pixel(PixelIdx{x,y},self.color);
-            }
-        }
+        Painter::new(surface).clear(self.color);
+    }
+    
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        recorder.clear(self.color);
+        true
     }
 }
 
@@ -25,5 +179,4 @@ pub struct SolidCell {
 // gui cell types
 // layer (front-back)
 // split (vertical or horizontal)
-// free-draw 
 // swap (can be one of two or more cells depending on conditions)
\ No newline at end of file
@@ -0,0 +1,323 @@
+use crate::{
+    CommonEvent,Direction,Distance,Partition,Partitionable,PixelIdx,PixelIndexSlice,Size,
+    display::Recorder,
+    window::SurfaceSlice,
+};
+
+use super::{Align,ChildCell,GuiCell,forward_event};
+
+// distribution of leftover space along the main axis
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub enum Justify {
+    #[default]
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+    SpaceEvenly,
+}
+
+// C is what the child is boxed as, which is dyn GuiCell + Sync for items of a Sync FlexCell
+pub struct FlexItem<C: ?Sized = dyn GuiCell> {
+    cell: Box<C>,
+    grow: f32,
+    shrink: f32,
+    basis: Option<Distance>, // None sizes the item by GuiCell::measure
+    align: Option<Align>,    // None uses the container's alignment
+} impl FlexItem {
+    pub fn new(cell: Box<dyn GuiCell>) -> Self { Self::boxed(cell) }
+} impl FlexItem<dyn GuiCell + Sync> {
+    pub fn new_sync(cell: Box<dyn GuiCell + Sync>) -> Self { Self::boxed(cell) }
+} impl<C: ?Sized + ChildCell> FlexItem<C> {
+    fn boxed(cell: Box<C>) -> Self {
+        Self {
+            cell,
+            grow: 0.0,
+            shrink: 1.0,
+            basis: None,
+            align: None,
+        }
+    }
+    
+    pub fn with_grow  (mut self, grow:   f32     ) -> Self { self.grow   = grow;        self }
+    pub fn with_shrink(mut self, shrink: f32     ) -> Self { self.shrink = shrink;      self }
+    pub fn with_basis (mut self, basis:  Distance) -> Self { self.basis  = Some(basis); self }
+    pub fn with_align (mut self, align:  Align   ) -> Self { self.align  = Some(align); self }
+    
+    pub fn cell(&self) -> &C { &self.cell }
+    pub fn cell_mut(&mut self) -> &mut C { &mut self.cell }
+}
+
+// lays out any number of children in a row or column, like a css flexbox without wrapping
+// the _sync constructors make one that only takes Sync children, and so is Sync itself
+pub struct FlexCell<C: ?Sized = dyn GuiCell> {
+    direction: Direction, // Horizontal for rows, Vertical for columns
+    items: Vec<FlexItem<C>>,
+    gap: usize,
+    justify: Justify,
+    align: Align,
+} impl FlexCell {
+    pub fn new(direction: Direction) -> Self { Self::empty(direction) }
+    pub fn row   () -> Self { Self::new(Direction::Horizontal) }
+    pub fn column() -> Self { Self::new(Direction::Vertical  ) }
+} impl FlexCell<dyn GuiCell + Sync> {
+    pub fn new_sync(direction: Direction) -> Self { Self::empty(direction) }
+    pub fn row_sync   () -> Self { Self::new_sync(Direction::Horizontal) }
+    pub fn column_sync() -> Self { Self::new_sync(Direction::Vertical  ) }
+} impl<C: ?Sized + ChildCell> FlexCell<C> {
+    fn empty(direction: Direction) -> Self {
+        Self {
+            direction,
+            items: Vec::new(),
+            gap: 0,
+            justify: Justify::default(),
+            align: Align::default(),
+        }
+    }
+    pub fn with_gap    (mut self, gap:     usize  ) -> Self { self.gap     = gap;     self }
+    pub fn with_justify(mut self, justify: Justify) -> Self { self.justify = justify; self }
+    pub fn with_align  (mut self, align:   Align  ) -> Self { self.align   = align;   self }
+    
+    pub fn with_item (mut self, item: FlexItem<C>) -> Self { self.push(item);                  self }
+    pub fn with_child(mut self, cell: Box<C>     ) -> Self { self.push(FlexItem::boxed(cell)); self }
+    
+    pub fn push(&mut self, item: FlexItem<C>) { self.items.push(item); }
+    
+    pub fn items(&self) -> &[FlexItem<C>] { &self.items }
+    pub fn items_mut(&mut self) -> &mut [FlexItem<C>] { &mut self.items }
+    
+    fn cross_direction(&self) -> Direction {
+        match self.direction {
+            Direction::Horizontal => Direction::Vertical,
+            Direction::Vertical => Direction::Horizontal,
+        }
+    }
+    
+    // (main,cross) lengths of a size
+    fn split(&self, size: Size) -> (usize,usize) {
+        match self.direction {
+            Direction::Horizontal => (size.width, size.height),
+            Direction::Vertical => (size.height, size.width),
+        }
+    }
+    
+    fn base_length(&self, item: &FlexItem<C>, size: Size) -> f32 {
+        let main = self.split(size).0;
+        match item.basis {
+            Some(Distance::Pixels(px)) => px as f32,
+            Some(Distance::Relative(p)) => main as f32 * p,
+            None => self.split(item.cell.measure(size)).0 as f32,
+        }
+    }
+    
+    // areas of each child within a slice of the given size, relative to the slice
+    pub fn layout(&self, size: Size) -> Vec<PixelIndexSlice> {
+        let n = self.items.len();
+        if n == 0 { return Vec::new(); }
+        
+        let (main, cross) = self.split(size);
+        let gaps = (self.gap * (n - 1)) as f32;
+        
+        let mut lengths: Vec<f32> = self.items.iter().map(|it| self.base_length(it, size)).collect();
+        let mut free = main as f32 - gaps - lengths.iter().sum::<f32>();
+        
+        if free > 0.0 {
+            let total_grow: f32 = self.items.iter().map(|it| it.grow).sum();
+            if total_grow > 0.0 {
+                for (len, item) in lengths.iter_mut().zip(&self.items) {
+                    *len += free * item.grow / total_grow;
+                }
+                free = 0.0;
+            }
+        } else if free < 0.0 {
+            // like css, shrinking is weighted by base size so small items don't vanish first
+            let total_shrink: f32 = self.items.iter().zip(&lengths).map(|(it,len)| it.shrink * len).sum();
+            if total_shrink > 0.0 {
+                for (len, item) in lengths.iter_mut().zip(&self.items) {
+                    *len = (*len + free * item.shrink * *len / total_shrink).max(0.0);
+                }
+            }
+            free = 0.0;
+        }
+        
+        let (lead, between) = match self.justify {
+            Justify::Start        => (0.0, 0.0),
+            Justify::Center       => (free / 2.0, 0.0),
+            Justify::End          => (free, 0.0),
+            Justify::SpaceBetween => if n > 1 { (0.0, free / (n - 1) as f32) } else { (0.0, 0.0) },
+            Justify::SpaceAround  => (free / n as f32 / 2.0, free / n as f32),
+            Justify::SpaceEvenly  => (free / (n + 1) as f32, free / (n + 1) as f32),
+        };
+        
+        let cross_dir = self.cross_direction();
+        let mut rest = PixelIndexSlice {offset: PixelIdx::new(0,0), size};
+        let mut cursor = 0usize;
+        let mut pos = lead;
+        
+        let mut areas = Vec::with_capacity(n);
+        for (item, len) in self.items.iter().zip(lengths) {
+            // round both edges rather than the length so rounding error doesn't accumulate
+            let start = (pos.round() as usize).max(cursor);
+            let end = ((pos + len).round() as usize).max(start);
+            pos += len + self.gap as f32 + between;
+            
+            let Partition {children: (_, track), ..} = rest.partition(self.direction, Distance::Pixels(start - cursor));
+            let Partition {children: (track, after), ..} = track.partition(self.direction, Distance::Pixels(end - start));
+            rest = after;
+            cursor = end;
+            
+            let align = item.align.unwrap_or(self.align);
+            let wanted = match align {
+                Align::Stretch => cross,
+                _ => self.split(item.cell.measure(size)).1,
+            };
+            let (offset, length) = align.place(wanted, cross);
+            let area = track.partition(cross_dir, Distance::Pixels(offset)).children.1
+                .partition(cross_dir, Distance::Pixels(length)).children.0;
+            
+            areas.push(area);
+        }
+        
+        areas
+    }
+} impl<C: ?Sized + ChildCell> GuiCell for FlexCell<C> {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let areas = self.layout(surface.size());
+        for (item, area) in self.items.iter().zip(areas) {
+            let mut slice = surface.subslice(area);
+            if slice.is_visible() { item.cell.draw(&mut slice); }
+        }
+    }
+    
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        let areas = self.layout(recorder.size());
+        for (item, area) in self.items.iter().zip(areas) { recorder.cell(item.cell.as_cell(), area); }
+        true
+    }
+    
+    fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+        let areas = self.layout(size);
+        let mut consumed = false;
+        for (item, area) in self.items.iter_mut().zip(areas) {
+            consumed |= forward_event(item.cell.as_cell_mut(), e, area);
+        }
+        consumed
+    }
+    
+    fn measure(&self, available: Size) -> Size {
+        let n = self.items.len();
+        let mut main = (self.gap * n.saturating_sub(1)) as f32;
+        let mut cross = 0;
+        for item in &self.items {
+            main += self.base_length(item, available);
+            cross = cross.max(self.split(item.cell.measure(available)).1);
+        }
+        let main = main.round() as usize;
+        match self.direction {
+            Direction::Horizontal => Size {width: main, height: cross},
+            Direction::Vertical => Size {width: cross, height: main},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // draws nothing, and would like to be the size it's given
+    struct Fixed(Size);
+    impl GuiCell for Fixed {
+        fn draw(&self, _surface: &mut SurfaceSlice) {}
+        fn measure(&self, _available: Size) -> Size { self.0 }
+    }
+    
+    fn fixed(width: usize, height: usize) -> FlexItem {
+        FlexItem::new(Box::new(Fixed(Size {width, height})))
+    }
+    
+    // (start,end) along the main axis
+    fn spans(cell: &FlexCell, size: Size) -> Vec<(usize,usize)> {
+        cell.layout(size).iter().map(|area| match cell.direction {
+            Direction::Horizontal => (area.offset.x, area.offset.x + area.size.width),
+            Direction::Vertical => (area.offset.y, area.offset.y + area.size.height),
+        }).collect()
+    }
+    
+    const ROW: Size = Size {width: 100, height: 20};
+    
+    #[test]
+    fn grow() {
+        // what's left over goes to items by their grow
+        let cell = FlexCell::row().with_item(fixed(10, 5).with_grow(1.0)).with_item(fixed(20, 5).with_grow(3.0));
+        assert_eq!(spans(&cell, ROW), [(0, 28), (28, 100)]);
+        // or nowhere, if nothing grows
+        let cell = FlexCell::row().with_item(fixed(10, 5)).with_item(fixed(20, 5));
+        assert_eq!(spans(&cell, ROW), [(0, 10), (10, 30)]);
+        // bases override what items measure, and relative ones are of the main axis
+        let cell = FlexCell::row()
+            .with_item(fixed(10, 5).with_basis(Distance::Pixels(40)))
+            .with_item(fixed(10, 5).with_basis(Distance::Relative(0.25)).with_grow(1.0))
+            .with_gap(4);
+        assert_eq!(spans(&cell, ROW), [(0, 40), (44, 100)]);
+    }
+    
+    #[test]
+    fn shrink() {
+        // overflow is taken out of items by shrink times base size
+        let cell = FlexCell::row().with_item(fixed(80, 5)).with_item(fixed(40, 5));
+        assert_eq!(spans(&cell, ROW), [(0, 67), (67, 100)]);
+        let cell = FlexCell::row().with_item(fixed(80, 5).with_shrink(0.0)).with_item(fixed(40, 5));
+        assert_eq!(spans(&cell, ROW), [(0, 80), (80, 100)]);
+        // with nothing to shrink, items overflow and get cut off at the end
+        let cell = FlexCell::row().with_item(fixed(80, 5).with_shrink(0.0)).with_item(fixed(40, 5).with_shrink(0.0));
+        assert_eq!(spans(&cell, ROW), [(0, 80), (80, 100)]);
+    }
+    
+    #[test]
+    fn justify() {
+        let cases = [
+            (Justify::Start,        [(0, 10), (15, 35)]),
+            (Justify::Center,       [(33, 43), (48, 68)]),
+            (Justify::End,          [(65, 75), (80, 100)]),
+            (Justify::SpaceBetween, [(0, 10), (80, 100)]),
+            (Justify::SpaceAround,  [(16, 26), (64, 84)]),
+            (Justify::SpaceEvenly,  [(22, 32), (58, 78)]),
+        ];
+        for (justify, expected) in cases {
+            let cell = FlexCell::row().with_gap(5).with_justify(justify).with_item(fixed(10, 5)).with_item(fixed(20, 5));
+            assert_eq!(spans(&cell, ROW), expected, "{justify:?}");
+        }
+        // growing takes up the space justify would have spread out
+        let cell = FlexCell::row().with_justify(Justify::End).with_item(fixed(10, 5).with_grow(1.0));
+        assert_eq!(spans(&cell, ROW), [(0, 100)]);
+    }
+    
+    #[test]
+    fn align() {
+        let cell = |align: Align| FlexCell::row().with_align(align).with_item(fixed(10, 6));
+        let cross = |cell: FlexCell| cell.layout(ROW).iter().map(|area| (area.offset.y, area.size.height)).collect::<Vec<_>>();
+        assert_eq!(cross(cell(Align::Start)), [(0, 6)]);
+        assert_eq!(cross(cell(Align::Center)), [(7, 6)]);
+        assert_eq!(cross(cell(Align::End)), [(14, 6)]);
+        assert_eq!(cross(cell(Align::Stretch)), [(0, 20)]);
+        // items can align themselves differently from the rest
+        let mixed = FlexCell::row().with_align(Align::End).with_item(fixed(10, 6)).with_item(fixed(10, 6).with_align(Align::Start));
+        assert_eq!(cross(mixed), [(14, 6), (0, 6)]);
+        
+        // columns are the same turned on their side
+        let column = FlexCell::column().with_align(Align::Center).with_gap(2).with_item(fixed(6, 10)).with_item(fixed(6, 10).with_grow(1.0));
+        let areas = column.layout(Size {width: 20, height: 100});
+        assert_eq!(spans(&column, Size {width: 20, height: 100}), [(0, 10), (12, 100)]);
+        assert_eq!((areas[0].offset.x, areas[0].size.width), (7, 6));
+    }
+    
+    #[test]
+    fn measure() {
+        let cell = FlexCell::row().with_gap(5).with_item(fixed(10, 6)).with_item(fixed(20, 8));
+        assert_eq!(cell.measure(ROW), Size {width: 35, height: 8});
+        let cell = FlexCell::column().with_gap(5).with_item(fixed(10, 6)).with_item(fixed(20, 8));
+        assert_eq!(cell.measure(ROW), Size {width: 20, height: 19});
+        assert_eq!(FlexCell::row().measure(ROW), Size::ZERO);
+    }
+}
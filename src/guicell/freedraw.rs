@@ -0,0 +1,42 @@
+use crate::{CommonEvent,Size,draw::Painter,window::SurfaceSlice};
+
+use super::GuiCell;
+
+type DrawFn<S>  = Box<dyn Fn(&mut Painter, &S)>;
+type EventFn<S> = Box<dyn Fn(&mut S, &CommonEvent, Size) -> bool>;
+
+// a cell defined entirely by closures, for prototyping without a dedicated struct
+// `S` is whatever state the closures need to share; use () if there is none
+pub struct FreeDrawCell<S> {
+    state: S,
+    draw_fn: DrawFn<S>,
+    event_fn: Option<EventFn<S>>,
+} impl<S> FreeDrawCell<S> {
+    pub fn new(state: S, draw_fn: impl Fn(&mut Painter, &S) + 'static) -> Self {
+        Self {
+            state,
+            draw_fn: Box::new(draw_fn),
+            event_fn: None,
+        }
+    }
+    
+    // the handler returns whether it consumed the event, same as GuiCell::handle_event
+    pub fn with_event_handler(mut self, event_fn: impl Fn(&mut S, &CommonEvent, Size) -> bool + 'static) -> Self {
+        self.event_fn = Some(Box::new(event_fn));
+        self
+    }
+    
+    pub fn state(&self) -> &S { &self.state }
+    pub fn state_mut(&mut self) -> &mut S { &mut self.state }
+} impl<S> GuiCell for FreeDrawCell<S> {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        (self.draw_fn)(&mut Painter::new(surface), &self.state);
+    }
+    
+    fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+        match &self.event_fn {
+            Some(f) => f(&mut self.state, e, size),
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    CommonEvent,Distance,Partitionable,PixelIdx,PixelIndexSlice,Size,
+    display::Recorder,
+    window::SurfaceSlice,
+};
+
+use super::{Align,ChildCell,GuiCell,forward_event};
+
+// (first track,track count,content length) of a child along one axis
+type Span = (usize,usize,usize);
+
+// sizing rule for one row or column of a grid
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum Track {
+    Length(Distance), // relative lengths are relative to the whole grid
+    Fraction(f32),    // share of whatever is left over, like css `fr`
+    Auto,             // as big as the largest child that sits only in this track
+}
+
+// C is what the child is boxed as, which is dyn GuiCell + Sync for children of a Sync GridCell
+pub struct GridChild<C: ?Sized = dyn GuiCell> {
+    cell: Box<C>,
+    row: usize,
+    col: usize,
+    row_span: usize,
+    col_span: usize,
+    align_x: Option<Align>, // None uses the grid's alignment
+    align_y: Option<Align>,
+} impl GridChild {
+    pub fn new(cell: Box<dyn GuiCell>, row: usize, col: usize) -> Self { Self::boxed(cell, row, col) }
+} impl GridChild<dyn GuiCell + Sync> {
+    pub fn new_sync(cell: Box<dyn GuiCell + Sync>, row: usize, col: usize) -> Self { Self::boxed(cell, row, col) }
+} impl<C: ?Sized + ChildCell> GridChild<C> {
+    fn boxed(cell: Box<C>, row: usize, col: usize) -> Self {
+        Self {
+            cell,
+            row,
+            col,
+            row_span: 1,
+            col_span: 1,
+            align_x: None,
+            align_y: None,
+        }
+    }
+    
+    pub fn with_span(mut self, rows: usize, cols: usize) -> Self {
+        self.row_span = rows.max(1);
+        self.col_span = cols.max(1);
+        self
+    }
+    
+    pub fn with_align(mut self, x: Align, y: Align) -> Self {
+        self.align_x = Some(x);
+        self.align_y = Some(y);
+        self
+    }
+    
+    pub fn cell(&self) -> &C { &self.cell }
+    pub fn cell_mut(&mut self) -> &mut C { &mut self.cell }
+}
+
+// new_sync makes one that only takes Sync children, and so is Sync itself
+pub struct GridCell<C: ?Sized = dyn GuiCell> {
+    rows: Vec<Track>,
+    cols: Vec<Track>,
+    row_gap: usize,
+    col_gap: usize,
+    align_x: Align,
+    align_y: Align,
+    children: Vec<GridChild<C>>,
+} impl GridCell {
+    pub fn new(rows: Vec<Track>, cols: Vec<Track>) -> Self { Self::empty(rows, cols) }
+} impl GridCell<dyn GuiCell + Sync> {
+    pub fn new_sync(rows: Vec<Track>, cols: Vec<Track>) -> Self { Self::empty(rows, cols) }
+} impl<C: ?Sized + ChildCell> GridCell<C> {
+    fn empty(rows: Vec<Track>, cols: Vec<Track>) -> Self {
+        Self {
+            rows,
+            cols,
+            row_gap: 0,
+            col_gap: 0,
+            align_x: Align::default(),
+            align_y: Align::default(),
+            children: Vec::new(),
+        }
+    }
+    
+    pub fn with_gap(mut self, row_gap: usize, col_gap: usize) -> Self {
+        self.row_gap = row_gap;
+        self.col_gap = col_gap;
+        self
+    }
+    
+    pub fn with_align(mut self, x: Align, y: Align) -> Self {
+        self.align_x = x;
+        self.align_y = y;
+        self
+    }
+    
+    pub fn with_child(mut self, child: GridChild<C>) -> Self { self.push(child); self }
+    
+    pub fn push(&mut self, child: GridChild<C>) { self.children.push(child); }
+    
+    pub fn children(&self) -> &[GridChild<C>] { &self.children }
+    pub fn children_mut(&mut self) -> &mut [GridChild<C>] { &mut self.children }
+    
+    // (start,length) of each track along one axis
+    fn size_tracks(
+        tracks: &[Track],
+        total: usize,
+        gap: usize,
+        fractions_as_auto: bool,
+        spans: &[Span],
+    ) -> Vec<(usize,usize)> {
+        let n = tracks.len();
+        if n == 0 { return Vec::new(); }
+        
+        let is_auto = |t: &Track| match t {
+            Track::Auto => true,
+            Track::Fraction(_) => fractions_as_auto,
+            Track::Length(_) => false,
+        };
+        
+        let mut lengths: Vec<f32> = tracks.iter().map(|t| match t {
+            Track::Length(Distance::Pixels(px)) => *px as f32,
+            Track::Length(Distance::Relative(p)) => total as f32 * p,
+            _ => 0.0,
+        }).collect();
+        
+        // children within a single auto track first, then spanning children only add what's still missing
+        for &(first, _, content) in spans.iter().filter(|s| s.1 == 1) {
+            if is_auto(&tracks[first]) {
+                lengths[first] = lengths[first].max(content as f32);
+            }
+        }
+        for &(first, count, content) in spans.iter().filter(|s| s.1 > 1) {
+            let range = first..first + count;
+            let autos: Vec<usize> = range.clone().filter(|&i| is_auto(&tracks[i])).collect();
+            if autos.is_empty() { continue; }
+            let have: f32 = lengths[range].iter().sum::<f32>() + (gap * (count - 1)) as f32;
+            let missing = content as f32 - have;
+            if missing > 0.0 {
+                for i in &autos {
+                    lengths[*i] += missing / autos.len() as f32;
+                }
+            }
+        }
+        
+        if !fractions_as_auto {
+            let used: f32 = lengths.iter().sum::<f32>() + (gap * (n - 1)) as f32;
+            let free = (total as f32 - used).max(0.0);
+            let total_fr: f32 = tracks.iter().map(|t| if let Track::Fraction(fr) = t { *fr } else { 0.0 }).sum();
+            if total_fr > 0.0 {
+                for (len, track) in lengths.iter_mut().zip(tracks) {
+                    if let Track::Fraction(fr) = track {
+                        *len = free * fr / total_fr;
+                    }
+                }
+            }
+        }
+        
+        // round edges rather than lengths so errors don't accumulate
+        let mut pos = 0.0f32;
+        lengths.iter().map(|len| {
+            let start = pos.round() as usize;
+            let end = (pos + len).round() as usize;
+            pos += len + gap as f32;
+            (start, end.max(start) - start)
+        }).collect()
+    }
+    
+    // row and column spans of every child, clamped to the grid
+    fn spans(&self, available: Size) -> (Vec<Span>,Vec<Span>) {
+        let (n_rows, n_cols) = (self.rows.len(), self.cols.len());
+        let mut rows = Vec::with_capacity(self.children.len());
+        let mut cols = Vec::with_capacity(self.children.len());
+        for child in &self.children {
+            if child.row >= n_rows || child.col >= n_cols {
+                rows.push((0,0,0));
+                cols.push((0,0,0));
+                continue;
+            }
+            let wanted = child.cell.measure(available);
+            rows.push((child.row, child.row_span.min(n_rows - child.row), wanted.height));
+            cols.push((child.col, child.col_span.min(n_cols - child.col), wanted.width));
+        }
+        (rows, cols)
+    }
+    
+    // area of each child within a slice of the given size, relative to the slice
+    // children placed outside of the grid get an empty area
+    pub fn layout(&self, size: Size) -> Vec<PixelIndexSlice> {
+        let (row_spans, col_spans) = self.spans(size);
+        let live = |s: &&Span| s.1 > 0;
+        let row_spans_live: Vec<_> = row_spans.iter().filter(live).copied().collect();
+        let col_spans_live: Vec<_> = col_spans.iter().filter(live).copied().collect();
+        
+        let rows = Self::size_tracks(&self.rows, size.height, self.row_gap, false, &row_spans_live);
+        let cols = Self::size_tracks(&self.cols, size.width,  self.col_gap, false, &col_spans_live);
+        
+        let whole = PixelIndexSlice {offset: PixelIdx::new(0,0), size};
+        
+        self.children.iter().zip(row_spans.iter().zip(&col_spans)).map(|(child, (&(r, rn, h), &(c, cn, w)))| {
+            if rn == 0 || cn == 0 {
+                return PixelIndexSlice::default();
+            }
+            let (y, _) = rows[r];
+            let (x, _) = cols[c];
+            let bottom = rows[r + rn - 1].0 + rows[r + rn - 1].1;
+            let right  = cols[c + cn - 1].0 + cols[c + cn - 1].1;
+            
+            let (dx, width ) = child.align_x.unwrap_or(self.align_x).place(w, right - x);
+            let (dy, height) = child.align_y.unwrap_or(self.align_y).place(h, bottom - y);
+            
+            whole.subslice(PixelIndexSlice {
+                offset: PixelIdx::new(x + dx, y + dy),
+                size: Size {width, height},
+            })
+        }).collect()
+    }
+} impl<C: ?Sized + ChildCell> GuiCell for GridCell<C> {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let areas = self.layout(surface.size());
+        for (child, area) in self.children.iter().zip(areas) {
+            if area.size == Size::ZERO { continue; }
+            let mut slice = surface.subslice(area);
+            if slice.is_visible() { child.cell.draw(&mut slice); }
+        }
+    }
+    
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        let areas = self.layout(recorder.size());
+        for (child, area) in self.children.iter().zip(areas) {
+            if area.size == Size::ZERO { continue; }
+            recorder.cell(child.cell.as_cell(), area);
+        }
+        true
+    }
+    
+    fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+        let areas = self.layout(size);
+        let mut consumed = false;
+        for (child, area) in self.children.iter_mut().zip(areas) {
+            if area.size == Size::ZERO { continue; }
+            consumed |= forward_event(child.cell.as_cell_mut(), e, area);
+        }
+        consumed
+    }
+    
+    fn measure(&self, available: Size) -> Size {
+        let (row_spans, col_spans) = self.spans(available);
+        let row_spans: Vec<_> = row_spans.into_iter().filter(|s| s.1 > 0).collect();
+        let col_spans: Vec<_> = col_spans.into_iter().filter(|s| s.1 > 0).collect();
+        
+        let extent = |tracks: &[(usize,usize)]| tracks.last().map(|(start, len)| start + len).unwrap_or(0);
+        Size {
+            width:  extent(&Self::size_tracks(&self.cols, available.width,  self.col_gap, true, &col_spans)),
+            height: extent(&Self::size_tracks(&self.rows, available.height, self.row_gap, true, &row_spans)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // draws nothing, and would like to be the size it's given
+    struct Fixed(Size);
+    impl GuiCell for Fixed {
+        fn draw(&self, _surface: &mut SurfaceSlice) {}
+        fn measure(&self, _available: Size) -> Size { self.0 }
+    }
+    
+    fn fixed(width: usize, height: usize, row: usize, col: usize) -> GridChild {
+        GridChild::new(Box::new(Fixed(Size {width, height})), row, col)
+    }
+    
+    // (x,y,width,height) of every child
+    fn areas(grid: &GridCell, width: usize, height: usize) -> Vec<(usize,usize,usize,usize)> {
+        grid.layout(Size {width, height}).iter().map(|a| (a.offset.x, a.offset.y, a.size.width, a.size.height)).collect()
+    }
+    
+    #[test]
+    fn tracks() {
+        // lengths first, then autos as big as what's in them, then fractions share what's left
+        let cols = vec![Track::Length(Distance::Pixels(20)), Track::Auto, Track::Fraction(1.0), Track::Fraction(3.0)];
+        let rows = vec![Track::Length(Distance::Relative(0.25)), Track::Fraction(1.0)];
+        let grid = GridCell::new(rows, cols)
+            .with_child(fixed(0, 0, 0, 0))
+            .with_child(fixed(15, 5, 0, 1))
+            .with_child(fixed(0, 0, 1, 2))
+            .with_child(fixed(0, 0, 1, 3));
+        assert_eq!(areas(&grid, 100, 40), [(0, 0, 20, 10), (20, 0, 15, 10), (35, 10, 16, 30), (51, 10, 49, 30)]);
+        
+        // gaps come out of what fractions get
+        let grid = GridCell::new(vec![Track::Auto], vec![Track::Fraction(1.0); 2]).with_gap(0, 10)
+            .with_child(fixed(0, 4, 0, 0))
+            .with_child(fixed(0, 4, 0, 1));
+        assert_eq!(areas(&grid, 100, 40), [(0, 0, 45, 4), (55, 0, 45, 4)]);
+    }
+    
+    #[test]
+    fn spans() {
+        // spanning children only grow the auto tracks they're in by what the others don't already give them
+        let grid = GridCell::new(vec![Track::Auto; 2], vec![Track::Auto; 2]).with_gap(0, 4)
+            .with_child(fixed(10, 5, 0, 0))
+            .with_child(fixed(40, 5, 1, 0).with_span(1, 2));
+        assert_eq!(areas(&grid, 100, 40), [(0, 0, 23, 5), (0, 5, 40, 5)]);
+        assert_eq!(grid.measure(Size {width: 100, height: 40}), Size {width: 40, height: 10});
+        
+        // spans past the end are cut short, and children outside the grid get nothing
+        let grid = GridCell::new(vec![Track::Fraction(1.0); 2], vec![Track::Fraction(1.0); 2])
+            .with_child(fixed(0, 0, 1, 1).with_span(5, 5))
+            .with_child(fixed(0, 0, 0, 2))
+            .with_child(fixed(0, 0, 0, 0).with_span(2, 1));
+        assert_eq!(areas(&grid, 40, 20), [(20, 10, 20, 10), (0, 0, 0, 0), (0, 0, 20, 20)]);
+    }
+    
+    #[test]
+    fn align() {
+        let grid = |x, y| GridCell::new(vec![Track::Fraction(1.0)], vec![Track::Fraction(1.0)]).with_align(x, y).with_child(fixed(10, 6, 0, 0));
+        assert_eq!(areas(&grid(Align::Start, Align::End), 30, 20), [(0, 14, 10, 6)]);
+        assert_eq!(areas(&grid(Align::Center, Align::Stretch), 30, 20), [(10, 0, 10, 20)]);
+        // children can align themselves differently from the rest
+        let grid = grid(Align::Start, Align::Start).with_child(fixed(10, 6, 0, 0).with_align(Align::End, Align::Center));
+        assert_eq!(areas(&grid, 30, 20), [(0, 0, 10, 6), (20, 7, 10, 6)]);
+    }
+    
+    #[test]
+    fn measure() {
+        // fractions are measured as if they were auto
+        let grid = GridCell::new(vec![Track::Auto], vec![Track::Fraction(1.0), Track::Length(Distance::Pixels(5))]).with_gap(0, 2)
+            .with_child(fixed(12, 7, 0, 0));
+        assert_eq!(grid.measure(Size {width: 100, height: 100}), Size {width: 19, height: 7});
+        assert_eq!(GridCell::new(vec![], vec![]).measure(Size {width: 100, height: 100}), Size::ZERO);
+    }
+}
@@ -0,0 +1,119 @@
+use std::sync::{Arc,Mutex};
+
+use crate::{
+    PixelIdx,Size,
+    display::Recorder,
+    draw::{Painter,Transform},
+    image::{Filter,Image},
+    window::SurfaceSlice,
+};
+
+use super::GuiCell;
+
+// the image as it's drawn, where each copy of it goes, and how far all of them are moved
+type Placed = (Arc<Image>,Vec<PixelIdx>,(f32,f32));
+
+// how an image is sized to the slice it's drawn in
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum ScaleMode {
+    #[default]
+    Fit,     // as large as fits entirely, keeping its aspect ratio, centered
+    Fill,    // as small as covers the whole slice, keeping its aspect ratio, centered and cropped
+    Stretch, // exactly the slice's size
+    Center,  // at its own size, centered and cropped
+    Tile,    // at its own size, repeated from the top-left corner
+}
+
+// draws an image, scaled to its slice
+// the scaled image is kept until the slice changes size, since resampling every frame would be slow
+// it's behind a mutex so that tiles drawn on different threads can share it
+pub struct ImageCell {
+    image: Arc<Image>,
+    mode: ScaleMode,
+    filter: Filter,
+    cache: Mutex<Option<(Size,Arc<Image>)>>, // slice size, and the image scaled for it
+} impl ImageCell {
+    pub fn new(image: Arc<Image>) -> Self {
+        Self {image, mode: ScaleMode::default(), filter: Filter::default(), cache: Mutex::new(None)}
+    }
+    
+    pub fn with_mode(mut self, mode: ScaleMode) -> Self { self.set_mode(mode); self }
+    pub fn with_filter(mut self, filter: Filter) -> Self { self.set_filter(filter); self }
+    
+    pub fn image(&self) -> &Arc<Image> { &self.image }
+    pub fn mode(&self) -> ScaleMode { self.mode }
+    pub fn filter(&self) -> Filter { self.filter }
+    
+    pub fn set_image(&mut self, image: Arc<Image>) {
+        self.image = image;
+        self.cache.get_mut().unwrap().take();
+    }
+    pub fn set_mode(&mut self, mode: ScaleMode) {
+        self.mode = mode;
+        self.cache.get_mut().unwrap().take();
+    }
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.cache.get_mut().unwrap().take();
+    }
+    
+    // size the image is drawn at within a slice of the given size
+    fn scaled_size(&self, slice: Size) -> Size {
+        let Size {width, height} = self.image.size();
+        if width == 0 || height == 0 { return Size::ZERO; }
+        let (sx, sy) = (slice.width as f32 / width as f32, slice.height as f32 / height as f32);
+        let keep_aspect = |scale: f32| Size {
+            width:  ((width  as f32 * scale).round() as usize).max(1),
+            height: ((height as f32 * scale).round() as usize).max(1),
+        };
+        match self.mode {
+            ScaleMode::Fit => keep_aspect(sx.min(sy)),
+            ScaleMode::Fill => keep_aspect(sx.max(sy)),
+            ScaleMode::Stretch => slice,
+            ScaleMode::Center | ScaleMode::Tile => self.image.size(),
+        }
+    }
+    
+    // in a slice of the given size; the move centers the image, for modes that do
+    fn placed(&self, slice: Size) -> Option<Placed> {
+        let size = self.scaled_size(slice);
+        if size == Size::ZERO || slice == Size::ZERO { return None; }
+        
+        let image = if size == self.image.size() {
+            self.image.clone()
+        } else {
+            let mut cache = self.cache.lock().unwrap();
+            if cache.as_ref().is_none_or(|(cached, _)| *cached != slice) {
+                *cache = Some((slice, Arc::new(self.image.resized(size, self.filter))));
+            }
+            cache.as_ref().unwrap().1.clone()
+        };
+        
+        if self.mode == ScaleMode::Tile {
+            let at = (0..slice.height).step_by(size.height)
+                .flat_map(|y| (0..slice.width).step_by(size.width).map(move |x| PixelIdx::new(x, y)))
+                .collect();
+            return Some((image, at, (0.0, 0.0)));
+        }
+        // centered images can be larger than the slice, and hang off of both sides
+        let dx = (slice.width  as isize - size.width  as isize) / 2;
+        let dy = (slice.height as isize - size.height as isize) / 2;
+        Some((image, vec![PixelIdx::new(0, 0)], (dx as f32, dy as f32)))
+    }
+} impl GuiCell for ImageCell {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let Some((image, at, (dx, dy))) = self.placed(surface.size()) else { return };
+        let mut painter = Painter::new(surface);
+        painter.translate(dx, dy);
+        for at in at { painter.draw_image(&image, at); }
+    }
+    
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        let Some((image, at, (dx, dy))) = self.placed(recorder.size()) else { return true };
+        recorder.set_transform(Transform::translate(dx, dy));
+        for at in at { recorder.draw_image(image.clone(), at); }
+        true
+    }
+    
+    fn measure(&self, _available: Size) -> Size { self.image.size() }
+}
\ No newline at end of file
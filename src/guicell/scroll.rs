@@ -0,0 +1,423 @@
+use std::sync::Mutex;
+
+use crate::{
+    CommonEvent,MouseButton,MousePos,Partitionable,Pixel,PixelIdx,PixelIndexSlice,Size,
+    display::Recorder,
+    draw::Painter,
+    keys::Key,
+    window::SurfaceSlice,
+};
+
+use super::{ChildCell,GuiCell,forward_to,invalidate};
+
+#[derive(Clone,Copy,Debug)]
+enum Drag {
+    // content follows the cursor; anchor is where the cursor was, offset is the scroll offset at the time
+    Content {anchor: MousePos, offset: PixelIdx},
+    // thumb follows the cursor along one axis
+    VerticalThumb {anchor: isize, offset: usize},
+    HorizontalThumb {anchor: isize, offset: usize},
+}
+
+// lets a child lay itself out in a larger, virtual area and shows whichever part of it is scrolled to
+// new_sync makes one that only takes a Sync child, and so is Sync itself
+pub struct ScrollCell<C: ?Sized = dyn GuiCell> {
+    child: Box<C>,
+    content: Size,
+    offset: PixelIdx,
+    
+    scrollbars: bool,
+    bar_width: usize,
+    track_color: Pixel,
+    thumb_color: Pixel,
+    
+    line_step: usize, // pixels per wheel notch or arrow key press
+    
+    drag: Option<Drag>,
+    size: Mutex<Size>, // size of the whole cell, as of the last draw or event
+} impl ScrollCell {
+    pub fn new(child: Box<dyn GuiCell>, content: Size) -> Self { Self::boxed(child, content) }
+} impl ScrollCell<dyn GuiCell + Sync> {
+    pub fn new_sync(child: Box<dyn GuiCell + Sync>, content: Size) -> Self { Self::boxed(child, content) }
+} impl<C: ?Sized + ChildCell> ScrollCell<C> {
+    fn boxed(child: Box<C>, content: Size) -> Self {
+        Self {
+            child,
+            content,
+            offset: PixelIdx::new(0,0),
+            scrollbars: true,
+            bar_width: 12,
+            track_color: Pixel {r: 0xE8, g: 0xE8, b: 0xE8, a: 0xFF},
+            thumb_color: Pixel {r: 0xA0, g: 0xA0, b: 0xA0, a: 0xFF},
+            line_step: 40,
+            drag: None,
+            size: Mutex::new(Size::ZERO),
+        }
+    }
+    
+    pub fn with_scrollbars(mut self, visible: bool) -> Self { self.scrollbars = visible; self }
+    pub fn with_bar_width(mut self, width: usize) -> Self { self.bar_width = width; self }
+    pub fn with_colors(mut self, track: Pixel, thumb: Pixel) -> Self {
+        self.track_color = track;
+        self.thumb_color = thumb;
+        self
+    }
+    pub fn with_line_step(mut self, step: usize) -> Self { self.line_step = step; self }
+    
+    pub fn child(&self) -> &C { &self.child }
+    pub fn child_mut(&mut self) -> &mut C { &mut self.child }
+    
+    pub fn content_size(&self) -> Size { self.content }
+    pub fn set_content_size(&mut self, content: Size) {
+        self.content = content;
+        self.offset = self.clamp(self.offset);
+    }
+    
+    pub fn offset(&self) -> PixelIdx { self.offset }
+    
+    // scroll so that `offset` of the content is at the top-left of the viewport
+    // clamped so the content never scrolls past its end
+    pub fn scroll_to(&mut self, offset: PixelIdx) {
+        self.offset = self.clamp(offset);
+    }
+    
+    pub fn scroll_by(&mut self, dx: isize, dy: isize) {
+        self.scroll_to(PixelIdx::new(
+            (self.offset.x as isize + dx).max(0) as usize,
+            (self.offset.y as isize + dy).max(0) as usize,
+        ));
+    }
+    
+    // viewport size and whether the (vertical,horizontal) bars are shown, for a cell of the given size
+    fn layout(&self, size: Size) -> (Size,bool,bool) {
+        if !self.scrollbars { return (size, false, false); }
+        
+        // showing one bar shrinks the viewport, which may make the other one necessary
+        let mut vertical   = self.content.height > size.height;
+        let mut horizontal = self.content.width  > size.width.saturating_sub(if vertical { self.bar_width } else { 0 });
+        vertical |= self.content.height > size.height.saturating_sub(if horizontal { self.bar_width } else { 0 });
+        horizontal |= self.content.width > size.width.saturating_sub(if vertical { self.bar_width } else { 0 });
+        
+        let view = Size {
+            width:  size.width .saturating_sub(if vertical   { self.bar_width } else { 0 }),
+            height: size.height.saturating_sub(if horizontal { self.bar_width } else { 0 }),
+        };
+        (view, vertical, horizontal)
+    }
+    
+    fn max_offset(&self, view: Size) -> PixelIdx {
+        PixelIdx::new(
+            self.content.width .saturating_sub(view.width ),
+            self.content.height.saturating_sub(view.height),
+        )
+    }
+    
+    fn clamp(&self, offset: PixelIdx) -> PixelIdx {
+        let (view, _, _) = self.layout(*self.size.lock().unwrap());
+        let max = self.max_offset(view);
+        PixelIdx::new(offset.x.min(max.x), offset.y.min(max.y))
+    }
+    
+    // (start,length) of a thumb within a track of length `track`
+    fn thumb(track: usize, view: usize, content: usize, offset: usize) -> (usize,usize) {
+        if content <= view { return (0, track); }
+        let len = (track * view / content).max(track.min(16));
+        let start = (track - len) * offset / (content - view);
+        (start, len)
+    }
+    
+    fn vertical_bar(&self, size: Size, view: Size) -> PixelIndexSlice {
+        PixelIndexSlice {
+            offset: PixelIdx::new(view.width, 0),
+            size: Size {width: size.width - view.width, height: view.height},
+        }
+    }
+    
+    fn horizontal_bar(&self, size: Size, view: Size) -> PixelIndexSlice {
+        PixelIndexSlice {
+            offset: PixelIdx::new(0, view.height),
+            size: Size {width: view.width, height: size.height - view.height},
+        }
+    }
+    
+    // rects to fill for the tracks, thumbs and the corner between them, in order
+    fn bars(&self, size: Size, view: Size, vertical: bool, horizontal: bool) -> Vec<(PixelIndexSlice,Pixel)> {
+        let offset = self.clamp(self.offset);
+        let mut rects = Vec::new();
+        if vertical {
+            let bar = self.vertical_bar(size, view);
+            rects.push((bar, self.track_color));
+            let (start, len) = Self::thumb(bar.size.height, view.height, self.content.height, offset.y);
+            rects.push((PixelIndexSlice {
+                offset: PixelIdx::new(bar.offset.x + 2, start),
+                size: Size {width: bar.size.width.saturating_sub(4), height: len},
+            }, self.thumb_color));
+        }
+        if horizontal {
+            let bar = self.horizontal_bar(size, view);
+            rects.push((bar, self.track_color));
+            let (start, len) = Self::thumb(bar.size.width, view.width, self.content.width, offset.x);
+            rects.push((PixelIndexSlice {
+                offset: PixelIdx::new(start, bar.offset.y + 2),
+                size: Size {width: len, height: bar.size.height.saturating_sub(4)},
+            }, self.thumb_color));
+        }
+        if vertical && horizontal {
+            rects.push((PixelIndexSlice {
+                offset: PixelIdx::new(view.width, view.height),
+                size: Size {width: size.width - view.width, height: size.height - view.height},
+            }, self.track_color));
+        }
+        rects
+    }
+    
+    // scrolling in response to input the child didn't want
+    fn handle_scroll(&mut self, e: &CommonEvent, size: Size, view: Size, vertical: bool, horizontal: bool) -> bool {
+        let before = self.offset;
+        let step = self.line_step as isize;
+        let inside = |pos: MousePos| pos.to_idx().is_some_and(|idx| PixelIndexSlice {offset: PixelIdx::new(0,0), size}.contains(idx));
+        
+        match *e {
+            CommonEvent::MouseWheel(pos, dx, dy) if inside(pos) => {
+                self.scroll_by((dx * step as f32) as isize, (-dy * step as f32) as isize);
+            },
+            
+            CommonEvent::MouseDown(MouseButton::Left, pos) if inside(pos) => {
+                let idx = pos.to_idx().unwrap();
+                self.drag = Some(if vertical && self.vertical_bar(size, view).contains(idx) {
+                    Drag::VerticalThumb {anchor: pos.y, offset: self.offset.y}
+                } else if horizontal && self.horizontal_bar(size, view).contains(idx) {
+                    Drag::HorizontalThumb {anchor: pos.x, offset: self.offset.x}
+                } else {
+                    Drag::Content {anchor: pos, offset: self.offset}
+                });
+                return true;
+            },
+            
+            CommonEvent::MouseMove(pos) => match self.drag {
+                Some(Drag::Content {anchor, offset}) => {
+                    self.scroll_to(PixelIdx::new(
+                        (offset.x as isize - (pos.x - anchor.x)).max(0) as usize,
+                        (offset.y as isize - (pos.y - anchor.y)).max(0) as usize,
+                    ));
+                },
+                // thumb moves map back to content through the ratio of free track to scrollable content
+                Some(Drag::VerticalThumb {anchor, offset}) => {
+                    let track = view.height;
+                    let (_, len) = Self::thumb(track, view.height, self.content.height, 0);
+                    let free = track.saturating_sub(len).max(1) as isize;
+                    let scrollable = self.max_offset(view).y as isize;
+                    let y = offset as isize + (pos.y - anchor) * scrollable / free;
+                    self.scroll_to(PixelIdx::new(self.offset.x, y.max(0) as usize));
+                },
+                Some(Drag::HorizontalThumb {anchor, offset}) => {
+                    let track = view.width;
+                    let (_, len) = Self::thumb(track, view.width, self.content.width, 0);
+                    let free = track.saturating_sub(len).max(1) as isize;
+                    let scrollable = self.max_offset(view).x as isize;
+                    let x = offset as isize + (pos.x - anchor) * scrollable / free;
+                    self.scroll_to(PixelIdx::new(x.max(0) as usize, self.offset.y));
+                },
+                None => (),
+            },
+            
+            CommonEvent::MouseUp(MouseButton::Left, _) => {
+                return self.drag.take().is_some();
+            },
+            
+            CommonEvent::KeyDown(key) => match key {
+                Key::UpArrow    => self.scroll_by(0, -step),
+                Key::DownArrow  => self.scroll_by(0,  step),
+                Key::LeftArrow  => self.scroll_by(-step, 0),
+                Key::RightArrow => self.scroll_by( step, 0),
+                Key::PageUp     => self.scroll_by(0, -(view.height as isize)),
+                Key::PageDown   => self.scroll_by(0,   view.height as isize ),
+                Key::Home       => self.scroll_to(PixelIdx::new(self.offset.x, 0)),
+                Key::End        => self.scroll_to(PixelIdx::new(self.offset.x, usize::MAX)),
+                _ => (),
+            },
+            
+            _ => (),
+        }
+        
+        // everything in view moves, and so do the thumbs
+        if self.offset != before { invalidate(PixelIndexSlice {offset: PixelIdx::new(0,0), size}); }
+        self.offset != before || self.drag.is_some()
+    }
+} impl<C: ?Sized + ChildCell> GuiCell for ScrollCell<C> {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let size = surface.size();
+        *self.size.lock().unwrap() = size;
+        let (view, vertical, horizontal) = self.layout(size);
+        
+        let viewport = PixelIndexSlice {offset: PixelIdx::new(0,0), size: view};
+        let mut content = surface.subslice(viewport).scrolled(self.clamp(self.offset), self.content);
+        self.child.draw(&mut content);
+        
+        let mut painter = Painter::new(surface);
+        for (rect, color) in self.bars(size, view, vertical, horizontal) { painter.fill_rect(rect, color); }
+    }
+    
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        let size = recorder.size();
+        *self.size.lock().unwrap() = size;
+        let (view, vertical, horizontal) = self.layout(size);
+        
+        let offset = self.clamp(self.offset);
+        let viewport = PixelIndexSlice {offset: PixelIdx::new(0,0), size: view};
+        recorder.cell_at(self.child.as_cell(), (-(offset.x as isize), -(offset.y as isize)), self.content, viewport);
+        
+        for (rect, color) in self.bars(size, view, vertical, horizontal) { recorder.fill_rect(rect, color); }
+        true
+    }
+    
+    fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+        *self.size.get_mut().unwrap() = size;
+        let (view, vertical, horizontal) = self.layout(size);
+        self.offset = self.clamp(self.offset);
+        
+        // the child sees the cursor relative to the content's origin, but only while it's over the viewport
+        let over_view = e.position()
+            .and_then(|pos| pos.to_idx())
+            .is_some_and(|idx| PixelIndexSlice {offset: PixelIdx::new(0,0), size: view}.contains(idx));
+        
+        let to_child = self.drag.is_none() && (!e.is_targeted() || over_view);
+        if to_child {
+            let origin = MousePos::new(-(self.offset.x as isize), -(self.offset.y as isize));
+            let viewport = PixelIndexSlice {offset: PixelIdx::new(0,0), size: view};
+            if forward_to(self.child.as_cell_mut(), &e.relative_to(origin), self.content, origin, viewport) {
+                return true;
+            }
+        }
+        
+        self.handle_scroll(e, size, view, vertical, horizontal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    
+    use super::*;
+    use crate::guicell::collect_damage;
+    
+    type Log = Rc<RefCell<Vec<(CommonEvent,Size)>>>;
+    
+    // keeps the events it gets and the size it's told it is, without consuming any
+    struct Probe(Log);
+    impl GuiCell for Probe {
+        fn draw(&self, _surface: &mut SurfaceSlice) {}
+        fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+            self.0.borrow_mut().push((*e, size));
+            false
+        }
+    }
+    
+    const SIZE: Size = Size {width: 100, height: 100};
+    
+    // 200x300 of content in 100x100, so both bars are shown and the view is 90x90
+    fn scroll() -> (ScrollCell,Log) {
+        let log = Log::default();
+        let mut cell = ScrollCell::new(Box::new(Probe(log.clone())), Size {width: 200, height: 300}).with_bar_width(10);
+        cell.handle_event(&CommonEvent::Resize, SIZE);
+        log.borrow_mut().clear();
+        (cell, log)
+    }
+    
+    fn at(x: isize, y: isize) -> MousePos { MousePos::new(x, y) }
+    
+    #[test]
+    fn clamping() {
+        let (mut cell, _) = scroll();
+        cell.scroll_to(PixelIdx::new(500, 500));
+        assert_eq!(cell.offset(), PixelIdx::new(110, 210));
+        cell.scroll_by(-1000, -10);
+        assert_eq!(cell.offset(), PixelIdx::new(0, 200));
+        // shrinking the content pulls the offset back in
+        cell.set_content_size(Size {width: 200, height: 150});
+        assert_eq!(cell.offset(), PixelIdx::new(0, 60));
+        // content that fits can't be scrolled at all, and needs no bars
+        cell.set_content_size(Size {width: 50, height: 50});
+        assert_eq!(cell.offset(), PixelIdx::new(0, 0));
+        assert_eq!(cell.layout(SIZE), (SIZE, false, false));
+    }
+    
+    #[test]
+    fn wheel() {
+        let (mut cell, _) = scroll();
+        // a notch scrolls by a line, and toward the user is down
+        assert!(cell.handle_event(&CommonEvent::MouseWheel(at(50, 50), 0.0, -1.0), SIZE));
+        assert_eq!(cell.offset(), PixelIdx::new(0, 40));
+        assert!(cell.handle_event(&CommonEvent::MouseWheel(at(50, 50), 1.5, 0.5), SIZE));
+        assert_eq!(cell.offset(), PixelIdx::new(60, 20));
+        // not from outside the cell, and not past the edges
+        assert!(!cell.handle_event(&CommonEvent::MouseWheel(at(150, 50), 0.0, -1.0), SIZE));
+        assert!(cell.handle_event(&CommonEvent::MouseWheel(at(50, 50), 0.0, 10.0), SIZE));
+        assert!(!cell.handle_event(&CommonEvent::MouseWheel(at(50, 50), 0.0, 1.0), SIZE));
+        assert_eq!(cell.offset(), PixelIdx::new(60, 0));
+        
+        // scrolling redraws the whole cell
+        let damage = collect_damage(SIZE, || { cell.handle_event(&CommonEvent::MouseWheel(at(50, 50), 0.0, -1.0), SIZE); });
+        assert_eq!(damage.areas(), [PixelIndexSlice {offset: PixelIdx::new(0,0), size: SIZE}]);
+        
+        let (mut cell, _) = scroll();
+        cell.handle_event(&CommonEvent::KeyDown(Key::PageDown), SIZE);
+        cell.handle_event(&CommonEvent::KeyDown(Key::RightArrow), SIZE);
+        assert_eq!(cell.offset(), PixelIdx::new(40, 90));
+        cell.handle_event(&CommonEvent::KeyDown(Key::End), SIZE);
+        assert_eq!(cell.offset(), PixelIdx::new(40, 210));
+    }
+    
+    #[test]
+    fn drag() {
+        let (mut cell, _) = scroll();
+        // the vertical thumb is 27 of the 90 pixel track, so the 63 it can move covers the 210 the content can
+        assert!(cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, at(95, 10)), SIZE));
+        assert!(cell.handle_event(&CommonEvent::MouseMove(at(95, 31)), SIZE));
+        assert_eq!(cell.offset(), PixelIdx::new(0, 70));
+        assert!(cell.handle_event(&CommonEvent::MouseUp(MouseButton::Left, at(95, 31)), SIZE));
+        let thumb = PixelIndexSlice {offset: PixelIdx::new(92, 21), size: Size {width: 6, height: 27}};
+        assert_eq!(cell.bars(SIZE, Size {width: 90, height: 90}, true, true)[1].0, thumb);
+        // with the button up, moving doesn't scroll
+        assert!(!cell.handle_event(&CommonEvent::MouseMove(at(95, 60)), SIZE));
+        assert_eq!(cell.offset(), PixelIdx::new(0, 70));
+        
+        // the horizontal one is 40 of 90, and moves over 50 for 110
+        cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, at(10, 95)), SIZE);
+        cell.handle_event(&CommonEvent::MouseMove(at(35, 95)), SIZE);
+        cell.handle_event(&CommonEvent::MouseUp(MouseButton::Left, at(35, 95)), SIZE);
+        assert_eq!(cell.offset(), PixelIdx::new(55, 70));
+        
+        // content follows the cursor
+        cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, at(50, 50)), SIZE);
+        cell.handle_event(&CommonEvent::MouseMove(at(40, 30)), SIZE);
+        assert_eq!(cell.offset(), PixelIdx::new(65, 90));
+        cell.handle_event(&CommonEvent::MouseMove(at(200, 200)), SIZE);
+        assert_eq!(cell.offset(), PixelIdx::new(0, 0));
+    }
+    
+    #[test]
+    fn forwarding() {
+        let (mut cell, log) = scroll();
+        cell.scroll_to(PixelIdx::new(30, 40));
+        // the child sees the cursor relative to the content, and is told it's the content's size
+        cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, at(5, 6)), SIZE);
+        assert_eq!(*log.borrow(), [(CommonEvent::MouseDown(MouseButton::Left, at(35, 46)), Size {width: 200, height: 300})]);
+        // it didn't want the click, so that started a drag, which it sees none of
+        cell.handle_event(&CommonEvent::MouseMove(at(6, 6)), SIZE);
+        cell.handle_event(&CommonEvent::MouseUp(MouseButton::Left, at(6, 6)), SIZE);
+        assert_eq!(log.borrow().len(), 1);
+        assert_eq!(cell.offset(), PixelIdx::new(29, 40));
+        // clicks on the bars don't reach it either
+        cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, at(95, 6)), SIZE);
+        cell.handle_event(&CommonEvent::MouseUp(MouseButton::Left, at(95, 6)), SIZE);
+        assert_eq!(log.borrow().len(), 1);
+        // but events that aren't aimed at anything in particular go through from anywhere
+        cell.handle_event(&CommonEvent::MouseMove(at(95, 6)), SIZE);
+        assert_eq!(log.borrow()[1].0, CommonEvent::MouseMove(at(124, 46)));
+        cell.handle_event(&CommonEvent::KeyDown(Key::DownArrow), SIZE);
+        assert_eq!(log.borrow()[2].0, CommonEvent::KeyDown(Key::DownArrow));
+        assert_eq!(cell.offset(), PixelIdx::new(29, 80));
+    }
+}
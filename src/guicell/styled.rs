@@ -0,0 +1,332 @@
+use crate::{
+    CommonEvent,Insets,Partitionable,Pixel,PixelIdx,PixelIndexSlice,Size,
+    display::Recorder,
+    draw::{Paint,Painter},
+    window::SurfaceSlice,
+};
+
+use super::{ChildCell,GuiCell,forward_event};
+
+#[derive(Clone,Copy,Debug,Default)]
+pub struct Border {
+    pub widths: Insets,
+    pub color: Pixel,
+    pub radius: usize, // outer corner radius; 0 for square corners
+}
+
+// css-like box model: margin, then border, then padding, then the child
+// the background covers everything inside the border, and is painted in the cell's coordinates
+#[derive(Clone,Debug,Default)]
+pub struct Style {
+    pub margin: Insets,
+    pub border: Border,
+    pub padding: Insets,
+    pub background: Option<Paint>,
+} impl Style {
+    pub fn with_margin (mut self, margin:  Insets) -> Self { self.margin  = margin;  self }
+    pub fn with_padding(mut self, padding: Insets) -> Self { self.padding = padding; self }
+    pub fn with_background(mut self, paint: impl Into<Paint>) -> Self { self.background = Some(paint.into()); self }
+    pub fn with_border(mut self, widths: Insets, color: Pixel) -> Self {
+        self.border.widths = widths;
+        self.border.color = color;
+        self
+    }
+    pub fn with_radius(mut self, radius: usize) -> Self { self.border.radius = radius; self }
+    
+    // total space taken up around the child
+    pub fn insets(&self) -> Insets { self.margin + self.border.widths + self.padding }
+}
+
+// wraps any cell in a Style
+// the child only ever sees its content box, for both drawing and events,
+//  and what of that the rounded corners of the padding box cut off is clipped away
+// new_sync makes one that only takes a Sync child, and so is Sync itself
+pub struct StyledCell<C: ?Sized = dyn GuiCell> {
+    child: Box<C>,
+    style: Style,
+} impl StyledCell {
+    pub fn new(child: Box<dyn GuiCell>, style: Style) -> Self { Self {child, style} }
+} impl StyledCell<dyn GuiCell + Sync> {
+    pub fn new_sync(child: Box<dyn GuiCell + Sync>, style: Style) -> Self { Self {child, style} }
+} impl<C: ?Sized + ChildCell> StyledCell<C> {
+    
+    pub fn style(&self) -> &Style { &self.style }
+    pub fn style_mut(&mut self) -> &mut Style { &mut self.style }
+    
+    pub fn child(&self) -> &C { &self.child }
+    pub fn child_mut(&mut self) -> &mut C { &mut self.child }
+    
+    // (border box,padding box,content box) within a cell of the given size
+    fn boxes(&self, size: Size) -> (PixelIndexSlice,PixelIndexSlice,PixelIndexSlice) {
+        let border_box = PixelIndexSlice {offset: PixelIdx::new(0,0), size}.inset(self.style.margin);
+        let padding_box = border_box.inset(self.style.border.widths);
+        let content_box = padding_box.inset(self.style.padding);
+        (border_box, padding_box, content_box)
+    }
+    
+    // (outer radius,inner radii) of the corners of a border box, where the inner ones round the padding box
+    fn radii(&self, border_box: PixelIndexSlice) -> (f32,f32,f32) {
+        let Border {widths, radius, ..} = self.style.border;
+        let radius = (radius as f32).min(border_box.size.width as f32 / 2.0).min(border_box.size.height as f32 / 2.0);
+        let inner_rx = (radius - widths.left.max(widths.right) as f32).max(0.0);
+        let inner_ry = (radius - widths.top.max(widths.bottom) as f32).max(0.0);
+        (radius, inner_rx, inner_ry)
+    }
+    
+    // pixels of the content box within area that the padding box's corners cut into, and how much of each is left
+    fn corners(&self, size: Size, area: PixelIndexSlice) -> Vec<(PixelIdx,f32)> {
+        let (border_box, padding_box, content_box) = self.boxes(size);
+        let (_, rx, ry) = self.radii(border_box);
+        let area = area.intersect(&content_box);
+        if rx <= 0.0 || ry <= 0.0 || area.is_empty() { return Vec::new(); }
+        
+        // the right and bottom corners start after the left and top ones, so none overlap
+        let PixelIndexSlice {offset: PixelIdx {x: left, y: top}, size: Size {width, height}} = padding_box;
+        let (w, h) = (rx.ceil() as usize, ry.ceil() as usize);
+        let columns = [(left, left + w), ((left + width - w).max(left + w), left + width)];
+        let rows = [(top, top + h), ((top + height - h).max(top + h), top + height)];
+        
+        let mut corners = Vec::new();
+        for (y0, y1) in rows {
+            for y in y0.max(area.offset.y)..y1.min(area.offset.y + area.size.height) {
+                for (x0, x1) in columns {
+                    for x in x0.max(area.offset.x)..x1.min(area.offset.x + area.size.width) {
+                        let c = coverage((x as f32 + 0.5, y as f32 + 0.5), padding_box, rx, ry);
+                        if c < 1.0 { corners.push((PixelIdx::new(x,y), c)); }
+                    }
+                }
+            }
+        }
+        corners
+    }
+    
+    fn draw_box(&self, surface: &mut SurfaceSlice, border_box: PixelIndexSlice, padding_box: PixelIndexSlice) {
+        let Border {widths, color, ..} = self.style.border;
+        let has_border = widths != Insets::ZERO;
+        if self.style.background.is_none() && !has_border { return; }
+        
+        let (radius, inner_rx, inner_ry) = self.radii(border_box);
+        
+        let area = surface.visible_area().intersect(&border_box);
+        let mut painter = Painter::new(surface);
+        for y in area.offset.y..area.offset.y + area.size.height {
+            for x in area.offset.x..area.offset.x + area.size.width {
+                let center = (x as f32 + 0.5, y as f32 + 0.5);
+                let outer = coverage(center, border_box, radius, radius);
+                if outer <= 0.0 { continue; }
+                
+                // inner and ring don't overlap, so weight the background such that
+                //  plotting the border on top leaves each with its share of the pixel
+                let idx = PixelIdx::new(x,y);
+                let inner = if has_border { coverage(center, padding_box, inner_rx, inner_ry) } else { outer };
+                let ring = outer - inner;
+                if let Some(background) = &self.style.background && ring < 1.0 {
+                    painter.plot(idx, background.color_at(idx.into()), inner / (1.0 - ring));
+                }
+                if has_border {
+                    painter.plot(idx, color, ring);
+                }
+            }
+        }
+    }
+} impl<C: ?Sized + ChildCell> GuiCell for StyledCell<C> {
+    fn draw(&self, surface: &mut SurfaceSlice) {
+        let (border_box, padding_box, content_box) = self.boxes(surface.size());
+        self.draw_box(surface, border_box, padding_box);
+        let mut content = surface.subslice(content_box);
+        if !content.is_visible() { return; }
+        
+        // clipping the child to the corners puts back as much of what it drew over as the corners cut off
+        let corners: Vec<(PixelIdx,f32,Pixel)> = self.corners(surface.size(), surface.visible_area()).into_iter()
+            .map(|(idx, c)| (idx, c, surface.get_pixel(idx)))
+            .collect();
+        self.child.draw(&mut content);
+        for (idx, c, under) in corners {
+            let drawn = surface.get_pixel(idx);
+            surface.set_pixel(idx, under.lerp_premultiplied(drawn, c));
+        }
+    }
+    
+    // the box is drawn a pixel at a time, so it's kept as pixels
+    // recordings can only be clipped to rects, so a child the corners cut into is kept as pixels too, by draw
+    fn record(&self, recorder: &mut Recorder) -> bool {
+        if !self.corners(recorder.size(), recorder.visible_area()).is_empty() { return false; }
+        let (border_box, padding_box, content_box) = self.boxes(recorder.size());
+        if self.style.background.is_some() || self.style.border.widths != Insets::ZERO {
+            recorder.pixels(border_box, |surface| self.draw_box(surface, border_box, padding_box));
+        }
+        recorder.cell(self.child.as_cell(), content_box);
+        true
+    }
+    
+    fn handle_event(&mut self, e: &CommonEvent, size: Size) -> bool {
+        let (_, _, content_box) = self.boxes(size);
+        // clicks on what the corners clip off of the child don't reach it
+        if let Some(idx) = e.position().and_then(|pos| pos.to_idx()) && e.is_targeted() {
+            let area = PixelIndexSlice {offset: idx, size: Size {width: 1, height: 1}};
+            if self.corners(size, area).first().is_some_and(|(_, c)| *c < 0.5) { return false; }
+        }
+        forward_event(self.child.as_cell_mut(), e, content_box)
+    }
+    
+    fn measure(&self, available: Size) -> Size {
+        let insets = self.style.insets();
+        let inner = self.child.measure(Size {
+            width:  available.width .saturating_sub(insets.horizontal()),
+            height: available.height.saturating_sub(insets.vertical()),
+        });
+        Size {
+            width:  inner.width  + insets.horizontal(),
+            height: inner.height + insets.vertical(),
+        }
+    }
+}
+
+// approximate fraction of the pixel centered at `point` covered by a rect with elliptical corners
+fn coverage(point: (f32,f32), rect: PixelIndexSlice, rx: f32, ry: f32) -> f32 {
+    let (x, y) = point;
+    let left   = rect.offset.x as f32;
+    let top    = rect.offset.y as f32;
+    let right  = left + rect.size.width  as f32;
+    let bottom = top  + rect.size.height as f32;
+    
+    if x <= left || x >= right || y <= top || y >= bottom { return 0.0; }
+    if rx <= 0.0 || ry <= 0.0 { return 1.0; }
+    
+    // only points within a corner's bounding box can be partially covered
+    let cx = if x < left + rx { left + rx } else if x > right - rx { right - rx } else { return 1.0 };
+    let cy = if y < top + ry { top + ry } else if y > bottom - ry { bottom - ry } else { return 1.0 };
+    
+    let (dx, dy) = ((x - cx) / rx, (y - cy) / ry);
+    let distance = ((dx * dx + dy * dy).sqrt() - 1.0) * rx.min(ry);
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    
+    use super::*;
+    use crate::{
+        MouseButton,MousePos,
+        display::DisplayList,
+        guicell::{FlexCell,SolidCell},
+        image::Image,
+        keys::Key,
+        testing::render,
+    };
+    
+    const RED: Pixel = Pixel::rgb(0xFF, 0, 0);
+    
+    // fills itself, keeps the events it gets, and would like to be 7x5
+    struct Probe(Rc<RefCell<Vec<CommonEvent>>>);
+    impl GuiCell for Probe {
+        fn draw(&self, surface: &mut SurfaceSlice) { Painter::new(surface).clear(RED); }
+        fn handle_event(&mut self, e: &CommonEvent, _size: Size) -> bool {
+            self.0.borrow_mut().push(*e);
+            true
+        }
+        fn measure(&self, available: Size) -> Size { Size {width: available.width.min(7), height: 5} }
+    }
+    
+    fn rgba(p: Pixel) -> [u8; 4] { [p.r, p.g, p.b, p.a] }
+    
+    // margin 1,2,3,4 clockwise from the top, a 2 pixel border, and padding of 1 vertically and 3 horizontally
+    fn styled() -> (StyledCell,Rc<RefCell<Vec<CommonEvent>>>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let style = Style::default()
+            .with_margin(Insets::new(1, 2, 3, 4))
+            .with_border(Insets::uniform(2), Pixel::rgb(0, 0, 0xFF))
+            .with_padding(Insets::symmetric(1, 3));
+        (StyledCell::new(Box::new(Probe(events.clone())), style), events)
+    }
+    
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> PixelIndexSlice {
+        PixelIndexSlice {offset: PixelIdx::new(x, y), size: Size {width, height}}
+    }
+    
+    #[test]
+    fn boxes() {
+        let (cell, _) = styled();
+        let (border_box, padding_box, content_box) = cell.boxes(Size {width: 30, height: 20});
+        assert_eq!(border_box, rect(4, 1, 24, 16));
+        assert_eq!(padding_box, rect(6, 3, 20, 12));
+        assert_eq!(content_box, rect(9, 4, 14, 10));
+        assert_eq!(cell.style().insets(), Insets::new(4, 7, 6, 9));
+        
+        // the child only gets what's left inside of the content box
+        let image = render(&cell, Size {width: 30, height: 20});
+        assert_eq!(rgba(image.get_pixel(PixelIdx::new(9, 4))), rgba(RED));
+        assert_eq!(rgba(image.get_pixel(PixelIdx::new(22, 13))), rgba(RED));
+        assert_eq!(rgba(image.get_pixel(PixelIdx::new(8, 4))), [0, 0, 0, 0]);
+        assert_eq!(rgba(image.get_pixel(PixelIdx::new(5, 1))), [0, 0, 0xFF, 0xFF]);
+        
+        // insets bigger than the cell leave nothing
+        assert!(cell.boxes(Size {width: 10, height: 5}).2.is_empty());
+    }
+    
+    #[test]
+    fn events() {
+        let (mut cell, events) = styled();
+        let size = Size {width: 30, height: 20};
+        let click = |x, y| CommonEvent::MouseDown(MouseButton::Left, MousePos::new(x, y));
+        
+        // relative to the content box
+        assert!(cell.handle_event(&click(10, 5), size));
+        assert_eq!(*events.borrow(), [click(1, 1)]);
+        // clicks on the border, padding or margin don't reach the child
+        for (x, y) in [(8, 5), (5, 5), (0, 0), (23, 5), (10, 14)] {
+            assert!(!cell.handle_event(&click(x, y), size), "{x},{y}");
+        }
+        assert_eq!(events.borrow().len(), 1);
+        // but what isn't aimed anywhere in particular does
+        assert!(cell.handle_event(&CommonEvent::KeyDown(Key::Enter), size));
+        assert_eq!(events.borrow()[1], CommonEvent::KeyDown(Key::Enter));
+    }
+    
+    #[test]
+    fn measure() {
+        let (cell, _) = styled();
+        // the child is asked with the insets taken off, and they're added back on to its answer
+        assert_eq!(cell.measure(Size {width: 100, height: 100}), Size {width: 23, height: 15});
+        assert_eq!(cell.measure(Size {width: 20, height: 100}), Size {width: 20, height: 15});
+        assert_eq!(cell.measure(Size {width: 5, height: 5}), Size {width: 16, height: 15});
+    }
+    
+    #[test]
+    fn rounded_corners() {
+        let size = Size {width: 12, height: 12};
+        // a child is clipped just as much as a background of the same color would be
+        let clipped = render(&StyledCell::new(Box::new(SolidCell::new(RED)), Style::default().with_radius(4)), size);
+        let background = render(&StyledCell::new(Box::new(FlexCell::row()), Style::default().with_radius(4).with_background(RED)), size);
+        for (a, b) in clipped.pixels().iter().zip(background.pixels()) {
+            assert!(a.a.abs_diff(b.a) <= 1 && (a.a == 0 || rgba(*a)[..3] == [0xFF, 0, 0]), "{a:?} {b:?}");
+        }
+        assert_eq!(clipped.get_pixel(PixelIdx::new(0, 0)).a, 0);
+        assert_eq!(rgba(clipped.get_pixel(PixelIdx::new(0, 6))), rgba(RED));
+        assert!((1..0xFF).contains(&clipped.get_pixel(PixelIdx::new(0, 1)).a));
+        
+        // with a border, it's the inside of the border that the child is clipped to
+        let style = Style::default().with_radius(6).with_border(Insets::uniform(2), Pixel::rgb(0, 0, 0xFF));
+        let cell = StyledCell::new(Box::new(SolidCell::new(RED)), style);
+        let image = render(&cell, Size {width: 16, height: 16});
+        assert_eq!(rgba(image.get_pixel(PixelIdx::new(2, 8))), rgba(RED));
+        assert_eq!(image.get_pixel(PixelIdx::new(2, 2)).r, 0);
+        // clicks on the clipped off corners don't reach the child either
+        let (events, mut cell) = {
+            let events = Rc::new(RefCell::new(Vec::new()));
+            (events.clone(), StyledCell::new(Box::new(Probe(events)), Style::default().with_radius(6)))
+        };
+        assert!(!cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, MousePos::new(0, 0)), size));
+        assert!(cell.handle_event(&CommonEvent::MouseDown(MouseButton::Left, MousePos::new(3, 3)), size));
+        assert_eq!(events.borrow().len(), 1);
+        
+        // recordings can't clip to the corners, so they keep what draw does instead
+        let list = DisplayList::record(&cell, size);
+        let mut recorded = Image::new(size);
+        unsafe { recorded.with_slice(|s| list.rasterize(s, rect(0, 0, 12, 12))) };
+        let drawn = render(&cell, size);
+        assert!(recorded.pixels().iter().zip(drawn.pixels()).all(|(a, b)| rgba(*a) == rgba(*b)));
+    }
+}
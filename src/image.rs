@@ -0,0 +1,224 @@
+mod bmp;
+mod deflate;
+mod gif;
+mod inflate;
+mod jpeg;
+mod png;
+mod ppm;
+mod resample;
+
+use std::fmt;
+
+pub use resample::Filter;
+
+use crate::{Pixel,PixelIdx,Size,simd,window::SurfaceSlice};
+
+// decoders refuse images with more pixels than this, rather than trying to allocate them
+const MAX_PIXELS: usize = 1 << 28;
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    UnknownFormat,
+    Malformed(&'static str),   // what couldn't be read
+    Unsupported(&'static str), // what the image needs that isn't implemented
+} impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "couldn't read or write image: {e}"),
+            ImageError::UnknownFormat => write!(f, "unknown image format"),
+            ImageError::Malformed(what) => write!(f, "malformed image: bad {what}"),
+            ImageError::Unsupported(what) => write!(f, "unsupported image: {what}"),
+        }
+    }
+} impl std::error::Error for ImageError {
+} impl From<std::io::Error> for ImageError {
+    fn from(e: std::io::Error) -> Self { ImageError::Io(e) }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ImageFormat {
+    Png,
+    Bmp,
+    Ppm, // and the rest of netpbm: pbm and pgm
+    Jpeg,
+    Gif, // just the first frame
+} impl ImageFormat {
+    // going by the first few bytes
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        match data {
+            _ if data.starts_with(&png::SIGNATURE) => Some(ImageFormat::Png),
+            [b'B', b'M', ..] => Some(ImageFormat::Bmp),
+            [b'P', b'1'..=b'6', ..] => Some(ImageFormat::Ppm),
+            [0xFF, 0xD8, 0xFF, ..] => Some(ImageFormat::Jpeg),
+            _ if data.starts_with(b"GIF8") => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+    
+    // going by a file's extension, ignoring case
+    pub fn from_extension(path: impl AsRef<std::path::Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(ImageFormat::Png),
+            "bmp" | "dib" => Some(ImageFormat::Bmp),
+            "ppm" | "pgm" | "pbm" | "pnm" => Some(ImageFormat::Ppm),
+            "jpg" | "jpeg" | "jpe" => Some(ImageFormat::Jpeg),
+            "gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+// sizes of decoded images, which can't be empty or absurdly large
+fn checked_size(width: usize, height: usize) -> Result<Size,ImageError> {
+    if width == 0 || height == 0 { return Err(ImageError::Malformed("dimensions")); }
+    if width.checked_mul(height).is_none_or(|pixels| pixels > MAX_PIXELS) {
+        return Err(ImageError::Unsupported("dimensions this large"));
+    }
+    Ok(Size {width, height})
+}
+
+// an owned, in-memory buffer of pixels, stored row by row
+// same pixel layout as a window's surface, so it can be drawn into like one
+#[derive(Clone,Debug,Default)]
+pub struct Image {
+    size: Size,
+    pixels: Vec<Pixel>,
+} impl Image {
+    
+    pub fn new(size: Size) -> Self {
+        Self::filled(size, Pixel::default())
+    }
+    
+    pub fn filled(size: Size, color: Pixel) -> Self {
+        Self {
+            size,
+            pixels: vec![color; size.width * size.height],
+        }
+    }
+    
+    pub fn from_pixels(size: Size, pixels: Vec<Pixel>) -> Self {
+        if pixels.len() != size.width * size.height {
+            panic!("Expected {} pixels for an image of size {:?}, got {}", size.width * size.height, size, pixels.len());
+        }
+        Self {size, pixels}
+    }
+    
+    // packed r,g,b,a bytes, as most other libraries keep them
+    pub fn from_rgba(size: Size, bytes: &[u8]) -> Self {
+        let mut image = Self::new(size);
+        simd::from_rgba(bytes, &mut image.pixels);
+        image
+    }
+    
+    pub fn to_rgba(&self) -> Vec<u8> {
+        let mut bytes = vec![0; self.pixels.len() * 4];
+        simd::to_rgba(&self.pixels, &mut bytes);
+        bytes
+    }
+    
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self,ImageError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+    
+    // in whichever format the data looks like it's in
+    pub fn from_bytes(data: &[u8]) -> Result<Self,ImageError> {
+        Self::decode(data, ImageFormat::detect(data).ok_or(ImageError::UnknownFormat)?)
+    }
+    
+    // pixels come out with straight alpha, and opaque for formats without it
+    pub fn decode(data: &[u8], format: ImageFormat) -> Result<Self,ImageError> {
+        match format {
+            ImageFormat::Png => png::decode(data),
+            ImageFormat::Bmp => bmp::decode(data),
+            ImageFormat::Ppm => ppm::decode(data),
+            ImageFormat::Jpeg => jpeg::decode(data),
+            ImageFormat::Gif => gif::decode(data),
+        }
+    }
+    
+    // png keeps alpha, dropping it when everything is opaque; ppm always drops it
+    pub fn encode(&self, format: ImageFormat) -> Result<Vec<u8>,ImageError> {
+        match format {
+            ImageFormat::Png => Ok(png::encode(self)),
+            ImageFormat::Ppm => Ok(ppm::encode(self)),
+            _ => Err(ImageError::Unsupported("encoding to that format")),
+        }
+    }
+    
+    // in the format the path's extension names
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(),ImageError> {
+        let format = ImageFormat::from_extension(&path).ok_or(ImageError::UnknownFormat)?;
+        std::fs::write(path, self.encode(format)?)?;
+        Ok(())
+    }
+    
+    pub fn size  (&self) -> Size  { self.size }
+    pub fn width (&self) -> usize { self.size.width  }
+    pub fn height(&self) -> usize { self.size.height }
+    
+    pub fn pixels(&self) -> &[Pixel] { &self.pixels }
+    pub fn pixels_mut(&mut self) -> &mut [Pixel] { &mut self.pixels }
+    pub fn into_pixels(self) -> Vec<Pixel> { self.pixels }
+    
+    pub fn row(&self, y: usize) -> &[Pixel] {
+        &self.pixels[y * self.size.width..(y + 1) * self.size.width]
+    }
+    
+    // out-of-bounds reads return Pixel::default(), like SurfaceSlice
+    pub fn get_pixel(&self, idx: PixelIdx) -> Pixel {
+        if idx.x >= self.size.width || idx.y >= self.size.height { return Pixel::default(); }
+        self.pixels[idx.y * self.size.width + idx.x]
+    }
+    
+    pub fn set_pixel(&mut self, idx: PixelIdx, pixel: Pixel) {
+        if idx.x >= self.size.width || idx.y >= self.size.height { return; }
+        self.pixels[idx.y * self.size.width + idx.x] = pixel;
+    }
+    
+    // lend out a slice covering the whole image
+    // slices don't borrow what they point into, so nothing stops f from keeping this one, or a subslice or
+    //  partition of it, past the call; the caller has to make sure none of them are used once it returns
+    #[allow(clippy::missing_safety_doc)]
+    pub unsafe fn with_slice<R>(&mut self, f: impl FnOnce(&mut SurfaceSlice) -> R) -> R {
+        let mut slice = SurfaceSlice::new(self.pixels.as_mut_ptr(), self.size);
+        f(&mut slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // every channel varies, so a swapped or dropped one shows
+    fn pattern(size: Size, alpha: impl Fn(usize) -> u8) -> Image {
+        let mut image = Image::new(size);
+        for (i, p) in image.pixels_mut().iter_mut().enumerate() {
+            *p = Pixel {r: (i * 7) as u8, g: (i * 13 + 50) as u8, b: (i * 3) as u8 ^ 0xFF, a: alpha(i)};
+        }
+        image
+    }
+    
+    fn round_trip(image: &Image, format: ImageFormat) {
+        let back = Image::decode(&image.encode(format).unwrap(), format).unwrap();
+        assert_eq!(back.size(), image.size());
+        for (i, (p, q)) in image.pixels().iter().zip(back.pixels()).enumerate() {
+            assert_eq!([p.r, p.g, p.b, p.a], [q.r, q.g, q.b, q.a], "{format:?} pixel {i}");
+        }
+    }
+    
+    #[test]
+    fn png_round_trip() {
+        round_trip(&pattern(Size {width: 37, height: 11}, |_| 255), ImageFormat::Png);
+        round_trip(&pattern(Size {width: 37, height: 11}, |i| (i * 29) as u8), ImageFormat::Png);
+        round_trip(&pattern(Size {width: 1, height: 1}, |_| 255), ImageFormat::Png);
+        round_trip(&pattern(Size {width: 1, height: 1}, |_| 90), ImageFormat::Png);
+    }
+    
+    #[test]
+    fn ppm_round_trip() {
+        round_trip(&pattern(Size {width: 37, height: 11}, |_| 255), ImageFormat::Ppm);
+        round_trip(&pattern(Size {width: 1, height: 1}, |_| 255), ImageFormat::Ppm);
+    }
+}
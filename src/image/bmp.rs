@@ -0,0 +1,234 @@
+use crate::{Pixel,PixelIdx};
+
+use super::{Image,ImageError};
+
+// windows bitmaps, from the old os/2 header up to v5, uncompressed or run-length encoded
+
+const RGB: u32 = 0;
+const RLE8: u32 = 1;
+const RLE4: u32 = 2;
+const BITFIELDS: u32 = 3;
+const ALPHA_BITFIELDS: u32 = 6;
+
+pub(super) fn decode(data: &[u8]) -> Result<Image,ImageError> {
+    use ImageError::*;
+    
+    if !data.starts_with(b"BM") { return Err(Malformed("bmp signature")); }
+    let u16_at = |pos: usize| data.get(pos..pos + 2).map(|b| u16::from_le_bytes([b[0], b[1]])).ok_or(Malformed("bmp header"));
+    let u32_at = |pos: usize| data.get(pos..pos + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or(Malformed("bmp header"));
+    
+    let offset = u32_at(10)? as usize;
+    let header_size = u32_at(14)? as usize;
+    let (width, height, bpp, compression, colors) = if header_size == 12 {
+        (u16_at(18)? as i32, u16_at(20)? as i16 as i32, u16_at(24)?, RGB, 0)
+    } else if header_size >= 40 {
+        (u32_at(18)? as i32, u32_at(22)? as i32, u16_at(28)?, u32_at(30)?, u32_at(46)? as usize)
+    } else {
+        return Err(Malformed("bmp header"));
+    };
+    // rows go from the bottom up, unless the height is negative
+    let top_down = height < 0;
+    if width <= 0 || (top_down && matches!(compression, RLE8 | RLE4)) {
+        return Err(Malformed("bmp dimensions"));
+    }
+    let size = super::checked_size(width as usize, height.unsigned_abs() as usize)?;
+    
+    // channel masks are part of newer headers, and come right after the older ones
+    let masks = match compression {
+        BITFIELDS | ALPHA_BITFIELDS => {
+            let alpha = if header_size >= 56 || compression == ALPHA_BITFIELDS { u32_at(66)? } else { 0 };
+            [u32_at(54)?, u32_at(58)?, u32_at(62)?, alpha]
+        },
+        _ => match bpp {
+            16 => [0x7C00, 0x03E0, 0x001F, 0],
+            // 32 bit images only have alpha if a newer header says where it is
+            _ => [0xFF0000, 0xFF00, 0xFF, if header_size >= 56 { u32_at(66)? } else { 0 }],
+        },
+    };
+    
+    // the palette comes after the header, and the masks if they're not part of it
+    let mut palette_start = 14 + header_size;
+    if header_size == 40 && compression == BITFIELDS { palette_start += 12; }
+    if header_size == 40 && compression == ALPHA_BITFIELDS { palette_start += 16; }
+    let entry = if header_size == 12 { 3 } else { 4 };
+    let palette: Vec<Pixel> = match bpp {
+        1 | 4 | 8 => {
+            let count = if colors == 0 { 1 << bpp } else { colors.min(256) };
+            let bytes = data.get(palette_start..palette_start + count * entry).ok_or(Malformed("bmp palette"))?;
+            bytes.chunks(entry).map(|c| Pixel::rgb(c[2], c[1], c[0])).collect()
+        },
+        _ => Vec::new(),
+    };
+    let color = |index: usize| palette.get(index).copied().ok_or(Malformed("bmp palette index"));
+    
+    let pixels = data.get(offset..).ok_or(Malformed("bmp pixel data"))?;
+    let mut image = Image::new(size);
+    let row_of = |y: usize| if top_down { y } else { size.height - 1 - y };
+    match (compression, bpp) {
+        (RLE8, 8) | (RLE4, 4) => rle(pixels, bpp, &mut image, color)?,
+        (RGB, 1 | 4 | 8) => {
+            let stride = (size.width * bpp as usize).div_ceil(32) * 4;
+            for y in 0..size.height {
+                let row = pixels.get(y * stride..(y + 1) * stride).ok_or(Malformed("bmp pixel data"))?;
+                for x in 0..size.width {
+                    let bit = x * bpp as usize;
+                    let index = (row[bit / 8] >> (8 - bpp as usize - bit % 8)) as usize & ((1 << bpp) - 1);
+                    image.set_pixel(PixelIdx::new(x, row_of(y)), color(index)?);
+                }
+            }
+        },
+        (RGB | BITFIELDS | ALPHA_BITFIELDS, 16 | 24 | 32) => {
+            let bytes = bpp as usize / 8;
+            let stride = (size.width * bytes).div_ceil(4) * 4;
+            for y in 0..size.height {
+                let row = pixels.get(y * stride..(y + 1) * stride).ok_or(Malformed("bmp pixel data"))?;
+                for (x, p) in row.chunks(bytes).take(size.width).enumerate() {
+                    let pixel = match bytes {
+                        3 => Pixel::rgb(p[2], p[1], p[0]),
+                        2 => masked(u16::from_le_bytes([p[0], p[1]]) as u32, masks),
+                        _ => masked(u32::from_le_bytes([p[0], p[1], p[2], p[3]]), masks),
+                    };
+                    image.set_pixel(PixelIdx::new(x, row_of(y)), pixel);
+                }
+            }
+        },
+        (RGB | BITFIELDS | ALPHA_BITFIELDS | RLE8 | RLE4, _) => return Err(Malformed("bmp bit depth")),
+        _ => return Err(Unsupported("bmp compression")),
+    }
+    Ok(image)
+}
+
+// a pixel whose channels are wherever masks say they are, scaled up to 8 bits
+fn masked(value: u32, masks: [u32; 4]) -> Pixel {
+    let channel = |mask: u32| {
+        if mask == 0 { return 0xFF; }
+        let max = mask >> mask.trailing_zeros();
+        ((value & mask) >> mask.trailing_zeros()) as u64 * 255 / max as u64
+    };
+    let [r, g, b, a] = masks.map(|mask| channel(mask) as u8);
+    Pixel::rgba(r, g, b, a)
+}
+
+// run-length encoded pixels, from the bottom up, which can skip over pixels and leave them transparent
+fn rle(
+    data: &[u8],
+    bpp: u16,
+    image: &mut Image,
+    color: impl Fn(usize) -> Result<Pixel,ImageError>,
+) -> Result<(),ImageError> {
+    let size = image.size();
+    let (mut x, mut y) = (0usize, 0usize);
+    let put = |image: &mut Image, x: &mut usize, y: usize, index: usize| -> Result<(),ImageError> {
+        if y < size.height { image.set_pixel(PixelIdx::new(*x, size.height - 1 - y), color(index)?); }
+        *x += 1;
+        Ok(())
+    };
+    let mut pos = 0;
+    let mut byte = || {
+        let b = data.get(pos).copied().ok_or(ImageError::Malformed("bmp pixel data"));
+        pos += 1;
+        b
+    };
+    loop {
+        let (count, value) = (byte()?, byte()?);
+        if count > 0 {
+            // a run of one index, or alternating ones for 4 bit images
+            for i in 0..count {
+                let index = if bpp == 8 { value } else if i % 2 == 0 { value >> 4 } else { value & 0x0F };
+                put(image, &mut x, y, index as usize)?;
+            }
+            continue;
+        }
+        match value {
+            0 => (x, y) = (0, y + 1),
+            1 => return Ok(()),
+            2 => {
+                let (dx, dy) = (byte()?, byte()?);
+                (x, y) = (x + dx as usize, y + dy as usize);
+            },
+            // as many indices as that, padded to a whole number of 16 bit words
+            count => {
+                let bytes = if bpp == 8 { count as usize } else { (count as usize).div_ceil(2) };
+                let mut indices = Vec::with_capacity(bytes);
+                for _ in 0..bytes { indices.push(byte()?); }
+                if bytes % 2 == 1 { byte()?; }
+                for i in 0..count as usize {
+                    let index = if bpp == 8 { indices[i] } else if i % 2 == 0 { indices[i / 2] >> 4 } else { indices[i / 2] & 0x0F };
+                    put(image, &mut x, y, index as usize)?;
+                }
+            },
+        }
+        if y >= size.height { return Ok(()); }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+    
+    // 4x3 rle8: bottom row 3 red then green, middle row white blue green red, top row skipped but for blue at x 1
+    const RLE8: &[u8] = &[
+        0x42, 0x4D, 0x5C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x00,
+        0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x01, 0x00, 0x00, 0x00, 0x16, 0x00, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x04, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x03, 0x00,
+        0x01, 0x01, 0x00, 0x00, 0x00, 0x04, 0x03, 0x02, 0x01, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01, 0x00, 0x01, 0x02, 0x00, 0x01,
+    ];
+    
+    // 5x2 rle4: bottom row alternates red and blue, top row white green white then nothing
+    const RLE4: &[u8] = &[
+        0x42, 0x4D, 0x50, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x02, 0x00,
+        0x00, 0x00, 0x01, 0x00, 0x04, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x04, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0x00, 0x05, 0x02,
+        0x00, 0x00, 0x00, 0x03, 0x31, 0x30, 0x00, 0x01,
+    ];
+    
+    // 3x2 top down 565: red green blue, then white, (8,4,8), black
+    const BITFIELDS: &[u8] = &[
+        0x42, 0x4D, 0x52, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x42, 0x00, 0x00, 0x00, 0x28, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0xFE, 0xFF,
+        0xFF, 0xFF, 0x01, 0x00, 0x10, 0x00, 0x03, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x13, 0x0B, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x00, 0x00, 0xE0, 0x07, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00, 0x00, 0xF8, 0xE0, 0x07, 0x1F, 0x00,
+        0x00, 0x00, 0xFF, 0xFF, 0x21, 0x08, 0x00, 0x00, 0x00, 0x00,
+    ];
+    
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const GREEN: [u8; 4] = [0, 255, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+    const CLEAR: [u8; 4] = [0, 0, 0, 0];
+    
+    fn rows(image: &Image) -> Vec<Vec<[u8; 4]>> {
+        (0..image.height()).map(|y| image.row(y).iter().map(|p| [p.r, p.g, p.b, p.a]).collect()).collect()
+    }
+    
+    #[test]
+    fn rle8() {
+        let image = decode(RLE8).unwrap();
+        assert_eq!(image.size(), Size {width: 4, height: 3});
+        assert_eq!(rows(&image), [
+            vec![CLEAR, BLUE, CLEAR, CLEAR],
+            vec![WHITE, BLUE, GREEN, RED],
+            vec![RED, RED, RED, GREEN],
+        ]);
+    }
+    
+    #[test]
+    fn rle4() {
+        let image = decode(RLE4).unwrap();
+        assert_eq!(rows(&image), [
+            vec![WHITE, GREEN, WHITE, CLEAR, CLEAR],
+            vec![RED, BLUE, RED, BLUE, RED],
+        ]);
+        // a run that never gets to its end of image
+        assert!(decode(&RLE4[..RLE4.len() - 2]).is_err());
+    }
+    
+    #[test]
+    fn bitfields() {
+        let image = decode(BITFIELDS).unwrap();
+        assert_eq!(rows(&image), [
+            vec![RED, GREEN, BLUE],
+            vec![WHITE, [8, 4, 8, 255], [0, 0, 0, 255]],
+        ]);
+    }
+}
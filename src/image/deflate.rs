@@ -0,0 +1,114 @@
+use super::inflate::{DISTANCE_BASE,DISTANCE_EXTRA,LENGTH_BASE,LENGTH_EXTRA,adler32};
+
+// zlib streams of deflate data, for writing pngs
+// one block with the fixed huffman codes, and back-references found through a hash of the next three bytes;
+// not as small as zlib makes them, but close enough for screenshots, which repeat a lot
+
+const WINDOW: usize = 32768;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64; // candidates to try per position, before settling for the best so far
+const HASH_BITS: u32 = 15;
+
+pub(super) fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut bits = BitWriter {out: vec![0x78, 0x01], buffer: 0, count: 0};
+    bits.write(1, 1); // last block
+    bits.write(1, 2); // fixed codes
+    
+    let hash = |i: usize| {
+        let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+        (v.wrapping_mul(0x9E3779B1) >> (32 - HASH_BITS)) as usize
+    };
+    // most recent position with each hash, and the one before each position with the same hash
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut previous = vec![usize::MAX; data.len()];
+    let insert = |i: usize, head: &mut [usize], previous: &mut [usize]| {
+        if i + MIN_MATCH > data.len() { return; }
+        let h = hash(i);
+        previous[i] = head[h];
+        head[h] = i;
+    };
+    
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(i)];
+            let max = MAX_MATCH.min(data.len() - i);
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || i - candidate > WINDOW { break; }
+                let len = data[candidate..].iter().zip(&data[i..i + max]).take_while(|(a, b)| a == b).count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, i - candidate);
+                    if len == max { break; }
+                }
+                candidate = previous[candidate];
+            }
+        }
+        
+        if best_len >= MIN_MATCH {
+            bits.length(best_len);
+            bits.distance(best_distance);
+            for k in i..i + best_len { insert(k, &mut head, &mut previous); }
+            i += best_len;
+        } else {
+            bits.literal(data[i] as u16);
+            insert(i, &mut head, &mut previous);
+            i += 1;
+        }
+    }
+    bits.literal(256);
+    
+    let mut out = bits.finish();
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+// bits go in from the least significant bit of each byte, but huffman codes go in from their most significant bit
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u32,
+    count: u32,
+} impl BitWriter {
+    fn write(&mut self, value: u32, n: u32) {
+        self.buffer |= value << self.count;
+        self.count += n;
+        while self.count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+    
+    fn code(&mut self, code: u32, n: u32) {
+        self.write(code.reverse_bits() >> (32 - n), n);
+    }
+    
+    // literal bytes, the end of the block, and lengths, as the fixed codes have them
+    fn literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xC0 + symbol - 280, 8),
+        }
+    }
+    
+    fn length(&mut self, len: usize) {
+        let i = LENGTH_BASE.iter().rposition(|base| *base as usize <= len).unwrap_or(0);
+        self.literal(257 + i as u16);
+        self.write((len - LENGTH_BASE[i] as usize) as u32, LENGTH_EXTRA[i] as u32);
+    }
+    
+    fn distance(&mut self, distance: usize) {
+        let i = DISTANCE_BASE.iter().rposition(|base| *base as usize <= distance).unwrap_or(0);
+        self.code(i as u32, 5);
+        self.write((distance - DISTANCE_BASE[i] as usize) as u32, DISTANCE_EXTRA[i] as u32);
+    }
+    
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 { self.out.push(self.buffer as u8); }
+        self.out
+    }
+}
\ No newline at end of file
@@ -0,0 +1,224 @@
+use crate::{Pixel,PixelIdx};
+
+use super::{Image,ImageError};
+
+// the first frame of a gif, on a canvas the size of the whole animation
+
+const MAX_CODE_SIZE: u32 = 12;
+
+pub(super) fn decode(data: &[u8]) -> Result<Image,ImageError> {
+    use ImageError::*;
+    
+    if !data.starts_with(b"GIF87a") && !data.starts_with(b"GIF89a") { return Err(Malformed("gif signature")); }
+    let mut r = Reader {data, pos: 6};
+    let width = r.u16()? as usize;
+    let height = r.u16()? as usize;
+    let flags = r.u8()?;
+    r.skip(2)?; // background color and aspect ratio, which nothing draws with anymore
+    let global = if flags & 0x80 != 0 { palette(&mut r, flags)? } else { Vec::new() };
+    // the canvas waits for a frame with data in it, so a header alone can't ask for a huge allocation
+    let screen = super::checked_size(width, height)?;
+    
+    let mut transparent = None;
+    loop {
+        match r.u8()? {
+            // extensions, of which only graphic control matters: it says which index is transparent
+            0x21 => {
+                let label = r.u8()?;
+                if label == 0xF9 {
+                    let block = r.bytes(r.data.get(r.pos).copied().ok_or(Malformed("gif extension"))? as usize + 1)?;
+                    if block.len() >= 5 && block[1] & 1 != 0 { transparent = Some(block[4]); }
+                }
+                r.sub_blocks()?;
+            },
+            0x2C => {
+                let (left, top) = (r.u16()? as usize, r.u16()? as usize);
+                let frame = super::checked_size(r.u16()? as usize, r.u16()? as usize)?;
+                let (width, height) = (frame.width, frame.height);
+                let flags = r.u8()?;
+                let local = if flags & 0x80 != 0 { Some(palette(&mut r, flags)?) } else { None };
+                let palette = local.as_ref().unwrap_or(&global);
+                if palette.is_empty() { return Err(Malformed("gif palette")); }
+                
+                let code_size = r.u8()? as u32;
+                if !(1..MAX_CODE_SIZE).contains(&code_size) { return Err(Malformed("gif code size")); }
+                // only as much room as could land on the canvas is set aside up front; the rest grows as it decodes
+                let visible = width.min(screen.width.saturating_sub(left)) * height.min(screen.height.saturating_sub(top));
+                let indices = lzw(&r.sub_blocks()?, code_size, width * height, visible)?;
+                if indices.is_empty() { return Err(Malformed("gif image data")); }
+                let mut image = Image::new(screen);
+                
+                // interlaced rows come in four passes: every 8th from 0, every 8th from 4, every 4th from 2, then the rest
+                let rows: Vec<usize> = if flags & 0x40 != 0 {
+                    [(0, 8), (4, 8), (2, 4), (1, 2)].into_iter().flat_map(|(start, step)| (start..height).step_by(step)).collect()
+                } else {
+                    (0..height).collect()
+                };
+                for (row, y) in indices.chunks(width.max(1)).zip(rows) {
+                    for (x, index) in row.iter().enumerate() {
+                        if Some(*index) == transparent { continue; }
+                        let color = palette.get(*index as usize).copied().unwrap_or(Pixel::BLACK);
+                        image.set_pixel(PixelIdx::new(left + x, top + y), color);
+                    }
+                }
+                return Ok(image);
+            },
+            0x3B => return Err(Malformed("gif without any images")),
+            _ => return Err(Malformed("gif block")),
+        }
+    }
+}
+
+fn palette(r: &mut Reader, flags: u8) -> Result<Vec<Pixel>,ImageError> {
+    let count = 2 << (flags & 7);
+    Ok(r.bytes(count * 3)?.chunks(3).map(|c| Pixel::rgb(c[0], c[1], c[2])).collect())
+}
+
+// variable length lzw codes, packed from the least significant bit up
+// decodes up to len indices; frames that stop short leave the rest transparent
+fn lzw(data: &[u8], min_code_size: u32, len: usize, capacity: usize) -> Result<Vec<u8>,ImageError> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    // each code is a previous code with one more index on the end
+    let mut prefixes = [0u16; 4096];
+    let mut suffixes = [0u8; 4096];
+    let mut firsts = [0u8; 4096];
+    for i in 0..clear {
+        suffixes[i as usize] = i as u8;
+        firsts[i as usize] = i as u8;
+    }
+    
+    let mut out = Vec::with_capacity(capacity.min(len));
+    let mut stack = Vec::new();
+    let (mut size, mut next, mut previous) = (min_code_size + 1, end + 1, None::<u16>);
+    let (mut buffer, mut bits, mut pos) = (0u32, 0u32, 0usize);
+    while out.len() < len {
+        while bits < size {
+            let Some(byte) = data.get(pos) else { return Ok(out) };
+            buffer |= (*byte as u32) << bits;
+            bits += 8;
+            pos += 1;
+        }
+        let code = (buffer & ((1 << size) - 1)) as u16;
+        buffer >>= size;
+        bits -= size;
+        
+        if code == clear {
+            (size, next, previous) = (min_code_size + 1, end + 1, None);
+            continue;
+        }
+        if code == end { break; }
+        let Some(prev) = previous else {
+            if code >= clear { return Err(ImageError::Malformed("gif image data")); }
+            out.push(code as u8);
+            previous = Some(code);
+            continue;
+        };
+        
+        // a code that's about to be defined is the previous one with its own first index on the end
+        let first = match code {
+            _ if code < next => firsts[code as usize],
+            _ if code == next => firsts[prev as usize],
+            _ => return Err(ImageError::Malformed("gif image data")),
+        };
+        if next < 4096 {
+            prefixes[next as usize] = prev;
+            suffixes[next as usize] = first;
+            firsts[next as usize] = firsts[prev as usize];
+            next += 1;
+            if next == 1 << size && size < MAX_CODE_SIZE { size += 1; }
+        }
+        let mut c = code;
+        while c > end {
+            stack.push(suffixes[c as usize]);
+            c = prefixes[c as usize];
+        }
+        stack.push(c as u8);
+        out.extend(stack.drain(..).rev());
+        previous = Some(code);
+    }
+    out.truncate(len);
+    Ok(out)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+} impl<'a> Reader<'a> {
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8],ImageError> {
+        let bytes = self.data.get(self.pos..self.pos + n).ok_or(ImageError::Malformed("gif data"))?;
+        self.pos += n;
+        Ok(bytes)
+    }
+    
+    fn u8(&mut self) -> Result<u8,ImageError> { Ok(self.bytes(1)?[0]) }
+    fn u16(&mut self) -> Result<u16,ImageError> { self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]])) }
+    fn skip(&mut self, n: usize) -> Result<(),ImageError> { self.bytes(n).map(|_| ()) }
+    
+    // blocks of up to 255 bytes, each after its length, until an empty one
+    fn sub_blocks(&mut self) -> Result<Vec<u8>,ImageError> {
+        let mut out = Vec::new();
+        loop {
+            let len = self.u8()? as usize;
+            if len == 0 { return Ok(out); }
+            out.extend_from_slice(self.bytes(len)?);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Size;
+    
+    // 7x11 screen with a 5x10 interlaced frame at (1,1) of (x + 2y) % 4 in red, green, blue and clear
+    const INTERLACED: &[u8] = &[
+        0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x07, 0x00, 0x0B, 0x00, 0x81, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF,
+        0xFF, 0x21, 0xF9, 0x04, 0x01, 0x00, 0x00, 0x03, 0x00, 0x2C, 0x01, 0x00, 0x01, 0x00, 0x05, 0x00, 0x0A, 0x00, 0x40, 0x02, 0x0D, 0x44, 0x34, 0x60,
+        0xA8, 0x97, 0xFB, 0x1C, 0x60, 0x87, 0x0A, 0x8B, 0xD3, 0x29, 0x00, 0x3B,
+    ];
+    
+    // a two color gif whose one frame is however big it says, with data for just its first pixel
+    fn gif(screen: (u16, u16), frame: (u16, u16), data: &[u8]) -> Vec<u8> {
+        let mut out = b"GIF89a".to_vec();
+        out.extend(screen.0.to_le_bytes());
+        out.extend(screen.1.to_le_bytes());
+        out.extend([0x80, 0, 0, 0, 0, 0, 255, 255, 255, 0x2C, 0, 0, 0, 0]);
+        out.extend(frame.0.to_le_bytes());
+        out.extend(frame.1.to_le_bytes());
+        out.extend([0, 2, data.len() as u8]);
+        out.extend(data);
+        out.extend([0, 0x3B]);
+        out
+    }
+    
+    // clear, index 1, end, in 3 bit codes
+    const ONE_WHITE: &[u8] = &[0x4C, 0x01];
+    
+    #[test]
+    fn interlaced() {
+        let image = decode(INTERLACED).unwrap();
+        assert_eq!(image.size(), Size {width: 7, height: 11});
+        let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255], [0, 0, 0, 0]];
+        for y in 0..11 {
+            for x in 0..7 {
+                let p = image.get_pixel(PixelIdx::new(x, y));
+                let expected = if (1..6).contains(&x) && y >= 1 { colors[(x - 1 + 2 * (y - 1)) % 4] } else { [0, 0, 0, 0] };
+                assert_eq!([p.r, p.g, p.b, p.a], expected, "({x},{y})");
+            }
+        }
+    }
+    
+    #[test]
+    fn bounds() {
+        let image = decode(&gif((1, 1), (1, 1), ONE_WHITE)).unwrap();
+        let p = image.get_pixel(PixelIdx::new(0, 0));
+        assert_eq!([p.r, p.g, p.b, p.a], [255, 255, 255, 255]);
+        // a frame far bigger than the canvas only keeps what lands on it
+        assert_eq!(decode(&gif((1, 1), (16384, 16384), ONE_WHITE)).unwrap().size(), Size {width: 1, height: 1});
+        // too many pixels to ever decode
+        assert!(decode(&gif((1, 1), (65535, 65535), ONE_WHITE)).is_err());
+        // a huge canvas with nothing to put on it
+        assert!(decode(&gif((16384, 16384), (16384, 16384), &[0x2C])).is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use super::ImageError;
+
+// zlib streams (rfc 1950) of deflate data (rfc 1951), as png uses
+
+const MALFORMED: ImageError = ImageError::Malformed("compressed data");
+
+// length and distance codes start at a base and add some extra bits
+pub(super) const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+pub(super) const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+pub(super) const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769,
+    1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+pub(super) const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+// the order code length code lengths come in
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+// limit is how many bytes the data should come out as; anything that inflates past it is malformed,
+//  which keeps a few bytes of input from asking for gigabytes of output
+pub(super) fn zlib(data: &[u8], limit: usize) -> Result<Vec<u8>,ImageError> {
+    let [cmf, flg, ..] = *data else { return Err(MALFORMED) };
+    if cmf & 0x0F != 8 || !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) { return Err(MALFORMED); }
+    if flg & 0x20 != 0 { return Err(ImageError::Unsupported("preset dictionaries")); }
+    
+    let mut bits = Bits {data, pos: 2, buffer: 0, count: 0};
+    let out = inflate(&mut bits, limit)?;
+    let pos = bits.pos - bits.count as usize / 8; // whole bytes read ahead go back
+    let checksum = data.get(pos..pos + 4).ok_or(MALFORMED)?;
+    if u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]]) != adler32(&out) {
+        return Err(ImageError::Malformed("checksum"));
+    }
+    Ok(out)
+}
+
+pub(super) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    // sums can go this many bytes before they need reducing
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        (a, b) = (a % 65521, b % 65521);
+    }
+    b << 16 | a
+}
+
+fn inflate(bits: &mut Bits, limit: usize) -> Result<Vec<u8>,ImageError> {
+    let mut out = Output {bytes: Vec::new(), limit};
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => stored(bits, &mut out)?,
+            1 => {
+                let mut lengths = [0u8; 288 + 30];
+                lengths[..144].fill(8);
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                lengths[280..288].fill(8);
+                lengths[288..].fill(5);
+                codes(bits, &mut out, &Huffman::new(&lengths[..288])?, &Huffman::new(&lengths[288..])?)?;
+            },
+            2 => {
+                let (literals, distances) = dynamic(bits)?;
+                codes(bits, &mut out, &literals, &distances)?;
+            },
+            _ => return Err(MALFORMED),
+        }
+        if last { return Ok(out.bytes); }
+    }
+}
+
+// what's been inflated so far, and how much it may grow to
+struct Output {
+    bytes: Vec<u8>,
+    limit: usize,
+} impl Output {
+    fn room(&self, n: usize) -> Result<(),ImageError> {
+        if self.bytes.len() + n > self.limit { Err(MALFORMED) } else { Ok(()) }
+    }
+}
+
+fn stored(bits: &mut Bits, out: &mut Output) -> Result<(),ImageError> {
+    // stored blocks start on a byte boundary
+    bits.buffer = 0;
+    bits.count = 0;
+    let header = bits.data.get(bits.pos..bits.pos + 4).ok_or(MALFORMED)?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    if len != !u16::from_le_bytes([header[2], header[3]]) { return Err(MALFORMED); }
+    let start = bits.pos + 4;
+    out.room(len as usize)?;
+    out.bytes.extend_from_slice(bits.data.get(start..start + len as usize).ok_or(MALFORMED)?);
+    bits.pos = start + len as usize;
+    Ok(())
+}
+
+fn dynamic(bits: &mut Bits) -> Result<(Huffman,Huffman),ImageError> {
+    let literals = bits.bits(5)? as usize + 257;
+    let distances = bits.bits(5)? as usize + 1;
+    let code_lengths = bits.bits(4)? as usize + 4;
+    if literals > 286 || distances > 30 { return Err(MALFORMED); }
+    
+    let mut lengths = [0u8; 19];
+    for i in CODE_LENGTH_ORDER.iter().take(code_lengths) { lengths[*i] = bits.bits(3)? as u8; }
+    let code = Huffman::new(&lengths)?;
+    
+    // literal and distance lengths are one sequence, which repeats can run across
+    let mut lengths = vec![0u8; literals + distances];
+    let mut i = 0;
+    while i < lengths.len() {
+        let symbol = code.decode(bits)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => (*lengths[..i].last().ok_or(MALFORMED)?, 3 + bits.bits(2)? as usize),
+            17 => (0, 3 + bits.bits(3)? as usize),
+            _ => (0, 11 + bits.bits(7)? as usize),
+        };
+        if i + repeat > lengths.len() { return Err(MALFORMED); }
+        lengths[i..i + repeat].fill(value);
+        i += repeat;
+    }
+    if lengths[256] == 0 { return Err(MALFORMED); }
+    Ok((Huffman::new(&lengths[..literals])?, Huffman::new(&lengths[literals..])?))
+}
+
+fn codes(bits: &mut Bits, out: &mut Output, literals: &Huffman, distances: &Huffman) -> Result<(),ImageError> {
+    loop {
+        let symbol = literals.decode(bits)? as usize;
+        match symbol {
+            0..=255 => {
+                out.room(1)?;
+                out.bytes.push(symbol as u8);
+            },
+            256 => return Ok(()),
+            _ => {
+                let i = symbol - 257;
+                if i >= LENGTH_BASE.len() { return Err(MALFORMED); }
+                let len = LENGTH_BASE[i] as usize + bits.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                let i = distances.decode(bits)? as usize;
+                if i >= DISTANCE_BASE.len() { return Err(MALFORMED); }
+                let distance = DISTANCE_BASE[i] as usize + bits.bits(DISTANCE_EXTRA[i] as u32)? as usize;
+                if distance > out.bytes.len() { return Err(MALFORMED); }
+                out.room(len)?;
+                // copies can overlap what they're copying, so they go a byte at a time
+                let start = out.bytes.len() - distance;
+                for k in 0..len { out.bytes.push(out.bytes[start + k]); }
+            },
+        }
+    }
+}
+
+// bits are packed starting from the least significant bit of each byte
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+} impl Bits<'_> {
+    fn bits(&mut self, n: u32) -> Result<u32,ImageError> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or(MALFORMED)?;
+            self.pos += 1;
+            self.buffer |= (byte as u32) << self.count;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u32 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+}
+
+// canonical huffman code, given how many bits each symbol's code has
+struct Huffman {
+    counts: [u16; 16], // how many codes there are of each length
+    symbols: Vec<u16>, // sorted by code
+} impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self,ImageError> {
+        let mut counts = [0u16; 16];
+        for len in lengths { counts[*len as usize] += 1; }
+        counts[0] = 0;
+        // more codes of some length than there's room for
+        let mut left = 1i32;
+        for count in &counts[1..] {
+            left = left * 2 - *count as i32;
+            if left < 0 { return Err(MALFORMED); }
+        }
+        
+        let mut offsets = [0u16; 16];
+        for len in 1..15 { offsets[len + 1] = offsets[len] + counts[len]; }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, len) in lengths.iter().enumerate() {
+            if *len == 0 { continue; }
+            symbols[offsets[*len as usize] as usize] = symbol as u16;
+            offsets[*len as usize] += 1;
+        }
+        Ok(Self {counts, symbols})
+    }
+    
+    // one bit at a time, going by how many codes come before each length
+    fn decode(&self, bits: &mut Bits) -> Result<u16,ImageError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for count in &self.counts[1..] {
+            code |= bits.bits(1)? as i32;
+            let count = *count as i32;
+            if code - first < count { return Ok(self.symbols[(index + code - first) as usize]); }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(MALFORMED)
+    }
+}
\ No newline at end of file
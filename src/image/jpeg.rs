@@ -0,0 +1,518 @@
+use crate::Pixel;
+
+use super::{Image,ImageError};
+
+// baseline and extended sequential huffman jpegs, in grayscale, ycbcr or rgb
+
+const MALFORMED: ImageError = ImageError::Malformed("jpeg data");
+// how many zero bytes to make up at a marker before giving up on the scan
+const MAX_MADE_UP: u32 = 64;
+
+// where each coefficient goes, in the order they're stored
+const ZIGZAG: [usize; 64] = [
+    0, 1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+struct Component {
+    id: u8,
+    h: usize, // sampling factors
+    v: usize,
+    quantization: usize,
+    // blocks wide and high, enough to fill whole mcus
+    blocks_wide: usize,
+    blocks_high: usize,
+    samples: Vec<u8>, // blocks_wide * 8 samples per row, grown a block row at a time as scans fill it
+    prediction: i32,
+}
+
+struct Decoder {
+    quantization: [[u16; 64]; 4],
+    dc_tables: [Option<Huffman>; 4],
+    ac_tables: [Option<Huffman>; 4],
+    components: Vec<Component>,
+    width: usize,
+    height: usize,
+    max_h: usize,
+    max_v: usize,
+    restart_interval: usize,
+    adobe_transform: Option<u8>, // from adobe's app14 segment, which says whether 3 components are rgb
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image,ImageError> {
+    use ImageError::*;
+    
+    if !data.starts_with(&[0xFF, 0xD8]) { return Err(Malformed("jpeg signature")); }
+    let mut decoder = Decoder::new();
+    let mut pos = 2;
+    let mut frame = false;
+    loop {
+        // markers can be padded with any number of 0xFF bytes
+        while data.get(pos) == Some(&0xFF) && data.get(pos + 1) == Some(&0xFF) { pos += 1; }
+        let [0xFF, marker] = *data.get(pos..pos + 2).ok_or(Malformed("jpeg marker"))? else {
+            return Err(Malformed("jpeg marker"));
+        };
+        pos += 2;
+        match marker {
+            0xD9 => break,
+            0xD0..=0xD7 | 0x01 => continue,
+            _ => {},
+        }
+        let len = data.get(pos..pos + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).ok_or(MALFORMED)?;
+        let segment = data.get(pos + 2..pos + len.max(2)).ok_or(Malformed("jpeg segment"))?;
+        pos += len;
+        match marker {
+            0xC0 | 0xC1 => {
+                decoder.frame(segment)?;
+                frame = true;
+            },
+            0xC2 | 0xC6 | 0xCA | 0xCE => return Err(Unsupported("progressive jpegs")),
+            0xC3 | 0xC5 | 0xC7 | 0xCB | 0xCD | 0xCF => return Err(Unsupported("lossless and hierarchical jpegs")),
+            0xC9 => return Err(Unsupported("arithmetic coded jpegs")),
+            0xC4 => decoder.huffman_tables(segment)?,
+            0xDB => decoder.quantization_tables(segment)?,
+            0xDD => decoder.restart_interval = segment.get(..2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).ok_or(MALFORMED)?,
+            0xEE if segment.starts_with(b"Adobe") => decoder.adobe_transform = segment.get(11).copied(),
+            0xDA => {
+                if !frame { return Err(Malformed("jpeg scan before frame")); }
+                pos = decoder.scan(segment, data, pos)?;
+            },
+            _ => {},
+        }
+    }
+    if !frame { return Err(Malformed("jpeg without a frame")); }
+    decoder.image()
+}
+
+impl Decoder {
+    fn new() -> Self {
+        Self {
+            quantization: [[1; 64]; 4],
+            dc_tables: Default::default(),
+            ac_tables: Default::default(),
+            components: Vec::new(),
+            width: 0,
+            height: 0,
+            max_h: 1,
+            max_v: 1,
+            restart_interval: 0,
+            adobe_transform: None,
+        }
+    }
+    
+    fn frame(&mut self, segment: &[u8]) -> Result<(),ImageError> {
+        let [precision, h0, h1, w0, w1, count, ref rest @ ..] = *segment else { return Err(MALFORMED) };
+        if precision != 8 { return Err(ImageError::Unsupported("jpegs of more than 8 bits")); }
+        let (width, height) = (u16::from_be_bytes([w0, w1]) as usize, u16::from_be_bytes([h0, h1]) as usize);
+        if height == 0 { return Err(ImageError::Unsupported("jpegs with a height set later")); }
+        super::checked_size(width, height)?;
+        if !matches!(count, 1 | 3) { return Err(ImageError::Unsupported("jpegs that aren't grayscale or three colors")); }
+        let specs = rest.get(..count as usize * 3).ok_or(MALFORMED)?;
+        
+        for spec in specs.chunks(3) {
+            let (h, v) = ((spec[1] >> 4) as usize, (spec[1] & 0x0F) as usize);
+            if !(1..=4).contains(&h) || !(1..=4).contains(&v) || spec[2] > 3 { return Err(MALFORMED); }
+            self.components.push(Component {
+                id: spec[0], h, v, quantization: spec[2] as usize,
+                blocks_wide: 0, blocks_high: 0, samples: Vec::new(), prediction: 0,
+            });
+        }
+        self.max_h = self.components.iter().map(|c| c.h).max().unwrap_or(1);
+        self.max_v = self.components.iter().map(|c| c.v).max().unwrap_or(1);
+        (self.width, self.height) = (width, height);
+        let (mcus_wide, mcus_high) = self.mcus();
+        for c in &mut self.components {
+            c.blocks_wide = mcus_wide * c.h;
+            c.blocks_high = mcus_high * c.v;
+        }
+        Ok(())
+    }
+    
+    fn mcus(&self) -> (usize,usize) {
+        (self.width.div_ceil(8 * self.max_h), self.height.div_ceil(8 * self.max_v))
+    }
+    
+    fn huffman_tables(&mut self, mut segment: &[u8]) -> Result<(),ImageError> {
+        while let [info, ref rest @ ..] = *segment {
+            let counts = rest.get(..16).ok_or(MALFORMED)?;
+            let total: usize = counts.iter().map(|c| *c as usize).sum();
+            let values = rest.get(16..16 + total).ok_or(MALFORMED)?;
+            let table = Some(Huffman::new(counts, values)?);
+            match (info >> 4, info & 0x0F) {
+                (0, id @ 0..=3) => self.dc_tables[id as usize] = table,
+                (1, id @ 0..=3) => self.ac_tables[id as usize] = table,
+                _ => return Err(MALFORMED),
+            }
+            segment = &rest[16 + total..];
+        }
+        Ok(())
+    }
+    
+    fn quantization_tables(&mut self, mut segment: &[u8]) -> Result<(),ImageError> {
+        while let [info, ref rest @ ..] = *segment {
+            let id = (info & 0x0F) as usize;
+            if id > 3 { return Err(MALFORMED); }
+            // 16 bit values for 12 bit jpegs, though baseline ones can have them too
+            let wide = info >> 4 != 0;
+            let len = if wide { 128 } else { 64 };
+            let values = rest.get(..len).ok_or(MALFORMED)?;
+            for (k, q) in self.quantization[id].iter_mut().enumerate() {
+                *q = if wide { u16::from_be_bytes([values[k * 2], values[k * 2 + 1]]) } else { values[k] as u16 };
+            }
+            segment = &rest[len..];
+        }
+        Ok(())
+    }
+    
+    // decodes the entropy coded data after a scan header, returning where it ends
+    fn scan(&mut self, segment: &[u8], data: &[u8], pos: usize) -> Result<usize,ImageError> {
+        let count = *segment.first().ok_or(MALFORMED)? as usize;
+        let specs = segment.get(1..1 + count * 2).ok_or(MALFORMED)?;
+        let mut scan = Vec::with_capacity(count);
+        for spec in specs.chunks(2) {
+            let index = self.components.iter().position(|c| c.id == spec[0]).ok_or(MALFORMED)?;
+            let (dc, ac) = ((spec[1] >> 4) as usize, (spec[1] & 0x0F) as usize);
+            if dc > 3 || ac > 3 || self.dc_tables[dc].is_none() || self.ac_tables[ac].is_none() {
+                return Err(ImageError::Malformed("jpeg huffman table"));
+            }
+            scan.push((index, dc, ac));
+        }
+        if scan.is_empty() { return Err(MALFORMED); }
+        for c in &mut self.components { c.prediction = 0; }
+        
+        let mut bits = Bits {data, pos, buffer: 0, count: 0, made_up: 0};
+        let mut block = [0i32; 64];
+        // scans of one component go through its blocks left to right, top to bottom, covering just the image,
+        // where scans of several go through mcus that each have h by v blocks of every component
+        let (mcus_wide, mcus_high) = if let [(index, ..)] = scan[..] {
+            let c = &self.components[index];
+            (
+                (self.width * c.h).div_ceil(self.max_h * 8),
+                (self.height * c.v).div_ceil(self.max_v * 8),
+            )
+        } else {
+            self.mcus()
+        };
+        let single = scan.len() == 1;
+        let total = mcus_wide * mcus_high;
+        for mcu in 0..total {
+            if self.restart_interval > 0 && mcu > 0 && mcu % self.restart_interval == 0 {
+                bits.restart()?;
+                for c in &mut self.components { c.prediction = 0; }
+            }
+            let (mx, my) = (mcu % mcus_wide, mcu / mcus_wide);
+            for &(index, dc, ac) in &scan {
+                let (h, v) = if single { (1, 1) } else { (self.components[index].h, self.components[index].v) };
+                for by in 0..v {
+                    for bx in 0..h {
+                        let q = &self.quantization[self.components[index].quantization];
+                        let c = &mut self.components[index];
+                        let (dc, ac) = (self.dc_tables[dc].as_ref().unwrap(), self.ac_tables[ac].as_ref().unwrap());
+                        decode_block(&mut bits, dc, ac, q, &mut c.prediction, &mut block)?;
+                        let (x, y) = (mx * h + bx, my * v + by);
+                        let stride = c.blocks_wide * 8;
+                        // planes only grow with the data that fills them, rather than all at once from the frame header
+                        if c.samples.len() < (y + 1) * 8 * stride { c.samples.resize((y + 1) * 8 * stride, 0); }
+                        idct(&block, &mut c.samples[y * 8 * stride + x * 8..], stride);
+                    }
+                }
+            }
+        }
+        Ok(bits.end())
+    }
+    
+    fn image(&mut self) -> Result<Image,ImageError> {
+        let size = super::checked_size(self.width, self.height)?;
+        // every component needs a scan, and single component scans can stop short of the padding blocks
+        for c in &mut self.components {
+            if c.samples.is_empty() { return Err(ImageError::Malformed("jpeg component without a scan")); }
+            c.samples.resize(c.blocks_wide * c.blocks_high * 64, 0);
+        }
+        let mut pixels = Vec::with_capacity(size.width * size.height);
+        // ycbcr unless adobe says otherwise
+        let rgb = self.components.len() == 3 && self.adobe_transform == Some(0);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let mut values = [0u8; 3];
+                for (value, c) in values.iter_mut().zip(&self.components) {
+                    *value = self.sample(c, x, y);
+                }
+                pixels.push(match self.components.len() {
+                    1 => Pixel::rgb(values[0], values[0], values[0]),
+                    _ if rgb => Pixel::rgb(values[0], values[1], values[2]),
+                    _ => ycbcr(values[0], values[1], values[2]),
+                });
+            }
+        }
+        Ok(Image::from_pixels(size, pixels))
+    }
+    
+    // a component's value at an image pixel, interpolated between samples if it has fewer of them
+    fn sample(&self, c: &Component, x: usize, y: usize) -> u8 {
+        let stride = c.blocks_wide * 8;
+        let (w, h) = (stride, c.blocks_high * 8);
+        if c.h == self.max_h && c.v == self.max_v { return c.samples[y * stride + x]; }
+        let fx = ((x as f32 + 0.5) * c.h as f32 / self.max_h as f32 - 0.5).clamp(0.0, (w - 1) as f32);
+        let fy = ((y as f32 + 0.5) * c.v as f32 / self.max_v as f32 - 0.5).clamp(0.0, (h - 1) as f32);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+        let at = |x: usize, y: usize| c.samples[y * stride + x] as f32;
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        (top + (bottom - top) * ty).round() as u8
+    }
+}
+
+fn ycbcr(y: u8, cb: u8, cr: u8) -> Pixel {
+    let (y, cb, cr) = (y as f32, cb as f32 - 128.0, cr as f32 - 128.0);
+    let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+    Pixel::rgb(
+        channel(y + 1.402 * cr),
+        channel(y - 0.344136 * cb - 0.714136 * cr),
+        channel(y + 1.772 * cb),
+    )
+}
+
+fn decode_block(
+    bits: &mut Bits,
+    dc: &Huffman,
+    ac: &Huffman,
+    quantization: &[u16; 64],
+    prediction: &mut i32,
+    block: &mut [i32; 64],
+) -> Result<(),ImageError> {
+    block.fill(0);
+    let size = dc.decode(bits)?;
+    if size > 11 { return Err(MALFORMED); }
+    // 8 bit samples can't make a dc coefficient this big, so only purpose-made files get here
+    *prediction += bits.extended(size)?;
+    if prediction.abs() > 2047 { return Err(MALFORMED); }
+    block[0] = *prediction * quantization[0] as i32;
+    
+    let mut k = 1;
+    while k < 64 {
+        let rs = ac.decode(bits)?;
+        let (run, size) = ((rs >> 4) as usize, rs & 0x0F);
+        if size == 0 {
+            // sixteen zeros, or the rest of them
+            if run != 15 { break; }
+            k += 16;
+            continue;
+        }
+        k += run;
+        if k > 63 { return Err(MALFORMED); }
+        block[ZIGZAG[k]] = bits.extended(size)? * quantization[k] as i32;
+        k += 1;
+    }
+    Ok(())
+}
+
+// inverse dct of a block into 8 rows of out, a row and then a column at a time
+fn idct(block: &[i32; 64], out: &mut [u8], stride: usize) {
+    use std::sync::OnceLock;
+    static COSINES: OnceLock<[[f32; 8]; 8]> = OnceLock::new();
+    let cosines = COSINES.get_or_init(|| {
+        let mut table = [[0.0; 8]; 8];
+        for (x, row) in table.iter_mut().enumerate() {
+            for (u, c) in row.iter_mut().enumerate() {
+                let scale = if u == 0 { std::f32::consts::FRAC_1_SQRT_2 } else { 1.0 };
+                *c = scale * ((2 * x + 1) as f32 * u as f32 * std::f32::consts::PI / 16.0).cos() / 2.0;
+            }
+        }
+        table
+    });
+    
+    let mut rows = [0.0f32; 64];
+    for y in 0..8 {
+        for x in 0..8 {
+            rows[y * 8 + x] = (0..8).map(|u| cosines[x][u] * block[y * 8 + u] as f32).sum();
+        }
+    }
+    for x in 0..8 {
+        for y in 0..8 {
+            let v: f32 = (0..8).map(|v| cosines[y][v] * rows[v * 8 + x]).sum();
+            out[y * stride + x] = (v + 128.0).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// entropy coded bits, most significant first, where a 0xFF byte is followed by a 0 that isn't data
+// at a marker, it makes up zeros rather than reading past it, though only enough to finish off a block or two
+struct Bits<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+    made_up: u32,
+} impl Bits<'_> {
+    fn bit(&mut self) -> Result<u32,ImageError> {
+        if self.count == 0 {
+            let byte = match self.data.get(self.pos..self.pos + 2) {
+                Some([0xFF, 0x00]) => { self.pos += 2; 0xFF },
+                Some([0xFF, _]) if self.made_up < MAX_MADE_UP => { self.made_up += 1; 0 },
+                Some([0xFF, _]) => return Err(MALFORMED),
+                _ => match self.data.get(self.pos) {
+                    Some(byte) => { self.pos += 1; *byte },
+                    None => return Err(MALFORMED),
+                },
+            };
+            (self.buffer, self.count) = (byte as u32, 8);
+        }
+        self.count -= 1;
+        Ok((self.buffer >> self.count) & 1)
+    }
+    
+    fn bits(&mut self, n: u8) -> Result<u32,ImageError> {
+        let mut value = 0;
+        for _ in 0..n { value = value << 1 | self.bit()?; }
+        Ok(value)
+    }
+    
+    // n bits of a signed value, where ones starting with 0 are negative
+    fn extended(&mut self, n: u8) -> Result<i32,ImageError> {
+        if n == 0 { return Ok(0); }
+        let value = self.bits(n)? as i32;
+        Ok(if value < 1 << (n - 1) { value - (1 << n) + 1 } else { value })
+    }
+    
+    // restart markers come every so many mcus, on a byte boundary
+    fn restart(&mut self) -> Result<(),ImageError> {
+        (self.count, self.made_up) = (0, 0);
+        match self.data.get(self.pos..self.pos + 2) {
+            Some([0xFF, 0xD0..=0xD7]) => { self.pos += 2; Ok(()) },
+            _ => Err(ImageError::Malformed("jpeg restart marker")),
+        }
+    }
+    
+    // where the next marker is, past whatever the scan didn't use
+    fn end(&self) -> usize {
+        let mut pos = self.pos;
+        while let Some(window) = self.data.get(pos..pos + 2) {
+            if window[0] == 0xFF && window[1] != 0 && !(0xD0..=0xD7).contains(&window[1]) { return pos; }
+            pos += 1;
+        }
+        self.data.len()
+    }
+}
+
+struct Huffman {
+    max_codes: [i32; 17], // the largest code of each length, or -1 if there aren't any
+    offsets: [i32; 17],   // what to add to a code of each length for the index of its value
+    values: Vec<u8>,
+} impl Huffman {
+    fn new(counts: &[u8], values: &[u8]) -> Result<Self,ImageError> {
+        let (mut max_codes, mut offsets) = ([-1i32; 17], [0i32; 17]);
+        let (mut code, mut index) = (0i32, 0i32);
+        for len in 1..=16 {
+            let count = counts[len - 1] as i32;
+            offsets[len] = index - code;
+            if count > 0 {
+                code += count;
+                index += count;
+                max_codes[len] = code - 1;
+            }
+            if code > 1 << len { return Err(ImageError::Malformed("jpeg huffman table")); }
+            code <<= 1;
+        }
+        Ok(Self {max_codes, offsets, values: values.to_vec()})
+    }
+    
+    fn decode(&self, bits: &mut Bits) -> Result<u8,ImageError> {
+        let mut code = 0i32;
+        for len in 1..=16 {
+            code = code << 1 | bits.bit()? as i32;
+            if code <= self.max_codes[len] {
+                return self.values.get((code + self.offsets[len]) as usize).copied().ok_or(MALFORMED);
+            }
+        }
+        Err(MALFORMED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // 32x16 4:2:0 with a restart marker between its two mcus; y = 40 + 4x + 2y, and per chroma sample cb = 96 + 4x and cr = 96 + 8y
+    const SUBSAMPLED: &[u8] = &[
+        0xFF, 0xD8, 0xFF, 0xDB, 0x00, 0x43, 0x00, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01,
+        0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0xFF,
+        0xC0, 0x00, 0x11, 0x08, 0x00, 0x10, 0x00, 0x20, 0x03, 0x01, 0x22, 0x00, 0x02, 0x11, 0x00, 0x03, 0x11, 0x00, 0xFF, 0xC4, 0x00, 0xD2, 0x00, 0x00,
+        0x01, 0x05, 0x01, 0x01, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08,
+        0x09, 0x0A, 0x0B, 0x10, 0x00, 0x02, 0x01, 0x03, 0x03, 0x02, 0x04, 0x03, 0x05, 0x05, 0x04, 0x04, 0x00, 0x00, 0x01, 0x7D, 0x01, 0x02, 0x03, 0x00,
+        0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51, 0x61, 0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xA1, 0x08, 0x23, 0x42, 0xB1, 0xC1,
+        0x15, 0x52, 0xD1, 0xF0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0A, 0x16, 0x17, 0x18, 0x19, 0x1A, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2A, 0x34, 0x35,
+        0x36, 0x37, 0x38, 0x39, 0x3A, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4A, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5A, 0x63, 0x64, 0x65,
+        0x66, 0x67, 0x68, 0x69, 0x6A, 0x73, 0x74, 0x75, 0x76, 0x77, 0x78, 0x79, 0x7A, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8A, 0x92, 0x93, 0x94,
+        0x95, 0x96, 0x97, 0x98, 0x99, 0x9A, 0xA2, 0xA3, 0xA4, 0xA5, 0xA6, 0xA7, 0xA8, 0xA9, 0xAA, 0xB2, 0xB3, 0xB4, 0xB5, 0xB6, 0xB7, 0xB8, 0xB9, 0xBA,
+        0xC2, 0xC3, 0xC4, 0xC5, 0xC6, 0xC7, 0xC8, 0xC9, 0xCA, 0xD2, 0xD3, 0xD4, 0xD5, 0xD6, 0xD7, 0xD8, 0xD9, 0xDA, 0xE1, 0xE2, 0xE3, 0xE4, 0xE5, 0xE6,
+        0xE7, 0xE8, 0xE9, 0xEA, 0xF1, 0xF2, 0xF3, 0xF4, 0xF5, 0xF6, 0xF7, 0xF8, 0xF9, 0xFA, 0xFF, 0xDD, 0x00, 0x04, 0x00, 0x01, 0xFF, 0xDA, 0x00, 0x0C,
+        0x03, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x00, 0x3F, 0x00, 0xFE, 0x79, 0xFE, 0x1B, 0x78, 0x6F, 0xFE, 0x3D, 0xFF, 0x00, 0x77, 0xFD, 0xDE, 0xDF,
+        0x4A, 0xFD, 0x00, 0xF8, 0x6D, 0xE1, 0xBF, 0xF8, 0xF7, 0xFD, 0xDF, 0xF7, 0x7B, 0x7D, 0x2B, 0xE7, 0xFF, 0x00, 0x86, 0xDE, 0x1B, 0xFF, 0x00, 0x8F,
+        0x7F, 0xDD, 0xFF, 0x00, 0x77, 0xB7, 0xD2, 0xBF, 0x40, 0x3E, 0x1B, 0x78, 0x6F, 0xFE, 0x3D, 0xFF, 0x00, 0x77, 0xFD, 0xDE, 0xDF, 0x4A, 0xF9, 0xBF,
+        0xE1, 0xB7, 0xFC, 0xBB, 0xFF, 0x00, 0xC0, 0x7F, 0xA5, 0x73, 0xFF, 0x00, 0xF0, 0xCD, 0xBF, 0xF4, 0xE1, 0xFF, 0x00, 0x90, 0xBF, 0xFA, 0xD5, 0xFF,
+        0xD0, 0xC7, 0xF8, 0x6D, 0xE1, 0xBF, 0xF8, 0xF7, 0xFD, 0xDF, 0xF7, 0x7B, 0x7D, 0x2B, 0xF4, 0x03, 0xE1, 0xB7, 0x86, 0xFF, 0x00, 0xE3, 0xDF, 0xF7,
+        0x7F, 0xDD, 0xED, 0xF4, 0xAF, 0x9F, 0xFE, 0x1B, 0x78, 0x6F, 0xFE, 0x3D, 0xFF, 0x00, 0x77, 0xFD, 0xDE, 0xDF, 0x4A, 0xFD, 0x00, 0xF8, 0x6D, 0xE1,
+        0xBF, 0xF8, 0xF7, 0xFD, 0xDF, 0xF7, 0x7B, 0x7D, 0x2B, 0xDC, 0x3E, 0x1B, 0x7F, 0xCB, 0xBF, 0xFC, 0x07, 0xFA, 0x57, 0x3F, 0xFF, 0x00, 0x0C, 0xDB,
+        0xFF, 0x00, 0x4E, 0x1F, 0xF9, 0x0B, 0xFF, 0x00, 0xAD, 0x5F, 0xFF, 0xD9,
+    ];
+    
+    // a grayscale jpeg whose dc and ac tables each have one code, 0, for dc_size and the end of a block
+    fn gray(width: u16, height: u16, dc_size: u8, data: &[u8]) -> Vec<u8> {
+        let mut out = vec![0xFF, 0xD8, 0xFF, 0xDB, 0, 67, 0];
+        out.extend([1; 64]);
+        out.extend([0xFF, 0xC0, 0, 11, 8]);
+        out.extend(height.to_be_bytes());
+        out.extend(width.to_be_bytes());
+        out.extend([1, 1, 0x11, 0]);
+        for (class, value) in [(0x00, dc_size), (0x10, 0)] {
+            out.extend([0xFF, 0xC4, 0, 20, class, 1]);
+            out.extend([0; 15]);
+            out.push(value);
+        }
+        out.extend([0xFF, 0xDA, 0, 8, 1, 1, 0x00, 0, 63, 0]);
+        out.extend(data);
+        out.extend([0xFF, 0xD9]);
+        out
+    }
+    
+    #[test]
+    fn subsampled() {
+        let image = decode(SUBSAMPLED).unwrap();
+        assert_eq!(image.size(), crate::Size {width: 32, height: 16});
+        for y in 0..16 {
+            for x in 0..32 {
+                // chroma is interpolated between the samples around each pixel
+                let fx = ((x as f32 + 0.5) / 2.0 - 0.5).clamp(0.0, 15.0);
+                let fy = ((y as f32 + 0.5) / 2.0 - 0.5).clamp(0.0, 7.0);
+                let expected = ycbcr((40 + 4 * x + 2 * y) as u8, (96.0 + 4.0 * fx).round() as u8, (96.0 + 8.0 * fy).round() as u8);
+                let p = image.get_pixel(crate::PixelIdx::new(x, y));
+                for (got, want) in [p.r, p.g, p.b].into_iter().zip([expected.r, expected.g, expected.b]) {
+                    assert!(got.abs_diff(want) <= 3, "({x},{y}): {got} vs {want}");
+                }
+            }
+        }
+        
+        // without its restart marker, the second mcu is out of place
+        let marker = SUBSAMPLED.windows(2).position(|w| w == [0xFF, 0xD0]).unwrap();
+        let data = [&SUBSAMPLED[..marker], &SUBSAMPLED[marker + 2..]].concat();
+        assert!(matches!(decode(&data), Err(ImageError::Malformed("jpeg restart marker"))));
+    }
+    
+    #[test]
+    fn dc_overflow() {
+        // 0, then 2047 in 11 bits, then 0 for the end of the block, padded with ones
+        assert!(decode(&gray(8, 8, 11, &[0x7F, 0xF7])).is_ok());
+        // a second block adds another 2047, more than any 8 bit block could have
+        assert!(decode(&gray(16, 8, 11, &[0x7F, 0xF3, 0xFF, 0x00, 0xBF])).is_err());
+    }
+    
+    #[test]
+    fn missing_data() {
+        // every block is two zero bits, so a header alone would be enough to fill the whole thing with made up zeros
+        assert!(decode(&gray(8, 8, 0, &[0x3F])).is_ok());
+        assert!(decode(&gray(16384, 16384, 0, &[])).is_err());
+    }
+}
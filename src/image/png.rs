@@ -0,0 +1,429 @@
+use crate::{Pixel,Size,simd};
+
+use super::{Image,ImageError,deflate,inflate};
+
+pub(super) const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+// where each pass of adam7 interlacing starts, and how far apart its pixels are: x, y, dx, dy
+const ADAM7: [(usize,usize,usize,usize); 7] = [
+    (0, 0, 8, 8), (4, 0, 8, 8), (0, 4, 4, 8), (2, 0, 4, 4), (0, 2, 2, 4), (1, 0, 2, 2), (0, 1, 1, 2),
+];
+
+#[derive(Clone,Copy,PartialEq)]
+enum Color {
+    Gray,
+    Rgb,
+    Palette,
+    GrayAlpha,
+    Rgba,
+} impl Color {
+    fn channels(self) -> usize {
+        match self {
+            Color::Gray | Color::Palette => 1,
+            Color::GrayAlpha => 2,
+            Color::Rgb => 3,
+            Color::Rgba => 4,
+        }
+    }
+}
+
+pub(super) fn decode(data: &[u8]) -> Result<Image,ImageError> {
+    use ImageError::*;
+    
+    if !data.starts_with(&SIGNATURE) { return Err(Malformed("png signature")); }
+    let mut header = None;
+    let mut palette: Vec<Pixel> = Vec::new();
+    let mut transparency: Option<&[u8]> = None;
+    let mut compressed = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let chunk = data.get(pos..pos + 8).ok_or(Malformed("png chunk"))?;
+        let len = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as usize;
+        let kind = &data[pos + 4..pos + 8];
+        let body = data.get(pos + 8..(pos + 8).saturating_add(len)).ok_or(Malformed("png chunk"))?;
+        let crc = data.get(pos + 8 + len..pos + 12 + len).ok_or(Malformed("png chunk"))?;
+        if u32::from_be_bytes([crc[0], crc[1], crc[2], crc[3]]) != crc32(&data[pos + 4..pos + 8 + len]) {
+            return Err(Malformed("png checksum"));
+        }
+        pos += 12 + len;
+        
+        match kind {
+            b"IHDR" => header = Some(Header::new(body)?),
+            b"PLTE" => {
+                if !len.is_multiple_of(3) || len > 256 * 3 { return Err(Malformed("png palette")); }
+                palette = body.chunks(3).map(|c| Pixel::rgb(c[0], c[1], c[2])).collect();
+            },
+            b"tRNS" => transparency = Some(body),
+            b"IDAT" => compressed.extend_from_slice(body),
+            b"IEND" => break,
+            // chunks that can be skipped have a lowercase first letter
+            _ if kind[0].is_ascii_uppercase() => return Err(Unsupported("critical png chunk")),
+            _ => {},
+        }
+    }
+    
+    let header = header.ok_or(Malformed("png header"))?;
+    if header.color == Color::Palette {
+        if palette.is_empty() { return Err(Malformed("png palette")); }
+        for (pixel, alpha) in palette.iter_mut().zip(transparency.unwrap_or_default()) { pixel.a = *alpha; }
+    }
+    // the one color that's transparent, for images without alpha
+    let key = match (header.color, transparency) {
+        (Color::Gray, Some(t)) if t.len() >= 2 => Some([u16::from_be_bytes([t[0], t[1]]), 0, 0]),
+        (Color::Rgb, Some(t)) if t.len() >= 6 => Some([
+            u16::from_be_bytes([t[0], t[1]]), u16::from_be_bytes([t[2], t[3]]), u16::from_be_bytes([t[4], t[5]]),
+        ]),
+        _ => None,
+    };
+    
+    let size = header.size;
+    let passes: &[(usize,usize,usize,usize)] = if header.interlaced { &ADAM7 } else { &[(0, 0, 1, 1)] };
+    // (width,height,bytes per row) of each pass; every row also has a filter byte
+    let pass_size = |(x0, y0, dx, dy): (usize,usize,usize,usize)| {
+        let width = (size.width + dx - 1 - x0) / dx;
+        let height = (size.height + dy - 1 - y0) / dy;
+        (width, height, (width * header.color.channels() * header.depth as usize).div_ceil(8))
+    };
+    let expected = passes.iter()
+        .map(|pass| pass_size(*pass))
+        .filter(|(width, height, _)| *width > 0 && *height > 0)
+        .map(|(_, height, stride)| (stride + 1) * height)
+        .sum();
+    let raw = inflate::zlib(&compressed, expected)?;
+    
+    let mut pixels = vec![Pixel::TRANSPARENT; size.width * size.height];
+    let mut pos = 0;
+    for &(x0, y0, dx, dy) in passes {
+        let (width, height, stride) = pass_size((x0, y0, dx, dy));
+        if width == 0 || height == 0 { continue; }
+        let len = (stride + 1) * height;
+        let pass = raw.get(pos..pos + len).ok_or(Malformed("png image data"))?;
+        pos += len;
+        
+        let rows = unfilter(pass, stride, header.bytes_per_pixel())?;
+        for (row, y) in rows.chunks(stride).zip((y0..).step_by(dy)) {
+            for (i, x) in (x0..).step_by(dx).take(width).enumerate() {
+                pixels[y * size.width + x] = header.pixel(row, i, &palette, key)?;
+            }
+        }
+    }
+    Ok(Image::from_pixels(size, pixels))
+}
+
+struct Header {
+    size: Size,
+    depth: u8,
+    color: Color,
+    interlaced: bool,
+} impl Header {
+    fn new(body: &[u8]) -> Result<Self,ImageError> {
+        use ImageError::*;
+        
+        if body.len() != 13 { return Err(Malformed("png header")); }
+        let width = u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize;
+        let height = u32::from_be_bytes([body[4], body[5], body[6], body[7]]) as usize;
+        let depth = body[8];
+        let color = match body[9] {
+            0 => Color::Gray,
+            2 => Color::Rgb,
+            3 => Color::Palette,
+            4 => Color::GrayAlpha,
+            6 => Color::Rgba,
+            _ => return Err(Malformed("png color type")),
+        };
+        let depths: &[u8] = match color {
+            Color::Gray => &[1, 2, 4, 8, 16],
+            Color::Palette => &[1, 2, 4, 8],
+            _ => &[8, 16],
+        };
+        if !depths.contains(&depth) { return Err(Malformed("png bit depth")); }
+        if body[10] != 0 || body[11] != 0 { return Err(Unsupported("png compression method")); }
+        let interlaced = match body[12] {
+            0 => false,
+            1 => true,
+            _ => return Err(Malformed("png interlace method")),
+        };
+        Ok(Self {size: super::checked_size(width, height)?, depth, color, interlaced})
+    }
+    
+    // filters work on whole bytes, so pixels of less than a byte count as one
+    fn bytes_per_pixel(&self) -> usize { (self.color.channels() * self.depth as usize).div_ceil(8) }
+    
+    // sample i of a row, at whatever depth the image is
+    fn sample(&self, row: &[u8], i: usize) -> u16 {
+        match self.depth {
+            16 => u16::from_be_bytes([row[i * 2], row[i * 2 + 1]]),
+            8 => row[i] as u16,
+            depth => {
+                let per_byte = 8 / depth as usize;
+                let shift = 8 - depth as usize * (i % per_byte + 1);
+                (row[i / per_byte] >> shift) as u16 & ((1 << depth) - 1)
+            },
+        }
+    }
+    
+    fn pixel(&self, row: &[u8], x: usize, palette: &[Pixel], key: Option<[u16; 3]>) -> Result<Pixel,ImageError> {
+        let channels = self.color.channels();
+        let sample = |c: usize| self.sample(row, x * channels + c);
+        // down to 8 bits, or up from less
+        let scale = |v: u16| match self.depth {
+            16 => (v >> 8) as u8,
+            8 => v as u8,
+            depth => (v as u32 * 255 / ((1 << depth) - 1)) as u8,
+        };
+        let opaque = |samples: [u16; 3]| if key == Some(samples) { 0 } else { 0xFF };
+        Ok(match self.color {
+            Color::Gray => {
+                let v = sample(0);
+                Pixel::rgba(scale(v), scale(v), scale(v), opaque([v, 0, 0]))
+            },
+            Color::Rgb => {
+                let (r, g, b) = (sample(0), sample(1), sample(2));
+                Pixel::rgba(scale(r), scale(g), scale(b), opaque([r, g, b]))
+            },
+            Color::Palette => *palette.get(sample(0) as usize).ok_or(ImageError::Malformed("png palette index"))?,
+            Color::GrayAlpha => {
+                let v = scale(sample(0));
+                Pixel::rgba(v, v, v, scale(sample(1)))
+            },
+            Color::Rgba => Pixel::rgba(scale(sample(0)), scale(sample(1)), scale(sample(2)), scale(sample(3))),
+        })
+    }
+}
+
+// undoes each row's filter, which predicts bytes from the ones to the left and above
+fn unfilter(data: &[u8], stride: usize, bpp: usize) -> Result<Vec<u8>,ImageError> {
+    let mut out = vec![0u8; data.len() / (stride + 1) * stride];
+    for (y, line) in data.chunks(stride + 1).enumerate() {
+        let (done, rest) = out.split_at_mut(y * stride);
+        let previous = if y > 0 { &done[(y - 1) * stride..] } else { &[][..] };
+        let row = &mut rest[..stride];
+        row.copy_from_slice(&line[1..]);
+        let up = |i: usize| previous.get(i).copied().unwrap_or(0);
+        for i in 0..stride {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up_left = if i >= bpp { up(i - bpp) } else { 0 };
+            let prediction = match line[0] {
+                0 => 0,
+                1 => left,
+                2 => up(i),
+                3 => ((left as u16 + up(i) as u16) / 2) as u8,
+                4 => paeth(left, up(i), up_left),
+                _ => return Err(ImageError::Malformed("png filter type")),
+            };
+            row[i] = row[i].wrapping_add(prediction);
+        }
+    }
+    Ok(out)
+}
+
+// 8-bit rgb, or rgba when any pixel isn't opaque
+pub(super) fn encode(image: &Image) -> Vec<u8> {
+    let opaque = image.pixels().iter().all(|p| p.a == 255);
+    let (color, bpp) = if opaque { (2u8, 3) } else { (6u8, 4) };
+    let stride = image.width() * bpp;
+    
+    let mut raw = Vec::with_capacity(image.height() * (stride + 1));
+    let mut previous = vec![0u8; stride];
+    let mut row = Vec::with_capacity(stride);
+    for y in 0..image.height() {
+        row.clear();
+        if bpp == 4 {
+            row.resize(stride, 0);
+            simd::to_rgba(image.row(y), &mut row);
+        } else {
+            for p in image.row(y) { row.extend_from_slice(&[p.r, p.g, p.b]); }
+        }
+        filter(&row, &previous, bpp, &mut raw);
+        std::mem::swap(&mut row, &mut previous);
+    }
+    
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&(image.width() as u32).to_be_bytes());
+    header.extend_from_slice(&(image.height() as u32).to_be_bytes());
+    header.extend_from_slice(&[8, color, 0, 0, 0]);
+    
+    let mut out = SIGNATURE.to_vec();
+    chunk(&mut out, b"IHDR", &header);
+    chunk(&mut out, b"IDAT", &deflate::zlib(&raw));
+    chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+// filters a row with whichever filter leaves the smallest bytes, counting them as signed, which tends to compress best
+fn filter(row: &[u8], previous: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let mut best: Option<(u64,u8,Vec<u8>)> = None;
+    for kind in 0..5u8 {
+        let filtered: Vec<u8> = (0..row.len()).map(|i| {
+            let left = if i >= bpp { row[i - bpp] } else { 0 };
+            let up_left = if i >= bpp { previous[i - bpp] } else { 0 };
+            let prediction = match kind {
+                0 => 0,
+                1 => left,
+                2 => previous[i],
+                3 => ((left as u16 + previous[i] as u16) / 2) as u8,
+                _ => paeth(left, previous[i], up_left),
+            };
+            row[i].wrapping_sub(prediction)
+        }).collect();
+        let cost = filtered.iter().map(|b| (*b as i8).unsigned_abs() as u64).sum();
+        if best.as_ref().is_none_or(|(least, _, _)| cost < *least) { best = Some((cost, kind, filtered)); }
+    }
+    let (_, kind, filtered) = best.unwrap();
+    out.push(kind);
+    out.extend_from_slice(&filtered);
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc { a } else if pb <= pc { b } else { c }
+}
+
+pub(super) fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    !data.iter().fold(!0u32, |c, byte| TABLE[((c ^ *byte as u32) & 0xFF) as usize] ^ (c >> 8))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PixelIdx;
+    
+    // 5x3, 2 bit palette of red, green, blue, white; index (x + y) % 4; trns makes red clear and green half
+    const PALETTED: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03,
+        0x02, 0x03, 0x00, 0x00, 0x00, 0x26, 0x58, 0x2D, 0x6B, 0x00, 0x00, 0x00, 0x0C, 0x50, 0x4C, 0x54, 0x45, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00,
+        0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFB, 0x00, 0x60, 0xF6, 0x00, 0x00, 0x00, 0x02, 0x74, 0x52, 0x4E, 0x53, 0x00, 0x80, 0x9B, 0x2B, 0x4E, 0x18, 0x00,
+        0x00, 0x00, 0x11, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x63, 0x90, 0x66, 0x60, 0xCC, 0xB9, 0xC2, 0xE4, 0xEA, 0x00, 0x00, 0x07, 0x23, 0x01, 0xE4,
+        0x55, 0x4A, 0x65, 0x75, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+    
+    // 3x2, 16 bit rgba
+    const RGBA16: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x02,
+        0x10, 0x06, 0x00, 0x00, 0x00, 0xCD, 0xE4, 0xBA, 0x59, 0x00, 0x00, 0x00, 0x2F, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x63, 0x10, 0x32, 0xF9, 0xCF,
+        0xC0, 0xF0, 0xFF, 0xFF, 0x7F, 0x95, 0x8C, 0xFF, 0x0C, 0xC6, 0x40, 0xDA, 0x6C, 0xCE, 0x7F, 0x86, 0x34, 0x20, 0xCD, 0x28, 0x64, 0xF2, 0x0E, 0x2C,
+        0x21, 0x64, 0xC2, 0xC0, 0x60, 0xCC, 0x70, 0x80, 0x01, 0x46, 0x03, 0x00, 0x62, 0x44, 0x14, 0xD6, 0x85, 0x2B, 0x35, 0xFD, 0x00, 0x00, 0x00, 0x00,
+        0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+    
+    // 11x10 adam7 rgb, r = x * 20, g = y * 20, b = x * y * 3
+    const INTERLACED: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x0B, 0x00, 0x00, 0x00, 0x0A,
+        0x08, 0x02, 0x00, 0x00, 0x01, 0x9A, 0x95, 0x03, 0x42, 0x00, 0x00, 0x00, 0xDD, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x7D, 0x8F, 0x41, 0x4A, 0xC4,
+        0x40, 0x10, 0x45, 0x5F, 0x26, 0x43, 0x60, 0x9A, 0xA0, 0x18, 0xBE, 0xD8, 0x18, 0x0C, 0x8A, 0x41, 0xCB, 0x01, 0x21, 0xAB, 0x59, 0xE5, 0x00, 0x2E,
+        0x6B, 0xED, 0x6A, 0x16, 0x73, 0x88, 0x1C, 0x62, 0x0E, 0xD1, 0x87, 0x98, 0x03, 0xB8, 0xCC, 0xB1, 0xEC, 0x64, 0x21, 0xE2, 0x42, 0x78, 0x34, 0x45,
+        0x57, 0xF3, 0xDF, 0x6F, 0x80, 0x04, 0x45, 0x3E, 0x12, 0x5F, 0x78, 0x1E, 0x3D, 0x1D, 0xC9, 0x83, 0xFB, 0x90, 0xFC, 0x88, 0xC1, 0x04, 0x73, 0xBE,
+        0x37, 0x8F, 0xCE, 0x90, 0xD9, 0xE0, 0x11, 0xFF, 0xC0, 0xF3, 0xC2, 0x30, 0xAB, 0xDD, 0xE2, 0x64, 0x7D, 0xB2, 0x61, 0xB6, 0xB1, 0xC8, 0xCF, 0x8D,
+        0xFE, 0x37, 0x08, 0x46, 0x38, 0xC1, 0x19, 0x2E, 0x39, 0x49, 0x56, 0x19, 0xF5, 0x0F, 0x1B, 0xAC, 0xC2, 0x1A, 0xAC, 0xC3, 0xF6, 0xD8, 0xA1, 0x0C,
+        0x5E, 0x4B, 0xAD, 0xF4, 0x24, 0xBD, 0x4A, 0xEF, 0xDB, 0x65, 0x4F, 0x03, 0x1D, 0xEC, 0x61, 0x20, 0x07, 0x4A, 0xA5, 0xA9, 0x1A, 0xB5, 0x73, 0xD5,
+        0x27, 0x5D, 0x4F, 0x6A, 0xCE, 0xBA, 0x4D, 0x8A, 0x17, 0xB5, 0xB3, 0xBA, 0x22, 0xFB, 0xC4, 0xEE, 0x1F, 0x36, 0xCB, 0xC7, 0x16, 0x6D, 0xBD, 0x9A,
+        0xE3, 0x2A, 0xEF, 0x57, 0xFF, 0x90, 0x2B, 0x60, 0x63, 0xC9, 0x27, 0x41, 0x57, 0x41, 0x37, 0x41, 0x0A, 0xBA, 0x0B, 0xBA, 0x0F, 0x7A, 0x08, 0x7A,
+        0x0C, 0x7A, 0x0E, 0x7A, 0x09, 0x7A, 0xDB, 0x2E, 0x19, 0xE4, 0x6A, 0xF5, 0xDA, 0x2E, 0x42, 0xFB, 0x87, 0x6F, 0xC3, 0xB0, 0x25, 0xF9, 0x31, 0x07,
+        0xE5, 0xB6, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+    
+    // 4x2, 4 bit gray of (x * 5 + y) % 16, where 5 is transparent
+    const GRAY_KEY: &[u8] = &[
+        0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x02,
+        0x04, 0x00, 0x00, 0x00, 0x00, 0x9F, 0x33, 0xCF, 0xBE, 0x00, 0x00, 0x00, 0x02, 0x74, 0x52, 0x4E, 0x53, 0x00, 0x05, 0x06, 0xF9, 0x39, 0xB7, 0x00,
+        0x00, 0x00, 0x0E, 0x49, 0x44, 0x41, 0x54, 0x78, 0xDA, 0x63, 0x60, 0x5D, 0xCF, 0x28, 0x36, 0x0B, 0x00, 0x03, 0xA4, 0x01, 0x66, 0x1B, 0xC4, 0x85,
+        0x65, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+    ];
+    
+    fn rgba(image: &Image, x: usize, y: usize) -> [u8; 4] {
+        let p = image.get_pixel(PixelIdx::new(x, y));
+        [p.r, p.g, p.b, p.a]
+    }
+    
+    fn chunk(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = (body.len() as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out.extend_from_slice(&crc32(&out[4..]).to_be_bytes());
+        out
+    }
+    
+    #[test]
+    fn paletted() {
+        let image = decode(PALETTED).unwrap();
+        assert_eq!(image.size(), Size {width: 5, height: 3});
+        let colors = [[255, 0, 0, 0], [0, 255, 0, 128], [0, 0, 255, 255], [255, 255, 255, 255]];
+        for y in 0..3 {
+            for x in 0..5 { assert_eq!(rgba(&image, x, y), colors[(x + y) % 4], "({x},{y})"); }
+        }
+    }
+    
+    #[test]
+    fn sixteen_bit() {
+        let image = decode(RGBA16).unwrap();
+        assert_eq!(image.size(), Size {width: 3, height: 2});
+        for y in 0..2 {
+            for x in 0..3 {
+                let samples = [0x1234 * (x + 1), 0xFF00 - y * 0x1100, 0x00FF + x * 0x3300, 0xFFFF - x * y * 0x4000];
+                assert_eq!(rgba(&image, x, y), samples.map(|s| (s >> 8) as u8), "({x},{y})");
+            }
+        }
+    }
+    
+    #[test]
+    fn interlaced() {
+        let image = decode(INTERLACED).unwrap();
+        assert_eq!(image.size(), Size {width: 11, height: 10});
+        for y in 0..10 {
+            for x in 0..11 { assert_eq!(rgba(&image, x, y), [(x * 20) as u8, (y * 20) as u8, (x * y * 3) as u8, 255], "({x},{y})"); }
+        }
+    }
+    
+    #[test]
+    fn color_key() {
+        let image = decode(GRAY_KEY).unwrap();
+        for y in 0..2 {
+            for x in 0..4 {
+                let v = ((x * 5 + y) % 16) as u8;
+                assert_eq!(rgba(&image, x, y), [v * 17, v * 17, v * 17, if v == 5 { 0 } else { 255 }], "({x},{y})");
+            }
+        }
+    }
+    
+    // a few bytes that inflate to far more than the header has room for
+    #[test]
+    fn inflate_limit() {
+        let header = [0, 0, 0, 1, 0, 0, 0, 1, 8, 0, 0, 0, 0];
+        let mut data = SIGNATURE.to_vec();
+        data.extend(chunk(b"IHDR", &header));
+        data.extend(chunk(b"IDAT", &deflate::zlib(&[0; 1 << 20])));
+        data.extend(chunk(b"IEND", &[]));
+        assert!(matches!(decode(&data), Err(ImageError::Malformed("compressed data"))));
+        
+        let mut data = SIGNATURE.to_vec();
+        data.extend(chunk(b"IHDR", &header));
+        data.extend(chunk(b"IDAT", &deflate::zlib(&[0, 7])));
+        data.extend(chunk(b"IEND", &[]));
+        assert_eq!(rgba(&decode(&data).unwrap(), 0, 0), [7, 7, 7, 255]);
+    }
+}
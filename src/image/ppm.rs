@@ -0,0 +1,102 @@
+use crate::Pixel;
+
+use super::{Image,ImageError};
+
+// netpbm bitmaps, graymaps and pixmaps (p1 to p6), in both their plain text and binary forms
+
+pub(super) fn decode(data: &[u8]) -> Result<Image,ImageError> {
+    use ImageError::*;
+    
+    let kind = match data {
+        [b'P', kind @ b'1'..=b'6', ..] => kind - b'0',
+        _ => return Err(Malformed("netpbm signature")),
+    };
+    let mut header = Header {data, pos: 2};
+    let width = header.number()?;
+    let height = header.number()?;
+    // bitmaps have no maximum; their 1 is black
+    let max = if kind == 1 || kind == 4 { 1 } else { header.number()? };
+    if max == 0 || max > 0xFFFF { return Err(Malformed("netpbm maximum value")); }
+    let size = super::checked_size(width, height)?;
+    let count = size.width * size.height;
+    
+    let channels = match kind {
+        3 | 6 => 3,
+        _ => 1,
+    };
+    let samples: Vec<u16> = match kind {
+        1 => {
+            // digits don't need whitespace between them, but each one is still a byte
+            let mut samples = Vec::with_capacity(count.min(data.len()));
+            while samples.len() < count {
+                header.whitespace();
+                match data.get(header.pos) {
+                    Some(b @ (b'0' | b'1')) => samples.push((b - b'0') as u16),
+                    _ => return Err(Malformed("netpbm pixel data")),
+                }
+                header.pos += 1;
+            }
+            samples
+        },
+        2 | 3 => (0..count * channels).map(|_| {
+            header.number().map(|v| v.min(max) as u16).map_err(|_| Malformed("netpbm pixel data"))
+        }).collect::<Result<_,_>>()?,
+        _ => {
+            // exactly one whitespace char between the header and binary data
+            let body = data.get(header.pos + 1..).ok_or(Malformed("netpbm pixel data"))?;
+            if kind == 4 {
+                let stride = size.width.div_ceil(8);
+                let bits = body.get(..stride * size.height).ok_or(Malformed("netpbm pixel data"))?;
+                bits.chunks(stride).flat_map(|row| (0..size.width).map(|x| (row[x / 8] >> (7 - x % 8)) as u16 & 1)).collect()
+            } else {
+                let bytes = if max > 0xFF { 2 } else { 1 };
+                let body = body.get(..count * channels * bytes).ok_or(Malformed("netpbm pixel data"))?;
+                match bytes {
+                    2 => body.chunks(2).map(|b| u16::from_be_bytes([b[0], b[1]]).min(max as u16)).collect(),
+                    _ => body.iter().map(|b| (*b as u16).min(max as u16)).collect(),
+                }
+            }
+        },
+    };
+    
+    let scale = |v: u16| (v as u32 * 255 / max as u32) as u8;
+    let pixels = match kind {
+        1 | 4 => samples.iter().map(|v| if *v == 1 { Pixel::BLACK } else { Pixel::WHITE }).collect(),
+        2 | 5 => samples.iter().map(|v| { let v = scale(*v); Pixel::rgb(v, v, v) }).collect(),
+        _ => samples.chunks(3).map(|c| Pixel::rgb(scale(c[0]), scale(c[1]), scale(c[2]))).collect(),
+    };
+    Ok(Image::from_pixels(size, pixels))
+}
+
+// binary pixmap (p6); it has no alpha, so colors are written as they are and alpha is dropped
+pub(super) fn encode(image: &Image) -> Vec<u8> {
+    let mut out = format!("P6\n{} {}\n255\n", image.width(), image.height()).into_bytes();
+    out.reserve(image.pixels().len() * 3);
+    for p in image.pixels() { out.extend_from_slice(&[p.r, p.g, p.b]); }
+    out
+}
+
+// the header is whitespace separated numbers, with comments from # to the end of the line
+struct Header<'a> {
+    data: &'a [u8],
+    pos: usize,
+} impl Header<'_> {
+    fn whitespace(&mut self) {
+        while let Some(b) = self.data.get(self.pos) {
+            match b {
+                b'#' => while self.data.get(self.pos).is_some_and(|b| *b != b'\n' && *b != b'\r') { self.pos += 1; },
+                _ if b.is_ascii_whitespace() => self.pos += 1,
+                _ => return,
+            }
+        }
+    }
+    
+    fn number(&mut self) -> Result<usize,ImageError> {
+        self.whitespace();
+        let start = self.pos;
+        while self.data.get(self.pos).is_some_and(u8::is_ascii_digit) { self.pos += 1; }
+        std::str::from_utf8(&self.data[start..self.pos]).ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or(ImageError::Malformed("netpbm header"))
+    }
+}
\ No newline at end of file
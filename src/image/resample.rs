@@ -0,0 +1,129 @@
+use std::f32::consts::PI;
+
+use crate::{Pixel,Size};
+
+use super::Image;
+
+// how to pick colors for pixels that fall between those of the original, when scaling an image
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Filter {
+    Nearest,  // blocky, but keeps pixel art sharp
+    Bilinear,
+    #[default]
+    Bicubic,  // catmull-rom, which is smooth without blurring much
+    Lanczos,  // sharpest, with slight ringing around hard edges
+} impl Filter {
+    // how many source pixels to either side are weighed in, before scaling down widens it
+    fn support(self) -> f32 {
+        match self {
+            Filter::Nearest => 0.5,
+            Filter::Bilinear => 1.0,
+            Filter::Bicubic => 2.0,
+            Filter::Lanczos => 3.0,
+        }
+    }
+    
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Filter::Nearest => if x < 0.5 { 1.0 } else { 0.0 },
+            Filter::Bilinear => (1.0 - x).max(0.0),
+            Filter::Bicubic => {
+                const A: f32 = -0.5;
+                if x < 1.0 {
+                    ((A + 2.0) * x - (A + 3.0)) * x * x + 1.0
+                } else if x < 2.0 {
+                    ((A * x - 5.0 * A) * x + 8.0 * A) * x - 4.0 * A
+                } else {
+                    0.0
+                }
+            },
+            Filter::Lanczos => {
+                if x < 1e-6 { return 1.0; }
+                if x >= 3.0 { return 0.0; }
+                let px = PI * x;
+                3.0 * px.sin() * (px / 3.0).sin() / (px * px)
+            },
+        }
+    }
+}
+
+// which source pixels go into each destination pixel along one axis, and how much each counts
+struct Weights {
+    starts: Vec<usize>,
+    weights: Vec<Vec<f32>>,
+} impl Weights {
+    fn new(from: usize, to: usize, filter: Filter) -> Self {
+        let ratio = from as f32 / to as f32;
+        // scaling down widens the filter, so every source pixel counts towards something
+        let scale = ratio.max(1.0);
+        let support = filter.support() * scale;
+        let (mut starts, mut weights) = (Vec::with_capacity(to), Vec::with_capacity(to));
+        for i in 0..to {
+            let center = (i as f32 + 0.5) * ratio;
+            let start = (center - support).floor().max(0.0) as usize;
+            let end = ((center + support).ceil() as usize).min(from);
+            let mut w: Vec<f32> = (start..end).map(|j| filter.weight((j as f32 + 0.5 - center) / scale)).collect();
+            let total: f32 = w.iter().sum();
+            if total.abs() > f32::EPSILON {
+                w.iter_mut().for_each(|w| *w /= total);
+            } else {
+                // nothing in range, which can only happen to nearest; take the closest pixel
+                let nearest = (center as usize).min(from - 1);
+                starts.push(nearest);
+                weights.push(vec![1.0]);
+                continue;
+            }
+            starts.push(start);
+            weights.push(w);
+        }
+        Self {starts, weights}
+    }
+}
+
+impl Image {
+    // the whole image, scaled to size
+    // colors are mixed premultiplied, so transparent pixels don't bleed their color into their neighbours
+    pub fn resized(&self, size: Size, filter: Filter) -> Image {
+        if size == self.size() { return self.clone(); }
+        if size.width == 0 || size.height == 0 || self.width() == 0 || self.height() == 0 {
+            return Image::new(size);
+        }
+        
+        let premultiplied: Vec<[f32; 4]> = self.pixels().iter().map(|p| {
+            let a = p.a as f32 / 255.0;
+            [p.r as f32 * a, p.g as f32 * a, p.b as f32 * a, p.a as f32]
+        }).collect();
+        
+        // across first, into an image that's as wide as the result and as tall as the original
+        let (width, height) = (self.width(), self.height());
+        let across = Weights::new(width, size.width, filter);
+        let mut wide = vec![[0.0f32; 4]; size.width * height];
+        for y in 0..height {
+            let row = &premultiplied[y * width..(y + 1) * width];
+            for x in 0..size.width {
+                wide[y * size.width + x] = mix(across.weights[x].iter().enumerate().map(|(k, w)| (&row[across.starts[x] + k], *w)));
+            }
+        }
+        
+        let down = Weights::new(height, size.height, filter);
+        let mut pixels = Vec::with_capacity(size.width * size.height);
+        for y in 0..size.height {
+            for x in 0..size.width {
+                let [r, g, b, a] = mix(down.weights[y].iter().enumerate().map(|(k, w)| (&wide[(down.starts[y] + k) * size.width + x], *w)));
+                // filters with negative lobes can overshoot
+                let a = a.clamp(0.0, 255.0);
+                let channel = |v: f32| if a > 0.0 { (v * 255.0 / a).round().clamp(0.0, 255.0) as u8 } else { 0 };
+                pixels.push(Pixel::rgba(channel(r), channel(g), channel(b), a.round() as u8));
+            }
+        }
+        Image::from_pixels(size, pixels)
+    }
+}
+
+fn mix<'a>(samples: impl Iterator<Item = (&'a [f32; 4], f32)>) -> [f32; 4] {
+    samples.fold([0.0; 4], |mut sum, (p, w)| {
+        for c in 0..4 { sum[c] += p[c] * w; }
+        sum
+    })
+}
\ No newline at end of file
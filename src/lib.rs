@@ -4,10 +4,18 @@
 pub mod window;
 pub mod guicell;
 pub mod keys;
+pub mod draw;
+pub mod image;
+pub mod blend;
+pub mod simd;
+pub mod display;
+pub mod text;
+pub mod testing;
 
 pub use window::init;
 
 use std::ops::{Add,AddAssign,Sub};
+use std::time::Duration;
 use std::sync::OnceLock;
 
 mod private { pub struct Internal; }
@@ -23,6 +31,15 @@ pub struct Partition<T: Partitionable> {
 pub trait Partitionable: Sized {
     // result is left,right or top,bottom
     fn partition(&self,dir: Direction,dst: Distance) -> Partition<Self>;
+    
+    // area is relative to the top-left of self
+    fn subslice(&self, area: PixelIndexSlice) -> Self {
+        use Direction::*;
+        self.partition(Horizontal, Distance::Pixels(area.offset.x)).children.1
+            .partition(Horizontal, Distance::Pixels(area.size.width)).children.0
+            .partition(Vertical, Distance::Pixels(area.offset.y)).children.1
+            .partition(Vertical, Distance::Pixels(area.size.height)).children.0
+    }
 }
 
 #[derive(Clone,Copy,Debug,PartialEq)]
@@ -44,11 +61,71 @@ pub enum CommonEvent {
     KeyUp(keys::Key),
     Maximize,
     Minimize,
-    MouseMove,
+    MouseMove(MousePos),
+    MouseDown(MouseButton,MousePos),
+    MouseUp(MouseButton,MousePos),
+    MouseWheel(MousePos,f32,f32), // horizontal,vertical in notches; positive is right/away from the user
     Resize,
     QueryByCursor,
     SetCursor,
     Shutdown,
+    Text(char),     // a character typed, after keyboard layout and modifiers; KeyDown/KeyUp are the keys themselves
+    Tick(Duration), // time passed since the last tick, for anything that animates
+} impl CommonEvent {
+    
+    pub fn position(&self) -> Option<MousePos> {
+        match self {
+            CommonEvent::MouseMove(pos)
+            | CommonEvent::MouseDown(_,pos)
+            | CommonEvent::MouseUp(_,pos)
+            | CommonEvent::MouseWheel(pos,_,_) => Some(*pos),
+            _ => None,
+        }
+    }
+    
+    // events that are only meant for whatever is under the cursor
+    // other mouse events go to everyone, so that e.g. drags can end outside of the cell that started them
+    pub fn is_targeted(&self) -> bool {
+        matches!(self, CommonEvent::MouseDown(..) | CommonEvent::MouseWheel(..))
+    }
+    
+    // the same event, with positions relative to origin
+    pub fn relative_to(&self, origin: MousePos) -> Self {
+        let rel = |pos: MousePos| MousePos {x: pos.x - origin.x, y: pos.y - origin.y};
+        match *self {
+            CommonEvent::MouseMove(pos)          => CommonEvent::MouseMove(rel(pos)),
+            CommonEvent::MouseDown(b,pos)        => CommonEvent::MouseDown(b,rel(pos)),
+            CommonEvent::MouseUp(b,pos)          => CommonEvent::MouseUp(b,rel(pos)),
+            CommonEvent::MouseWheel(pos,dx,dy)   => CommonEvent::MouseWheel(rel(pos),dx,dy),
+            e => e,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+// cursor position relative to whoever receives the event
+// unlike PixelIdx, this can be negative, since the cursor may be outside of the receiver
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct MousePos {
+    pub x: isize,
+    pub y: isize,
+} impl MousePos {
+    pub fn new(x: isize, y: isize) -> Self { Self {x,y} }
+    
+    pub fn to_idx(self) -> Option<PixelIdx> {
+        if self.x < 0 || self.y < 0 { return None; }
+        Some(PixelIdx::new(self.x as usize, self.y as usize))
+    }
+} impl From<PixelIdx> for MousePos {
+    fn from(idx: PixelIdx) -> Self {
+        Self {x: idx.x as isize, y: idx.y as isize}
+    }
 }
 
 // Custom events must be Send because threads can send events to each other's windows
@@ -73,6 +150,28 @@ pub enum Event<T: 'static + Send> {
 #[derive(Clone,Copy,Debug,Default)]
 pub struct Pixel {
     pub b: u8, pub g: u8, pub r: u8, pub a: u8
+} impl Pixel {
+    pub const TRANSPARENT: Pixel = Pixel::rgba(0x00, 0x00, 0x00, 0x00);
+    pub const BLACK:       Pixel = Pixel::rgb (0x00, 0x00, 0x00);
+    pub const WHITE:       Pixel = Pixel::rgb (0xFF, 0xFF, 0xFF);
+    
+    // alpha is straight (not premultiplied), and 0xFF is opaque
+    pub const fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self { Self {b,g,r,a} }
+    pub const fn rgb (r: u8, g: u8, b: u8) -> Self { Self::rgba(r, g, b, 0xFF) }
+    
+    pub const fn with_alpha(self, a: u8) -> Self { Self {a, ..self} }
+    
+    // linear interpolation between self (t = 0) and other (t = 1), channel by channel
+    pub fn lerp(self, other: Pixel, t: f32) -> Pixel {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * t).round() as u8;
+        Pixel {
+            b: mix(self.b, other.b),
+            g: mix(self.g, other.g),
+            r: mix(self.r, other.r),
+            a: mix(self.a, other.a),
+        }
+    }
 } impl From<u32> for Pixel {
     fn from(a: u32) -> Self {
         let mut rt = Pixel::default();
@@ -87,7 +186,7 @@ pub struct Pixel {
     }
 }
 
-#[derive(Clone,Copy,Debug,Default)]
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
 pub struct PixelIdx {
     pub x: usize,
     pub y: usize,
@@ -106,7 +205,7 @@ pub struct PixelIdx {
     }
 }
 
-#[derive(Copy,Clone,Debug,Default)]
+#[derive(Copy,Clone,Debug,Default,PartialEq)]
 pub struct PixelIndexSlice {
     pub offset: PixelIdx,
     pub size: Size,
@@ -114,24 +213,65 @@ pub struct PixelIndexSlice {
     
     pub fn size(&self) -> Size { self.size }
     
+    pub fn is_empty(&self) -> bool { self.size.width == 0 || self.size.height == 0 }
+    
     pub fn contains(&self, idx: PixelIdx) -> bool {
         idx.x >= self.offset.x && idx.y >= self.offset.y 
         && idx.x < self.offset.x + self.size.width && idx.y < self.offset.y + self.size.height
     }
+    
+    // shrink each side by the given amount, never past zero size
+    pub fn inset(&self, insets: Insets) -> Self {
+        let width  = self.size.width .saturating_sub(insets.left + insets.right );
+        let height = self.size.height.saturating_sub(insets.top  + insets.bottom);
+        Self {
+            offset: PixelIdx::new(
+                self.offset.x + insets.left.min(self.size.width ),
+                self.offset.y + insets.top .min(self.size.height),
+            ),
+            size: Size {width, height},
+        }
+    }
+    
+    // overlapping area, or a default (empty) slice if there is none
+    pub fn intersect(&self, other: &Self) -> Self {
+        let left   = self.offset.x.max(other.offset.x);
+        let top    = self.offset.y.max(other.offset.y);
+        let right  = (self.offset.x + self.size.width ).min(other.offset.x + other.size.width );
+        let bottom = (self.offset.y + self.size.height).min(other.offset.y + other.size.height);
+        if left >= right || top >= bottom { return Self::default(); }
+        Self {
+            offset: PixelIdx::new(left, top),
+            size: Size {width: right - left, height: bottom - top},
+        }
+    }
+    
+    // smallest area covering both; empty slices don't count
+    pub fn union(&self, other: &Self) -> Self {
+        if self.is_empty() { return *other; }
+        if other.is_empty() { return *self; }
+        let left   = self.offset.x.min(other.offset.x);
+        let top    = self.offset.y.min(other.offset.y);
+        let right  = (self.offset.x + self.size.width ).max(other.offset.x + other.size.width );
+        let bottom = (self.offset.y + self.size.height).max(other.offset.y + other.size.height);
+        Self {
+            offset: PixelIdx::new(left, top),
+            size: Size {width: right - left, height: bottom - top},
+        }
+    }
 } impl Partitionable for PixelIndexSlice {
     fn partition(&self, dir: Direction, dst: Distance) -> Partition<Self> {
         
+        let len = match dir {
+            Direction::Horizontal => self.size.width,
+            Direction::Vertical => self.size.height
+        };
+        
+        // clamped so that the second child can't underflow
         let px_len: usize = match dst {
             Distance::Pixels(px) => px,
-            Distance::Relative(p) => {
-                (
-                    match dir {
-                        Direction::Horizontal => self.size.width,
-                        Direction::Vertical => self.size.height
-                    } as f64 * p as f64
-                ) as usize
-            }
-        };
+            Distance::Relative(p) => (len as f64 * p as f64) as usize,
+        }.min(len);
         
         Partition {
             children: match dir {
@@ -162,6 +302,35 @@ pub struct PixelIndexSlice {
     }
 }
 
+// per-side distances, e.g. for padding, margins and borders
+#[derive(Copy,Clone,Debug,Default,PartialEq)]
+pub struct Insets {
+    pub top: usize,
+    pub right: usize,
+    pub bottom: usize,
+    pub left: usize,
+} impl Insets {
+    
+    pub const ZERO: Self = Insets {top: 0, right: 0, bottom: 0, left: 0};
+    
+    // same order as css: top, right, bottom, left
+    pub const fn new(top: usize, right: usize, bottom: usize, left: usize) -> Self {
+        Self {top, right, bottom, left}
+    }
+    pub const fn uniform(all: usize) -> Self { Self::new(all, all, all, all) }
+    pub const fn symmetric(vertical: usize, horizontal: usize) -> Self {
+        Self::new(vertical, horizontal, vertical, horizontal)
+    }
+    
+    pub const fn horizontal(&self) -> usize { self.left + self.right  }
+    pub const fn vertical  (&self) -> usize { self.top  + self.bottom }
+} impl Add for Insets {
+    type Output = Self;
+    fn add(self, a: Self) -> Self::Output {
+        Self::new(self.top + a.top, self.right + a.right, self.bottom + a.bottom, self.left + a.left)
+    }
+}
+
 // The purpose of this struct is to remove ambiguity of (usize,usize) 
 // as that can be interpreted as rows,cols, or width,height
 #[derive(Copy,Clone,Debug,Default,PartialEq)]
@@ -0,0 +1,246 @@
+use std::sync::atomic::{AtomicU8,Ordering};
+
+use crate::{Pixel,blend::BlendMode};
+
+#[cfg(any(target_arch="x86", target_arch="x86_64"))]
+mod x86;
+#[cfg(target_arch="aarch64")]
+mod neon;
+
+// bulk operations on rows of pixels, with a vector path for whatever the cpu has, picked the first time one's used
+// every path gives exactly the same pixels as the scalar one, which is Pixel::blend and friends,
+//  so frames and snapshots don't depend on the machine they're drawn on
+// blending only has vector paths for SrcOver, the one nearly everything uses; other modes go pixel by pixel
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Level {
+    Scalar,
+    Sse2,
+    Avx2,
+    Neon,
+} impl Level {
+    // the fastest the running cpu supports
+    pub fn detect() -> Level {
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))] {
+            if is_x86_feature_detected!("avx2") { return Level::Avx2; }
+            if is_x86_feature_detected!("sse2") { return Level::Sse2; }
+        }
+        #[cfg(target_arch="aarch64")] {
+            if std::arch::is_aarch64_feature_detected!("neon") { return Level::Neon; }
+        }
+        Level::Scalar
+    }
+    
+    pub fn is_supported(self) -> bool {
+        match self {
+            Level::Scalar => true,
+            #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+            Level::Sse2 => is_x86_feature_detected!("sse2"),
+            #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+            Level::Avx2 => is_x86_feature_detected!("avx2"),
+            #[cfg(target_arch="aarch64")]
+            Level::Neon => std::arch::is_aarch64_feature_detected!("neon"),
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+    
+    const ALL: [Level; 4] = [Level::Scalar, Level::Sse2, Level::Avx2, Level::Neon];
+}
+
+// 0 until detected, then 1 + the level's index in Level::ALL
+static LEVEL: AtomicU8 = AtomicU8::new(0);
+
+pub fn level() -> Level {
+    match LEVEL.load(Ordering::Relaxed) {
+        0 => {
+            let level = Level::detect();
+            LEVEL.store(Level::ALL.iter().position(|l| *l == level).unwrap() as u8 + 1, Ordering::Relaxed);
+            level
+        },
+        n => Level::ALL[n as usize - 1],
+    }
+}
+
+// for comparing paths against one another; returns false, and changes nothing, if the cpu can't do level
+// applies to every thread
+pub fn set_level(level: Level) -> bool {
+    if !level.is_supported() { return false; }
+    LEVEL.store(Level::ALL.iter().position(|l| *l == level).unwrap() as u8 + 1, Ordering::Relaxed);
+    true
+}
+
+pub fn fill(dst: &mut [Pixel], color: Pixel) {
+    match level() {
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Avx2 => unsafe { x86::fill_avx2(dst, color) },
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Sse2 => unsafe { x86::fill_sse2(dst, color) },
+        #[cfg(target_arch="aarch64")]
+        Level::Neon => unsafe { neon::fill(dst, color) },
+        _ => dst.fill(color),
+    }
+}
+
+// memcpy already uses the widest copies the cpu has, so there's nothing to pick here
+pub fn copy(dst: &mut [Pixel], src: &[Pixel]) {
+    dst.copy_from_slice(src);
+}
+
+// color drawn onto every pixel of dst
+pub fn blend(dst: &mut [Pixel], color: Pixel, mode: BlendMode) {
+    match mode {
+        BlendMode::Src => return fill(dst, color),
+        BlendMode::Dst => return,
+        BlendMode::SrcOver if color.a == 0xFF => return fill(dst, color),
+        BlendMode::SrcOver if color.a == 0x00 => return,
+        BlendMode::SrcOver => (),
+        _ => return dst.iter_mut().for_each(|d| *d = color.blend(*d, mode)),
+    }
+    match level() {
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Avx2 => unsafe { x86::over_avx2(dst, Source::Solid(color)) },
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Sse2 => unsafe { x86::over_sse2(dst, Source::Solid(color)) },
+        #[cfg(target_arch="aarch64")]
+        Level::Neon => unsafe { neon::over(dst, Source::Solid(color)) },
+        _ => dst.iter_mut().for_each(|d| *d = color.blend(*d, mode)),
+    }
+}
+
+// each pixel of src drawn onto the one at the same place in dst
+pub fn blend_span(dst: &mut [Pixel], src: &[Pixel], mode: BlendMode) {
+    assert_eq!(dst.len(), src.len(), "blended spans must be the same length");
+    match mode {
+        BlendMode::Src => return copy(dst, src),
+        BlendMode::Dst => return,
+        BlendMode::SrcOver => (),
+        _ => return dst.iter_mut().zip(src).for_each(|(d, s)| *d = s.blend(*d, mode)),
+    }
+    match level() {
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Avx2 => unsafe { x86::over_avx2(dst, Source::Span(src)) },
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Sse2 => unsafe { x86::over_sse2(dst, Source::Span(src)) },
+        #[cfg(target_arch="aarch64")]
+        Level::Neon => unsafe { neon::over(dst, Source::Span(src)) },
+        _ => dst.iter_mut().zip(src).for_each(|(d, s)| *d = s.blend(*d, mode)),
+    }
+}
+
+// what's drawn onto a span: one color all along it, or another span of the same length
+#[derive(Clone,Copy)]
+enum Source<'a> {
+    Solid(Pixel),
+    Span(&'a [Pixel]),
+} impl Source<'_> {
+    fn get(&self, i: usize) -> Pixel {
+        match self {
+            Source::Solid(color) => *color,
+            Source::Span(span) => span[i],
+        }
+    }
+}
+
+// pixels to r,g,b,a bytes, as most file formats and other libraries want them
+pub fn to_rgba(src: &[Pixel], dst: &mut [u8]) {
+    assert_eq!(dst.len(), src.len() * 4, "rgba needs 4 bytes per pixel");
+    swap_red_blue(src.as_ptr() as *const u8, dst.as_mut_ptr(), src.len());
+}
+
+pub fn from_rgba(src: &[u8], dst: &mut [Pixel]) {
+    assert_eq!(src.len(), dst.len() * 4, "rgba needs 4 bytes per pixel");
+    swap_red_blue(src.as_ptr(), dst.as_mut_ptr() as *mut u8, dst.len());
+}
+
+// pixels are b,g,r,a in memory, so going either way is the same swap
+fn swap_red_blue(src: *const u8, dst: *mut u8, count: usize) {
+    match level() {
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Avx2 => unsafe { x86::swap_red_blue_avx2(src, dst, count) },
+        #[cfg(any(target_arch="x86", target_arch="x86_64"))]
+        Level::Sse2 => unsafe { x86::swap_red_blue_sse2(src, dst, count) },
+        #[cfg(target_arch="aarch64")]
+        Level::Neon => unsafe { neon::swap_red_blue(src, dst, count) },
+        _ => unsafe { swap_red_blue_scalar(src, dst, count, 0) },
+    }
+}
+
+// from pixel start on; vector paths use it for whatever's left over
+// src and dst hold count pixels each
+unsafe fn swap_red_blue_scalar(src: *const u8, dst: *mut u8, count: usize, start: usize) {
+    for i in start..count {
+        unsafe {
+            let [b, g, r, a] = *(src.add(i * 4) as *const [u8; 4]);
+            *(dst.add(i * 4) as *mut [u8; 4]) = [r, g, b, a];
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const LENGTHS: [usize; 6] = [0, 1, 3, 7, 9, 33];
+    
+    // xorshift, so runs are repeatable
+    struct Random(u32);
+    impl Random {
+        fn next(&mut self) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 17;
+            self.0 ^= self.0 << 5;
+            self.0
+        }
+        fn pixel(&mut self) -> Pixel {
+            let [b, g, r, a] = self.next().to_le_bytes();
+            // opaque and clear pixels take their own paths, so they need to come up often
+            let a = match a % 4 { 0 => 0x00, 1 => 0xFF, _ => a };
+            Pixel {r, g, b, a}
+        }
+        fn pixels(&mut self, len: usize) -> Vec<Pixel> { (0..len).map(|_| self.pixel()).collect() }
+    }
+    
+    fn bytes(pixels: &[Pixel]) -> Vec<[u8; 4]> { pixels.iter().map(|p| [p.r, p.g, p.b, p.a]).collect() }
+    
+    // everything a level gives for one set of inputs
+    fn run(dst: &[Pixel], src: &[Pixel], color: Pixel) -> Vec<Vec<[u8; 4]>> {
+        let mut out = Vec::new();
+        let mut filled = dst.to_vec();
+        fill(&mut filled, color);
+        out.push(bytes(&filled));
+        for mode in BlendMode::ALL {
+            let mut blended = dst.to_vec();
+            blend(&mut blended, color, mode);
+            out.push(bytes(&blended));
+            let mut spanned = dst.to_vec();
+            blend_span(&mut spanned, src, mode);
+            out.push(bytes(&spanned));
+        }
+        let mut rgba = vec![0; src.len() * 4];
+        to_rgba(src, &mut rgba);
+        let mut back = vec![Pixel::default(); src.len()];
+        from_rgba(&rgba, &mut back);
+        out.push(rgba.chunks(4).map(|c| [c[0], c[1], c[2], c[3]]).collect());
+        out.push(bytes(&back));
+        out
+    }
+    
+    #[test]
+    fn levels_match_scalar() {
+        let previous = level();
+        let mut random = Random(0x2545F491);
+        for len in LENGTHS {
+            for _ in 0..16 {
+                let (dst, src, color) = (random.pixels(len), random.pixels(len), random.pixel());
+                assert!(set_level(Level::Scalar));
+                let expected = run(&dst, &src, color);
+                for level in Level::ALL.into_iter().filter(|l| l.is_supported()) {
+                    set_level(level);
+                    assert!(run(&dst, &src, color) == expected, "{level:?} differs from scalar at length {len}");
+                }
+            }
+        }
+        set_level(previous);
+    }
+}
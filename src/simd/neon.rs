@@ -0,0 +1,85 @@
+use std::arch::aarch64::*;
+
+use crate::Pixel;
+use crate::blend::BlendMode;
+
+use super::{Source,swap_red_blue_scalar};
+
+// 4 pixels at a time, 16 when swapping channels; whatever's left over at the end goes through the scalar path
+
+#[target_feature(enable="neon")]
+pub(super) unsafe fn fill(dst: &mut [Pixel], color: Pixel) {
+    let v = vdupq_n_u32(u32::from(color));
+    let mut chunks = dst.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        unsafe { vst1q_u32(chunk.as_mut_ptr() as *mut u32, v); }
+    }
+    chunks.into_remainder().fill(color);
+}
+
+// loading 4 channels apart puts every pixel's blue in one register and its red in another, so swapping is free
+#[target_feature(enable="neon")]
+pub(super) unsafe fn swap_red_blue(src: *const u8, dst: *mut u8, count: usize) {
+    let mut i = 0;
+    while i + 16 <= count {
+        unsafe {
+            let v = vld4q_u8(src.add(i * 4));
+            vst4q_u8(dst.add(i * 4), uint8x16x4_t(v.2, v.1, v.0, v.3));
+        }
+        i += 16;
+    }
+    unsafe { swap_red_blue_scalar(src, dst, count, i); }
+}
+
+// src over dst for one pixel, each channel in its own float lane, b,g,r,a as they are in memory
+// this is Pixel::blend for SrcOver step by step, in the same order, so that it rounds the same
+#[target_feature(enable="neon")]
+fn over_lanes(s: uint32x4_t, d: uint32x4_t) -> uint32x4_t {
+    let (zero, one, max) = (vdupq_n_f32(0.0), vdupq_n_f32(1.0), vdupq_n_f32(255.0));
+    let alpha_lane = vsetq_lane_u32::<3>(u32::MAX, vdupq_n_u32(0));
+    let s = vdivq_f32(vcvtq_f32_u32(s), max);
+    let d = vdivq_f32(vcvtq_f32_u32(d), max);
+    let sa = vdupq_laneq_f32::<3>(s);
+    let da = vdupq_laneq_f32::<3>(d);
+    let fd = vsubq_f32(one, sa);
+    let color = vaddq_f32(vmulq_f32(s, sa), vmulq_f32(vmulq_f32(d, da), fd));
+    let a = vminq_f32(vmaxq_f32(vaddq_f32(sa, vmulq_f32(da, fd)), zero), one);
+    let straight = vbslq_f32(alpha_lane, a, vdivq_f32(color, a));
+    // round half away from zero, as f32::round does; the clamp keeps everything positive, where truncating is flooring
+    let t = vminq_f32(vmaxq_f32(vmulq_f32(straight, max), zero), max);
+    let whole = vcvtq_u32_f32(t);
+    let up = vcgeq_f32(vsubq_f32(t, vcvtq_f32_u32(whole)), vdupq_n_f32(0.5));
+    // nothing left at all comes out fully transparent
+    vbicq_u32(vsubq_u32(whole, up), vcleq_f32(a, zero))
+}
+
+// src alphas of 0 and 255 skip the arithmetic, as they do in Pixel::blend
+#[target_feature(enable="neon")]
+pub(super) unsafe fn over(dst: &mut [Pixel], src: Source) {
+    let alpha = vdupq_n_u32(0xFF000000);
+    let mut i = 0;
+    while i + 4 <= dst.len() {
+        let d = unsafe { vld1q_u32(dst.as_ptr().add(i) as *const u32) };
+        let s = match src {
+            Source::Solid(color) => vdupq_n_u32(u32::from(color)),
+            Source::Span(span) => unsafe { vld1q_u32(span.as_ptr().add(i) as *const u32) },
+        };
+        let widen = |v: uint32x4_t| {
+            let bytes = vreinterpretq_u8_u32(v);
+            let (lo, hi) = (vmovl_u8(vget_low_u8(bytes)), vmovl_u8(vget_high_u8(bytes)));
+            [vmovl_u16(vget_low_u16(lo)), vmovl_u16(vget_high_u16(lo)), vmovl_u16(vget_low_u16(hi)), vmovl_u16(vget_high_u16(hi))]
+        };
+        let (ws, wd) = (widen(s), widen(d));
+        let out = [0, 1, 2, 3].map(|k| over_lanes(ws[k], wd[k]));
+        let lo = vcombine_u16(vmovn_u32(out[0]), vmovn_u32(out[1]));
+        let hi = vcombine_u16(vmovn_u32(out[2]), vmovn_u32(out[3]));
+        let blended = vreinterpretq_u32_u8(vcombine_u8(vmovn_u16(lo), vmovn_u16(hi)));
+        
+        let sa = vandq_u32(s, alpha);
+        let (opaque, clear) = (vceqq_u32(sa, alpha), vceqq_u32(sa, vdupq_n_u32(0)));
+        let result = vbslq_u32(opaque, s, vbslq_u32(clear, d, blended));
+        unsafe { vst1q_u32(dst.as_mut_ptr().add(i) as *mut u32, result); }
+        i += 4;
+    }
+    for (j, d) in dst.iter_mut().enumerate().skip(i) { *d = src.get(j).blend(*d, BlendMode::SrcOver); }
+}
@@ -0,0 +1,170 @@
+#[cfg(target_arch="x86")]
+use std::arch::x86::*;
+#[cfg(target_arch="x86_64")]
+use std::arch::x86_64::*;
+
+use crate::Pixel;
+use crate::blend::BlendMode;
+
+use super::{Source,swap_red_blue_scalar};
+
+// sse2 does 4 pixels at a time, avx2 8; whatever's left over at the end goes through the scalar path
+
+#[target_feature(enable="sse2")]
+pub(super) unsafe fn fill_sse2(dst: &mut [Pixel], color: Pixel) {
+    let v = _mm_set1_epi32(u32::from(color) as i32);
+    let mut chunks = dst.chunks_exact_mut(4);
+    for chunk in &mut chunks {
+        unsafe { _mm_storeu_si128(chunk.as_mut_ptr() as *mut __m128i, v); }
+    }
+    chunks.into_remainder().fill(color);
+}
+
+#[target_feature(enable="avx2")]
+pub(super) unsafe fn fill_avx2(dst: &mut [Pixel], color: Pixel) {
+    let v = _mm256_set1_epi32(u32::from(color) as i32);
+    let mut chunks = dst.chunks_exact_mut(8);
+    for chunk in &mut chunks {
+        unsafe { _mm256_storeu_si256(chunk.as_mut_ptr() as *mut __m256i, v); }
+    }
+    chunks.into_remainder().fill(color);
+}
+
+// red and blue are bytes 0 and 2 of each pixel, so they trade places by shifting within each 32 bit lane
+#[target_feature(enable="sse2")]
+pub(super) unsafe fn swap_red_blue_sse2(src: *const u8, dst: *mut u8, count: usize) {
+    let green_alpha = _mm_set1_epi32(0xFF00FF00u32 as i32);
+    let red_blue = _mm_set1_epi32(0x00FF00FF);
+    let mut i = 0;
+    while i + 4 <= count {
+        unsafe {
+            let v = _mm_loadu_si128(src.add(i * 4) as *const __m128i);
+            let rb = _mm_and_si128(v, red_blue);
+            let swapped = _mm_or_si128(_mm_slli_epi32(rb, 16), _mm_srli_epi32(rb, 16));
+            _mm_storeu_si128(dst.add(i * 4) as *mut __m128i, _mm_or_si128(_mm_and_si128(v, green_alpha), swapped));
+        }
+        i += 4;
+    }
+    unsafe { swap_red_blue_scalar(src, dst, count, i); }
+}
+
+#[target_feature(enable="avx2")]
+pub(super) unsafe fn swap_red_blue_avx2(src: *const u8, dst: *mut u8, count: usize) {
+    let green_alpha = _mm256_set1_epi32(0xFF00FF00u32 as i32);
+    let red_blue = _mm256_set1_epi32(0x00FF00FF);
+    let mut i = 0;
+    while i + 8 <= count {
+        unsafe {
+            let v = _mm256_loadu_si256(src.add(i * 4) as *const __m256i);
+            let rb = _mm256_and_si256(v, red_blue);
+            let swapped = _mm256_or_si256(_mm256_slli_epi32(rb, 16), _mm256_srli_epi32(rb, 16));
+            _mm256_storeu_si256(dst.add(i * 4) as *mut __m256i, _mm256_or_si256(_mm256_and_si256(v, green_alpha), swapped));
+        }
+        i += 8;
+    }
+    unsafe { swap_red_blue_scalar(src, dst, count, i); }
+}
+
+// src over dst for one pixel, each channel in its own float lane, b,g,r,a as they are in memory
+// this is Pixel::blend for SrcOver step by step, in the same order, so that it rounds the same:
+//  straight channels c/255, then premultiplied src + dst*(1 - src alpha), then divided back out by the clamped alpha
+#[target_feature(enable="sse2")]
+fn over_sse2_lanes(s: __m128i, d: __m128i) -> __m128i {
+    let (zero, one, max) = (_mm_setzero_ps(), _mm_set1_ps(1.0), _mm_set1_ps(255.0));
+    let alpha_lane = _mm_castsi128_ps(_mm_set_epi32(-1, 0, 0, 0));
+    let s = _mm_div_ps(_mm_cvtepi32_ps(s), max);
+    let d = _mm_div_ps(_mm_cvtepi32_ps(d), max);
+    let sa = _mm_shuffle_ps::<0xFF>(s, s);
+    let da = _mm_shuffle_ps::<0xFF>(d, d);
+    let fd = _mm_sub_ps(one, sa);
+    let color = _mm_add_ps(_mm_mul_ps(s, sa), _mm_mul_ps(_mm_mul_ps(d, da), fd));
+    let a = _mm_min_ps(_mm_max_ps(_mm_add_ps(sa, _mm_mul_ps(da, fd)), zero), one);
+    let straight = _mm_or_ps(_mm_and_ps(alpha_lane, a), _mm_andnot_ps(alpha_lane, _mm_div_ps(color, a)));
+    // round half away from zero, as f32::round does; there's no instruction for that before sse4.1
+    let t = _mm_min_ps(_mm_max_ps(_mm_mul_ps(straight, max), zero), max);
+    let whole = _mm_cvttps_epi32(t);
+    let up = _mm_castps_si128(_mm_cmpge_ps(_mm_sub_ps(t, _mm_cvtepi32_ps(whole)), _mm_set1_ps(0.5)));
+    // nothing left at all comes out fully transparent
+    _mm_andnot_si128(_mm_castps_si128(_mm_cmple_ps(a, zero)), _mm_sub_epi32(whole, up))
+}
+
+// the same, for two pixels, one in each half
+#[target_feature(enable="avx2")]
+fn over_avx2_lanes(s: __m256i, d: __m256i) -> __m256i {
+    let (zero, one, max) = (_mm256_setzero_ps(), _mm256_set1_ps(1.0), _mm256_set1_ps(255.0));
+    let alpha_lane = _mm256_castsi256_ps(_mm256_set_epi32(-1, 0, 0, 0, -1, 0, 0, 0));
+    let s = _mm256_div_ps(_mm256_cvtepi32_ps(s), max);
+    let d = _mm256_div_ps(_mm256_cvtepi32_ps(d), max);
+    let sa = _mm256_shuffle_ps::<0xFF>(s, s);
+    let da = _mm256_shuffle_ps::<0xFF>(d, d);
+    let fd = _mm256_sub_ps(one, sa);
+    let color = _mm256_add_ps(_mm256_mul_ps(s, sa), _mm256_mul_ps(_mm256_mul_ps(d, da), fd));
+    let a = _mm256_min_ps(_mm256_max_ps(_mm256_add_ps(sa, _mm256_mul_ps(da, fd)), zero), one);
+    let straight = _mm256_blendv_ps(_mm256_div_ps(color, a), a, alpha_lane);
+    let t = _mm256_min_ps(_mm256_max_ps(_mm256_mul_ps(straight, max), zero), max);
+    let whole = _mm256_cvttps_epi32(t);
+    let up = _mm256_castps_si256(_mm256_cmp_ps::<_CMP_GE_OQ>(_mm256_sub_ps(t, _mm256_cvtepi32_ps(whole)), _mm256_set1_ps(0.5)));
+    _mm256_andnot_si256(_mm256_castps_si256(_mm256_cmp_ps::<_CMP_LE_OQ>(a, zero)), _mm256_sub_epi32(whole, up))
+}
+
+// src alphas of 0 and 255 skip the arithmetic, as they do in Pixel::blend
+#[target_feature(enable="sse2")]
+pub(super) unsafe fn over_sse2(dst: &mut [Pixel], src: Source) {
+    let zero = _mm_setzero_si128();
+    let alpha = _mm_set1_epi32(0xFF000000u32 as i32);
+    let mut i = 0;
+    while i + 4 <= dst.len() {
+        let d = unsafe { _mm_loadu_si128(dst.as_ptr().add(i) as *const __m128i) };
+        let s = match src {
+            Source::Solid(color) => _mm_set1_epi32(u32::from(color) as i32),
+            Source::Span(span) => unsafe { _mm_loadu_si128(span.as_ptr().add(i) as *const __m128i) },
+        };
+        let widen = |v: __m128i| {
+            let (lo, hi) = (_mm_unpacklo_epi8(v, zero), _mm_unpackhi_epi8(v, zero));
+            [_mm_unpacklo_epi16(lo, zero), _mm_unpackhi_epi16(lo, zero), _mm_unpacklo_epi16(hi, zero), _mm_unpackhi_epi16(hi, zero)]
+        };
+        let (ws, wd) = (widen(s), widen(d));
+        let out = [0, 1, 2, 3].map(|k| over_sse2_lanes(ws[k], wd[k]));
+        let blended = _mm_packus_epi16(_mm_packs_epi32(out[0], out[1]), _mm_packs_epi32(out[2], out[3]));
+        
+        let sa = _mm_and_si128(s, alpha);
+        let (opaque, clear) = (_mm_cmpeq_epi32(sa, alpha), _mm_cmpeq_epi32(sa, zero));
+        let select = |mask: __m128i, a: __m128i, b: __m128i| _mm_or_si128(_mm_and_si128(mask, a), _mm_andnot_si128(mask, b));
+        let result = select(opaque, s, select(clear, d, blended));
+        unsafe { _mm_storeu_si128(dst.as_mut_ptr().add(i) as *mut __m128i, result); }
+        i += 4;
+    }
+    for (j, d) in dst.iter_mut().enumerate().skip(i) { *d = src.get(j).blend(*d, BlendMode::SrcOver); }
+}
+
+#[target_feature(enable="avx2")]
+pub(super) unsafe fn over_avx2(dst: &mut [Pixel], src: Source) {
+    let alpha = _mm256_set1_epi32(0xFF000000u32 as i32);
+    // packing works within each half, which leaves pixels in the order 0,2,4,6,1,3,5,7
+    let order = _mm256_setr_epi32(0, 4, 1, 5, 2, 6, 3, 7);
+    let mut i = 0;
+    while i + 8 <= dst.len() {
+        let d = unsafe { _mm256_loadu_si256(dst.as_ptr().add(i) as *const __m256i) };
+        let s = match src {
+            Source::Solid(color) => _mm256_set1_epi32(u32::from(color) as i32),
+            Source::Span(span) => unsafe { _mm256_loadu_si256(span.as_ptr().add(i) as *const __m256i) },
+        };
+        // two pixels at a time, from the 8 bytes they take up
+        let pair = |v: __m256i, k: i32| _mm256_cvtepu8_epi32(match k {
+            0 => _mm256_castsi256_si128(v),
+            1 => _mm_srli_si128::<8>(_mm256_castsi256_si128(v)),
+            2 => _mm256_extracti128_si256::<1>(v),
+            _ => _mm_srli_si128::<8>(_mm256_extracti128_si256::<1>(v)),
+        });
+        let out = [0, 1, 2, 3].map(|k| over_avx2_lanes(pair(s, k), pair(d, k)));
+        let packed = _mm256_packus_epi16(_mm256_packs_epi32(out[0], out[1]), _mm256_packs_epi32(out[2], out[3]));
+        let blended = _mm256_permutevar8x32_epi32(packed, order);
+        
+        let sa = _mm256_and_si256(s, alpha);
+        let (opaque, clear) = (_mm256_cmpeq_epi32(sa, alpha), _mm256_cmpeq_epi32(sa, _mm256_setzero_si256()));
+        let result = _mm256_blendv_epi8(_mm256_blendv_epi8(blended, d, clear), s, opaque);
+        unsafe { _mm256_storeu_si256(dst.as_mut_ptr().add(i) as *mut __m256i, result); }
+        i += 8;
+    }
+    for (j, d) in dst.iter_mut().enumerate().skip(i) { *d = src.get(j).blend(*d, BlendMode::SrcOver); }
+}
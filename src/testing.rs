@@ -0,0 +1,213 @@
+mod driver;
+mod record;
+
+use std::fmt;
+use std::path::{Path,PathBuf};
+
+use crate::{
+    Pixel,Size,
+    guicell::GuiCell,
+    image::{Image,ImageError,ImageFormat},
+};
+
+pub use driver::Driver;
+pub use record::{RecordedEvent,Recording,RecordingError};
+
+// rendering cells without a window, driving windows with scripted input, recording and replaying what they're sent, and comparing what they draw to golden pngs
+// set GUI_BLESS=1 to write the current output as the new golden images instead of comparing
+
+pub const BLESS_VAR: &str = "GUI_BLESS";
+
+// a cell drawn into an owned image of the given size, starting out transparent
+pub fn render(cell: &dyn GuiCell, size: Size) -> Image {
+    render_on(cell, size, Pixel::default())
+}
+
+pub fn render_on(cell: &dyn GuiCell, size: Size, background: Pixel) -> Image {
+    let mut image = Image::filled(size, background);
+    // cells draw into the slice they're handed without keeping it, same as they do for a window
+    unsafe { image.with_slice(|slice| cell.draw(slice)) };
+    image
+}
+
+// panics with what went wrong unless cell matches the golden image called name, in tests/snapshots
+pub fn assert_snapshot(name: &str, cell: &dyn GuiCell, size: Size) {
+    if let Err(e) = Snapshot::default().check(name, cell, size) { panic!("{e}"); }
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Image(ImageError),
+    Missing(PathBuf), // no golden image yet
+    SizeMismatch {expected: Size, actual: Size, actual_path: PathBuf},
+    Mismatch {pixels: usize, max_difference: u8, diff_path: PathBuf},
+} impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Image(e) => write!(f, "snapshot: {e}"),
+            SnapshotError::Missing(path) => write!(f, "snapshot {} doesn't exist; run with {BLESS_VAR}=1 to create it", path.display()),
+            SnapshotError::SizeMismatch {expected, actual, actual_path} => write!(f,
+                "snapshot is {}x{}, but {}x{} was drawn (written to {})",
+                expected.width, expected.height, actual.width, actual.height, actual_path.display(),
+            ),
+            SnapshotError::Mismatch {pixels, max_difference, diff_path} => write!(f,
+                "{pixels} pixels differ from the snapshot, by up to {max_difference} (diff written to {})", diff_path.display(),
+            ),
+        }
+    }
+} impl std::error::Error for SnapshotError {
+} impl From<ImageError> for SnapshotError {
+    fn from(e: ImageError) -> Self { SnapshotError::Image(e) }
+}
+
+// where golden images live, and how closely output has to match them
+#[derive(Clone,Debug)]
+pub struct Snapshot {
+    dir: PathBuf,
+    tolerance: u8,      // how far each channel may be off and still match
+    background: Pixel,  // what cells are drawn over
+} impl Default for Snapshot {
+    // tests/snapshots of the crate being tested, exact matches, transparent background
+    fn default() -> Self {
+        let root = std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+        Self::new(root.join("tests").join("snapshots"))
+    }
+} impl Snapshot {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {dir: dir.into(), tolerance: 0, background: Pixel::default()}
+    }
+    
+    pub fn with_tolerance(mut self, tolerance: u8) -> Self { self.tolerance = tolerance; self }
+    pub fn with_background(mut self, background: Pixel) -> Self { self.background = background; self }
+    
+    pub fn dir(&self) -> &Path { &self.dir }
+    pub fn tolerance(&self) -> u8 { self.tolerance }
+    pub fn background(&self) -> Pixel { self.background }
+    
+    pub fn path(&self, name: &str) -> PathBuf { self.dir.join(format!("{name}.png")) }
+    
+    pub fn check(&self, name: &str, cell: &dyn GuiCell, size: Size) -> Result<(),SnapshotError> {
+        self.check_image(name, &render_on(cell, size, self.background))
+    }
+    
+    // on a mismatch, what was drawn goes next to the golden image as name.actual.png,
+    // and name.diff.png shows differing pixels in red over a faded copy of the golden image
+    pub fn check_image(&self, name: &str, actual: &Image) -> Result<(),SnapshotError> {
+        let path = self.path(name);
+        let actual_path = self.dir.join(format!("{name}.actual.png"));
+        let diff_path = self.dir.join(format!("{name}.diff.png"));
+        // leftovers from earlier failures would only confuse
+        let clean_up = || {
+            let _ = std::fs::remove_file(&actual_path);
+            let _ = std::fs::remove_file(&diff_path);
+        };
+        
+        if blessing() {
+            std::fs::create_dir_all(&self.dir).map_err(ImageError::from)?;
+            actual.save(&path)?;
+            clean_up();
+            return Ok(());
+        }
+        if !path.exists() { return Err(SnapshotError::Missing(path)); }
+        let expected = Image::decode(&std::fs::read(&path).map_err(ImageError::from)?, ImageFormat::Png)?;
+        
+        if expected.size() != actual.size() {
+            actual.save(&actual_path)?;
+            return Err(SnapshotError::SizeMismatch {expected: expected.size(), actual: actual.size(), actual_path});
+        }
+        
+        let (mut pixels, mut max_difference) = (0, 0);
+        let mut diff = Image::new(actual.size());
+        for ((e, a), d) in expected.pixels().iter().zip(actual.pixels()).zip(diff.pixels_mut()) {
+            let difference = difference(*e, *a);
+            if difference > self.tolerance {
+                pixels += 1;
+                max_difference = max_difference.max(difference);
+                *d = Pixel::rgb(255, 0, 0);
+            } else {
+                let gray = ((e.r as u32 + e.g as u32 + e.b as u32) / 3 * e.a as u32 / 255 / 3) as u8;
+                *d = Pixel::rgb(gray, gray, gray);
+            }
+        }
+        if pixels == 0 {
+            clean_up();
+            return Ok(());
+        }
+        
+        actual.save(&actual_path)?;
+        diff.save(&diff_path)?;
+        Err(SnapshotError::Mismatch {pixels, max_difference, diff_path})
+    }
+}
+
+pub fn blessing() -> bool {
+    std::env::var_os(BLESS_VAR).is_some_and(|v| !v.is_empty() && v != "0")
+}
+
+// largest difference of any channel
+fn difference(a: Pixel, b: Pixel) -> u8 {
+    [a.r.abs_diff(b.r), a.g.abs_diff(b.g), a.b.abs_diff(b.b), a.a.abs_diff(b.a)].into_iter().max().unwrap_or(0)
+}
+
+// a real window around root, for the crate's own tests of windows
+// tests run on several threads, and the system has to be set up once before any of them makes one
+#[cfg(test)]
+pub(crate) fn driver(size: Size, root: impl GuiCell + 'static) -> Driver {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(|| unsafe { crate::init().unwrap() });
+    Driver::new(crate::window::Window::new("test", size, Box::new(root), &|_, _| true).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn image(size: Size, color: Pixel) -> Image { Image::filled(size, color) }
+    
+    // the bless variable is process wide, so everything that depends on it runs in this one test
+    #[test]
+    fn snapshots() {
+        let dir = std::env::temp_dir().join(format!("gui-snapshot-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let snapshot = Snapshot::new(&dir).with_tolerance(4);
+        let size = Size {width: 5, height: 3};
+        let golden = image(size, Pixel::rgb(100, 150, 200));
+        let (actual_path, diff_path) = (dir.join("box.actual.png"), dir.join("box.diff.png"));
+        
+        assert!(matches!(snapshot.check_image("box", &golden), Err(SnapshotError::Missing(_))));
+        
+        unsafe { std::env::set_var(BLESS_VAR, "1"); }
+        let blessed = snapshot.check_image("box", &golden);
+        unsafe { std::env::remove_var(BLESS_VAR); }
+        blessed.unwrap();
+        let written = Image::decode(&std::fs::read(snapshot.path("box")).unwrap(), ImageFormat::Png).unwrap();
+        assert_eq!(written.size(), size);
+        assert!(written.pixels().iter().zip(golden.pixels()).all(|(a, b)| difference(*a, *b) == 0));
+        
+        // within tolerance
+        snapshot.check_image("box", &image(size, Pixel::rgb(104, 146, 200))).unwrap();
+        assert!(!actual_path.exists() && !diff_path.exists());
+        
+        let mut changed = golden.clone();
+        changed.set_pixel(crate::PixelIdx::new(1, 2), Pixel::rgb(100, 150, 220));
+        match snapshot.check_image("box", &changed) {
+            Err(SnapshotError::Mismatch {pixels: 1, max_difference: 20, diff_path: path}) => assert_eq!(path, diff_path),
+            other => panic!("expected a mismatch, got {other:?}"),
+        }
+        assert!(actual_path.exists());
+        let diff = Image::decode(&std::fs::read(&diff_path).unwrap(), ImageFormat::Png).unwrap();
+        let red = diff.get_pixel(crate::PixelIdx::new(1, 2));
+        assert_eq!([red.r, red.g, red.b], [255, 0, 0]);
+        
+        match snapshot.check_image("box", &image(Size {width: 4, height: 3}, Pixel::rgb(100, 150, 200))) {
+            Err(SnapshotError::SizeMismatch {expected, actual, ..}) => assert_eq!((expected, actual), (size, Size {width: 4, height: 3})),
+            other => panic!("expected a size mismatch, got {other:?}"),
+        }
+        
+        // passing again clears out what the failures left
+        snapshot.check_image("box", &golden).unwrap();
+        assert!(!actual_path.exists() && !diff_path.exists());
+        
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
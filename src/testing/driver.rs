@@ -0,0 +1,228 @@
+use std::pin::Pin;
+use std::time::Duration;
+
+use crate::{
+    CommonEvent,MouseButton,MousePos,Size,
+    guicell::GuiCell,
+    image::Image,
+    keys::Key,
+    window::{Clock,Window},
+};
+
+// scripted input for a window, for testing interactive cells end to end
+// events go through the window's usual dispatch, event handler and all, as if the system had sent them,
+// so it works on any backend; the window's clock is swapped for a virtual one that only moves when advanced
+// cell state can be read back through root(), or by sharing it with the test, e.g. through an Rc<RefCell<_>>
+pub struct Driver {
+    window: Pin<Box<Window>>,
+    cursor: MousePos,
+} impl Driver {
+    pub fn new(window: Pin<Box<Window>>) -> Self {
+        let mut driver = Self {window, cursor: MousePos::default()};
+        driver.window_mut().set_clock(Clock::Virtual(Duration::ZERO));
+        driver
+    }
+    
+    pub fn window(&self) -> &Window { &self.window }
+    pub fn into_window(self) -> Pin<Box<Window>> { self.window }
+    
+    // dispatching never moves the window, so handing out &mut keeps the pin's promise
+    pub(crate) fn window_mut(&mut self) -> &mut Window {
+        unsafe { self.window.as_mut().get_unchecked_mut() }
+    }
+    
+    pub fn root(&self) -> &dyn GuiCell { self.window.root() }
+    pub fn root_mut(&mut self) -> &mut dyn GuiCell { self.window_mut().root_mut() }
+    
+    pub fn size(&self) -> Size { self.window.size() }
+    pub fn now(&self) -> Duration { self.window.now() }
+    pub fn cursor(&self) -> MousePos { self.cursor }
+    
+    // backends that resize synchronously send their own Resize; the rest get one from here
+    pub fn resize(&mut self, size: Size) -> &mut Self {
+        self.window.set_size(size);
+        if self.size() != size { self.send(CommonEvent::Resize); }
+        self
+    }
+    
+    // any event, as is
+    pub fn send(&mut self, e: CommonEvent) -> &mut Self {
+        if let Some(pos) = e.position() { self.cursor = pos; }
+        self.window_mut().handle_event(e, crate::private::Internal);
+        self
+    }
+    
+    // moves the clock forward, then tells cells how much time went by
+    pub fn advance(&mut self, by: Duration) -> &mut Self {
+        let mut clock = self.window.clock();
+        clock.advance(by);
+        self.window_mut().set_clock(clock);
+        self.send(CommonEvent::Tick(by))
+    }
+    
+    pub fn move_to(&mut self, pos: impl Into<MousePos>) -> &mut Self {
+        self.send(CommonEvent::MouseMove(pos.into()))
+    }
+    
+    // at the cursor
+    pub fn press(&mut self, button: MouseButton) -> &mut Self {
+        self.send(CommonEvent::MouseDown(button, self.cursor))
+    }
+    pub fn release(&mut self, button: MouseButton) -> &mut Self {
+        self.send(CommonEvent::MouseUp(button, self.cursor))
+    }
+    
+    pub fn click(&mut self, button: MouseButton, pos: impl Into<MousePos>) -> &mut Self {
+        self.move_to(pos).press(button).release(button)
+    }
+    
+    // press at from, then move to to in steps of about equal length, then release
+    pub fn drag(&mut self, button: MouseButton, from: impl Into<MousePos>, to: impl Into<MousePos>, steps: usize) -> &mut Self {
+        let (from, to) = (from.into(), to.into());
+        self.move_to(from).press(button);
+        let steps = steps.max(1) as isize;
+        for i in 1..=steps {
+            self.move_to(MousePos::new(
+                from.x + (to.x - from.x) * i / steps,
+                from.y + (to.y - from.y) * i / steps,
+            ));
+        }
+        self.release(button)
+    }
+    
+    // in notches, at the cursor; positive is right/away from the user
+    pub fn wheel(&mut self, dx: f32, dy: f32) -> &mut Self {
+        self.send(CommonEvent::MouseWheel(self.cursor, dx, dy))
+    }
+    
+    pub fn key_down(&mut self, key: Key) -> &mut Self { self.send(CommonEvent::KeyDown(key)) }
+    pub fn key_up(&mut self, key: Key) -> &mut Self { self.send(CommonEvent::KeyUp(key)) }
+    
+    pub fn key(&mut self, key: Key) -> &mut Self { self.key_down(key).key_up(key) }
+    
+    // as typed characters only; keys that produce them aren't pressed
+    pub fn type_text(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() { self.send(CommonEvent::Text(c)); }
+        self
+    }
+    
+    // draws a frame and returns a copy of it
+    // the draw doesn't go through dispatch, so event handlers and recordings don't see it
+    pub fn frame(&mut self) -> Image {
+        self.window_mut().draw();
+        self.window.capture()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::{
+        Pixel,
+        guicell::SolidCell,
+        testing::driver,
+        window::SurfaceSlice,
+    };
+    
+    const GREEN: Pixel = Pixel::rgb(0, 0xFF, 0);
+    
+    // keeps whatever reaches it, and fills itself green
+    struct Probe(Rc<RefCell<Vec<CommonEvent>>>);
+    impl GuiCell for Probe {
+        fn draw(&self, surface: &mut SurfaceSlice) { SolidCell::new(GREEN).draw(surface); }
+        fn handle_event(&mut self, e: &CommonEvent, _size: Size) -> bool {
+            self.0.borrow_mut().push(*e);
+            false
+        }
+    }
+    
+    fn probe() -> (Driver, Rc<RefCell<Vec<CommonEvent>>>) {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let driver = driver(Size {width: 200, height: 150}, Probe(seen.clone()));
+        // whatever the system sent while opening the window isn't part of any script
+        seen.borrow_mut().clear();
+        (driver, seen)
+    }
+    
+    fn pos(x: isize, y: isize) -> MousePos { MousePos::new(x, y) }
+    
+    #[test]
+    fn mouse() {
+        use CommonEvent::*;
+        use MouseButton::*;
+        let (mut driver, seen) = probe();
+        
+        driver.click(Left, pos(5, 6));
+        assert_eq!(driver.cursor(), pos(5, 6));
+        assert_eq!(seen.take(), [MouseMove(pos(5, 6)), MouseDown(Left, pos(5, 6)), MouseUp(Left, pos(5, 6))]);
+        
+        // wheels and bare presses happen wherever the cursor was left
+        driver.wheel(0.0, -1.5).press(Right).release(Right);
+        assert_eq!(seen.take(), [MouseWheel(pos(5, 6), 0.0, -1.5), MouseDown(Right, pos(5, 6)), MouseUp(Right, pos(5, 6))]);
+        
+        driver.drag(Middle, pos(0, 0), pos(9, 3), 3);
+        assert_eq!(seen.take(), [
+            MouseMove(pos(0, 0)), MouseDown(Middle, pos(0, 0)),
+            MouseMove(pos(3, 1)), MouseMove(pos(6, 2)), MouseMove(pos(9, 3)),
+            MouseUp(Middle, pos(9, 3)),
+        ]);
+        assert_eq!(driver.cursor(), pos(9, 3));
+        
+        // anything else with a position moves the cursor too
+        driver.send(MouseUp(Left, pos(-4, 7)));
+        assert_eq!(driver.cursor(), pos(-4, 7));
+    }
+    
+    #[test]
+    fn keyboard() {
+        use CommonEvent::*;
+        let (mut driver, seen) = probe();
+        driver.key(Key::A).key_down(Key::LShift).type_text("hé").key_up(Key::LShift);
+        assert_eq!(seen.take(), [KeyDown(Key::A), KeyUp(Key::A), KeyDown(Key::LShift), Text('h'), Text('é'), KeyUp(Key::LShift)]);
+        assert_eq!(driver.cursor(), pos(0, 0));
+    }
+    
+    #[test]
+    fn clock() {
+        let (mut driver, seen) = probe();
+        assert!(matches!(driver.window().clock(), Clock::Virtual(_)));
+        assert_eq!(driver.now(), Duration::ZERO);
+        
+        let frame = Duration::from_millis(16);
+        driver.advance(frame).advance(frame * 2);
+        assert_eq!(driver.now(), frame * 3);
+        assert_eq!(seen.take(), [CommonEvent::Tick(frame), CommonEvent::Tick(frame * 2)]);
+        
+        // nothing else moves it
+        driver.click(MouseButton::Left, pos(1, 1)).frame();
+        assert_eq!(driver.now(), frame * 3);
+    }
+    
+    #[test]
+    fn frames_and_recordings() {
+        let (mut driver, seen) = probe();
+        driver.window().start_recording();
+        driver.click(MouseButton::Left, pos(2, 3));
+        
+        let frame = driver.frame();
+        assert_eq!(frame.size(), driver.size());
+        assert!(frame.pixels().iter().all(|p| [p.r, p.g, p.b, p.a] == [0, 0xFF, 0, 0xFF]));
+        
+        // drawing a frame isn't an event, so neither the cell nor the recording sees one
+        driver.advance(Duration::from_millis(5)).move_to(pos(4, 4));
+        assert_eq!(seen.borrow().len(), 5);
+        assert!(!seen.borrow().contains(&CommonEvent::Draw));
+        let recording = driver.window().stop_recording().unwrap();
+        let events: Vec<_> = recording.events.iter().map(|r| (r.time.as_millis(), r.event)).collect();
+        assert_eq!(events, [
+            (0, CommonEvent::MouseMove(pos(2, 3))),
+            (0, CommonEvent::MouseDown(MouseButton::Left, pos(2, 3))),
+            (0, CommonEvent::MouseUp(MouseButton::Left, pos(2, 3))),
+            (5, CommonEvent::Tick(Duration::from_millis(5))),
+            (5, CommonEvent::MouseMove(pos(4, 4))),
+        ]);
+        assert!(recording.events.iter().all(|r| r.size == driver.size()));
+    }
+}
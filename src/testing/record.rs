@@ -0,0 +1,187 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::{
+    CommonEvent,MouseButton,MousePos,Size,
+    image::Image,
+    keys::Key,
+    window::Clock,
+};
+
+use super::Driver;
+
+// every event a window was given, for replaying bugs that don't happen twice on their own
+// saved as text, one event per line: microseconds since the window was created, window width and height, then the event
+
+const HEADER: &str = "gui recording 1";
+
+#[derive(Debug)]
+pub enum RecordingError {
+    Io(std::io::Error),
+    Malformed(usize, &'static str), // line, and what on it couldn't be read
+} impl fmt::Display for RecordingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordingError::Io(e) => write!(f, "couldn't read or write recording: {e}"),
+            RecordingError::Malformed(line, what) => write!(f, "malformed recording: bad {what} on line {line}"),
+        }
+    }
+} impl std::error::Error for RecordingError {
+} impl From<std::io::Error> for RecordingError {
+    fn from(e: std::io::Error) -> Self { RecordingError::Io(e) }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct RecordedEvent {
+    pub time: Duration, // by the window's clock
+    pub size: Size,     // of the window, after the event's had its effect on it (resizes, mostly)
+    pub event: CommonEvent,
+}
+
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Recording {
+    pub events: Vec<RecordedEvent>,
+} impl Recording {
+    pub fn new() -> Self { Self::default() }
+    
+    pub fn push(&mut self, event: RecordedEvent) { self.events.push(event); }
+    
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(),RecordingError> {
+        std::fs::write(path, self.to_string())?;
+        Ok(())
+    }
+    
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self,RecordingError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+    
+    pub fn parse(text: &str) -> Result<Self,RecordingError> {
+        let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        if lines.next().is_none_or(|(_, line)| line != HEADER) { return Err(RecordingError::Malformed(1, "header")); }
+        
+        let mut events = Vec::new();
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let mut words = line.split_ascii_whitespace();
+            let mut number = |what| words.next().and_then(|w| w.parse::<u64>().ok()).ok_or(RecordingError::Malformed(n, what));
+            let time = Duration::from_micros(number("time")?);
+            let size = Size {width: number("size")? as usize, height: number("size")? as usize};
+            let event = parse_event(&mut words).ok_or(RecordingError::Malformed(n, "event"))?;
+            if words.next().is_some() { return Err(RecordingError::Malformed(n, "event")); }
+            events.push(RecordedEvent {time, size, event});
+        }
+        Ok(Self {events})
+    }
+    
+    // every event into the driver's window, with its clock set to when each happened,
+    // and the window resized to whatever size it was
+    pub fn replay(&self, driver: &mut Driver) {
+        self.replay_each(driver, |_, _| {});
+    }
+    
+    // a frame after each event, to compare against what's expected
+    pub fn replay_frames(&self, driver: &mut Driver) -> Vec<Image> {
+        let mut frames = Vec::with_capacity(self.events.len());
+        self.replay_each(driver, |driver, _| frames.push(driver.frame()));
+        frames
+    }
+    
+    pub fn replay_each(&self, driver: &mut Driver, mut after: impl FnMut(&mut Driver, &RecordedEvent)) {
+        for recorded in &self.events {
+            driver.window_mut().set_clock(Clock::Virtual(recorded.time));
+            match recorded.event {
+                CommonEvent::Resize if driver.size() != recorded.size => { driver.resize(recorded.size); },
+                e => {
+                    if driver.size() != recorded.size { driver.resize(recorded.size); }
+                    driver.send(e);
+                },
+            }
+            after(driver, recorded);
+        }
+    }
+} impl fmt::Display for Recording {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{HEADER}")?;
+        for RecordedEvent {time, size, event} in &self.events {
+            write!(f, "{} {} {} ", time.as_micros(), size.width, size.height)?;
+            write_event(f, event)?;
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_event(f: &mut fmt::Formatter, e: &CommonEvent) -> fmt::Result {
+    use CommonEvent::*;
+    match *e {
+        KeyDown(key) => write!(f, "KeyDown {key:?}"),
+        KeyUp(key) => write!(f, "KeyUp {key:?}"),
+        MouseMove(pos) => write!(f, "MouseMove {} {}", pos.x, pos.y),
+        MouseDown(button, pos) => write!(f, "MouseDown {button:?} {} {}", pos.x, pos.y),
+        MouseUp(button, pos) => write!(f, "MouseUp {button:?} {} {}", pos.x, pos.y),
+        MouseWheel(pos, dx, dy) => write!(f, "MouseWheel {} {} {dx} {dy}", pos.x, pos.y),
+        // as a code point, since the character itself may be whitespace
+        Text(c) => write!(f, "Text {:X}", c as u32),
+        Tick(by) => write!(f, "Tick {}", by.as_micros()),
+        e => write!(f, "{e:?}"),
+    }
+}
+
+fn parse_event<'a>(words: &mut impl Iterator<Item = &'a str>) -> Option<CommonEvent> {
+    use CommonEvent::*;
+    fn number<'a, T: std::str::FromStr>(words: &mut impl Iterator<Item = &'a str>) -> Option<T> { words.next()?.parse().ok() }
+    let key = |word: &str| Key::ALL.into_iter().find(|key| format!("{key:?}") == word);
+    let button = |word: &str| match word {
+        "Left" => Some(MouseButton::Left),
+        "Right" => Some(MouseButton::Right),
+        "Middle" => Some(MouseButton::Middle),
+        _ => None,
+    };
+    Some(match words.next()? {
+        "Close" => Close,
+        "Draw" => Draw,
+        "KeyDown" => KeyDown(key(words.next()?)?),
+        "KeyUp" => KeyUp(key(words.next()?)?),
+        "Maximize" => Maximize,
+        "Minimize" => Minimize,
+        "MouseMove" => MouseMove(MousePos::new(number(words)?, number(words)?)),
+        "MouseDown" => MouseDown(button(words.next()?)?, MousePos::new(number(words)?, number(words)?)),
+        "MouseUp" => MouseUp(button(words.next()?)?, MousePos::new(number(words)?, number(words)?)),
+        "MouseWheel" => MouseWheel(MousePos::new(number(words)?, number(words)?), number(words)?, number(words)?),
+        "Resize" => Resize,
+        "QueryByCursor" => QueryByCursor,
+        "SetCursor" => SetCursor,
+        "Shutdown" => Shutdown,
+        "Text" => Text(char::from_u32(u32::from_str_radix(words.next()?, 16).ok()?)?),
+        "Tick" => Tick(Duration::from_micros(number(words)?)),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn round_trip() {
+        use CommonEvent::*;
+        let pos = MousePos::new(-3, 40);
+        let mut events = vec![
+            Close, Draw, Maximize, Minimize, Resize, QueryByCursor, SetCursor, Shutdown,
+            MouseMove(pos),
+            MouseWheel(pos, 0.5, -1.25), MouseWheel(pos, -0.1, 3.0),
+            Text('a'), Text(' '), Text('\n'), Text('\u{1F600}'),
+            Tick(Duration::from_micros(16_667)),
+        ];
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+            events.extend([MouseDown(button, pos), MouseUp(button, pos)]);
+        }
+        for key in Key::ALL { events.extend([KeyDown(key), KeyUp(key)]); }
+        
+        let recording = Recording {events: events.into_iter().enumerate().map(|(i, event)| RecordedEvent {
+            time: Duration::from_micros(i as u64 * 1000 + 7),
+            size: Size {width: 640 + i, height: 480},
+            event,
+        }).collect()};
+        assert_eq!(Recording::parse(&recording.to_string()).unwrap(), recording);
+    }
+}
@@ -0,0 +1,25 @@
+mod bidi;
+mod bitmap;
+mod builtin;
+mod cff;
+mod font;
+mod glyf;
+mod gpos;
+mod gsub;
+mod layout;
+mod linebreak;
+mod paragraph;
+mod reader;
+mod script;
+mod shape;
+#[cfg(test)]
+mod testfont;
+
+pub use bidi::TextDirection;
+pub use bitmap::BitmapFont;
+pub use font::{Font,FontError,Glyph,LineMetrics};
+pub use paragraph::{Align,Line,Paragraph,TextLayout,TextStyle,Weight};
+pub use shape::ShapedGlyph;
+
+// index of a glyph within a font
+pub type GlyphId = u16;
\ No newline at end of file
@@ -0,0 +1,542 @@
+// unicode's bidirectional algorithm (uax #9), which works out which way each char of mixed direction text runs
+// levels are even for left-to-right text and odd for right-to-left, nested as deep as embeddings go
+
+use super::script::is_mark;
+
+// which way a paragraph runs
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum TextDirection {
+    #[default]
+    Auto, // whichever way its first strongly directional char runs, or left-to-right if there isn't one
+    LeftToRight,
+    RightToLeft,
+}
+
+// as deep as embeddings and isolates can nest
+const MAX_DEPTH: u8 = 125;
+
+// named as they are in the unicode bidi algorithm
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+enum Class {
+    L, R, AL,             // strong: left-to-right, right-to-left, arabic letters
+    EN, ES, ET, AN, CS,   // numbers and what goes in them: european, separator, terminator, arabic, common separator
+    NSM, BN,              // marks, and controls that don't do anything
+    B, S, WS, ON,         // paragraph separators, segment separators (tabs), whitespace, and other neutrals
+    LRE, LRO, RLE, RLO, PDF,
+    LRI, RLI, FSI, PDI,
+}
+
+fn class(c: char) -> Class {
+    use Class::*;
+    match c {
+        '\n' | '\r' | '\u{1C}'..='\u{1E}' | '\u{85}' | '\u{2029}' => B,
+        '\t' | '\u{0B}' | '\u{1F}' => S,
+        '\u{0C}' | ' ' | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{2028}' | '\u{205F}' | '\u{3000}' => WS,
+        '\0'..='\u{08}' | '\u{0E}'..='\u{1B}' | '\u{7F}'..='\u{84}' | '\u{86}'..='\u{9F}' | '\u{AD}' |
+        '\u{180E}' | '\u{200B}'..='\u{200D}' | '\u{2060}'..='\u{2065}' | '\u{FEFF}' => BN,
+        '\u{200E}' => L,
+        '\u{200F}' => R,
+        '\u{61C}' => AL,
+        '\u{202A}' => LRE,
+        '\u{202B}' => RLE,
+        '\u{202C}' => PDF,
+        '\u{202D}' => LRO,
+        '\u{202E}' => RLO,
+        '\u{2066}' => LRI,
+        '\u{2067}' => RLI,
+        '\u{2068}' => FSI,
+        '\u{2069}' => PDI,
+        '0'..='9' | '\u{B2}' | '\u{B3}' | '\u{B9}' | '\u{6F0}'..='\u{6F9}' | '\u{2070}' | '\u{2074}'..='\u{2079}' |
+        '\u{2080}'..='\u{2089}' | '\u{2488}'..='\u{249B}' | '\u{FF10}'..='\u{FF19}' => EN,
+        '+' | '-' | '\u{207A}' | '\u{207B}' | '\u{208A}' | '\u{208B}' | '\u{2212}' | '\u{FB29}' |
+        '\u{FE62}' | '\u{FE63}' | '\u{FF0B}' | '\u{FF0D}' => ES,
+        '#'..='%' | '\u{A2}'..='\u{A5}' | '\u{B0}' | '\u{B1}' | '\u{58F}' | '\u{609}' | '\u{60A}' | '\u{66A}' |
+        '\u{2030}'..='\u{2034}' | '\u{20A0}'..='\u{20CF}' | '\u{2213}' | '\u{FE5F}' | '\u{FE69}' | '\u{FE6A}' |
+        '\u{FF03}'..='\u{FF05}' | '\u{FFE0}' | '\u{FFE1}' | '\u{FFE5}' | '\u{FFE6}' => ET,
+        '\u{600}'..='\u{605}' | '\u{660}'..='\u{669}' | '\u{66B}' | '\u{66C}' | '\u{6DD}' | '\u{8E2}' => AN,
+        ',' | '.' | '/' | ':' | '\u{A0}' | '\u{60C}' | '\u{202F}' | '\u{2044}' | '\u{FE50}' | '\u{FE52}' |
+        '\u{FE55}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF0F}' | '\u{FF1A}' => CS,
+        // devanagari's spacing vowel signs are marks, but they take up room like letters do
+        '\u{903}' | '\u{93B}' | '\u{93E}'..='\u{940}' | '\u{949}'..='\u{94C}' | '\u{94E}' | '\u{94F}' => L,
+        _ if is_mark(c) => NSM,
+        '\u{590}'..='\u{5FF}' | '\u{7C0}'..='\u{85F}' | '\u{FB1D}'..='\u{FB28}' | '\u{FB2A}'..='\u{FB4F}' |
+        '\u{10800}'..='\u{10FFF}' | '\u{1E800}'..='\u{1EDFF}' => R,
+        '\u{600}'..='\u{7BF}' | '\u{860}'..='\u{8FF}' | '\u{FB50}'..='\u{FD3D}' | '\u{FD40}'..='\u{FDCF}' | '\u{FDF0}'..='\u{FDFF}' |
+        '\u{FE70}'..='\u{FEFE}' | '\u{1EE00}'..='\u{1EEFF}' => AL,
+        '!' | '"' | '&'..='*' | ';'..='@' | '['..='`' | '{'..='~' | '\u{A1}' | '\u{A6}'..='\u{A9}' | '\u{AB}' | '\u{AC}' |
+        '\u{AE}' | '\u{AF}' | '\u{B4}' | '\u{B6}'..='\u{B8}' | '\u{BB}'..='\u{BF}' | '\u{D7}' | '\u{F7}' |
+        '\u{2010}'..='\u{2027}' | '\u{2035}'..='\u{2043}' | '\u{2045}'..='\u{205E}' | '\u{207C}'..='\u{207E}' |
+        '\u{208C}'..='\u{208E}' | '\u{2190}'..='\u{2211}' | '\u{2214}'..='\u{2335}' | '\u{237B}'..='\u{2394}' |
+        '\u{2396}'..='\u{2487}' | '\u{24EA}'..='\u{26AB}' | '\u{26AD}'..='\u{27FF}' | '\u{2900}'..='\u{2B73}' |
+        '\u{3001}'..='\u{3004}' | '\u{3008}'..='\u{3020}' | '\u{FD3E}' | '\u{FD3F}' | '\u{FE10}'..='\u{FE19}' |
+        '\u{FE30}'..='\u{FE4F}' | '\u{FE51}' | '\u{FE54}' | '\u{FE56}'..='\u{FE5E}' | '\u{FE60}' | '\u{FE61}' |
+        '\u{FE64}'..='\u{FE68}' | '\u{FE6B}' | '\u{FF01}' | '\u{FF02}' | '\u{FF06}'..='\u{FF0A}' | '\u{FF1B}'..='\u{FF20}' |
+        '\u{FF3B}'..='\u{FF40}' | '\u{FF5B}'..='\u{FF65}' | '\u{FFE2}'..='\u{FFE4}' | '\u{FFE8}'..='\u{FFEE}' => ON,
+        _ => L,
+    }
+}
+
+// the closing bracket for an opening one, for pairing them up so both sides of a pair go the same way
+fn closing_bracket(c: char) -> Option<char> {
+    Some(match c {
+        '(' => ')',
+        '[' => ']',
+        '{' => '}',
+        '\u{2045}' => '\u{2046}',
+        '\u{207D}' => '\u{207E}',
+        '\u{208D}' => '\u{208E}',
+        '\u{2308}' => '\u{2309}',
+        '\u{230A}' => '\u{230B}',
+        '\u{2329}' | '\u{3008}' => '\u{3009}',
+        '\u{300A}' => '\u{300B}',
+        '\u{300C}' => '\u{300D}',
+        '\u{300E}' => '\u{300F}',
+        '\u{3010}' => '\u{3011}',
+        '\u{FF08}' => '\u{FF09}',
+        '\u{FF3B}' => '\u{FF3D}',
+        '\u{FF5B}' => '\u{FF5D}',
+        _ => return None,
+    })
+}
+
+fn is_isolate_initiator(class: Class) -> bool { matches!(class, Class::LRI | Class::RLI | Class::FSI) }
+
+// chars the algorithm acts as if weren't there once embeddings are resolved
+fn is_removed(class: Class) -> bool {
+    matches!(class, Class::LRE | Class::RLE | Class::LRO | Class::RLO | Class::PDF | Class::BN)
+}
+
+// the direction of text as L or R, with numbers counting as right-to-left
+fn strong(class: Class) -> Option<Class> {
+    match class {
+        Class::L => Some(Class::L),
+        Class::R | Class::AL | Class::EN | Class::AN => Some(Class::R),
+        _ => None,
+    }
+}
+
+fn direction(level: u8) -> Class { if level.is_multiple_of(2) { Class::L } else { Class::R } }
+
+// resolved levels of every char of some text, split into paragraphs at paragraph separators
+pub(super) struct Bidi {
+    pub levels: Vec<u8>,              // one per char, not per byte
+    paragraphs: Vec<(usize,u8)>,      // byte offset each one ends at, and its own level
+} impl Bidi {
+    pub fn new(text: &str, direction: TextDirection) -> Self {
+        let chars: Vec<(usize,char)> = text.char_indices().collect();
+        let classes: Vec<Class> = chars.iter().map(|(_, c)| class(*c)).collect();
+        let mut levels = vec![0; chars.len()];
+        let mut paragraphs = Vec::new();
+        
+        let mut start = 0;
+        while start < chars.len() || paragraphs.is_empty() {
+            let mut end = classes[start..].iter().position(|c| *c == Class::B).map_or(chars.len(), |i| start + i + 1);
+            if end < chars.len() && chars[end - 1].1 == '\r' && chars[end].1 == '\n' { end += 1; }
+            let level = match direction {
+                TextDirection::LeftToRight => 0,
+                TextDirection::RightToLeft => 1,
+                TextDirection::Auto => first_strong(&classes[start..end]).map_or(0, |c| (c != Class::L) as u8),
+            };
+            let chars = &chars[start..end];
+            Resolver::new(chars, &classes[start..end], level).resolve(&mut levels[start..end]);
+            paragraphs.push((chars.last().map_or(text.len(), |(i, c)| i + c.len_utf8()), level));
+            start = end;
+        }
+        Self {levels, paragraphs}
+    }
+    
+    // level of the paragraph that the char at byte offset index is in
+    pub fn base_level(&self, index: usize) -> u8 {
+        self.paragraphs.iter().find(|(end, _)| index < *end).or(self.paragraphs.last()).map_or(0, |(_, level)| *level)
+    }
+}
+
+// the first strongly directional class in classes, not counting what's inside isolates
+fn first_strong(classes: &[Class]) -> Option<Class> {
+    let mut depth = 0usize;
+    for class in classes {
+        match class {
+            Class::L | Class::R | Class::AL if depth == 0 => return Some(*class),
+            Class::LRI | Class::RLI | Class::FSI => depth += 1,
+            Class::PDI if depth > 0 => depth -= 1,
+            Class::B => break,
+            _ => {},
+        }
+    }
+    None
+}
+
+struct Resolver<'a> {
+    chars: &'a [(usize,char)],
+    original: &'a [Class],
+    classes: Vec<Class>,
+    level: u8,
+    matching_pdi: Vec<Option<usize>>, // for isolate initiators
+    matched: Vec<bool>,               // for PDIs, whether an initiator matches them
+} impl<'a> Resolver<'a> {
+    fn new(chars: &'a [(usize,char)], original: &'a [Class], level: u8) -> Self {
+        let mut matching_pdi = vec![None; chars.len()];
+        let mut matched = vec![false; chars.len()];
+        let mut open: Vec<usize> = Vec::new();
+        for (i, class) in original.iter().enumerate() {
+            if is_isolate_initiator(*class) {
+                open.push(i);
+            } else if *class == Class::PDI && let Some(initiator) = open.pop() {
+                matching_pdi[initiator] = Some(i);
+                matched[i] = true;
+            }
+        }
+        Self {chars, original, classes: original.to_vec(), level, matching_pdi, matched}
+    }
+    
+    fn resolve(mut self, levels: &mut [u8]) {
+        self.explicit(levels);
+        for sequence in self.sequences(levels) {
+            self.resolve_sequence(&sequence, levels);
+        }
+        // removed chars take the level of what comes before them, so they end up in the same run when reordering
+        for i in 0..levels.len() {
+            if is_removed(self.original[i]) {
+                levels[i] = if i == 0 { self.level } else { levels[i - 1] };
+            }
+        }
+    }
+    
+    // explicit levels and directions, from embeddings, overrides and isolates (rules X1 to X8)
+    fn explicit(&mut self, levels: &mut [u8]) {
+        use Class::*;
+        
+        struct Status {level: u8, overridden: Option<Class>, isolate: bool}
+        let mut stack = vec![Status {level: self.level, overridden: None, isolate: false}];
+        let (mut overflow_isolates, mut overflow_embeddings, mut valid_isolates) = (0usize, 0usize, 0usize);
+        
+        for (i, &class) in self.original.iter().enumerate() {
+            let top = stack.last().unwrap();
+            let (level, overridden) = (top.level, top.overridden);
+            levels[i] = level;
+            let next_level = |rtl: bool| if rtl { (level + 1) | 1 } else { (level + 2) & !1 };
+            
+            match class {
+                RLE | LRE | RLO | LRO => {
+                    let new = next_level(matches!(class, RLE | RLO));
+                    if new <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                        let overridden = match class { RLO => Some(R), LRO => Some(L), _ => None };
+                        stack.push(Status {level: new, overridden, isolate: false});
+                    } else if overflow_isolates == 0 {
+                        overflow_embeddings += 1;
+                    }
+                },
+                RLI | LRI | FSI => {
+                    if let Some(o) = overridden { self.classes[i] = o; }
+                    let rtl = match class {
+                        RLI => true,
+                        LRI => false,
+                        _ => {
+                            let end = self.matching_pdi[i].unwrap_or(self.classes.len());
+                            first_strong(&self.original[i + 1..end]).is_some_and(|c| c != L)
+                        },
+                    };
+                    let new = next_level(rtl);
+                    if new <= MAX_DEPTH && overflow_isolates == 0 && overflow_embeddings == 0 {
+                        valid_isolates += 1;
+                        stack.push(Status {level: new, overridden: None, isolate: true});
+                    } else {
+                        overflow_isolates += 1;
+                    }
+                },
+                PDI => {
+                    if overflow_isolates > 0 {
+                        overflow_isolates -= 1;
+                    } else if valid_isolates > 0 {
+                        overflow_embeddings = 0;
+                        while stack.last().is_some_and(|s| !s.isolate) { stack.pop(); }
+                        stack.pop();
+                        valid_isolates -= 1;
+                    }
+                    let top = stack.last().unwrap();
+                    levels[i] = top.level;
+                    if let Some(o) = top.overridden { self.classes[i] = o; }
+                },
+                PDF if overflow_isolates > 0 => {},
+                PDF if overflow_embeddings > 0 => overflow_embeddings -= 1,
+                PDF => if !stack.last().unwrap().isolate && stack.len() >= 2 { stack.pop(); },
+                B => levels[i] = self.level,
+                BN => {},
+                _ => if let Some(o) = overridden { self.classes[i] = o; },
+            }
+        }
+    }
+    
+    // level runs chained into isolating run sequences, where text on either side of an isolate is resolved
+    // as though the isolate wasn't there (rules X9 and X10, BD13)
+    fn sequences(&self, levels: &[u8]) -> Vec<Vec<usize>> {
+        let mut runs: Vec<Vec<usize>> = Vec::new();
+        let mut last_level = None;
+        for i in (0..levels.len()).filter(|i| !is_removed(self.original[*i])) {
+            match runs.last_mut() {
+                Some(run) if last_level == Some(levels[i]) => run.push(i),
+                _ => runs.push(vec![i]),
+            }
+            last_level = Some(levels[i]);
+        }
+        
+        let mut sequences: Vec<Vec<usize>> = Vec::new();
+        let mut continuing: Vec<(usize,usize)> = Vec::new(); // (pdi that continues a sequence, which sequence)
+        for run in runs {
+            let first = run[0];
+            let sequence = match continuing.iter().position(|(pdi, _)| *pdi == first) {
+                Some(k) if self.original[first] == Class::PDI && self.matched[first] => continuing.swap_remove(k).1,
+                _ => { sequences.push(Vec::new()); sequences.len() - 1 },
+            };
+            let last = *run.last().unwrap();
+            sequences[sequence].extend(run);
+            if is_isolate_initiator(self.original[last]) && let Some(pdi) = self.matching_pdi[last] {
+                continuing.push((pdi, sequence));
+            }
+        }
+        sequences
+    }
+    
+    fn resolve_sequence(&mut self, sequence: &[usize], levels: &mut [u8]) {
+        use Class::*;
+        
+        let level = levels[sequence[0]];
+        let first = sequence[0];
+        let last = *sequence.last().unwrap();
+        // the direction at the start and end of the sequence, from whichever level is higher on each side
+        let before = (0..first).rev().find(|i| !is_removed(self.original[*i])).map_or(self.level, |i| levels[i]);
+        // an isolate initiator ending a sequence has nothing after it that's part of its paragraph
+        let after = if is_isolate_initiator(self.original[last]) {
+            self.level
+        } else {
+            (last + 1..levels.len()).find(|i| !is_removed(self.original[*i])).map_or(self.level, |i| levels[i])
+        };
+        let sos = direction(level.max(before));
+        let eos = direction(level.max(after));
+        
+        let mut types: Vec<Class> = sequence.iter().map(|i| self.classes[*i]).collect();
+        let n = types.len();
+        
+        // W1: marks take the type of what they're on
+        for k in 0..n {
+            if types[k] == NSM {
+                types[k] = match k.checked_sub(1).map(|j| types[j]) {
+                    None => sos,
+                    Some(LRI | RLI | FSI | PDI) => ON,
+                    Some(t) => t,
+                };
+            }
+        }
+        // W2: european numbers after arabic letters are arabic numbers; W3: arabic letters are right-to-left
+        let mut last_strong = sos;
+        for t in types.iter_mut() {
+            match *t {
+                L | R | AL => last_strong = *t,
+                EN if last_strong == AL => *t = AN,
+                _ => {},
+            }
+        }
+        for t in types.iter_mut() { if *t == AL { *t = R; } }
+        // W4: a single separator between two numbers of the same kind joins them
+        for k in 1..n.saturating_sub(1) {
+            match (types[k - 1], types[k], types[k + 1]) {
+                (EN, ES | CS, EN) => types[k] = EN,
+                (AN, CS, AN) => types[k] = AN,
+                _ => {},
+            }
+        }
+        // W5: terminators next to european numbers are part of them
+        let mut k = 0;
+        while k < n {
+            if types[k] != ET { k += 1; continue; }
+            let end = (k..n).find(|j| types[*j] != ET).unwrap_or(n);
+            let touches = (k > 0 && types[k - 1] == EN) || (end < n && types[end] == EN);
+            if touches { types[k..end].fill(EN); }
+            k = end;
+        }
+        // W6: leftover separators and terminators are neutral
+        for t in types.iter_mut() { if matches!(*t, ES | ET | CS) { *t = ON; } }
+        // W7: european numbers in left-to-right text are left-to-right
+        let mut last_strong = sos;
+        for t in types.iter_mut() {
+            match *t {
+                L | R => last_strong = *t,
+                EN if last_strong == L => *t = L,
+                _ => {},
+            }
+        }
+        
+        self.brackets(sequence, &mut types, sos, direction(level));
+        
+        // N1 and N2: neutrals between text going the same way go that way too, otherwise the way the embedding does
+        let neutral = |t: Class| matches!(t, B | S | WS | ON | LRI | RLI | FSI | PDI);
+        let mut k = 0;
+        while k < n {
+            if !neutral(types[k]) { k += 1; continue; }
+            let end = (k..n).find(|j| !neutral(types[*j])).unwrap_or(n);
+            let before = if k == 0 { sos } else { strong(types[k - 1]).unwrap_or(sos) };
+            let after = if end == n { eos } else { strong(types[end]).unwrap_or(eos) };
+            let resolved = if before == after { before } else { direction(level) };
+            types[k..end].fill(resolved);
+            k = end;
+        }
+        
+        // I1 and I2: levels go up to fit the resolved types
+        for (k, i) in sequence.iter().enumerate() {
+            let l = &mut levels[*i];
+            match (l.is_multiple_of(2), types[k]) {
+                (true, R) => *l += 1,
+                (true, AN | EN) => *l += 2,
+                (false, L | EN | AN) => *l += 1,
+                _ => {},
+            }
+        }
+    }
+    
+    // N0: both brackets of a pair go the same way, depending on what's between and before them
+    fn brackets(&self, sequence: &[usize], types: &mut [Class], sos: Class, embedding: Class) {
+        const MAX_OPEN: usize = 63;
+        let mut pairs: Vec<(usize,usize)> = Vec::new();
+        let mut open: Vec<(char,usize)> = Vec::new();
+        for (k, i) in sequence.iter().enumerate() {
+            if types[k] != Class::ON { continue; }
+            let c = self.chars[*i].1;
+            if let Some(close) = closing_bracket(c) {
+                if open.len() == MAX_OPEN { break; }
+                open.push((close, k));
+            } else if let Some(depth) = open.iter().rposition(|(close, _)| *close == c) {
+                pairs.push((open[depth].1, k));
+                open.truncate(depth);
+            }
+        }
+        pairs.sort_unstable();
+        
+        for (open, close) in pairs {
+            let inside: Vec<Class> = types[open + 1..close].iter().filter_map(|t| strong(*t)).collect();
+            let resolved = if inside.contains(&embedding) {
+                embedding
+            } else if let Some(opposite) = inside.first() {
+                let before = types[..open].iter().rev().find_map(|t| strong(*t)).unwrap_or(sos);
+                if before == *opposite { *opposite } else { embedding }
+            } else {
+                continue;
+            };
+            types[open] = resolved;
+            types[close] = resolved;
+            // marks on the brackets go with them
+            for bracket in [open, close] {
+                for k in bracket + 1..types.len() {
+                    if self.original[sequence[k]] != Class::NSM { break; }
+                    types[k] = resolved;
+                }
+            }
+        }
+    }
+}
+
+// L1: whitespace at the end of a line and before tabs and paragraph separators goes back to the paragraph's level
+// chars are the line's in logical order, and levels theirs
+pub(super) fn line_levels(chars: impl DoubleEndedIterator<Item=char>, levels: &mut [u8], base: u8) {
+    let mut trailing = true;
+    for (c, level) in chars.rev().zip(levels.iter_mut().rev()) {
+        match class(c) {
+            Class::S | Class::B => { *level = base; trailing = true; },
+            Class::WS | Class::LRI | Class::RLI | Class::FSI | Class::PDI => if trailing { *level = base; },
+            class if is_removed(class) => if trailing { *level = base; },
+            _ => trailing = false,
+        }
+    }
+}
+
+// L2: the order to show things with the given levels in, from left to right, as indices into levels
+pub(super) fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
+    let Some(&highest) = levels.iter().max() else { return order };
+    let lowest_odd = levels.iter().copied().filter(|l| l % 2 == 1).min().unwrap_or(highest + 1);
+    // every run at each level or higher gets reversed, from the highest level down
+    for level in (lowest_odd..=highest).rev() {
+        let mut k = 0;
+        while k < order.len() {
+            if levels[order[k]] < level { k += 1; continue; }
+            let end = (k..order.len()).find(|j| levels[order[*j]] < level).unwrap_or(order.len());
+            order[k..end].reverse();
+            k = end;
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn levels(text: &str, direction: TextDirection) -> Vec<u8> {
+        let bidi = Bidi::new(text, direction);
+        let mut levels = bidi.levels.clone();
+        line_levels(text.chars(), &mut levels, bidi.base_level(0));
+        levels
+    }
+    
+    // text in the order it's shown, left to right
+    fn visual(text: &str, direction: TextDirection) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        visual_order(&levels(text, direction)).into_iter().map(|i| chars[i]).collect()
+    }
+    
+    #[test]
+    fn paragraph_direction() {
+        assert_eq!(Bidi::new("abc", TextDirection::Auto).base_level(0), 0);
+        assert_eq!(Bidi::new("אבג", TextDirection::Auto).base_level(0), 1);
+        assert_eq!(Bidi::new("123 אב", TextDirection::Auto).base_level(0), 1);
+        assert_eq!(Bidi::new("abc", TextDirection::RightToLeft).base_level(0), 1);
+        // each paragraph works out its own
+        let bidi = Bidi::new("ab\nאב", TextDirection::Auto);
+        assert_eq!((bidi.base_level(0), bidi.base_level(3)), (0, 1));
+        assert_eq!(bidi.levels, [0, 0, 0, 1, 1]);
+    }
+    
+    #[test]
+    fn mixed() {
+        assert_eq!(levels("ab אב cd", TextDirection::Auto), [0, 0, 0, 1, 1, 0, 0, 0]);
+        assert_eq!(visual("ab אב cd", TextDirection::Auto), "ab בא cd");
+        assert_eq!(levels("אב cd גד", TextDirection::Auto), [1, 1, 1, 2, 2, 1, 1, 1]);
+        assert_eq!(visual("אב cd גד", TextDirection::Auto), "דג cd בא");
+        // trailing whitespace goes back to the paragraph's level
+        assert_eq!(levels("אב ", TextDirection::LeftToRight), [1, 1, 0]);
+    }
+    
+    #[test]
+    fn numbers() {
+        // numbers in right-to-left text still read left to right, along with their separators
+        assert_eq!(levels("אב 12", TextDirection::Auto), [1, 1, 1, 2, 2]);
+        assert_eq!(visual("אב 12", TextDirection::Auto), "12 בא");
+        assert_eq!(visual("אב 1.5 ג", TextDirection::Auto), "ג 1.5 בא");
+        // and after arabic letters, even european digits count as arabic numbers
+        assert_eq!(levels("\u{628} 12", TextDirection::Auto), [1, 1, 2, 2]);
+        assert_eq!(levels("ab 12", TextDirection::Auto), [0, 0, 0, 0, 0]);
+    }
+    
+    #[test]
+    fn brackets() {
+        // a pair goes the way of what's in it when that matches the embedding direction...
+        assert_eq!(levels("ab(אב)", TextDirection::Auto), [0, 0, 0, 1, 1, 0]);
+        // ...or else the way of the text before it, if that's what's inside
+        assert_eq!(levels("אב(cd)", TextDirection::Auto), [1, 1, 1, 2, 2, 1]);
+        assert_eq!(levels("ab (אב) גד", TextDirection::Auto), [0, 0, 0, 0, 1, 1, 0, 0, 1, 1]);
+        assert_eq!(levels("ab (cd) ef", TextDirection::RightToLeft), [2, 2, 2, 2, 2, 2, 2, 2, 2, 2]);
+        // and otherwise the embedding direction
+        assert_eq!(levels("אב (cd) ef", TextDirection::RightToLeft), [1, 1, 1, 1, 2, 2, 1, 1, 2, 2]);
+    }
+    
+    #[test]
+    fn isolates() {
+        // what's inside an isolate doesn't decide the paragraph's direction, or affect what's around it
+        assert_eq!(Bidi::new("\u{2067}אב\u{2069} c", TextDirection::Auto).base_level(0), 0);
+        assert_eq!(levels("a\u{2067}אב\u{2069}1", TextDirection::Auto), [0, 0, 1, 1, 0, 0]);
+        assert_eq!(levels("a\u{2066}b\u{2069}", TextDirection::RightToLeft), [2, 1, 2, 1]);
+        assert_eq!(levels("\u{2068}אב\u{2069}", TextDirection::LeftToRight), [0, 1, 1, 0]);
+        // one that's never closed runs to the end of the paragraph
+        assert_eq!(levels("a\u{2067}אב", TextDirection::Auto), [0, 0, 1, 1]);
+    }
+}
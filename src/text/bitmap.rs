@@ -0,0 +1,460 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::{
+    Pixel,PixelIdx,Size,
+    blend::BlendMode,
+    window::SurfaceSlice,
+};
+
+use super::builtin;
+use super::font::{FontError,Glyph};
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xB5, 0x4A, 0x86];
+
+// widest or tallest a glyph can be, and the most glyphs a psf can have
+// nothing real comes close; anything past these is a broken file asking for more memory than there is
+const MAX_GLYPH_SIZE: usize = 4096;
+const MAX_PSF_GLYPHS: usize = 0x10000;
+
+// a font of pixel art glyphs at one fixed size, loaded from a .bdf or .psf, or the built-in one
+// glyphs are Glyphs like any other font's, but every pixel is either fully covered or not at all
+pub struct BitmapFont {
+    glyphs: HashMap<char,(Glyph,usize)>, // and how far it moves the pen
+    fallback: Option<char>,              // drawn in place of characters the font doesn't have
+    ascent: usize,
+    descent: usize,
+} impl BitmapFont {
+    
+    // the font compiled into the crate, which is always there
+    pub fn builtin() -> &'static BitmapFont {
+        static BUILTIN: OnceLock<BitmapFont> = OnceLock::new();
+        BUILTIN.get_or_init(|| {
+            let glyphs = builtin::GLYPHS.iter().enumerate().map(|(i, rows)| {
+                let c = char::from_u32(builtin::FIRST as u32 + i as u32).unwrap_or(builtin::FIRST);
+                let coverage = rows.iter()
+                    .flat_map(|row| (0..builtin::WIDTH).map(move |x| row >> (builtin::WIDTH - 1 - x) & 1))
+                    .map(|bit| bit * 255)
+                    .collect();
+                let glyph = Glyph {
+                    size: Size {width: builtin::WIDTH, height: builtin::HEIGHT},
+                    left: 0,
+                    top: -(builtin::ABOVE as isize),
+                    coverage,
+                };
+                (c, (glyph, builtin::ADVANCE))
+            }).collect();
+            BitmapFont {glyphs, fallback: Some('?'), ascent: builtin::ASCENT, descent: builtin::DESCENT}
+        })
+    }
+    
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self,FontError> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+    
+    // bdf or psf, whichever data looks like
+    pub fn from_bytes(data: &[u8]) -> Result<Self,FontError> {
+        if data.starts_with(&PSF1_MAGIC) || data.starts_with(&PSF2_MAGIC) {
+            Self::from_psf(data)
+        } else {
+            Self::from_bdf(data)
+        }
+    }
+    
+    // glyph bitmap distribution format, as used by X11
+    // encodings are taken to be unicode, which is true of iso10646 fonts and close enough for latin-1 ones
+    pub fn from_bdf(data: &[u8]) -> Result<Self,FontError> {
+        use FontError::Malformed;
+        
+        let text = String::from_utf8_lossy(data);
+        let mut lines = text.lines().map(str::trim);
+        if !lines.next().is_some_and(|line| line.starts_with("STARTFONT")) {
+            return Err(Malformed("BDF header"));
+        }
+        
+        let mut bounds: Option<[isize; 4]> = None;
+        let (mut ascent, mut descent, mut default) = (None, None, None);
+        let mut glyphs = HashMap::new();
+        while let Some(line) = lines.next() {
+            let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+            match keyword {
+                "FONTBOUNDINGBOX" => bounds = Some(numbers(args).ok_or(Malformed("BDF bounding box"))?),
+                "FONT_ASCENT" => ascent = Some(numbers::<1>(args).ok_or(Malformed("BDF ascent"))?[0]),
+                "FONT_DESCENT" => descent = Some(numbers::<1>(args).ok_or(Malformed("BDF descent"))?[0]),
+                "DEFAULT_CHAR" => default = numbers::<1>(args).and_then(|[c]| char::from_u32(c as u32)),
+                "STARTCHAR" => {
+                    let (c, glyph, advance) = bdf_char(&mut lines, bounds).ok_or(Malformed("BDF character"))?;
+                    if let Some(c) = c { glyphs.insert(c, (glyph, advance)); }
+                },
+                _ => {},
+            }
+        }
+        
+        // the bounding box covers every glyph, so it'll do if the line metrics are missing
+        let [_, height, _, y] = bounds.unwrap_or_default();
+        let ascent = ascent.unwrap_or(height + y).max(0) as usize;
+        let descent = descent.unwrap_or(-y).max(0) as usize;
+        let fallback = [default, Some('\u{FFFD}'), Some('?')].into_iter()
+            .flatten()
+            .find(|c| glyphs.contains_key(c));
+        Ok(Self {glyphs, fallback, ascent, descent})
+    }
+    
+    // pc screen font, version 1 or 2, as used by the linux console
+    // fonts without a unicode table are assumed to be in code point order
+    pub fn from_psf(data: &[u8]) -> Result<Self,FontError> {
+        use FontError::{Malformed,Unsupported};
+        
+        let (count, glyph_size, width, height, glyphs_at, unicode) = if data.starts_with(&PSF1_MAGIC) {
+            const MODE_512: u8 = 0x01;
+            const MODE_HAS_TABLE: u8 = 0x06;
+            let [mode, height] = [data.get(2), data.get(3)].map(|b| b.copied().unwrap_or_default());
+            let count = if mode & MODE_512 != 0 { 512 } else { 256 };
+            (count, height as usize, 8, height as usize, 4, mode & MODE_HAS_TABLE != 0)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            const HAS_TABLE: u32 = 0x01;
+            let field = |i: usize| le_u32(data, 4 + i * 4).map(|v| v as usize).ok_or(Malformed("PSF header"));
+            let version = field(0)?;
+            if version != 0 { return Err(Unsupported("PSF version")); }
+            let (header, flags) = (field(1)?, field(2)?);
+            (field(3)?, field(4)?, field(6)?, field(5)?, header, flags as u32 & HAS_TABLE != 0)
+        } else {
+            return Err(Malformed("PSF header"));
+        };
+        
+        if width == 0 || height == 0 || width > MAX_GLYPH_SIZE || height > MAX_GLYPH_SIZE { return Err(Malformed("PSF glyph size")); }
+        if count > MAX_PSF_GLYPHS { return Err(Malformed("PSF glyph count")); }
+        let stride = width.div_ceil(8);
+        if glyph_size == 0 || glyph_size < stride * height { return Err(Malformed("PSF glyph size")); }
+        let table_at = count.checked_mul(glyph_size).and_then(|n| n.checked_add(glyphs_at))
+            .filter(|end| *end <= data.len())
+            .ok_or(Malformed("PSF glyphs"))?;
+        
+        let chars: Vec<Vec<char>> = if !unicode {
+            (0..count).map(|i| char::from_u32(i as u32).into_iter().collect()).collect()
+        } else if data.starts_with(&PSF1_MAGIC) {
+            psf1_table(&data[table_at..], count)
+        } else {
+            psf2_table(&data[table_at..], count)
+        };
+        
+        // psf has no idea where the baseline is, but this is where it is in the usual console fonts
+        let descent = height / 4;
+        let mut glyphs = HashMap::new();
+        for (i, chars) in chars.into_iter().enumerate() {
+            let bitmap = &data[glyphs_at + i * glyph_size..][..stride * height];
+            let coverage = (0..height)
+                .flat_map(|y| (0..width).map(move |x| bitmap[y * stride + x / 8] >> (7 - x % 8) & 1))
+                .map(|bit| bit * 255)
+                .collect();
+            let glyph = Glyph {
+                size: Size {width, height},
+                left: 0,
+                top: -((height - descent) as isize),
+                coverage,
+            };
+            for c in chars { glyphs.insert(c, (glyph.clone(), width)); }
+        }
+        let fallback = ['\u{FFFD}', '?'].into_iter().find(|c| glyphs.contains_key(c));
+        Ok(Self {glyphs, fallback, ascent: height - descent, descent})
+    }
+    
+    pub fn ascent(&self) -> usize { self.ascent }
+    
+    pub fn descent(&self) -> usize { self.descent }
+    
+    // distance from one baseline to the next
+    pub fn line_height(&self) -> usize { self.ascent + self.descent }
+    
+    pub fn has_glyph(&self, c: char) -> bool { self.glyphs.contains_key(&c) }
+    
+    // c's glyph and advance, or the fallback's if there's no glyph for it
+    pub fn glyph(&self, c: char) -> Option<(&Glyph,usize)> {
+        self.glyphs.get(&c)
+            .or_else(|| self.glyphs.get(&self.fallback?))
+            .map(|(glyph, advance)| (glyph, *advance))
+    }
+    
+    // size of the box text fills when drawn, one line per line of text
+    pub fn measure(&self, text: &str) -> Size {
+        self.layout(text, |_, _, _| {})
+    }
+    
+    // text with the top-left corner of its first line at `at`, returning the size it took up
+    pub fn draw(&self, surface: &mut SurfaceSlice, text: &str, at: PixelIdx, color: Pixel) -> Size {
+        self.layout(text, |glyph, x, y| {
+            for (i, c) in glyph.coverage.iter().enumerate() {
+                let px = at.x as isize + x + glyph.left + (i % glyph.size.width) as isize;
+                let py = at.y as isize + y + glyph.top + (i / glyph.size.width) as isize;
+                if *c == 0 || px < 0 || py < 0 { continue; }
+                surface.blend_pixel(PixelIdx::new(px as usize, py as usize), color, BlendMode::SrcOver);
+            }
+        })
+    }
+    
+    // calls f with every glyph and its pen position, relative to the top-left of the text
+    fn layout(&self, text: &str, mut f: impl FnMut(&Glyph,isize,isize)) -> Size {
+        let mut size = Size::ZERO;
+        for (i, line) in text.lines().enumerate() {
+            let y = (self.ascent + i * self.line_height()) as isize;
+            let mut x = 0;
+            for c in line.chars() {
+                let Some((glyph, advance)) = self.glyph(c) else { continue };
+                f(glyph, x as isize, y);
+                x += advance;
+            }
+            size.width = size.width.max(x);
+            size.height += self.line_height();
+        }
+        size
+    }
+} impl std::fmt::Debug for BitmapFont {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("BitmapFont")
+            .field("glyphs", &self.glyphs.len())
+            .field("ascent", &self.ascent)
+            .field("descent", &self.descent)
+            .finish_non_exhaustive()
+    }
+}
+
+// whitespace separated integers, exactly N of them
+fn numbers<const N: usize>(s: &str) -> Option<[isize; N]> {
+    let mut words = s.split_whitespace();
+    let mut out = [0; N];
+    for n in &mut out { *n = words.next()?.parse().ok()?; }
+    words.next().is_none().then_some(out)
+}
+
+// the rest of a glyph, after its STARTCHAR line
+// characters with no encoding come back as None, since there's no way to ask for them
+fn bdf_char<'a>(lines: &mut impl Iterator<Item = &'a str>, bounds: Option<[isize; 4]>) -> Option<(Option<char>,Glyph,usize)> {
+    let mut c = None;
+    let mut advance = 0;
+    let mut bbx = bounds;
+    loop {
+        let line = lines.next()?;
+        let (keyword, args) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            // "ENCODING -1 n" means n in some other encoding, which isn't unicode
+            "ENCODING" => c = args.split_whitespace().next()?.parse::<u32>().ok().and_then(char::from_u32),
+            "DWIDTH" => advance = args.split_whitespace().next()?.parse::<isize>().ok()?.max(0) as usize,
+            "BBX" => bbx = Some(numbers(args)?),
+            "BITMAP" => break,
+            "ENDCHAR" => return None,
+            _ => {},
+        }
+    }
+    
+    let [width, height, x, y] = bbx?;
+    let (width, height) = (width.max(0) as usize, height.max(0) as usize);
+    if width > MAX_GLYPH_SIZE || height > MAX_GLYPH_SIZE { return None; }
+    let top = y.checked_add(height as isize)?.checked_neg()?;
+    let mut coverage = Vec::with_capacity(width * height);
+    for _ in 0..height {
+        let row = lines.next()?;
+        let bytes: Vec<u8> = (0..row.len() / 2)
+            .map(|i| u8::from_str_radix(row.get(i * 2..i * 2 + 2)?, 16).ok())
+            .collect::<Option<_>>()?;
+        for col in 0..width {
+            let byte = bytes.get(col / 8).copied().unwrap_or(0);
+            coverage.push((byte >> (7 - col % 8) & 1) * 255);
+        }
+    }
+    if lines.next()? != "ENDCHAR" { return None; }
+    
+    let glyph = Glyph {
+        size: Size {width, height},
+        left: x,
+        top,
+        coverage,
+    };
+    Some((c, glyph, advance))
+}
+
+// characters of each glyph, from a psf1 unicode table of little-endian u16 lists ending in 0xFFFF
+// 0xFFFE starts sequences of several characters sharing one glyph, which are skipped
+fn psf1_table(table: &[u8], count: usize) -> Vec<Vec<char>> {
+    let mut values = table.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    (0..count).map(|_| {
+        let mut chars = Vec::new();
+        let mut sequence = false;
+        for v in values.by_ref() {
+            match v {
+                0xFFFF => break,
+                0xFFFE => sequence = true,
+                _ if !sequence => chars.extend(char::from_u32(v as u32)),
+                _ => {},
+            }
+        }
+        chars
+    }).collect()
+}
+
+// the same, for psf2, where the lists are utf-8 ending in 0xFF, with 0xFE starting sequences
+fn psf2_table(table: &[u8], count: usize) -> Vec<Vec<char>> {
+    let mut entries = table.split(|b| *b == 0xFF);
+    (0..count).map(|_| {
+        let entry = entries.next().unwrap_or_default();
+        let singles = entry.split(|b| *b == 0xFE).next().unwrap_or_default();
+        String::from_utf8_lossy(singles).chars().filter(|c| *c != '\u{FFFD}').collect()
+    }).collect()
+}
+
+fn le_u32(data: &[u8], pos: usize) -> Option<u32> {
+    let b = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::Image;
+    
+    const BDF: &str = "STARTFONT 2.1
+FONT -test-small
+FONTBOUNDINGBOX 4 6 0 -1
+STARTPROPERTIES 2
+FONT_ASCENT 5
+FONT_DESCENT 1
+ENDPROPERTIES
+CHARS 3
+STARTCHAR A
+ENCODING 65
+DWIDTH 5 0
+BBX 4 6 0 -1
+BITMAP
+60
+90
+F0
+90
+90
+00
+ENDCHAR
+STARTCHAR question
+ENCODING 63
+DWIDTH 5 0
+BBX 3 2 1 3
+BITMAP
+E0
+20
+ENDCHAR
+STARTCHAR unencoded
+ENCODING -1 300
+DWIDTH 5 0
+BBX 1 1 0 0
+BITMAP
+80
+ENDCHAR
+ENDFONT
+";
+    
+    fn rows(glyph: &Glyph) -> Vec<String> {
+        glyph.coverage.chunks(glyph.size.width).map(|row| row.iter().map(|c| if *c == 255 { '#' } else { '.' }).collect()).collect()
+    }
+    
+    fn psf2_header(count: u32, width: u32, height: u32, glyph_size: u32, flags: u32) -> Vec<u8> {
+        let mut data = PSF2_MAGIC.to_vec();
+        for field in [0, 32, flags, count, glyph_size, height, width] { data.extend(field.to_le_bytes()); }
+        data
+    }
+    
+    #[test]
+    fn bdf() {
+        let font = BitmapFont::from_bytes(BDF.as_bytes()).unwrap();
+        assert_eq!((font.ascent(), font.descent(), font.line_height()), (5, 1, 6));
+        let (a, advance) = font.glyph('A').unwrap();
+        assert_eq!((a.size, a.left, a.top, advance), (Size {width: 4, height: 6}, 0, -5, 5));
+        assert_eq!(rows(a), [".##.", "#..#", "####", "#..#", "#..#", "...."]);
+        // missing characters get the fallback, and ones without a unicode encoding can't be asked for
+        assert!(!font.has_glyph('Z'));
+        assert_eq!(font.glyph('Z').unwrap().0.size, Size {width: 3, height: 2});
+        assert_eq!(font.measure("AA\nA"), Size {width: 10, height: 12});
+    }
+    
+    #[test]
+    fn bdf_malformed() {
+        assert!(BitmapFont::from_bytes(b"not a font").is_err());
+        let oversized = BDF.replace("BBX 3 2 1 3", "BBX 100000 100000 0 0");
+        assert!(matches!(BitmapFont::from_bytes(oversized.as_bytes()), Err(FontError::Malformed(_))));
+        let overflowing = BDF.replace("BBX 3 2 1 3", &format!("BBX 3 2 1 {}", isize::MAX));
+        assert!(BitmapFont::from_bytes(overflowing.as_bytes()).is_err());
+        let truncated = &BDF[..BDF.find("00\nENDCHAR").unwrap()];
+        assert!(BitmapFont::from_bytes(truncated.as_bytes()).is_err());
+    }
+    
+    #[test]
+    fn psf1() {
+        // 256 8x8 glyphs in code point order, with A as a box
+        let mut data = vec![PSF1_MAGIC[0], PSF1_MAGIC[1], 0, 8];
+        data.resize(4 + 256 * 8, 0);
+        data[4 + 65 * 8..][..8].copy_from_slice(&[0xFF, 0x81, 0x81, 0x81, 0x81, 0x81, 0x81, 0xFF]);
+        let font = BitmapFont::from_bytes(&data).unwrap();
+        assert_eq!((font.ascent(), font.descent()), (6, 2));
+        let (a, advance) = font.glyph('A').unwrap();
+        assert_eq!((a.size, a.top, advance), (Size {width: 8, height: 8}, -6, 8));
+        assert_eq!(rows(a)[..2], ["########", "#......#"]);
+        assert!(font.has_glyph('\u{FF}') && !font.has_glyph('\u{100}'));
+        
+        // with a unicode table, giving glyph 1 both é and É, and glyph 2 a sequence that's skipped
+        let mut data = vec![PSF1_MAGIC[0], PSF1_MAGIC[1], 0x02, 1];
+        data.resize(4 + 256, 0);
+        data[5] = 0xFF;
+        for glyph in 0..256u16 {
+            let chars: &[u16] = match glyph { 1 => &[0xE9, 0xC9], 2 => &[0xFFFE, 0x65, 0x301], _ => &[] };
+            for v in chars.iter().chain(&[0xFFFF]) { data.extend(v.to_le_bytes()); }
+        }
+        let font = BitmapFont::from_bytes(&data).unwrap();
+        assert!(font.has_glyph('é') && font.has_glyph('É') && !font.has_glyph('e') && !font.has_glyph('\u{1}'));
+        assert_eq!(rows(font.glyph('é').unwrap().0), ["########"]);
+    }
+    
+    #[test]
+    fn psf2() {
+        // two 10x3 glyphs, the second for the euro sign
+        let mut data = psf2_header(2, 10, 3, 6, 1);
+        data.extend([0; 6]);
+        data.extend([0xC0, 0x40, 0x00, 0x00, 0xFF, 0xC0]);
+        data.extend(b"x\xFF\xE2\x82\xAC\xFEab\xFF");
+        let font = BitmapFont::from_bytes(&data).unwrap();
+        assert!(font.has_glyph('x') && font.has_glyph('€') && !font.has_glyph('a'));
+        let (euro, advance) = font.glyph('€').unwrap();
+        assert_eq!((euro.size, advance), (Size {width: 10, height: 3}, 10));
+        assert_eq!(rows(euro), ["##.......#", "..........", "##########"]);
+        
+        assert!(matches!(BitmapFont::from_bytes(&data[..data.len() - 20]), Err(FontError::Malformed(_))));
+        let mut newer = data.clone();
+        newer[4] = 1;
+        assert!(matches!(BitmapFont::from_bytes(&newer), Err(FontError::Unsupported(_))));
+    }
+    
+    #[test]
+    fn psf2_bad_sizes() {
+        // zero-sized glyphs would otherwise pass the length check with any count at all
+        for (count, width, height, glyph_size) in [(30_000_000, 8, 0, 0), (u32::MAX, 8, 0, 0), (10, 0, 8, 8), (10, 8, 8, 0), (0x20000, 1, 1, 1)] {
+            let mut data = psf2_header(count, width, height, glyph_size, 0);
+            data.resize(data.len() + 0x20000, 0);
+            assert!(matches!(BitmapFont::from_bytes(&data), Err(FontError::Malformed(_))), "{count} {width}x{height}");
+        }
+    }
+    
+    #[test]
+    fn builtin() {
+        let font = BitmapFont::builtin();
+        assert!(('!'..='~').all(|c| font.has_glyph(c)));
+        assert_eq!(font.line_height(), 10);
+        assert_eq!(font.measure("ab\ncde"), Size {width: 18, height: 20});
+        // the fallback is a question mark
+        assert_eq!(font.glyph('\u{263A}').unwrap().0.coverage, font.glyph('?').unwrap().0.coverage);
+        
+        let mut image = Image::filled(Size {width: 12, height: 10}, Pixel::rgb(0, 0, 0));
+        let size = unsafe { image.with_slice(|s| s.draw_text("!", PixelIdx::new(1, 0), Pixel::rgb(255, 255, 255))) };
+        assert_eq!(size, Size {width: 6, height: 10});
+        // the stem of the ! is in the middle column, starting below the blank row at the top
+        let lit: Vec<(usize, usize)> = (0..10).flat_map(|y| (0..12).map(move |x| (x, y)))
+            .filter(|(x, y)| image.get_pixel(PixelIdx::new(*x, *y)).r == 255)
+            .collect();
+        assert_eq!(lit, [(3, 1), (3, 2), (3, 3), (3, 4), (3, 5), (3, 7)]);
+    }
+}
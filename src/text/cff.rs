@@ -0,0 +1,454 @@
+use crate::draw::{Path,Point};
+
+use super::GlyphId;
+use super::reader::Reader;
+
+// subroutines calling subroutines, but not forever
+const MAX_DEPTH: usize = 10;
+
+// an INDEX: a count, then offsets, then the data they point into
+// positions are relative to the start of the cff table
+#[derive(Clone,Copy,Debug,Default)]
+struct Index {
+    count: usize,
+    off_size: u8,
+    offsets: usize,
+    data: usize, // offsets are 1-based, so this is one byte before the data
+} impl Index {
+    // the index at pos, and where it ends
+    fn parse(cff: &[u8], pos: usize) -> Option<(Index,usize)> {
+        let mut r = Reader::at(cff, pos);
+        let count = r.u16()? as usize;
+        if count == 0 { return Some((Index::default(), pos + 2)); }
+        let off_size = r.u8()?;
+        let offsets = r.pos();
+        let data = offsets + (count + 1) * off_size as usize - 1;
+        let end = Reader::at(cff, offsets + count * off_size as usize).offset(off_size)?;
+        Some((Index {count, off_size, offsets, data}, data + end))
+    }
+    
+    fn get<'a>(&self, cff: &'a [u8], i: usize) -> Option<&'a [u8]> {
+        if i >= self.count { return None; }
+        let mut r = Reader::at(cff, self.offsets + i * self.off_size as usize);
+        let start = r.offset(self.off_size)?;
+        let end = r.offset(self.off_size)?;
+        cff.get(self.data + start..self.data + end)
+    }
+    
+    // subroutine numbers are stored minus a bias that depends on how many there are
+    fn bias(&self) -> i32 {
+        match self.count {
+            0..1240 => 107,
+            1240..33900 => 1131,
+            _ => 32768,
+        }
+    }
+}
+
+// which private dict, and so which local subroutines, each glyph uses
+enum LocalSubrs {
+    Single(Index),
+    Cid {fd_select: usize, fds: Vec<Index>},
+}
+
+// compact font format (postscript) outlines, made of cubic curves
+pub(super) struct Cff {
+    charstrings: Index,
+    global_subrs: Index,
+    local_subrs: LocalSubrs,
+} impl Cff {
+    pub fn parse(cff: &[u8]) -> Option<Self> {
+        let mut r = Reader::new(cff);
+        let _major = r.u8()?;
+        let _minor = r.u8()?;
+        let header_size = r.u8()? as usize;
+        
+        let (_names, end) = Index::parse(cff, header_size)?;
+        let (top_dicts, end) = Index::parse(cff, end)?;
+        let (_strings, end) = Index::parse(cff, end)?;
+        let (global_subrs, _) = Index::parse(cff, end)?;
+        
+        let top = dict(top_dicts.get(cff, 0)?)?;
+        let operand = |op: u16| top.iter().find(|(o, _)| *o == op).map(|(_, v)| v.as_slice());
+        
+        if operand(CHARSTRING_TYPE).is_some_and(|v| v.first() != Some(&2.0)) { return None; }
+        let (charstrings, _) = Index::parse(cff, *operand(CHARSTRINGS)?.first()? as usize)?;
+        
+        let local_subrs = match (operand(FD_ARRAY), operand(FD_SELECT)) {
+            (Some(fd_array), Some(fd_select)) => {
+                let (fd_array, _) = Index::parse(cff, *fd_array.first()? as usize)?;
+                let fds = (0..fd_array.count)
+                    .map(|i| private_subrs(cff, &dict(fd_array.get(cff, i)?)?))
+                    .collect::<Option<Vec<Index>>>()?;
+                LocalSubrs::Cid {fd_select: *fd_select.first()? as usize, fds}
+            },
+            _ => LocalSubrs::Single(private_subrs(cff, &top)?),
+        };
+        
+        Some(Self {charstrings, global_subrs, local_subrs})
+    }
+    
+    pub fn glyph_count(&self) -> usize { self.charstrings.count }
+    
+    // outline in font units, with y pointing up
+    pub fn outline(&self, cff: &[u8], glyph: GlyphId) -> Option<Path> {
+        let code = self.charstrings.get(cff, glyph as usize)?;
+        let local = match &self.local_subrs {
+            LocalSubrs::Single(subrs) => *subrs,
+            LocalSubrs::Cid {fd_select, fds} => *fds.get(fd_index(cff, *fd_select, glyph)?)?,
+        };
+        let mut charstring = Charstring {
+            cff,
+            global: self.global_subrs,
+            local,
+            stack: Vec::with_capacity(48),
+            stems: 0,
+            pen: Point::default(),
+            open: false,
+            path: Path::new(),
+        };
+        charstring.run(code, 0)?;
+        if charstring.open { charstring.path.close(); }
+        Some(charstring.path)
+    }
+}
+
+const CHARSTRINGS: u16     = 17;
+const PRIVATE: u16         = 18;
+const SUBRS: u16           = 19;
+const CHARSTRING_TYPE: u16 = 1206;
+const FD_ARRAY: u16        = 1236;
+const FD_SELECT: u16       = 1237;
+
+type Dict = Vec<(u16,Vec<f64>)>;
+
+// operators and their operands; two-byte operators are 1200 + the second byte
+fn dict(data: &[u8]) -> Option<Dict> {
+    let mut r = Reader::new(data);
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    while let Some(b0) = r.u8() {
+        match b0 {
+            0..=11 | 13..=21 => entries.push((b0 as u16, std::mem::take(&mut operands))),
+            12 => entries.push((1200 + r.u8()? as u16, std::mem::take(&mut operands))),
+            28 => operands.push(r.i16()? as f64),
+            29 => operands.push(r.i32()? as f64),
+            30 => operands.push(real(&mut r)?),
+            32..=246 => operands.push(b0 as f64 - 139.0),
+            247..=250 => operands.push((b0 as f64 - 247.0) * 256.0 + r.u8()? as f64 + 108.0),
+            251..=254 => operands.push(-(b0 as f64 - 251.0) * 256.0 - r.u8()? as f64 - 108.0),
+            _ => return None,
+        }
+    }
+    Some(entries)
+}
+
+// real numbers are written out in nibbles, more or less as text
+fn real(r: &mut Reader) -> Option<f64> {
+    let mut s = String::new();
+    loop {
+        let b = r.u8()?;
+        for nibble in [b >> 4, b & 0xF] {
+            match nibble {
+                0..=9 => s.push((b'0' + nibble) as char),
+                0xA => s.push('.'),
+                0xB => s.push('E'),
+                0xC => s.push_str("E-"),
+                0xE => s.push('-'),
+                0xF => return s.parse().ok(),
+                _ => return None,
+            }
+        }
+    }
+}
+
+// local subroutines of the private dict a font dict points to
+fn private_subrs(cff: &[u8], font_dict: &Dict) -> Option<Index> {
+    let Some((_, private)) = font_dict.iter().find(|(op, _)| *op == PRIVATE) else { return Some(Index::default()) };
+    let (size, offset) = (*private.first()? as usize, *private.get(1)? as usize);
+    let private_dict = dict(cff.get(offset..offset.checked_add(size)?)?)?;
+    match private_dict.iter().find(|(op, _)| *op == SUBRS) {
+        Some((_, subrs)) => Some(Index::parse(cff, offset.checked_add(*subrs.first()? as usize)?)?.0),
+        None => Some(Index::default()),
+    }
+}
+
+fn fd_index(cff: &[u8], fd_select: usize, glyph: GlyphId) -> Option<usize> {
+    let mut r = Reader::at(cff, fd_select);
+    match r.u8()? {
+        0 => Reader::at(cff, fd_select + 1 + glyph as usize).u8().map(|fd| fd as usize),
+        3 => {
+            let ranges = r.u16()?;
+            let mut first = r.u16()?;
+            for _ in 0..ranges {
+                let fd = r.u8()?;
+                let next = r.u16()?;
+                if (first..next).contains(&glyph) { return Some(fd as usize); }
+                first = next;
+            }
+            None
+        },
+        _ => None,
+    }
+}
+
+// type 2 charstring interpreter, drawing into path
+struct Charstring<'a> {
+    cff: &'a [u8],
+    global: Index,
+    local: Index,
+    stack: Vec<f32>,
+    stems: usize,
+    pen: Point,
+    open: bool,
+    path: Path,
+} impl Charstring<'_> {
+    // Some(true) once endchar is reached
+    fn run(&mut self, code: &[u8], depth: usize) -> Option<bool> {
+        if depth > MAX_DEPTH { return None; }
+        let mut r = Reader::new(code);
+        while let Some(b0) = r.u8() {
+            match b0 {
+                // stem hints: only their count matters, to know how long hint masks are
+                1 | 3 | 18 | 23 => {
+                    self.stems += self.stack.len() / 2;
+                    self.stack.clear();
+                },
+                19 | 20 => {
+                    self.stems += self.stack.len() / 2;
+                    self.stack.clear();
+                    r.skip(self.stems.div_ceil(8))?;
+                },
+                
+                // a leading extra operand on the first of these is the glyph's width, which hmtx has anyway
+                21 => {
+                    let n = self.stack.len();
+                    if n < 2 { return None; }
+                    self.move_by(self.stack[n - 2], self.stack[n - 1]);
+                },
+                22 => {
+                    let dx = *self.stack.last()?;
+                    self.move_by(dx, 0.0);
+                },
+                4 => {
+                    let dy = *self.stack.last()?;
+                    self.move_by(0.0, dy);
+                },
+                
+                5 => {
+                    for pair in std::mem::take(&mut self.stack).chunks_exact(2) {
+                        self.line_by(pair[0], pair[1]);
+                    }
+                },
+                6 | 7 => {
+                    let mut horizontal = b0 == 6;
+                    for d in std::mem::take(&mut self.stack) {
+                        if horizontal { self.line_by(d, 0.0) } else { self.line_by(0.0, d) }
+                        horizontal = !horizontal;
+                    }
+                },
+                8 => {
+                    for a in std::mem::take(&mut self.stack).chunks_exact(6) {
+                        self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                    }
+                },
+                24 => {
+                    let args = std::mem::take(&mut self.stack);
+                    if args.len() < 2 { return None; }
+                    let (curves, line) = args.split_at(args.len() - 2);
+                    for a in curves.chunks_exact(6) {
+                        self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                    }
+                    self.line_by(line[0], line[1]);
+                },
+                25 => {
+                    let args = std::mem::take(&mut self.stack);
+                    if args.len() < 6 { return None; }
+                    let (lines, a) = args.split_at(args.len() - 6);
+                    for pair in lines.chunks_exact(2) {
+                        self.line_by(pair[0], pair[1]);
+                    }
+                    self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                },
+                26 | 27 => {
+                    let args = std::mem::take(&mut self.stack);
+                    let (mut first, rest) = if args.len() % 4 == 1 { (args[0], &args[1..]) } else { (0.0, &args[..]) };
+                    for a in rest.chunks_exact(4) {
+                        if b0 == 26 {
+                            self.curve_by(first, a[0], a[1], a[2], 0.0, a[3]);
+                        } else {
+                            self.curve_by(a[0], first, a[1], a[2], a[3], 0.0);
+                        }
+                        first = 0.0;
+                    }
+                },
+                30 | 31 => {
+                    let args = std::mem::take(&mut self.stack);
+                    let mut horizontal = b0 == 31;
+                    let mut i = 0;
+                    while i + 4 <= args.len() {
+                        let a = &args[i..];
+                        let last = if args.len() - i == 5 { a[4] } else { 0.0 };
+                        if horizontal {
+                            self.curve_by(a[0], 0.0, a[1], a[2], last, a[3]);
+                        } else {
+                            self.curve_by(0.0, a[0], a[1], a[2], a[3], last);
+                        }
+                        horizontal = !horizontal;
+                        i += 4;
+                    }
+                },
+                
+                10 | 29 => {
+                    let subrs = if b0 == 10 { self.local } else { self.global };
+                    let i = self.stack.pop()? as i32 + subrs.bias();
+                    let code = subrs.get(self.cff, usize::try_from(i).ok()?)?;
+                    if self.run(code, depth + 1)? { return Some(true); }
+                },
+                11 => return Some(false),
+                14 => {
+                    if self.open { self.path.close(); }
+                    self.open = false;
+                    return Some(true);
+                },
+                
+                12 => {
+                    let op = r.u8()?;
+                    let a = std::mem::take(&mut self.stack);
+                    let get = |i: usize| a.get(i).copied();
+                    match op {
+                        // flex: two curves that may get drawn as a line when small enough, which we don't bother with
+                        35 => {
+                            if a.len() < 12 { return None; }
+                            self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                            self.curve_by(a[6], a[7], a[8], a[9], a[10], a[11]);
+                        },
+                        34 => {
+                            let (dx1, dx2, dy2, dx3, dx4, dx5, dx6) = (get(0)?, get(1)?, get(2)?, get(3)?, get(4)?, get(5)?, get(6)?);
+                            self.curve_by(dx1, 0.0, dx2, dy2, dx3, 0.0);
+                            self.curve_by(dx4, 0.0, dx5, -dy2, dx6, 0.0);
+                        },
+                        36 => {
+                            if a.len() < 9 { return None; }
+                            self.curve_by(a[0], a[1], a[2], a[3], a[4], 0.0);
+                            self.curve_by(a[5], 0.0, a[6], a[7], a[8], -(a[1] + a[3] + a[7]));
+                        },
+                        37 => {
+                            if a.len() < 11 { return None; }
+                            let dx: f32 = a[..10].iter().step_by(2).sum();
+                            let dy: f32 = a[1..10].iter().step_by(2).sum();
+                            self.curve_by(a[0], a[1], a[2], a[3], a[4], a[5]);
+                            if dx.abs() > dy.abs() {
+                                self.curve_by(a[6], a[7], a[8], a[9], a[10], -dy);
+                            } else {
+                                self.curve_by(a[6], a[7], a[8], a[9], -dx, a[10]);
+                            }
+                        },
+                        // arithmetic and other rarities
+                        _ => {},
+                    }
+                },
+                
+                28 => self.stack.push(r.i16()? as f32),
+                32..=246 => self.stack.push(b0 as f32 - 139.0),
+                247..=250 => self.stack.push((b0 as f32 - 247.0) * 256.0 + r.u8()? as f32 + 108.0),
+                251..=254 => self.stack.push(-(b0 as f32 - 251.0) * 256.0 - r.u8()? as f32 - 108.0),
+                255 => self.stack.push(r.i32()? as f32 / 65536.0),
+                _ => self.stack.clear(),
+            }
+        }
+        Some(false)
+    }
+    
+    fn move_by(&mut self, dx: f32, dy: f32) {
+        self.stack.clear();
+        if self.open { self.path.close(); }
+        self.pen = Point::new(self.pen.x + dx, self.pen.y + dy);
+        self.path.move_to(self.pen);
+        self.open = true;
+    }
+    
+    fn line_by(&mut self, dx: f32, dy: f32) {
+        self.pen = Point::new(self.pen.x + dx, self.pen.y + dy);
+        self.path.line_to(self.pen);
+    }
+    
+    // each pair is relative to the one before
+    fn curve_by(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        let c1 = Point::new(self.pen.x + dx1, self.pen.y + dy1);
+        let c2 = Point::new(c1.x + dx2, c1.y + dy2);
+        self.pen = Point::new(c2.x + dx3, c2.y + dy3);
+        self.path.cubic_to(c1, c2, self.pen);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // a number as a 16 bit operand, which works in dicts and charstrings both
+    fn n(v: i16) -> [u8; 3] { [28, (v >> 8) as u8, v as u8] }
+    
+    // a cff table with one top dict, no strings or subroutines, and these charstrings
+    // private is the operands of the top dict's Private entry
+    fn cff(private: &[u8], charstrings: &[&[u8]]) -> Vec<u8> {
+        let mut top = vec![29, 0, 0, 0, 0, CHARSTRINGS as u8];
+        top.extend(private);
+        top.push(PRIVATE as u8);
+        let mut out = vec![1, 0, 4, 4, 0, 1, 1, 1, 2, b'T', 0, 1, 1, 1, top.len() as u8 + 1];
+        let at = out.len() + 1;
+        out.extend(top);
+        let start = out.len() as u32 + 4;
+        out[at..at + 4].copy_from_slice(&start.to_be_bytes());
+        out.extend([0, 0, 0, 0]);
+        out.extend((charstrings.len() as u16).to_be_bytes());
+        out.push(1);
+        let mut offset = 1;
+        for charstring in charstrings {
+            out.push(offset);
+            offset += charstring.len() as u8;
+        }
+        out.push(offset);
+        out.extend(charstrings.concat());
+        out
+    }
+    
+    // a private dict that's empty, at 0
+    const EMPTY: [u8; 6] = [28, 0, 0, 28, 0, 0];
+    
+    fn square() -> Vec<u8> {
+        [&n(100)[..], &n(0), &[21], &n(400), &n(0), &n(0), &n(700), &n(-400), &n(0), &[5, 14]].concat()
+    }
+    
+    #[test]
+    fn outline() {
+        let data = cff(&EMPTY, &[&[14], &square()]);
+        let cff = Cff::parse(&data).unwrap();
+        assert_eq!(cff.glyph_count(), 2);
+        assert!(cff.outline(&data, 0).unwrap().is_empty());
+        let contours = cff.outline(&data, 1).unwrap().flatten(0.1);
+        assert_eq!(contours.len(), 1);
+        let points: Vec<(f32,f32)> = contours[0].0.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(&points[..4], [(100.0, 0.0), (500.0, 0.0), (500.0, 700.0), (100.0, 700.0)]);
+        assert!(cff.outline(&data, 2).is_none());
+    }
+    
+    #[test]
+    fn truncated() {
+        let data = cff(&EMPTY, &[&[14], &square()]);
+        for len in 0..data.len() {
+            let cut = &data[..len];
+            assert!(Cff::parse(cut).is_none_or(|cff| cff.outline(cut, 1).is_none()), "{len} bytes");
+        }
+    }
+    
+    #[test]
+    fn private_overflow() {
+        // 1E30, which is far past anything a usize can add to
+        const HUGE: [u8; 4] = [30, 0x1B, 0x30, 0xFF];
+        let data = cff(&EMPTY, &[&[14]]);
+        assert!(Cff::parse(&data).is_some());
+        assert!(Cff::parse(&cff(&[&n(10)[..], &HUGE].concat(), &[&[14]])).is_none());
+        assert!(Cff::parse(&cff(&[&HUGE[..], &n(4)].concat(), &[&[14]])).is_none());
+    }
+}
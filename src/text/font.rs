@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc,Mutex};
+
+use crate::{
+    Size,
+    draw::{FillRule,Path,Point,Rasterizer},
+};
+
+use super::GlyphId;
+use super::cff::Cff;
+use super::glyf::Glyf;
+use super::layout::{Gdef,Kerning};
+use super::reader::{Reader,u16_at,i16_at,u32_at};
+use super::script::Script;
+use super::shape::{self,Plan,ShapedGlyph};
+
+// glyphs are rasterized at this many horizontal offsets within a pixel, so text spacing stays even
+const SUBPIXEL_STEPS: f32 = 4.0;
+
+// the glyph cache gets emptied when it grows past this
+const MAX_CACHED: usize = 4096;
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(std::io::Error),
+    Malformed(&'static str),   // what couldn't be read
+    Unsupported(&'static str), // what the font needs that isn't implemented
+} impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "couldn't read font: {e}"),
+            FontError::Malformed(what) => write!(f, "malformed font: bad {what}"),
+            FontError::Unsupported(what) => write!(f, "unsupported font: {what}"),
+        }
+    }
+} impl std::error::Error for FontError {
+} impl From<std::io::Error> for FontError {
+    fn from(e: std::io::Error) -> Self { FontError::Io(e) }
+}
+
+// vertical extent of a line of text, in pixels
+// descent and underline_offset are positive, going down from the baseline
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct LineMetrics {
+    pub ascent: f32,
+    pub descent: f32,
+    pub line_gap: f32,
+    pub underline_offset: f32,
+    pub underline_thickness: f32,
+} impl LineMetrics {
+    // distance from one baseline to the next
+    pub fn height(&self) -> f32 { self.ascent + self.descent + self.line_gap }
+}
+
+// a rasterized glyph: how much of each pixel it covers, from 0 to 255, row by row
+// (left,top) is where the top-left corner goes relative to the pen, which sits on the baseline
+#[derive(Clone,Debug,Default)]
+pub struct Glyph {
+    pub size: Size,
+    pub left: isize,
+    pub top: isize,
+    pub coverage: Vec<u8>,
+}
+
+enum Outlines {
+    Glyf(Glyf),
+    Cff(Cff,Range<usize>),
+}
+
+// a truetype or opentype font, loaded from a .ttf, .otf or .ttc
+// sizes are in pixels per em, which is what css calls font-size
+pub struct Font {
+    data: Vec<u8>,
+    units_per_em: u16,
+    glyph_count: u16,
+    ascent: i16,
+    descent: i16,
+    line_gap: i16,
+    underline: (i16,i16), // position and thickness
+    hmtx: Range<usize>,
+    h_metrics: u16,
+    cmap: Option<(u16,usize)>, // format and position of the best subtable
+    outlines: Outlines,
+    kerning: Kerning,
+    gsub: Option<usize>,
+    gpos: Option<usize>,
+    gdef: Gdef,
+    plans: Mutex<HashMap<Script,Arc<Plan>>>,
+    cache: Mutex<HashMap<(GlyphId,u32,u8),Arc<Glyph>>>,
+} impl Font {
+    
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self,FontError> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+    
+    // the first font, if data is a collection
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self,FontError> {
+        Self::from_collection(data, 0)
+    }
+    
+    // font number `index` of a collection; index 0 is the only font in anything else
+    pub fn from_collection(data: Vec<u8>, index: usize) -> Result<Self,FontError> {
+        use FontError::*;
+        
+        let start = match Reader::new(&data).tag() {
+            Some(tag) if &tag == b"ttcf" => {
+                let count = u32_at(&data, 8).ok_or(Malformed("collection header"))? as usize;
+                if index >= count { return Err(Malformed("collection index")); }
+                u32_at(&data, 12 + index * 4).ok_or(Malformed("collection header"))? as usize
+            },
+            Some(_) if index == 0 => 0,
+            _ => return Err(Malformed("header")),
+        };
+        let tables = table_directory(&data, start).ok_or(Malformed("table directory"))?;
+        let table = |tag: &[u8; 4]| tables.iter().find(|(t, _)| t == tag).map(|(_, range)| range.clone());
+        let required = |tag: &'static [u8; 4], name: &'static str| table(tag).ok_or(Malformed(name));
+        
+        let head = required(b"head", "head table")?;
+        let hhea = required(b"hhea", "hhea table")?;
+        let maxp = required(b"maxp", "maxp table")?;
+        let hmtx = required(b"hmtx", "hmtx table")?;
+        
+        let units_per_em = u16_at(&data, head.start + 18).filter(|u| *u > 0).ok_or(Malformed("head table"))?;
+        let glyph_count = u16_at(&data, maxp.start + 4).ok_or(Malformed("maxp table"))?;
+        let metric = |offset: usize| i16_at(&data, hhea.start + offset).ok_or(Malformed("hhea table"));
+        let (ascent, descent, line_gap) = (metric(4)?, metric(6)?, metric(8)?);
+        let h_metrics = metric(34)? as u16;
+        if h_metrics == 0 || hmtx.len() < h_metrics as usize * 4 { return Err(Malformed("hmtx table")); }
+        
+        let outlines = match (table(b"glyf"), table(b"loca"), table(b"CFF ")) {
+            (Some(glyf), Some(loca), _) => {
+                let long_offsets = i16_at(&data, head.start + 50).ok_or(Malformed("head table"))? != 0;
+                Outlines::Glyf(Glyf {glyf, loca, long_offsets})
+            },
+            (_, _, Some(cff)) => {
+                let parsed = Cff::parse(&data[cff.clone()]).ok_or(Malformed("CFF table"))?;
+                if parsed.glyph_count() < glyph_count as usize { return Err(Malformed("CFF table")); }
+                Outlines::Cff(parsed, cff)
+            },
+            _ if table(b"CFF2").is_some() => return Err(Unsupported("CFF2 outlines")),
+            _ => return Err(Malformed("outlines")),
+        };
+        
+        // fonts without post get something reasonable instead
+        let underline = table(b"post")
+            .and_then(|post| Some((i16_at(&data, post.start + 8)?, i16_at(&data, post.start + 10)?)))
+            .filter(|(_, thickness)| *thickness > 0)
+            .unwrap_or((-(units_per_em as i16 / 10), (units_per_em / 20).max(1) as i16));
+        
+        let cmap = table(b"cmap").and_then(|cmap| best_cmap(&data, cmap.start));
+        let kerning = Kerning::new(&data, table(b"GPOS").map(|r| r.start), table(b"kern").map(|r| r.start));
+        let gdef = Gdef::new(&data, table(b"GDEF").map(|r| r.start));
+        
+        Ok(Self {
+            data,
+            units_per_em,
+            glyph_count,
+            ascent,
+            descent,
+            line_gap,
+            underline,
+            hmtx,
+            h_metrics,
+            cmap,
+            outlines,
+            kerning,
+            gsub: table(b"GSUB").map(|r| r.start),
+            gpos: table(b"GPOS").map(|r| r.start),
+            gdef,
+            plans: Mutex::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+    
+    pub fn units_per_em(&self) -> u16 { self.units_per_em }
+    pub fn glyph_count(&self) -> u16 { self.glyph_count }
+    
+    fn scale(&self, size: f32) -> f32 { size / self.units_per_em as f32 }
+    
+    pub fn line_metrics(&self, size: f32) -> LineMetrics {
+        let scale = self.scale(size);
+        LineMetrics {
+            ascent: self.ascent as f32 * scale,
+            descent: -self.descent as f32 * scale,
+            line_gap: self.line_gap as f32 * scale,
+            underline_offset: -self.underline.0 as f32 * scale,
+            underline_thickness: self.underline.1 as f32 * scale,
+        }
+    }
+    
+    // None if the font has no glyph for c
+    pub fn glyph_index(&self, c: char) -> Option<GlyphId> {
+        let (format, pos) = self.cmap?;
+        let glyph = match format {
+            4 => cmap4(&self.data, pos, c as u32),
+            6 => cmap6(&self.data, pos, c as u32),
+            12 => cmap12(&self.data, pos, c as u32),
+            _ => None,
+        }?;
+        (glyph != 0 && glyph < self.glyph_count).then_some(glyph)
+    }
+    
+    pub fn has_glyph(&self, c: char) -> bool { self.glyph_index(c).is_some() }
+    
+    // how far the pen moves after drawing glyph, in pixels
+    pub fn advance(&self, glyph: GlyphId, size: f32) -> f32 {
+        self.advance_units(glyph) as f32 * self.scale(size)
+    }
+    
+    pub(super) fn advance_units(&self, glyph: GlyphId) -> u16 {
+        let i = glyph.min(self.h_metrics - 1) as usize;
+        u16_at(&self.data, self.hmtx.start + i * 4).unwrap_or(0)
+    }
+    
+    // adjustment to the advance of left when right comes right after it, in pixels
+    pub fn kerning(&self, left: GlyphId, right: GlyphId, size: f32) -> f32 {
+        self.kerning_units(left, right) as f32 * self.scale(size)
+    }
+    
+    pub(super) fn kerning_units(&self, left: GlyphId, right: GlyphId) -> i16 {
+        self.kerning.get(&self.data, left, right)
+    }
+    
+    // outline in font units, with y pointing up
+    pub fn outline(&self, glyph: GlyphId) -> Option<Path> {
+        if glyph >= self.glyph_count { return None; }
+        match &self.outlines {
+            Outlines::Glyf(glyf) => glyf.outline(&self.data, glyph),
+            Outlines::Cff(cff, range) => cff.outline(&self.data[range.clone()], glyph),
+        }
+    }
+    
+    // glyph rasterized at size, shifted right by `shift` (0 to 1) pixels
+    // cached, so drawing the same text over and over only rasterizes it once
+    pub fn glyph(&self, glyph: GlyphId, size: f32, shift: f32) -> Arc<Glyph> {
+        let step = (shift.clamp(0.0, 1.0) * SUBPIXEL_STEPS).floor().min(SUBPIXEL_STEPS - 1.0);
+        let key = (glyph, size.to_bits(), step as u8);
+        if let Some(cached) = self.cache.lock().unwrap().get(&key) {
+            return cached.clone();
+        }
+        
+        let rasterized = Arc::new(self.rasterize(glyph, size, step / SUBPIXEL_STEPS));
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHED { cache.clear(); }
+        cache.insert(key, rasterized.clone());
+        rasterized
+    }
+    
+    fn rasterize(&self, glyph: GlyphId, size: f32, shift: f32) -> Glyph {
+        let Some(outline) = self.outline(glyph) else { return Glyph::default() };
+        let scale = self.scale(size);
+        let contours = outline.map(|p| Point::new(p.x * scale + shift, -p.y * scale)).flatten(0.1);
+        if contours.is_empty() { return Glyph::default(); }
+        
+        let (mut min, mut max) = (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN));
+        for p in contours.iter().flat_map(|(points, _)| points) {
+            min = Point::new(min.x.min(p.x), min.y.min(p.y));
+            max = Point::new(max.x.max(p.x), max.y.max(p.y));
+        }
+        let (left, top) = (min.x.floor(), min.y.floor());
+        let size = Size {width: (max.x.ceil() - left) as usize, height: (max.y.ceil() - top) as usize};
+        
+        let mut raster = Rasterizer::new(size.width, size.height);
+        for (points, _) in &contours {
+            for (i, p) in points.iter().enumerate() {
+                let q = points[(i + 1) % points.len()];
+                raster.line(Point::new(p.x - left, p.y - top), Point::new(q.x - left, q.y - top));
+            }
+        }
+        Glyph {
+            size,
+            left: left as isize,
+            top: top as isize,
+            coverage: raster.coverage().map(|c| (FillRule::NonZero.apply(c) * 255.0).round() as u8).collect(),
+        }
+    }
+    
+    // a single line of text as the glyphs that draw it, from left to right, with ligatures formed,
+    // letters joined and marks placed where the font says, and right-to-left text running the right way
+    pub fn shape(&self, text: &str, size: f32) -> Vec<ShapedGlyph> {
+        shape::shape_text(self, text, size)
+    }
+    
+    // each glyph of a single line of text, and where its pen position is relative to the start
+    // one glyph per char, left to right, without any of what shape does apart from kerning
+    // characters the font doesn't have use its missing-glyph box
+    pub fn glyph_positions(&self, text: &str, size: f32) -> Vec<(GlyphId,f32)> {
+        let mut x = 0.0;
+        let mut previous: Option<GlyphId> = None;
+        text.chars().map(|c| {
+            let glyph = self.glyph_index(c).unwrap_or(0);
+            if let Some(previous) = previous {
+                x += self.kerning(previous, glyph, size);
+            }
+            let position = (glyph, x);
+            x += self.advance(glyph, size);
+            previous = Some(glyph);
+            position
+        }).collect()
+    }
+    
+    // how far the pen moves drawing shaped text
+    pub fn measure(&self, text: &str, size: f32) -> f32 {
+        self.shape(text, size).iter().map(|g| g.advance).sum()
+    }
+    
+    pub(super) fn data(&self) -> &[u8] { &self.data }
+    pub(super) fn gsub(&self) -> Option<usize> { self.gsub }
+    pub(super) fn gpos(&self) -> Option<usize> { self.gpos }
+    pub(super) fn gdef(&self) -> &Gdef { &self.gdef }
+    
+    // which lookups shaping script uses, worked out the first time it's needed
+    pub(super) fn plan(&self, script: Script) -> Arc<Plan> {
+        if let Some(plan) = self.plans.lock().unwrap().get(&script) {
+            return plan.clone();
+        }
+        let plan = Arc::new(Plan::new(self, script));
+        self.plans.lock().unwrap().insert(script, plan.clone());
+        plan
+    }
+} impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Font")
+            .field("units_per_em", &self.units_per_em)
+            .field("glyph_count", &self.glyph_count)
+            .finish_non_exhaustive()
+    }
+}
+
+// (tag,range) of every table whose range fits in data
+fn table_directory(data: &[u8], start: usize) -> Option<Vec<([u8; 4],Range<usize>)>> {
+    let mut r = Reader::at(data, start + 4);
+    let count = r.u16()?;
+    r.skip(6)?;
+    let mut tables = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let tag = r.tag()?;
+        let _checksum = r.u32()?;
+        let offset = r.u32()? as usize;
+        let length = r.u32()? as usize;
+        if offset.checked_add(length).is_some_and(|end| end <= data.len()) {
+            tables.push((tag, offset..offset + length));
+        }
+    }
+    Some(tables)
+}
+
+// the unicode subtable that covers the most, as (format,position)
+fn best_cmap(data: &[u8], cmap: usize) -> Option<(u16,usize)> {
+    let mut r = Reader::at(data, cmap + 2);
+    let mut best: Option<(u8,u16,usize)> = None;
+    for _ in 0..r.u16()? {
+        let (platform, encoding) = (r.u16()?, r.u16()?);
+        let pos = cmap + r.u32()? as usize;
+        let format = u16_at(data, pos)?;
+        let score = match (platform, encoding, format) {
+            (3, 10, 12) | (0, 4 | 6, 12) => 4,
+            (3, 1, 4) => 3,
+            (0, _, 4) => 2,
+            (0 | 3, _, 6) => 1,
+            _ => continue,
+        };
+        if best.is_none_or(|(s, _, _)| score > s) {
+            best = Some((score, format, pos));
+        }
+    }
+    best.map(|(_, format, pos)| (format, pos))
+}
+
+// segments of 16-bit code points
+fn cmap4(data: &[u8], pos: usize, c: u32) -> Option<GlyphId> {
+    let c = u16::try_from(c).ok()?;
+    let segments = u16_at(data, pos + 6)? as usize / 2;
+    let ends = pos + 14;
+    let starts = ends + segments * 2 + 2;
+    let deltas = starts + segments * 2;
+    let range_offsets = deltas + segments * 2;
+    
+    // first segment ending at or after c
+    let (mut lo, mut hi) = (0, segments);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if u16_at(data, ends + mid * 2)? < c { lo = mid + 1 } else { hi = mid }
+    }
+    let i = lo;
+    if i >= segments { return None; }
+    let start = u16_at(data, starts + i * 2)?;
+    if c < start { return None; }
+    
+    let delta = u16_at(data, deltas + i * 2)?;
+    let range_offset = u16_at(data, range_offsets + i * 2)? as usize;
+    if range_offset == 0 { return Some(c.wrapping_add(delta)); }
+    let glyph = u16_at(data, range_offsets + i * 2 + range_offset + (c - start) as usize * 2)?;
+    if glyph == 0 { None } else { Some(glyph.wrapping_add(delta)) }
+}
+
+// a single run of code points
+fn cmap6(data: &[u8], pos: usize, c: u32) -> Option<GlyphId> {
+    let first = u16_at(data, pos + 6)? as u32;
+    let count = u16_at(data, pos + 8)? as u32;
+    let i = c.checked_sub(first).filter(|i| *i < count)?;
+    u16_at(data, pos + 10 + i as usize * 2)
+}
+
+// groups of 32-bit code points
+fn cmap12(data: &[u8], pos: usize, c: u32) -> Option<GlyphId> {
+    let groups = u32_at(data, pos + 12)? as usize;
+    let (mut lo, mut hi) = (0, groups);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let group = pos + 16 + mid * 12;
+        let (start, end) = (u32_at(data, group)?, u32_at(data, group + 4)?);
+        if c < start { hi = mid }
+        else if c > end { lo = mid + 1 }
+        else { return u16::try_from(u32_at(data, group + 8)? + (c - start)).ok(); }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testfont::{self,FONT};
+    use crate::{Pixel,PixelIdx,draw::Painter,image::Image};
+    
+    #[test]
+    fn tables() {
+        let font = testfont::font();
+        assert_eq!((font.units_per_em(), font.glyph_count()), (1000, 16));
+        assert_eq!(font.line_metrics(100.0), LineMetrics {
+            ascent: 80.0, descent: 20.0, line_gap: 10.0, underline_offset: 10.0, underline_thickness: 5.0,
+        });
+        assert!(font.outline(3).is_some_and(|path| !path.is_empty()));
+        assert!(font.outline(16).is_none());
+    }
+    
+    #[test]
+    fn malformed() {
+        assert!(Font::from_bytes(Vec::new()).is_err());
+        assert!(Font::from_bytes(FONT[..200].to_vec()).is_err());
+        assert!(Font::from_collection(FONT.to_vec(), 1).is_err());
+        // a table directory pointing past the end leaves out every table
+        assert!(matches!(Font::from_bytes(FONT[..FONT.len() / 2].to_vec()), Err(FontError::Malformed(_))));
+    }
+    
+    #[test]
+    fn cmap() {
+        let font = testfont::font();
+        let glyphs = "AVzfi5 .(\u{628}".chars().map(|c| font.glyph_index(c)).collect::<Vec<_>>();
+        assert_eq!(glyphs, [2, 3, 2, 4, 5, 8, 1, 9, 14, 10].map(Some));
+        assert_eq!(font.glyph_index('\u{5D0}'), None);
+        assert_eq!(font.glyph_index('\u{1F600}'), None);
+        assert!(!font.has_glyph('~'));
+    }
+    
+    #[test]
+    fn metrics() {
+        let font = testfont::font();
+        assert_eq!(font.advance(2, 100.0), 60.0);
+        assert_eq!(font.advance(6, 20.0), 10.0);
+        assert_eq!(font.kerning(2, 3, 100.0), -8.0);
+        assert_eq!(font.kerning(3, 2, 100.0), 0.0);
+        assert_eq!(font.glyph_positions("AVA", 100.0), [(2, 0.0), (3, 52.0), (2, 112.0)]);
+        // missing chars get the .notdef box
+        assert_eq!(font.glyph_positions("~", 100.0), [(0, 0.0)]);
+    }
+    
+    #[test]
+    fn glyph_cache() {
+        let font = testfont::font();
+        let glyph = font.glyph(2, 20.0, 0.0);
+        // 100 to 500 across and 0 to 700 up is exactly 8 by 14 pixels at 20
+        assert_eq!((glyph.size, glyph.left, glyph.top), (Size {width: 8, height: 14}, 2, -14));
+        assert!(glyph.coverage.iter().all(|c| *c == 255));
+        
+        // shifts within the same step come from the cache
+        assert!(Arc::ptr_eq(&glyph, &font.glyph(2, 20.0, 0.1)));
+        let shifted = font.glyph(2, 20.0, 0.5);
+        assert!(!Arc::ptr_eq(&glyph, &shifted));
+        assert_eq!(shifted.size.width, 9);
+        assert!(shifted.coverage.contains(&128));
+    }
+    
+    #[test]
+    fn fill_text() {
+        let font = testfont::font();
+        let mut image = Image::filled(Size {width: 40, height: 20}, Pixel::BLACK);
+        let advance = unsafe { image.with_slice(|s| Painter::new(s).fill_text(&font, "AA", Point::new(4.0, 16.0), 20.0, Pixel::WHITE)) };
+        assert_eq!(advance, 24.0);
+        // each A is a block from 2 to 10 pixels along its advance, 14 pixels tall
+        for y in 0..20 {
+            for x in 0..40 {
+                let lit = (2..16).contains(&y) && [6..14, 18..26].iter().any(|r| r.contains(&x));
+                let p = image.get_pixel(PixelIdx::new(x, y));
+                assert_eq!([p.r, p.g, p.b], if lit { [255; 3] } else { [0; 3] }, "({x},{y})");
+            }
+        }
+    }
+}
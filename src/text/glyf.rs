@@ -0,0 +1,183 @@
+use std::ops::Range;
+
+use crate::draw::{Path,Point,Transform};
+
+use super::GlyphId;
+use super::reader::{Reader,u16_at,u32_at};
+
+// composite glyphs can be made of other composites, but not forever
+const MAX_DEPTH: usize = 8;
+
+// truetype outlines, made of quadratic curves
+pub(super) struct Glyf {
+    pub glyf: Range<usize>,
+    pub loca: Range<usize>,
+    pub long_offsets: bool,
+} impl Glyf {
+    // outline in font units, with y pointing up
+    pub fn outline(&self, data: &[u8], glyph: GlyphId) -> Option<Path> {
+        let mut path = Path::new();
+        self.append(data, glyph, Transform::IDENTITY, &mut path, 0)?;
+        Some(path)
+    }
+    
+    // where glyph's data is, relative to the start of glyf
+    fn range(&self, data: &[u8], glyph: GlyphId) -> Option<Range<usize>> {
+        let loca = &data[self.loca.clone()];
+        let i = glyph as usize;
+        let (start, end) = if self.long_offsets {
+            (u32_at(loca, i * 4)? as usize, u32_at(loca, i * 4 + 4)? as usize)
+        } else {
+            (u16_at(loca, i * 2)? as usize * 2, u16_at(loca, i * 2 + 2)? as usize * 2)
+        };
+        if start > end || end > self.glyf.len() { return None; }
+        Some(start..end)
+    }
+    
+    fn append(&self, data: &[u8], glyph: GlyphId, t: Transform, path: &mut Path, depth: usize) -> Option<()> {
+        if depth > MAX_DEPTH { return None; }
+        let range = self.range(data, glyph)?;
+        // glyphs without an outline, like space, have no data at all
+        if range.is_empty() { return Some(()); }
+        let glyph = &data[self.glyf.start + range.start..self.glyf.start + range.end];
+        
+        let contours = Reader::new(glyph).i16()?;
+        if contours >= 0 {
+            simple(glyph, contours as usize, t, path)
+        } else {
+            self.composite(data, glyph, t, path, depth)
+        }
+    }
+    
+    fn composite(&self, data: &[u8], glyph: &[u8], t: Transform, path: &mut Path, depth: usize) -> Option<()> {
+        const WORDS: u16          = 0x0001;
+        const XY_VALUES: u16      = 0x0002;
+        const SCALE: u16          = 0x0008;
+        const MORE: u16           = 0x0020;
+        const XY_SCALE: u16       = 0x0040;
+        const TWO_BY_TWO: u16     = 0x0080;
+        
+        let mut r = Reader::at(glyph, 10);
+        loop {
+            let flags = r.u16()?;
+            let component = r.u16()?;
+            let (arg1, arg2) = if flags & WORDS != 0 {
+                (r.i16()? as f32, r.i16()? as f32)
+            } else {
+                (r.i8()? as f32, r.i8()? as f32)
+            };
+            // anchoring by point numbers instead of offsets is rare enough to not bother with
+            let (e, f) = if flags & XY_VALUES != 0 { (arg1, arg2) } else { (0.0, 0.0) };
+            
+            let (mut a, mut b, mut c, mut d) = (1.0, 0.0, 0.0, 1.0);
+            if flags & SCALE != 0 {
+                a = r.f2dot14()?;
+                d = a;
+            } else if flags & XY_SCALE != 0 {
+                a = r.f2dot14()?;
+                d = r.f2dot14()?;
+            } else if flags & TWO_BY_TWO != 0 {
+                a = r.f2dot14()?;
+                b = r.f2dot14()?;
+                c = r.f2dot14()?;
+                d = r.f2dot14()?;
+            }
+            
+            let local = Transform {a, b, c, d, e, f};
+            self.append(data, component, local.then(t), path, depth + 1)?;
+            if flags & MORE == 0 { return Some(()); }
+        }
+    }
+}
+
+fn simple(glyph: &[u8], contours: usize, t: Transform, path: &mut Path) -> Option<()> {
+    const ON_CURVE: u8 = 0x01;
+    const X_SHORT: u8  = 0x02;
+    const Y_SHORT: u8  = 0x04;
+    const REPEAT: u8   = 0x08;
+    const X_SAME: u8   = 0x10; // or positive, if short
+    const Y_SAME: u8   = 0x20;
+    
+    let mut r = Reader::at(glyph, 10);
+    let mut ends = Vec::with_capacity(contours);
+    for _ in 0..contours { ends.push(r.u16()? as usize); }
+    let count = ends.last().map_or(0, |e| e + 1);
+    let instructions = r.u16()? as usize;
+    r.skip(instructions)?;
+    
+    let mut flags = Vec::with_capacity(count);
+    while flags.len() < count {
+        let flag = r.u8()?;
+        flags.push(flag);
+        if flag & REPEAT != 0 {
+            for _ in 0..r.u8()? { flags.push(flag); }
+        }
+    }
+    flags.truncate(count);
+    
+    // coordinates are deltas from the previous point
+    let mut coords = |short: u8, same: u8| -> Option<Vec<f32>> {
+        let mut v = 0i32;
+        let mut out = Vec::with_capacity(count);
+        for &flag in &flags {
+            if flag & short != 0 {
+                let d = r.u8()? as i32;
+                v += if flag & same != 0 { d } else { -d };
+            } else if flag & same == 0 {
+                v += r.i16()? as i32;
+            }
+            out.push(v as f32);
+        }
+        Some(out)
+    };
+    let xs = coords(X_SHORT, X_SAME)?;
+    let ys = coords(Y_SHORT, Y_SAME)?;
+    
+    let mut start = 0;
+    for end in ends {
+        if end < start || end >= count { return None; }
+        let points: Vec<(Point,bool)> = (start..=end)
+            .map(|i| (t.apply(Point::new(xs[i], ys[i])), flags[i] & ON_CURVE != 0))
+            .collect();
+        contour(&points, path);
+        start = end + 1;
+    }
+    Some(())
+}
+
+// one closed contour of on- and off-curve points
+// two off-curve points in a row have an implied on-curve point halfway between them
+fn contour(points: &[(Point,bool)], path: &mut Path) {
+    let Some(&(first, first_on)) = points.first() else { return };
+    let (last, last_on) = points[points.len() - 1];
+    
+    // start at an on-curve point, making one up if there isn't one at either end
+    let (start, rest) = if first_on {
+        (first, &points[1..])
+    } else if last_on {
+        (last, &points[..points.len() - 1])
+    } else {
+        (first.lerp(last, 0.5), points)
+    };
+    
+    path.move_to(start);
+    let mut control: Option<Point> = None;
+    for &(p, on) in rest {
+        match (control, on) {
+            (None, true) => { path.line_to(p); },
+            (None, false) => control = Some(p),
+            (Some(c), true) => {
+                path.quad_to(c, p);
+                control = None;
+            },
+            (Some(c), false) => {
+                path.quad_to(c, c.lerp(p, 0.5));
+                control = Some(p);
+            },
+        }
+    }
+    if let Some(c) = control {
+        path.quad_to(c, start);
+    }
+    path.close();
+}
\ No newline at end of file
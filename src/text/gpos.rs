@@ -0,0 +1,287 @@
+use super::Font;
+use super::layout::{Gdef,Glyphs,Lookup,MARK,RIGHT_TO_LEFT,class,coverage,match_context};
+use super::reader::{Reader,i16_at,u16_at};
+use super::shape::{Info,View};
+
+// glyph positioning: kerning, marks placed on the glyphs they go with, and cursive connections between letters
+
+const SINGLE: u16 = 1;
+const PAIR: u16 = 2;
+const CURSIVE: u16 = 3;
+const MARK_TO_BASE: u16 = 4;
+const MARK_TO_LIGATURE: u16 = 5;
+const MARK_TO_MARK: u16 = 6;
+const CONTEXT: u16 = 7;
+const CHAINED_CONTEXT: u16 = 8;
+const EXTENSION: u16 = 9;
+
+const MAX_NESTING: u8 = 8;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub(super) enum Attach {
+    Mark,    // drawn where the anchors line up, wherever that is
+    Cursive, // only moved up or down to connect, since advances already line it up across
+}
+
+// where a glyph goes, in font units with y pointing up
+// x and y are relative to the pen, or to the glyph it's attached to if it is
+#[derive(Clone,Copy,Debug,Default)]
+pub(super) struct Position {
+    pub advance: i32,
+    pub x: i32,
+    pub y: i32,
+    pub attach: Option<(usize,Attach)>,
+} impl Position {
+    fn adjust(&mut self, (x, y, advance): (i32,i32,i32)) {
+        self.x += x;
+        self.y += y;
+        self.advance += advance;
+    }
+}
+
+// applies lookups one after the other, each to every glyph its mask says it applies to
+pub(super) fn apply(font: &Font, lookups: &[(u16,u32,Lookup)], infos: &[Info], positions: &mut [Position], rtl: bool) {
+    let Some(table) = font.gpos() else { return };
+    let mut positioning = Positioning {data: font.data(), gdef: font.gdef(), table, infos, rtl, depth: 0};
+    for (_, mask, lookup) in lookups {
+        let mut i = 0;
+        while i < infos.len() {
+            i = if infos[i].mask & mask != 0 && !positioning.view(lookup).skipped(i) {
+                positioning.apply_at(lookup, positions, i).unwrap_or(i + 1)
+            } else {
+                i + 1
+            };
+        }
+    }
+}
+
+struct Positioning<'a> {
+    data: &'a [u8],
+    gdef: &'a Gdef,
+    table: usize,
+    infos: &'a [Info],
+    rtl: bool,
+    depth: u8,
+} impl Positioning<'_> {
+    fn view<'b>(&'b self, lookup: &'b Lookup) -> View<'b> {
+        View {data: self.data, gdef: self.gdef, lookup, infos: self.infos}
+    }
+    
+    fn apply_at(&mut self, lookup: &Lookup, positions: &mut [Position], i: usize) -> Option<usize> {
+        for subtable in &lookup.subtables {
+            let pos = *subtable;
+            let next = match lookup.kind {
+                SINGLE => self.single(pos, positions, i),
+                PAIR => self.pair(lookup, pos, positions, i),
+                CURSIVE => self.cursive(lookup, pos, positions, i),
+                MARK_TO_BASE | MARK_TO_LIGATURE => self.mark_to_base(lookup.kind, pos, positions, i),
+                MARK_TO_MARK => self.mark_to_mark(lookup, pos, positions, i),
+                CONTEXT | CHAINED_CONTEXT => self.context(lookup, pos, positions, i),
+                _ => None,
+            };
+            if next.is_some() { return next; }
+        }
+        None
+    }
+    
+    fn single(&self, pos: usize, positions: &mut [Position], i: usize) -> Option<usize> {
+        let data = self.data;
+        let mut r = Reader::at(data, pos);
+        let format = r.u16()?;
+        let index = coverage(data, pos + r.u16()? as usize, self.infos[i].glyph)?;
+        let value_format = r.u16()?;
+        let record = match format {
+            1 => pos + 6,
+            2 if index < r.u16()? as usize => pos + 8 + index * value_size(value_format),
+            _ => return None,
+        };
+        positions[i].adjust(value(data, record, value_format)?);
+        Some(i + 1)
+    }
+    
+    fn pair(&self, lookup: &Lookup, pos: usize, positions: &mut [Position], i: usize) -> Option<usize> {
+        let data = self.data;
+        let j = self.view(lookup).next(i)?;
+        let (first, second) = (self.infos[i].glyph, self.infos[j].glyph);
+        let mut r = Reader::at(data, pos);
+        let format = r.u16()?;
+        let index = coverage(data, pos + r.u16()? as usize, first)?;
+        let (format1, format2) = (r.u16()?, r.u16()?);
+        let (size1, size2) = (value_size(format1), value_size(format2));
+        
+        let record = match format {
+            1 => {
+                let set = pos + u16_at(data, pos + 10 + index * 2)? as usize;
+                let record_size = 2 + size1 + size2;
+                let (mut lo, mut hi) = (0, u16_at(data, set)? as usize);
+                loop {
+                    if lo >= hi { return None; }
+                    let mid = (lo + hi) / 2;
+                    let record = set + 2 + mid * record_size;
+                    let g = u16_at(data, record)?;
+                    if g == second { break record + 2; }
+                    if g < second { lo = mid + 1 } else { hi = mid }
+                }
+            },
+            2 => {
+                let class1 = class(data, pos + r.u16()? as usize, first) as usize;
+                let class2 = class(data, pos + r.u16()? as usize, second) as usize;
+                let (class1_count, class2_count) = (r.u16()? as usize, r.u16()? as usize);
+                if class1 >= class1_count || class2 >= class2_count { return None; }
+                pos + 16 + (class1 * class2_count + class2) * (size1 + size2)
+            },
+            _ => return None,
+        };
+        positions[i].adjust(value(data, record, format1)?);
+        positions[j].adjust(value(data, record + size1, format2)?);
+        // the second glyph can start a pair of its own, unless this one changed it
+        Some(if size2 > 0 { j + 1 } else { j })
+    }
+    
+    // joins the entry anchor of glyph j to the exit anchor of the glyph before it
+    fn cursive(&self, lookup: &Lookup, pos: usize, positions: &mut [Position], j: usize) -> Option<usize> {
+        let data = self.data;
+        let coverage_at = pos + u16_at(data, pos + 2)? as usize;
+        let record = |glyph, exit: bool| -> Option<(i32,i32)> {
+            let index = coverage(data, coverage_at, glyph)?;
+            if index >= u16_at(data, pos + 4)? as usize { return None; }
+            let offset = u16_at(data, pos + 6 + index * 4 + exit as usize * 2)?;
+            if offset == 0 { return None; }
+            anchor(data, pos + offset as usize)
+        };
+        let (entry_x, entry_y) = record(self.infos[j].glyph, false)?;
+        let i = self.view(lookup).previous(j)?;
+        let (exit_x, exit_y) = record(self.infos[i].glyph, true)?;
+        
+        if self.rtl {
+            let d = exit_x + positions[i].x;
+            positions[i].advance -= d;
+            positions[i].x -= d;
+            positions[j].advance = entry_x + positions[j].x;
+        } else {
+            positions[i].advance = exit_x + positions[i].x;
+            let d = entry_x + positions[j].x;
+            positions[j].advance -= d;
+            positions[j].x -= d;
+        }
+        // which way the attachment goes is up to the lookup, and only matters for which glyph moves
+        let (child, parent, y) = if lookup.flags & RIGHT_TO_LEFT != 0 {
+            (i, j, entry_y - exit_y)
+        } else {
+            (j, i, exit_y - entry_y)
+        };
+        positions[child].y = y;
+        positions[child].attach = Some((parent, Attach::Cursive));
+        Some(j + 1)
+    }
+    
+    // mark-to-base and mark-to-ligature, which attach a mark to the glyph before it that isn't a mark
+    fn mark_to_base(&self, kind: u16, pos: usize, positions: &mut [Position], i: usize) -> Option<usize> {
+        let data = self.data;
+        let infos = self.infos;
+        let mut r = Reader::at(data, pos + 2);
+        let mark_index = coverage(data, pos + r.u16()? as usize, infos[i].glyph)?;
+        let base_coverage = pos + r.u16()? as usize;
+        let class_count = r.u16()? as usize;
+        let (mark_array, base_array) = (pos + r.u16()? as usize, pos + r.u16()? as usize);
+        
+        let base = (0..i).rev().find(|k| infos[*k].class != MARK)?;
+        let base_index = coverage(data, base_coverage, infos[base].glyph)?;
+        let (class, mark_anchor) = mark_record(data, mark_array, mark_index)?;
+        if class >= class_count || base_index >= u16_at(data, base_array)? as usize { return None; }
+        
+        let anchors = if kind == MARK_TO_LIGATURE {
+            // which of the ligature's components the mark goes on: the one it was on before they were joined,
+            // or the last one if it came after
+            let attach = base_array + u16_at(data, base_array + 2 + base_index * 2)? as usize;
+            let count = u16_at(data, attach)? as usize;
+            if count == 0 { return None; }
+            let (mark, ligature) = (&infos[i], &infos[base]);
+            let component = if mark.ligature != 0 && mark.ligature == ligature.ligature && mark.component > 0 {
+                (mark.component as usize).min(count) - 1
+            } else {
+                count - 1
+            };
+            (attach, attach + 2 + component * class_count * 2)
+        } else {
+            (base_array, base_array + 2 + base_index * class_count * 2)
+        };
+        let base_anchor = anchor_at(data, anchors.0, anchors.1 + class * 2)?;
+        self.attach(positions, i, base, mark_anchor, base_anchor);
+        Some(i + 1)
+    }
+    
+    fn mark_to_mark(&self, lookup: &Lookup, pos: usize, positions: &mut [Position], i: usize) -> Option<usize> {
+        let data = self.data;
+        let infos = self.infos;
+        let mut r = Reader::at(data, pos + 2);
+        let mark_index = coverage(data, pos + r.u16()? as usize, infos[i].glyph)?;
+        let mark2_coverage = pos + r.u16()? as usize;
+        let class_count = r.u16()? as usize;
+        let (mark_array, mark2_array) = (pos + r.u16()? as usize, pos + r.u16()? as usize);
+        
+        // only marks on the same thing attach to each other
+        let previous = self.view(lookup).previous(i)?;
+        let (mark, mark2) = (&infos[i], &infos[previous]);
+        if mark2.class != MARK || mark.ligature != mark2.ligature || mark.component != mark2.component { return None; }
+        let mark2_index = coverage(data, mark2_coverage, mark2.glyph)?;
+        let (class, mark_anchor) = mark_record(data, mark_array, mark_index)?;
+        if class >= class_count || mark2_index >= u16_at(data, mark2_array)? as usize { return None; }
+        let mark2_anchor = anchor_at(data, mark2_array, mark2_array + 2 + (mark2_index * class_count + class) * 2)?;
+        self.attach(positions, i, previous, mark_anchor, mark2_anchor);
+        Some(i + 1)
+    }
+    
+    fn attach(&self, positions: &mut [Position], mark: usize, base: usize, (mark_x, mark_y): (i32,i32), (base_x, base_y): (i32,i32)) {
+        positions[mark].x = base_x - mark_x;
+        positions[mark].y = base_y - mark_y;
+        positions[mark].attach = Some((base, Attach::Mark));
+    }
+    
+    fn context(&mut self, lookup: &Lookup, pos: usize, positions: &mut [Position], i: usize) -> Option<usize> {
+        let chained = lookup.kind == CHAINED_CONTEXT;
+        let matched = match_context(self.data, pos, chained, &self.view(lookup), i)?;
+        if self.depth < MAX_NESTING {
+            self.depth += 1;
+            for (sequence_index, lookup_index) in matched.records {
+                let Some(&at) = matched.positions.get(sequence_index as usize) else { continue };
+                let Some(nested) = Lookup::load(self.data, self.table, lookup_index, EXTENSION) else { continue };
+                self.apply_at(&nested, positions, at);
+            }
+            self.depth -= 1;
+        }
+        Some(matched.positions.last().map_or(i, |p| p + 1).max(i + 1))
+    }
+}
+
+fn value_size(format: u16) -> usize { (format & 0xFF).count_ones() as usize * 2 }
+
+// (x placement, y placement, x advance) from a value record, leaving out the rest
+fn value(data: &[u8], pos: usize, format: u16) -> Option<(i32,i32,i32)> {
+    let mut r = Reader::at(data, pos);
+    let mut fields = [0; 3];
+    for (bit, field) in fields.iter_mut().enumerate() {
+        if format & (1 << bit) != 0 { *field = r.i16()? as i32; }
+    }
+    Some((fields[0], fields[1], fields[2]))
+}
+
+// (x,y) of the anchor at pos; the formats with contour points and device tables have x and y in the same place
+fn anchor(data: &[u8], pos: usize) -> Option<(i32,i32)> {
+    Some((i16_at(data, pos + 2)? as i32, i16_at(data, pos + 4)? as i32))
+}
+
+// the anchor whose offset from table is at pos, if there is one
+fn anchor_at(data: &[u8], table: usize, pos: usize) -> Option<(i32,i32)> {
+    match u16_at(data, pos)? {
+        0 => None,
+        offset => anchor(data, table + offset as usize),
+    }
+}
+
+// class and anchor of a mark, from a mark array
+fn mark_record(data: &[u8], array: usize, index: usize) -> Option<(usize,(i32,i32))> {
+    if index >= u16_at(data, array)? as usize { return None; }
+    let record = array + 2 + index * 4;
+    Some((u16_at(data, record)? as usize, anchor_at(data, array, record + 2)?))
+}
\ No newline at end of file
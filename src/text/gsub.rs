@@ -0,0 +1,222 @@
+use super::{Font,GlyphId};
+use super::layout::{Gdef,Glyphs,Lookup,MARK,coverage,match_context};
+use super::reader::{Reader,u16_at};
+use super::shape::{Info,View};
+
+// glyph substitution: single glyphs swapped for others, split into several, or joined into ligatures
+
+const SINGLE: u16 = 1;
+const MULTIPLE: u16 = 2;
+const ALTERNATE: u16 = 3;
+const LIGATURE: u16 = 4;
+const CONTEXT: u16 = 5;
+const CHAINED_CONTEXT: u16 = 6;
+const REVERSE_CHAINED: u16 = 8;
+const EXTENSION: u16 = 7;
+
+// contextual lookups can apply other lookups, which can be contextual themselves, but only so deep
+const MAX_NESTING: u8 = 8;
+
+pub(super) struct Substitution<'a> {
+    data: &'a [u8],
+    gdef: &'a Gdef,
+    table: Option<usize>,
+    next_ligature: u8,
+    depth: u8,
+} impl<'a> Substitution<'a> {
+    pub fn new(font: &'a Font) -> Self {
+        Self {data: font.data(), gdef: font.gdef(), table: font.gsub(), next_ligature: 1, depth: 0}
+    }
+    
+    // applies lookups one after the other, each to every glyph its mask says it applies to
+    pub fn apply(&mut self, lookups: &[(u16,u32,Lookup)], infos: &mut Vec<Info>) {
+        for (_, mask, lookup) in lookups {
+            // reverse chaining lookups go from the end, and never change how many glyphs there are
+            if lookup.kind == REVERSE_CHAINED {
+                for i in (0..infos.len()).rev() {
+                    if self.applies(lookup, *mask, infos, i) { self.apply_at(lookup, infos, i); }
+                }
+                continue;
+            }
+            let mut i = 0;
+            while i < infos.len() {
+                i = if self.applies(lookup, *mask, infos, i) {
+                    self.apply_at(lookup, infos, i).unwrap_or(i + 1)
+                } else {
+                    i + 1
+                };
+            }
+        }
+    }
+    
+    fn applies(&self, lookup: &Lookup, mask: u32, infos: &[Info], i: usize) -> bool {
+        infos[i].mask & mask != 0 && !self.view(lookup, infos).skipped(i)
+    }
+    
+    fn view<'b>(&'b self, lookup: &'b Lookup, infos: &'b [Info]) -> View<'b> {
+        View {data: self.data, gdef: self.gdef, lookup, infos}
+    }
+    
+    // applies the first subtable of lookup that does anything at glyph i, returning where to carry on from
+    fn apply_at(&mut self, lookup: &Lookup, infos: &mut Vec<Info>, i: usize) -> Option<usize> {
+        for subtable in &lookup.subtables {
+            let next = match lookup.kind {
+                SINGLE => self.single(*subtable, infos, i),
+                MULTIPLE | ALTERNATE => self.multiple(lookup.kind, *subtable, infos, i),
+                LIGATURE => self.ligature(lookup, *subtable, infos, i),
+                CONTEXT | CHAINED_CONTEXT => self.context(lookup, *subtable, infos, i),
+                REVERSE_CHAINED => self.reverse_chained(lookup, *subtable, infos, i),
+                _ => None,
+            };
+            if next.is_some() { return next; }
+        }
+        None
+    }
+    
+    fn set_glyph(&self, info: &mut Info, glyph: GlyphId) {
+        info.glyph = glyph;
+        if self.gdef.has_classes() { info.class = self.gdef.class(self.data, glyph); }
+    }
+    
+    fn single(&self, pos: usize, infos: &mut [Info], i: usize) -> Option<usize> {
+        let data = self.data;
+        let glyph = infos[i].glyph;
+        let mut r = Reader::at(data, pos);
+        let format = r.u16()?;
+        let index = coverage(data, pos + r.u16()? as usize, glyph)?;
+        let substitute = match format {
+            1 => glyph.wrapping_add(r.u16()?),
+            2 => u16_at(data, pos + 6 + index * 2)?,
+            _ => return None,
+        };
+        self.set_glyph(&mut infos[i], substitute);
+        Some(i + 1)
+    }
+    
+    // multiple substitution replaces a glyph with a sequence of them, alternates pick one of several
+    // the sequences and sets of alternates are laid out the same way
+    fn multiple(&self, kind: u16, pos: usize, infos: &mut Vec<Info>, i: usize) -> Option<usize> {
+        let data = self.data;
+        let index = coverage(data, pos + u16_at(data, pos + 2)? as usize, infos[i].glyph)?;
+        if index >= u16_at(data, pos + 4)? as usize { return None; }
+        let sequence = pos + u16_at(data, pos + 6 + index * 2)? as usize;
+        let mut r = Reader::at(data, sequence);
+        let glyphs: Vec<GlyphId> = (0..r.u16()?).map(|_| r.u16()).collect::<Option<_>>()?;
+        
+        if kind == ALTERNATE {
+            self.set_glyph(&mut infos[i], *glyphs.first()?);
+            return Some(i + 1);
+        }
+        let info = infos[i];
+        let replacement: Vec<Info> = glyphs.iter().map(|glyph| {
+            let mut info = info;
+            self.set_glyph(&mut info, *glyph);
+            info
+        }).collect();
+        infos.splice(i..=i, replacement);
+        Some(i + glyphs.len())
+    }
+    
+    fn ligature(&mut self, lookup: &Lookup, pos: usize, infos: &mut Vec<Info>, i: usize) -> Option<usize> {
+        let data = self.data;
+        let index = coverage(data, pos + u16_at(data, pos + 2)? as usize, infos[i].glyph)?;
+        if index >= u16_at(data, pos + 4)? as usize { return None; }
+        let set = pos + u16_at(data, pos + 6 + index * 2)? as usize;
+        
+        // the first ligature in the set whose components all follow, in order
+        let view = self.view(lookup, infos);
+        let matched = || -> Option<(GlyphId,Vec<usize>)> {
+            let mut r = Reader::at(data, set);
+            'ligatures: for _ in 0..r.u16()? {
+                let ligature = set + r.u16()? as usize;
+                let mut l = Reader::at(data, ligature);
+                let glyph = l.u16()?;
+                let mut components = vec![i];
+                for _ in 1..l.u16()? {
+                    let component = l.u16()?;
+                    match view.next(*components.last().unwrap()) {
+                        Some(j) if infos[j].glyph == component => components.push(j),
+                        _ => continue 'ligatures,
+                    }
+                }
+                return Some((glyph, components));
+            }
+            None
+        };
+        let (glyph, components) = matched()?;
+        self.ligate(infos, glyph, &components);
+        Some(i + 1)
+    }
+    
+    // replaces the glyphs at components with a ligature, leaving any marks between them behind it
+    fn ligate(&mut self, infos: &mut Vec<Info>, glyph: GlyphId, components: &[usize]) {
+        let (first, last) = (components[0], *components.last().unwrap());
+        // ligatures of marks are marks themselves, and don't get an id for other marks to go by
+        let id = if components.iter().all(|k| infos[*k].class == MARK) {
+            0
+        } else {
+            let id = self.next_ligature;
+            self.next_ligature = self.next_ligature.checked_add(1).unwrap_or(1);
+            id
+        };
+        let cluster = infos[first..=last].iter().map(|info| info.cluster).min().unwrap_or(0);
+        let mut component = 0;
+        for (k, info) in infos[first..=last].iter_mut().enumerate() {
+            info.cluster = cluster;
+            if components.contains(&(first + k)) {
+                component += 1;
+            } else if id != 0 {
+                (info.ligature, info.component) = (id, component);
+            }
+        }
+        self.set_glyph(&mut infos[first], glyph);
+        (infos[first].ligature, infos[first].component) = (id, 0);
+        for k in components[1..].iter().rev() { infos.remove(*k); }
+    }
+    
+    fn context(&mut self, lookup: &Lookup, pos: usize, infos: &mut Vec<Info>, i: usize) -> Option<usize> {
+        let chained = lookup.kind == CHAINED_CONTEXT;
+        let matched = match_context(self.data, pos, chained, &self.view(lookup, infos), i)?;
+        let mut positions = matched.positions;
+        if self.depth < MAX_NESTING && let Some(table) = self.table {
+            self.depth += 1;
+            for (sequence_index, lookup_index) in matched.records {
+                let Some(&at) = positions.get(sequence_index as usize) else { continue };
+                if at >= infos.len() { continue; }
+                let Some(nested) = Lookup::load(self.data, table, lookup_index, EXTENSION) else { continue };
+                let before = infos.len();
+                self.apply_at(&nested, infos, at);
+                // what comes after moves along with however many glyphs were added or taken away
+                let delta = infos.len() as isize - before as isize;
+                for p in positions.iter_mut().filter(|p| **p > at) {
+                    *p = (*p as isize + delta).max(at as isize) as usize;
+                }
+            }
+            self.depth -= 1;
+        }
+        let end = positions.last().map_or(i, |p| p + 1);
+        Some(end.clamp(i + 1, infos.len().max(i + 1)))
+    }
+    
+    fn reverse_chained(&self, lookup: &Lookup, pos: usize, infos: &mut [Info], i: usize) -> Option<usize> {
+        let data = self.data;
+        let view = self.view(lookup, infos);
+        let index = coverage(data, pos + u16_at(data, pos + 2)? as usize, infos[i].glyph)?;
+        let mut r = Reader::at(data, pos + 4);
+        let mut k = i;
+        for _ in 0..r.u16()? {
+            k = view.previous(k)?;
+            coverage(data, pos + r.u16()? as usize, infos[k].glyph)?;
+        }
+        let mut k = i;
+        for _ in 0..r.u16()? {
+            k = view.next(k)?;
+            coverage(data, pos + r.u16()? as usize, infos[k].glyph)?;
+        }
+        if index >= r.u16()? as usize { return None; }
+        r.skip(index * 2)?;
+        let substitute = r.u16()?;
+        self.set_glyph(&mut infos[i], substitute);
+        Some(i + 1)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,469 @@
+use super::GlyphId;
+use super::reader::{Reader,u16_at,i16_at,u32_at};
+
+// opentype layout tables: coverage, class definitions and lookups, shared by GPOS and GSUB
+
+// index of glyph within a coverage table, if it's covered at all
+pub(super) fn coverage(data: &[u8], pos: usize, glyph: GlyphId) -> Option<usize> {
+    let mut r = Reader::at(data, pos);
+    match r.u16()? {
+        1 => {
+            let count = r.u16()? as usize;
+            let (mut lo, mut hi) = (0, count);
+            while lo < hi {
+                let mid = (lo + hi) / 2;
+                let g = u16_at(data, pos + 4 + mid * 2)?;
+                if g == glyph { return Some(mid); }
+                if g < glyph { lo = mid + 1 } else { hi = mid }
+            }
+            None
+        },
+        2 => {
+            let (start, end, index) = range_record(data, pos, glyph)?;
+            Some(index as usize + (glyph - start) as usize).filter(|_| glyph <= end)
+        },
+        _ => None,
+    }
+}
+
+// class of glyph according to a class definition table; glyphs not listed are class 0
+pub(super) fn class(data: &[u8], pos: usize, glyph: GlyphId) -> u16 {
+    let lookup = || -> Option<u16> {
+        let mut r = Reader::at(data, pos);
+        match r.u16()? {
+            1 => {
+                let start = r.u16()?;
+                let count = r.u16()?;
+                let i = glyph.checked_sub(start)?;
+                if i >= count { return None; }
+                u16_at(data, pos + 6 + i as usize * 2)
+            },
+            2 => range_record(data, pos, glyph).map(|(_, _, class)| class),
+            _ => None,
+        }
+    };
+    lookup().unwrap_or(0)
+}
+
+// binary search for the (start,end,value) record containing glyph, in a format 2 coverage or class table
+fn range_record(data: &[u8], pos: usize, glyph: GlyphId) -> Option<(u16,u16,u16)> {
+    let count = u16_at(data, pos + 2)? as usize;
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let mut r = Reader::at(data, pos + 4 + mid * 6);
+        let (start, end, value) = (r.u16()?, r.u16()?, r.u16()?);
+        if glyph < start { hi = mid }
+        else if glyph > end { lo = mid + 1 }
+        else { return Some((start, end, value)); }
+    }
+    None
+}
+
+// subtables of every lookup used by any feature with the given tag, as (lookup type, position)
+// extension lookups are unwrapped, and types are left for the caller to check
+pub(super) fn feature_subtables(data: &[u8], table: usize, tag: &[u8; 4], extension_type: u16) -> Vec<(u16,usize)> {
+    let find = || -> Option<Vec<(u16,usize)>> {
+        let features = table + u16_at(data, table + 6)? as usize;
+        let lookups = table + u16_at(data, table + 8)? as usize;
+        
+        let mut indices: Vec<u16> = Vec::new();
+        let mut r = Reader::at(data, features);
+        for _ in 0..r.u16()? {
+            let feature_tag = r.tag()?;
+            let feature = features + r.u16()? as usize;
+            if &feature_tag != tag { continue; }
+            let mut f = Reader::at(data, feature + 2);
+            for _ in 0..f.u16()? { indices.push(f.u16()?); }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        
+        let mut subtables = Vec::new();
+        for i in indices {
+            let lookup = lookups + u16_at(data, lookups + 2 + i as usize * 2)? as usize;
+            let mut r = Reader::at(data, lookup);
+            let kind = r.u16()?;
+            let _flags = r.u16()?;
+            for _ in 0..r.u16()? {
+                let subtable = lookup + r.u16()? as usize;
+                if kind == extension_type {
+                    let kind = u16_at(data, subtable + 2)?;
+                    subtables.push((kind, subtable + u32_at(data, subtable + 4)? as usize));
+                } else {
+                    subtables.push((kind, subtable));
+                }
+            }
+        }
+        Some(subtables)
+    };
+    find().unwrap_or_default()
+}
+
+// glyph classes in GDEF
+pub(super) const BASE: u16 = 1;
+pub(super) const LIGATURE: u16 = 2;
+pub(super) const MARK: u16 = 3;
+
+// lookup flags
+pub(super) const RIGHT_TO_LEFT: u16 = 0x0001;
+const IGNORE_BASE: u16 = 0x0002;
+const IGNORE_LIGATURES: u16 = 0x0004;
+const IGNORE_MARKS: u16 = 0x0008;
+const USE_MARK_FILTERING_SET: u16 = 0x0010;
+const MARK_ATTACHMENT_TYPE: u16 = 0xFF00;
+
+// glyph definitions, which say which glyphs are marks and ligatures so lookups can skip over them
+#[derive(Clone,Copy,Debug,Default)]
+pub(super) struct Gdef {
+    classes: Option<usize>,
+    mark_classes: Option<usize>,
+    mark_sets: Option<usize>,
+} impl Gdef {
+    pub fn new(data: &[u8], gdef: Option<usize>) -> Self {
+        let Some(gdef) = gdef else { return Self::default() };
+        let offset = |at: usize| u16_at(data, gdef + at).filter(|o| *o != 0).map(|o| gdef + o as usize);
+        let minor = u16_at(data, gdef + 2).unwrap_or(0);
+        Self {
+            classes: offset(4),
+            mark_classes: offset(10),
+            mark_sets: if minor >= 2 { offset(12) } else { None },
+        }
+    }
+    
+    pub fn has_classes(&self) -> bool { self.classes.is_some() }
+    
+    pub fn class(&self, data: &[u8], glyph: GlyphId) -> u16 {
+        self.classes.map_or(0, |pos| class(data, pos, glyph))
+    }
+    
+    // whether a lookup with these flags passes over a glyph of the given class
+    pub fn skips(&self, data: &[u8], lookup: &Lookup, glyph: GlyphId, glyph_class: u16) -> bool {
+        let flags = lookup.flags;
+        match glyph_class {
+            BASE => flags & IGNORE_BASE != 0,
+            LIGATURE => flags & IGNORE_LIGATURES != 0,
+            MARK if flags & IGNORE_MARKS != 0 => true,
+            MARK if flags & USE_MARK_FILTERING_SET != 0 => {
+                !self.in_mark_set(data, lookup.mark_set, glyph)
+            },
+            MARK if flags & MARK_ATTACHMENT_TYPE != 0 => {
+                self.mark_classes.is_none_or(|pos| class(data, pos, glyph) != flags >> 8)
+            },
+            _ => false,
+        }
+    }
+    
+    fn in_mark_set(&self, data: &[u8], set: u16, glyph: GlyphId) -> bool {
+        let find = || -> Option<usize> {
+            let sets = self.mark_sets?;
+            if set >= u16_at(data, sets + 2)? { return None; }
+            let coverage_at = sets + u32_at(data, sets + 4 + set as usize * 4)? as usize;
+            coverage(data, coverage_at, glyph)
+        };
+        find().is_some()
+    }
+}
+
+// a lookup from GSUB or GPOS, with extension subtables already unwrapped
+#[derive(Clone,Debug)]
+pub(super) struct Lookup {
+    pub kind: u16,
+    pub flags: u16,
+    pub mark_set: u16,
+    pub subtables: Vec<usize>,
+} impl Lookup {
+    pub fn load(data: &[u8], table: usize, index: u16, extension_type: u16) -> Option<Self> {
+        let list = table + u16_at(data, table + 8)? as usize;
+        if index >= u16_at(data, list)? { return None; }
+        let lookup = list + u16_at(data, list + 2 + index as usize * 2)? as usize;
+        let mut r = Reader::at(data, lookup);
+        let mut kind = r.u16()?;
+        let flags = r.u16()?;
+        let count = r.u16()?;
+        let mut subtables = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let subtable = lookup + r.u16()? as usize;
+            if kind == extension_type {
+                kind = u16_at(data, subtable + 2)?;
+                subtables.push(subtable + u32_at(data, subtable + 4)? as usize);
+            } else {
+                subtables.push(subtable);
+            }
+        }
+        let mark_set = if flags & USE_MARK_FILTERING_SET != 0 { r.u16()? } else { 0 };
+        Some(Self {kind, flags, mark_set, subtables})
+    }
+}
+
+// lookups that the given features use in the first of scripts the font has, falling back to its default script
+// each comes with the masks of all the features that use it, in the order they have to be applied
+pub(super) fn lookups(data: &[u8], table: usize, scripts: &[[u8; 4]], features: &[([u8; 4],u32)], extension_type: u16) -> Vec<(u16,u32,Lookup)> {
+    let find = || -> Option<Vec<(u16,u32,Lookup)>> {
+        let script_list = table + u16_at(data, table + 4)? as usize;
+        let feature_list = table + u16_at(data, table + 6)? as usize;
+        
+        let mut script = None;
+        for tag in scripts.iter().chain([b"DFLT", b"dflt", b"latn"]) {
+            let mut r = Reader::at(data, script_list + 2);
+            for _ in 0..u16_at(data, script_list)? {
+                let (t, offset) = (r.tag()?, r.u16()?);
+                if &t == tag { script = Some(script_list + offset as usize); }
+            }
+            if script.is_some() { break; }
+        }
+        let script = script?;
+        // the default language, or whichever one comes first if there isn't one
+        let lang_sys = match u16_at(data, script)? {
+            0 => script + u16_at(data, script + 6)? as usize,
+            offset => script + offset as usize,
+        };
+        
+        let mut r = Reader::at(data, lang_sys + 2);
+        // the required feature, if there is one, is only used when asked for by its tag like any other
+        let mut indices = vec![r.u16()?];
+        for _ in 0..r.u16()? { indices.push(r.u16()?); }
+        
+        let mut masks: Vec<(u16,u32)> = Vec::new();
+        for index in indices {
+            if index == 0xFFFF { continue; }
+            let mut r = Reader::at(data, feature_list + 2 + index as usize * 6);
+            let tag = r.tag()?;
+            let feature = feature_list + r.u16()? as usize;
+            let Some((_, mask)) = features.iter().find(|(t, _)| *t == tag) else { continue };
+            let mut f = Reader::at(data, feature + 2);
+            for _ in 0..f.u16()? {
+                let lookup = f.u16()?;
+                match masks.iter_mut().find(|(l, _)| *l == lookup) {
+                    Some((_, m)) => *m |= *mask,
+                    None => masks.push((lookup, *mask)),
+                }
+            }
+        }
+        masks.sort_unstable_by_key(|(lookup, _)| *lookup);
+        Some(masks.into_iter()
+            .filter_map(|(index, mask)| Some((index, mask, Lookup::load(data, table, index, extension_type)?)))
+            .collect())
+    };
+    find().unwrap_or_default()
+}
+
+// what lookups see of the glyphs they're applied to
+pub(super) trait Glyphs {
+    fn len(&self) -> usize;
+    fn glyph(&self, i: usize) -> GlyphId;
+    // whether the lookup being applied passes over glyph i as if it wasn't there
+    fn skipped(&self, i: usize) -> bool;
+    
+    fn next(&self, i: usize) -> Option<usize> { (i + 1..self.len()).find(|j| !self.skipped(*j)) }
+    fn previous(&self, i: usize) -> Option<usize> { (0..i).rev().find(|j| !self.skipped(*j)) }
+}
+
+// how a rule in a contextual lookup identifies glyphs
+#[derive(Clone,Copy)]
+enum Matcher {
+    Glyph,
+    Class(Option<usize>), // class definition table
+    Coverage(usize),      // subtable the coverage offsets are relative to
+} impl Matcher {
+    fn matches(self, data: &[u8], value: u16, glyph: GlyphId) -> bool {
+        match self {
+            Matcher::Glyph => glyph == value,
+            Matcher::Class(def) => def.map_or(0, |def| class(data, def, glyph)) == value,
+            Matcher::Coverage(base) => coverage(data, base + value as usize, glyph).is_some(),
+        }
+    }
+}
+
+// a contextual rule that matched: where its input glyphs are, and which lookups apply at which of them
+pub(super) struct ContextMatch {
+    pub positions: Vec<usize>,
+    pub records: Vec<(u16,u16)>, // (index into positions, lookup index)
+}
+
+// the first rule of a (chained) contextual subtable that matches at glyph i
+pub(super) fn match_context(data: &[u8], subtable: usize, chained: bool, glyphs: &impl Glyphs, i: usize) -> Option<ContextMatch> {
+    let glyph = glyphs.glyph(i);
+    let format = u16_at(data, subtable)?;
+    
+    // formats 1 and 2 group their rules into sets by first glyph or its class, and leave the first glyph out of them
+    // format 3 is a single rule of coverage tables, laid out like the rules of the other two
+    let (matchers, rules) = match format {
+        1 | 2 => {
+            let index = coverage(data, subtable + u16_at(data, subtable + 2)? as usize, glyph)?;
+            let def = |at: usize| u16_at(data, subtable + at).filter(|o| *o != 0).map(|o| subtable + o as usize);
+            let (matchers, set, sets) = match (format, chained) {
+                (1, _) => ([Matcher::Glyph; 3], index, subtable + 4),
+                (_, false) => {
+                    let input = def(4);
+                    let set = input.map_or(0, |input| class(data, input, glyph));
+                    ([Matcher::Class(None), Matcher::Class(input), Matcher::Class(None)], set as usize, subtable + 6)
+                },
+                (_, true) => {
+                    let input = def(6);
+                    let set = input.map_or(0, |input| class(data, input, glyph));
+                    ([Matcher::Class(def(4)), Matcher::Class(input), Matcher::Class(def(8))], set as usize, subtable + 10)
+                },
+            };
+            if set >= u16_at(data, sets)? as usize { return None; }
+            let set = match u16_at(data, sets + 2 + set * 2)? {
+                0 => return None,
+                offset => subtable + offset as usize,
+            };
+            let mut r = Reader::at(data, set);
+            let rules = (0..r.u16()?).map(|_| Some(set + r.u16()? as usize)).collect::<Option<Vec<_>>>()?;
+            (matchers, rules)
+        },
+        3 => ([Matcher::Coverage(subtable); 3], vec![subtable + 2]),
+        _ => return None,
+    };
+    let first_included = format == 3;
+    
+    let sequence = |r: &mut Reader, count: usize| -> Option<Vec<u16>> {
+        (0..count).map(|_| r.u16()).collect()
+    };
+    let [back_matcher, input_matcher, ahead_matcher] = matchers;
+    let try_rule = |pos: usize| -> Option<ContextMatch> {
+        let mut r = Reader::at(data, pos);
+        let backtrack = if chained { let count = r.u16()? as usize; sequence(&mut r, count)? } else { Vec::new() };
+        let input_count = r.u16()? as usize;
+        let mut lookup_count = if chained { 0 } else { r.u16()? as usize };
+        let input = sequence(&mut r, if first_included { input_count } else { input_count.checked_sub(1)? })?;
+        let lookahead = if chained { let count = r.u16()? as usize; sequence(&mut r, count)? } else { Vec::new() };
+        if chained { lookup_count = r.u16()? as usize; }
+        
+        let rest = if first_included {
+            let (first, rest) = input.split_first()?;
+            if !input_matcher.matches(data, *first, glyph) { return None; }
+            rest
+        } else { &input[..] };
+        let mut positions = vec![i];
+        let mut j = i;
+        for value in rest {
+            j = glyphs.next(j)?;
+            if !input_matcher.matches(data, *value, glyphs.glyph(j)) { return None; }
+            positions.push(j);
+        }
+        let mut k = i;
+        for value in &backtrack {
+            k = glyphs.previous(k)?;
+            if !back_matcher.matches(data, *value, glyphs.glyph(k)) { return None; }
+        }
+        let mut k = j;
+        for value in &lookahead {
+            k = glyphs.next(k)?;
+            if !ahead_matcher.matches(data, *value, glyphs.glyph(k)) { return None; }
+        }
+        let records = (0..lookup_count).map(|_| Some((r.u16()?, r.u16()?))).collect::<Option<_>>()?;
+        Some(ContextMatch {positions, records})
+    };
+    rules.into_iter().find_map(try_rule)
+}
+// horizontal kerning between pairs of glyphs, from GPOS if the font has it, otherwise from kern
+#[derive(Clone,Debug,Default)]
+pub(super) struct Kerning {
+    pairs: Vec<usize>,   // GPOS pair adjustment subtables
+    kern: Option<usize>, // format 0 subtable of the old kern table
+} impl Kerning {
+    pub fn new(data: &[u8], gpos: Option<usize>, kern: Option<usize>) -> Self {
+        const PAIR_ADJUSTMENT: u16 = 2;
+        const EXTENSION: u16 = 9;
+        
+        let pairs: Vec<usize> = gpos
+            .map(|gpos| feature_subtables(data, gpos, b"kern", EXTENSION))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(kind, _)| *kind == PAIR_ADJUSTMENT)
+            .map(|(_, pos)| pos)
+            .collect();
+        let kern = if pairs.is_empty() { kern.and_then(|kern| kern_subtable(data, kern)) } else { None };
+        Self {pairs, kern}
+    }
+    
+    // in font units; negative moves right closer to left
+    pub fn get(&self, data: &[u8], left: GlyphId, right: GlyphId) -> i16 {
+        if let Some(pos) = self.kern {
+            return kern_pair(data, pos, left, right).unwrap_or(0);
+        }
+        self.pairs.iter()
+            .find_map(|pos| pair_adjustment(data, *pos, left, right))
+            .unwrap_or(0)
+    }
+}
+
+// first horizontal format 0 subtable of a version 0 kern table
+fn kern_subtable(data: &[u8], kern: usize) -> Option<usize> {
+    let mut r = Reader::at(data, kern);
+    if r.u16()? != 0 { return None; }
+    let mut pos = kern + 4;
+    for _ in 0..r.u16()? {
+        let length = u16_at(data, pos + 2)? as usize;
+        let coverage = u16_at(data, pos + 4)?;
+        let (format, horizontal) = (coverage >> 8, coverage & 1 != 0);
+        if format == 0 && horizontal { return Some(pos + 6); }
+        pos += length;
+    }
+    None
+}
+
+fn kern_pair(data: &[u8], pos: usize, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let count = u16_at(data, pos)? as usize;
+    let key = (left as u32) << 16 | right as u32;
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        let record = pos + 8 + mid * 6;
+        let k = u32_at(data, record)?;
+        if k == key { return i16_at(data, record + 4); }
+        if k < key { lo = mid + 1 } else { hi = mid }
+    }
+    None
+}
+
+// size of a value record with the given format, and where its x advance is within it
+fn value_record(format: u16) -> (usize,Option<usize>) {
+    const X_ADVANCE: u16 = 0x0004;
+    let size = format.count_ones() as usize * 2;
+    let advance = (format & X_ADVANCE != 0).then(|| (format & 0x0003).count_ones() as usize * 2);
+    (size, advance)
+}
+
+// x advance adjustment of left when followed by right, from a PairPos subtable
+// None if the subtable doesn't cover the pair at all
+fn pair_adjustment(data: &[u8], pos: usize, left: GlyphId, right: GlyphId) -> Option<i16> {
+    let mut r = Reader::at(data, pos);
+    let format = r.u16()?;
+    let index = coverage(data, pos + r.u16()? as usize, left)?;
+    let (size1, advance) = value_record(r.u16()?);
+    let (size2, _) = value_record(r.u16()?);
+    let adjustment = |record: usize| match advance {
+        Some(offset) => i16_at(data, record + offset),
+        None => Some(0),
+    };
+    
+    match format {
+        1 => {
+            let set = pos + u16_at(data, pos + 10 + index * 2)? as usize;
+            let count = u16_at(data, set)? as usize;
+            let record_size = 2 + size1 + size2;
+            let (mut lo, mut hi) = (0, count);
+            while lo < hi {
+                let mid = (lo + hi) / 2;
+                let record = set + 2 + mid * record_size;
+                let g = u16_at(data, record)?;
+                if g == right { return adjustment(record + 2); }
+                if g < right { lo = mid + 1 } else { hi = mid }
+            }
+            None
+        },
+        2 => {
+            let class_def1 = pos + r.u16()? as usize;
+            let class_def2 = pos + r.u16()? as usize;
+            let _class1_count = r.u16()?;
+            let class2_count = r.u16()? as usize;
+            let (c1, c2) = (class(data, class_def1, left) as usize, class(data, class_def2, right) as usize);
+            adjustment(pos + 16 + (c1 * class2_count + c2) * (size1 + size2))
+        },
+        _ => None,
+    }
+}
\ No newline at end of file
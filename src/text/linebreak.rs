@@ -0,0 +1,128 @@
+// where lines of text may be broken, following the parts of unicode's line breaking algorithm (uax #14)
+// that matter for the scripts we can draw; it's a subset, not the full pair table
+
+use super::script::is_mark;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+enum Class {
+    Mandatory, // line and paragraph separators
+    Cr,
+    Lf,
+    Space,
+    ZeroWidth, // zero width space, which allows a break without taking up room
+    Glue,      // no-break space and friends, which forbid one
+    Combining, // marks and joiners, which stay with what they're attached to
+    Open,      // opening brackets, which stay with what follows
+    Close,     // closing brackets and punctuation, which stay with what precedes
+    Hyphen,    // hyphens and dashes, which allow a break after
+    Ideographic, // cjk and emoji, which allow a break on either side
+    Numeric,     // digits, which hyphens in front of stay with, as in -5
+    Alphabetic,
+}
+
+fn class(c: char) -> Class {
+    use Class::*;
+    match c {
+        '\n' => Lf,
+        '\r' => Cr,
+        '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}' => Mandatory,
+        '\u{200B}' => ZeroWidth,
+        '\u{A0}' | '\u{2007}' | '\u{2011}' | '\u{202F}' | '\u{2060}' | '\u{FEFF}' => Glue,
+        ' ' | '\t' | '\u{1680}' | '\u{2000}'..='\u{200A}' | '\u{205F}' => Space,
+        _ if is_mark(c) => Combining,
+        '(' | '[' | '{' | '\u{A1}' | '\u{BF}' | '\u{2018}' | '\u{201C}' |
+        '\u{3008}' | '\u{300A}' | '\u{300C}' | '\u{300E}' | '\u{3010}' | '\u{FF08}' | '\u{FF3B}' | '\u{FF5B}' => Open,
+        ')' | ']' | '}' | '!' | '?' | ',' | '.' | ':' | ';' | '%' | '\u{2019}' | '\u{201D}' | '\u{2026}' |
+        '\u{3001}' | '\u{3002}' | '\u{3009}' | '\u{300B}' | '\u{300D}' | '\u{300F}' | '\u{3011}' |
+        '\u{FF01}' | '\u{FF09}' | '\u{FF0C}' | '\u{FF0E}' | '\u{FF1A}' | '\u{FF1B}' | '\u{FF1F}' | '\u{FF3D}' | '\u{FF5D}' => Close,
+        '-' | '\u{AD}' | '\u{2010}' | '\u{2012}' | '\u{2013}' | '\u{2014}' => Hyphen,
+        '\u{2E80}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}' | '\u{F900}'..='\u{FAFF}' | '\u{FF00}'..='\u{FFEF}' |
+        '\u{1F300}'..='\u{1FAFF}' | '\u{20000}'..='\u{3FFFD}' => Ideographic,
+        '0'..='9' | '\u{660}'..='\u{669}' | '\u{6F0}'..='\u{6F9}' | '\u{966}'..='\u{96F}' => Numeric,
+        _ => Alphabetic,
+    }
+}
+
+// byte offsets where a line may start, and whether it must
+// the start of the text isn't included, and neither is its end
+pub(super) fn breaks(text: &str) -> Vec<(usize,bool)> {
+    use Class::*;
+    
+    let mut breaks = Vec::new();
+    let mut chars = text.char_indices();
+    let Some((_, first)) = chars.next() else { return breaks };
+    let mut previous = class(first);
+    // what came before any spaces, since spaces don't change whether a break is allowed after an opening bracket
+    let mut before_spaces = previous;
+    
+    for (i, c) in chars {
+        let current = class(c);
+        let allowed = match (previous, current) {
+            (Cr, Lf) => None,
+            (Mandatory | Cr | Lf, _) => Some(true),
+            (_, Mandatory | Cr | Lf | Space | ZeroWidth | Combining | Close) => None,
+            (ZeroWidth, _) => Some(false),
+            (Glue, _) | (_, Glue) => None,
+            (Space, _) if before_spaces == Open => None,
+            (Hyphen, Numeric) => None,
+            (Space | Hyphen, _) => Some(false),
+            (Open, _) => None,
+            (Ideographic, _) | (_, Ideographic) => Some(false),
+            _ => None,
+        };
+        if let Some(mandatory) = allowed { breaks.push((i, mandatory)); }
+        
+        // combining marks take on the class of what they're attached to
+        if current != Combining { previous = current; }
+        if current != Space && current != Combining { before_spaces = current; }
+    }
+    breaks
+}
+
+// whether c is trailing whitespace that can hang past the end of a line without counting towards its width
+pub(super) fn is_space(c: char) -> bool {
+    matches!(class(c), Class::Space | Class::ZeroWidth | Class::Mandatory | Class::Cr | Class::Lf)
+}
+
+// whether c ends a line without being drawn
+pub(super) fn is_newline(c: char) -> bool {
+    matches!(class(c), Class::Mandatory | Class::Cr | Class::Lf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn opportunities() {
+        assert_eq!(breaks(""), []);
+        assert_eq!(breaks("ab cd  ef"), [(3, false), (7, false)]);
+        assert_eq!(breaks("well-known"), [(5, false)]);
+        // cjk breaks between any two ideographs
+        assert_eq!(breaks("日本語"), [(3, false), (6, false)]);
+    }
+    
+    #[test]
+    fn mandatory() {
+        assert_eq!(breaks("a\nb"), [(2, true)]);
+        assert_eq!(breaks("a\r\nb"), [(3, true)]);
+        assert_eq!(breaks("a\n\nb"), [(2, true), (3, true)]);
+        assert_eq!(breaks("a\u{2029}b"), [(4, true)]);
+        // a newline at the very end doesn't start another line here; layout adds that one itself
+        assert_eq!(breaks("a\n"), []);
+    }
+    
+    #[test]
+    fn forbidden() {
+        // brackets stay with what they enclose, even across spaces
+        assert_eq!(breaks("( a )"), []);
+        assert_eq!(breaks("a (b) c"), [(2, false), (6, false)]);
+        // no-break spaces, and marks on what they're on
+        assert_eq!(breaks("a\u{A0}b"), []);
+        assert_eq!(breaks("e\u{301} x"), [(4, false)]);
+        // a hyphen in front of a number is a minus sign
+        assert_eq!(breaks("x -5"), [(2, false)]);
+        assert_eq!(breaks("3-5"), []);
+        assert_eq!(breaks("3-x"), [(2, false)]);
+    }
+}
@@ -0,0 +1,621 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{
+    Pixel,Size,
+    draw::{FillRule,Paint,Painter,Path,Point},
+};
+
+use super::{Font,GlyphId};
+use super::bidi::{self,Bidi,TextDirection};
+use super::linebreak::{self,is_newline,is_space};
+use super::script;
+use super::shape;
+
+// rounding errors shouldn't push the last word of a line that exactly fits onto the next one
+const FIT_EPSILON: f32 = 0.01;
+
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub enum Align {
+    #[default]
+    Start,   // left, or right in right-to-left paragraphs
+    End,
+    Left,
+    Center,
+    Right,
+    Justify, // spreads wrapped lines out to the full width; the last line of a paragraph is aligned to the start
+}
+
+// how heavy text is, from 100 (thin) to 900 (black)
+// fonts only come in the one weight they were made in, so bolder than normal is faked by smearing glyphs sideways
+#[derive(Clone,Copy,Debug,PartialEq,Eq,PartialOrd,Ord)]
+pub struct Weight(pub u16);
+impl Weight {
+    pub const NORMAL: Weight = Weight(400);
+    pub const BOLD: Weight = Weight(700);
+    
+    // how far glyphs get smeared at size, in pixels
+    fn smear(self, size: f32) -> f32 {
+        self.0.saturating_sub(Self::NORMAL.0) as f32 / 300.0 * size / 20.0
+    }
+} impl Default for Weight {
+    fn default() -> Self { Self::NORMAL }
+}
+
+#[derive(Clone,Debug)]
+pub struct TextStyle {
+    pub font: Arc<Font>,
+    pub fallbacks: Vec<Arc<Font>>, // tried in order for chars font doesn't have
+    pub size: f32, // pixels per em
+    pub color: Paint,
+    pub weight: Weight,
+    pub underline: bool,
+} impl TextStyle {
+    pub fn new(font: Arc<Font>, size: f32) -> Self {
+        Self {font, fallbacks: Vec::new(), size, color: Pixel::BLACK.into(), weight: Weight::NORMAL, underline: false}
+    }
+    
+    pub fn with_fallback(mut self, font: Arc<Font>) -> Self { self.fallbacks.push(font); self }
+    pub fn with_color(mut self, color: impl Into<Paint>) -> Self { self.color = color.into(); self }
+    pub fn with_weight(mut self, weight: Weight) -> Self { self.weight = weight; self }
+    pub fn with_bold(self) -> Self { self.with_weight(Weight::BOLD) }
+    pub fn with_underline(mut self, underline: bool) -> Self { self.underline = underline; self }
+    
+    // font first, then its fallbacks
+    fn fonts(&self) -> impl Iterator<Item = &Arc<Font>> { [&self.font].into_iter().chain(&self.fallbacks) }
+    fn font(&self, index: usize) -> &Font { self.fonts().nth(index).unwrap_or(&self.font) }
+}
+
+// text made of spans of differently styled text, and how to lay it out
+#[derive(Clone,Debug)]
+pub struct Paragraph {
+    text: String,
+    spans: Vec<(Range<usize>,TextStyle)>,
+    align: Align,
+    direction: TextDirection,
+    line_height: f32,       // multiple of the fonts' own line height
+    max_width: Option<f32>,
+    wrap: bool,             // whether lines longer than max_width are broken or just run on
+    max_lines: Option<usize>,
+    ellipsis: bool,         // whether text cut off by max_width or max_lines ends in "…"
+} impl Paragraph {
+    pub fn new() -> Self {
+        Self {
+            text: String::new(),
+            spans: Vec::new(),
+            align: Align::Start,
+            direction: TextDirection::Auto,
+            line_height: 1.0,
+            max_width: None,
+            wrap: true,
+            max_lines: None,
+            ellipsis: false,
+        }
+    }
+    
+    pub fn with_span(mut self, text: &str, style: TextStyle) -> Self { self.push(text, style); self }
+    pub fn with_align(mut self, align: Align) -> Self { self.align = align; self }
+    pub fn with_direction(mut self, direction: TextDirection) -> Self { self.direction = direction; self }
+    pub fn with_line_height(mut self, line_height: f32) -> Self { self.line_height = line_height; self }
+    pub fn with_max_width(mut self, width: f32) -> Self { self.max_width = Some(width); self }
+    pub fn with_wrap(mut self, wrap: bool) -> Self { self.wrap = wrap; self }
+    pub fn with_max_lines(mut self, lines: usize) -> Self { self.max_lines = Some(lines); self }
+    pub fn with_ellipsis(mut self, ellipsis: bool) -> Self { self.ellipsis = ellipsis; self }
+    
+    pub fn push(&mut self, text: &str, style: TextStyle) {
+        let start = self.text.len();
+        self.text.push_str(text);
+        self.spans.push((start..self.text.len(), style));
+    }
+    
+    // all the spans' text, which is what layout byte offsets index into
+    pub fn text(&self) -> &str { &self.text }
+    
+    pub fn layout(&self) -> TextLayout {
+        let styles: Vec<TextStyle> = self.spans.iter().map(|(_, style)| style.clone()).collect();
+        if styles.is_empty() { return TextLayout {lines: Vec::new(), styles, width: 0.0}; }
+        let bidi = Bidi::new(&self.text, self.direction);
+        let clusters = self.shape(&bidi);
+        let wrap_width = self.max_width.filter(|_| self.wrap);
+        
+        // break into lines, keeping whether each one was cut short
+        let mut lines: Vec<(Vec<Cluster>,bool)> = self.break_lines(&clusters, wrap_width).into_iter()
+            .map(|range| (clusters[range].to_vec(), false))
+            .collect();
+        if let Some(max) = self.max_lines && lines.len() > max {
+            lines.truncate(max);
+            if let Some((_, cut)) = lines.last_mut() { *cut = true; }
+        }
+        for (line, cut) in &mut lines {
+            let too_wide = self.max_width.is_some_and(|max| visible_width(line) > max + FIT_EPSILON);
+            if self.ellipsis && (*cut || too_wide) {
+                self.ellipsize(line, &bidi);
+                *cut = true;
+            }
+        }
+        
+        let widest = lines.iter().map(|(line, _)| visible_width(line)).fold(0.0, f32::max);
+        let box_width = self.max_width.unwrap_or(widest);
+        let count = lines.len();
+        let mut top = 0.0;
+        let lines = lines.into_iter().enumerate().map(|(i, (clusters, cut))| {
+            let span = clusters.first().map_or(self.spans.len() - 1, |c| c.span);
+            let metrics = clusters.iter().map(|c| (c.span, c.font)).chain([(span, 0)])
+                .map(|(span, font)| {
+                    let style = &styles[span];
+                    let m = style.font(font).line_metrics(style.size);
+                    (m.ascent, m.descent, m.height() * self.line_height)
+                })
+                .fold((0.0, 0.0, 0.0), |a: (f32,f32,f32), b| (a.0.max(b.0), a.1.max(b.1), a.2.max(b.2)));
+            let (ascent, descent, height) = metrics;
+            
+            // wrapped lines get justified, but not ones ending a paragraph
+            let ends_paragraph = i + 1 == count || clusters.last().is_some_and(|c| is_newline(c.c));
+            let justify = self.align == Align::Justify && !ends_paragraph && !cut;
+            let base = bidi.base_level(clusters.first().map_or(self.text.len(), |c| c.index));
+            let mut line = Line::new(clusters, self.text.len(), base);
+            line.align(self.align, justify, box_width);
+            // any extra height from line_height is split evenly above and below
+            (line.top, line.height) = (top, height);
+            line.baseline = top + (height - ascent - descent) / 2.0 + ascent;
+            top += height;
+            line
+        }).collect();
+        
+        TextLayout {lines, styles, width: widest}
+    }
+    
+    // every glyph, in logical order, with the text it was shaped from and how far it moves the pen
+    // text is shaped in runs of the same span, font, script and direction
+    fn shape(&self, bidi: &Bidi) -> Vec<Cluster> {
+        let scripts = script::scripts(&self.text);
+        let mut chars = Vec::with_capacity(scripts.len());
+        let mut span = 0;
+        let mut previous: Option<(usize,usize)> = None; // span and font of the char before
+        for (k, (index, c)) in self.text.char_indices().enumerate() {
+            while self.spans[span].0.end <= index { span += 1; }
+            let style = &self.spans[span].1;
+            // marks and joiners stay in the font of what they're on, and everything else goes in the first font that has it
+            let font = match previous {
+                Some((previous_span, font)) if previous_span == span && script::is_mark(c) => font,
+                _ => style.fonts().position(|font| font.has_glyph(c)).unwrap_or(0),
+            };
+            previous = Some((span, font));
+            chars.push((index, c, (span, font, bidi.levels[k], scripts[k])));
+        }
+        
+        let mut clusters: Vec<Cluster> = Vec::with_capacity(chars.len());
+        // newlines aren't drawn, so they're runs of their own
+        for run in chars.chunk_by(|a, b| a.2 == b.2 && !is_newline(a.1) && !is_newline(b.1)) {
+            let (start, (span, font, level, script)) = (run[0].0, run[0].2);
+            let (last, c, _) = run[run.len() - 1];
+            let end = last + c.len_utf8();
+            let style = &self.spans[span].1;
+            let cluster = Cluster {index: start, len: end - start, c, span, font, glyph: 0, advance: 0.0, offset: Point::new(0.0, 0.0), level};
+            if is_newline(c) {
+                clusters.push(cluster);
+                continue;
+            }
+            
+            let glyphs = shape::shape(style.font(font), &self.text, start..end, script, level % 2 == 1, style.size);
+            for (k, g) in glyphs.iter().enumerate() {
+                let next = glyphs[k + 1..].iter().map(|g| g.cluster).find(|next| *next > g.cluster).unwrap_or(end);
+                // only glyphs that take up room get bolder by taking up more
+                let smear = if g.advance > 0.0 { style.weight.smear(style.size) } else { 0.0 };
+                clusters.push(Cluster {
+                    index: g.cluster,
+                    len: next - g.cluster,
+                    c: self.text[g.cluster..].chars().next().unwrap_or(' '),
+                    glyph: g.glyph,
+                    advance: g.advance + smear,
+                    offset: g.offset,
+                    ..cluster
+                });
+            }
+        }
+        clusters
+    }
+    
+    // ranges of clusters on each line, breaking wherever a line must end or would get wider than width
+    fn break_lines(&self, clusters: &[Cluster], width: Option<f32>) -> Vec<Range<usize>> {
+        // runs of clusters between break opportunities, and whether a line has to end after each
+        let mut segments: Vec<(Range<usize>,bool)> = Vec::new();
+        let mut breaks = linebreak::breaks(&self.text).into_iter().peekable();
+        let mut start = 0;
+        for (k, cluster) in clusters.iter().enumerate() {
+            // breaks inside of clusters, like between the chars of a ligature, don't count
+            while let Some(&(at, mandatory)) = breaks.peek() && at <= cluster.index {
+                if at == cluster.index && k > start {
+                    segments.push((start..k, mandatory));
+                    start = k;
+                }
+                breaks.next();
+            }
+        }
+        if start < clusters.len() { segments.push((start..clusters.len(), false)); }
+        
+        let mut lines = Vec::new();
+        let (mut line_start, mut line_end) = (0, 0);
+        let mut line_width = 0.0; // including trailing spaces
+        for (segment, mandatory) in segments {
+            let fits = |line_width: f32, extra: f32| width.is_none_or(|width| line_width + extra <= width + FIT_EPSILON);
+            let segment_width = visible_width(&clusters[segment.clone()]);
+            
+            if line_end > line_start && !fits(line_width, segment_width) {
+                lines.push(line_start..line_end);
+                (line_start, line_width) = (line_end, 0.0);
+            }
+            if fits(line_width, segment_width) {
+                line_width += clusters[segment.clone()].iter().map(|c| c.advance).sum::<f32>();
+                line_end = segment.end;
+            } else {
+                // too long for a line of its own, so it gets broken wherever
+                for k in segment {
+                    let cluster = &clusters[k];
+                    if line_end > line_start && !is_space(cluster.c) && !fits(line_width, cluster.advance) {
+                        lines.push(line_start..line_end);
+                        (line_start, line_width) = (line_end, 0.0);
+                    }
+                    line_width += cluster.advance;
+                    line_end = k + 1;
+                }
+            }
+            
+            if mandatory {
+                lines.push(line_start..line_end);
+                (line_start, line_width) = (line_end, 0.0);
+            }
+        }
+        if line_start < clusters.len() { lines.push(line_start..clusters.len()); }
+        // text ending in a newline has an empty line after it, for the caret to go
+        if lines.is_empty() || clusters.last().is_some_and(|c| is_newline(c.c)) {
+            lines.push(clusters.len()..clusters.len());
+        }
+        lines
+    }
+    
+    // cut line short enough to end in an ellipsis and still fit, and add the ellipsis
+    fn ellipsize(&self, line: &mut Vec<Cluster>, bidi: &Bidi) {
+        let last = line.last().copied().unwrap_or(Cluster {
+            index: self.text.len(), len: 0, c: ' ', span: self.spans.len() - 1, font: 0,
+            glyph: 0, advance: 0.0, offset: Point::new(0.0, 0.0), level: 0,
+        });
+        let style = &self.spans[last.span].1;
+        // it's in the span's own font, and goes whichever way the paragraph does
+        let font = &style.font;
+        let ellipsis: Vec<Cluster> = match font.glyph_index('…') {
+            Some(glyph) => vec![('…', glyph)],
+            None => vec![('.', font.glyph_index('.').unwrap_or(0)); 3],
+        }.into_iter().map(|(c, glyph)| Cluster {
+            index: last.index,
+            len: 0,
+            c,
+            font: 0,
+            glyph,
+            advance: font.advance(glyph, style.size) + style.weight.smear(style.size),
+            offset: Point::new(0.0, 0.0),
+            level: bidi.base_level(last.index),
+            ..last
+        }).collect();
+        let ellipsis_width: f32 = ellipsis.iter().map(|c| c.advance).sum();
+        
+        let max = self.max_width.unwrap_or(f32::INFINITY);
+        while let Some(c) = line.last()
+            && (is_space(c.c) || visible_width(line) + ellipsis_width > max + FIT_EPSILON)
+        {
+            // whole clusters at a time
+            let index = c.index;
+            while line.last().is_some_and(|c| c.index == index) { line.pop(); }
+        }
+        let index = line.last().map_or(last.index, |c| c.index + c.len);
+        line.extend(ellipsis.into_iter().map(|c| Cluster {index, ..c}));
+    }
+}
+
+impl Default for Paragraph {
+    fn default() -> Self { Self::new() }
+}
+
+// a glyph, and the chars of text it's part of drawing
+#[derive(Clone,Copy,Debug)]
+struct Cluster {
+    index: usize, // byte offset in the text; the ellipsis uses where the text was cut
+    len: usize,   // bytes of text, which is 0 for the ellipsis
+    c: char,      // the first of them
+    span: usize,
+    font: usize,  // which of the span's fonts, with its fallbacks after it
+    glyph: GlyphId,
+    advance: f32,
+    offset: Point,
+    level: u8,    // bidi level
+}
+
+// width of clusters, not counting whitespace hanging off the end
+fn visible_width(clusters: &[Cluster]) -> f32 {
+    let end = clusters.iter().rposition(|c| !is_space(c.c)).map_or(0, |i| i + 1);
+    clusters[..end].iter().map(|c| c.advance).sum()
+}
+
+#[derive(Clone,Copy,Debug)]
+struct Placed {
+    cluster: Cluster,
+    x: f32,
+    hanging: bool, // whitespace at the end of the line, which doesn't count towards its width
+}
+
+// one laid out line of a paragraph, with positions relative to the top-left of the layout
+#[derive(Clone,Debug)]
+pub struct Line {
+    pub range: Range<usize>, // bytes of the text on this line, including trailing whitespace
+    pub top: f32,
+    pub height: f32,
+    pub baseline: f32,
+    pub left: f32,           // where the line starts, after alignment
+    pub width: f32,          // not counting trailing whitespace
+    glyphs: Vec<Placed>,     // from left to right
+    rtl: bool,               // whether it's part of a right-to-left paragraph
+} impl Line {
+    // clusters in logical order, which get put in the order they're shown in
+    fn new(mut clusters: Vec<Cluster>, text_len: usize, base_level: u8) -> Self {
+        let start = clusters.first().map_or(text_len, |c| c.index);
+        let end = clusters.last().map_or(start, |c| c.index + c.len);
+        let width = visible_width(&clusters);
+        let visible = clusters.iter().rposition(|c| !is_space(c.c)).map_or(0, |i| i + 1);
+        
+        let mut levels: Vec<u8> = clusters.iter().map(|c| c.level).collect();
+        bidi::line_levels(clusters.iter().map(|c| c.c), &mut levels, base_level);
+        for (cluster, level) in clusters.iter_mut().zip(&levels) { cluster.level = *level; }
+        let glyphs = bidi::visual_order(&levels).into_iter()
+            .map(|i| Placed {cluster: clusters[i], x: 0.0, hanging: i >= visible})
+            .collect();
+        Self {range: start..end, top: 0.0, height: 0.0, baseline: 0.0, left: 0.0, width, glyphs, rtl: base_level % 2 == 1}
+    }
+    
+    // positions glyphs within a box of width, from the left edge
+    fn align(&mut self, align: Align, justify: bool, box_width: f32) {
+        let slack = (box_width - self.width).max(0.0);
+        self.left = match (align, self.rtl) {
+            (Align::Left, _) | (Align::Start | Align::Justify, false) | (Align::End, true) => 0.0,
+            (Align::Center, _) => slack / 2.0,
+            _ => slack,
+        };
+        
+        // justified lines spread the slack over their spaces, but not the ones hanging off the end
+        let gaps = self.glyphs.iter().filter(|g| !g.hanging && is_space(g.cluster.c)).count();
+        let extra = if justify && gaps > 0 { slack / gaps as f32 } else { 0.0 };
+        if extra > 0.0 { (self.left, self.width) = (0.0, box_width); }
+        
+        // in right-to-left lines, what hangs off the end is on the left
+        let hanging: f32 = match self.rtl {
+            true => self.glyphs.iter().take_while(|g| g.hanging).map(|g| g.cluster.advance).sum(),
+            false => 0.0,
+        };
+        let mut x = self.left - hanging;
+        for g in &mut self.glyphs {
+            g.x = x;
+            x += g.cluster.advance;
+            if !g.hanging && is_space(g.cluster.c) { x += extra; }
+        }
+    }
+    
+    // where a caret after the line's last char goes, which is before its newline if it has one
+    fn end(&self) -> (usize,f32) {
+        if let Some(g) = self.glyphs.iter().find(|g| is_newline(g.cluster.c)) {
+            return (g.cluster.index, g.x);
+        }
+        let x = match self.rtl {
+            true => self.glyphs.first().map_or(self.left, |g| g.x),
+            false => self.glyphs.last().map_or(self.left, |g| g.x + g.cluster.advance),
+        };
+        (self.range.end, x)
+    }
+}
+
+// a paragraph broken into lines and positioned, ready to draw
+#[derive(Clone,Debug)]
+pub struct TextLayout {
+    lines: Vec<Line>,
+    styles: Vec<TextStyle>,
+    width: f32,
+} impl TextLayout {
+    pub fn lines(&self) -> &[Line] { &self.lines }
+    
+    // widest line, not counting trailing whitespace
+    pub fn width(&self) -> f32 { self.width }
+    pub fn height(&self) -> f32 { self.lines.last().map_or(0.0, |line| line.top + line.height) }
+    
+    // in whole pixels, rounded up so nothing gets cut off
+    pub fn size(&self) -> Size {
+        Size {width: self.width().ceil() as usize, height: self.height().ceil() as usize}
+    }
+    
+    // byte offset of the caret position nearest to p, relative to the top-left of the layout
+    pub fn hit_test(&self, p: Point) -> usize {
+        let Some(line) = self.lines.iter().find(|line| p.y < line.top + line.height).or(self.lines.last()) else { return 0 };
+        let mut glyphs = line.glyphs.iter().filter(|g| !is_newline(g.cluster.c)).peekable();
+        let first = glyphs.peek().copied();
+        // the caret goes on whichever side of the glyph under p is nearer, which is the end of its text
+        // when that's the right side of left-to-right text or the left side of right-to-left text
+        let (g, right) = match glyphs.find(|g| p.x < g.x + g.cluster.advance) {
+            Some(g) => (g, p.x >= g.x + g.cluster.advance / 2.0),
+            None => match line.glyphs.iter().rfind(|g| !is_newline(g.cluster.c)).or(first) {
+                Some(g) => (g, true),
+                None => return line.end().0,
+            },
+        };
+        match right == (g.cluster.level % 2 == 0) {
+            true => g.cluster.index + g.cluster.len,
+            false => g.cluster.index,
+        }
+    }
+    
+    // top of the caret placed before the char at byte offset index, and how tall it is
+    // indices past the end of the text put it at the end
+    pub fn caret(&self, index: usize) -> (Point,f32) {
+        let Some(line) = self.lines.iter().rev().find(|line| line.range.start <= index).or(self.lines.first()) else {
+            return (Point::new(0.0, 0.0), 0.0);
+        };
+        // on the leading side of the cluster the index is in, or else the trailing side of the one ending there
+        let edge = |glyphs: Vec<&Placed>, left: bool| match left {
+            true => glyphs.iter().map(|g| g.x).fold(f32::INFINITY, f32::min),
+            false => glyphs.iter().map(|g| g.x + g.cluster.advance).fold(f32::NEG_INFINITY, f32::max),
+        };
+        let containing: Vec<&Placed> = line.glyphs.iter()
+            .filter(|g| g.cluster.index <= index && index < g.cluster.index + g.cluster.len)
+            .collect();
+        let ending: Vec<&Placed> = line.glyphs.iter()
+            .filter(|g| g.cluster.len > 0 && g.cluster.index + g.cluster.len == index)
+            .collect();
+        let x = if let Some(g) = containing.first() {
+            let ltr = g.cluster.level % 2 == 0;
+            edge(containing, ltr)
+        } else if let Some(g) = ending.first() {
+            let ltr = g.cluster.level % 2 == 0;
+            edge(ending, !ltr)
+        } else {
+            line.end().1
+        };
+        (Point::new(x, line.top), line.height)
+    }
+    
+    // with the top-left corner of the layout at origin
+    pub fn draw(&self, painter: &mut Painter, origin: Point) {
+        for line in &self.lines {
+            let baseline = origin.y + line.baseline;
+            // runs of the same span and font are drawn together
+            for run in line.glyphs.chunk_by(|a, b| (a.cluster.span, a.cluster.font) == (b.cluster.span, b.cluster.font)) {
+                let style = &self.styles[run[0].cluster.span];
+                let font = style.font(run[0].cluster.font);
+                let glyphs = || run.iter()
+                    .filter(|g| !is_newline(g.cluster.c))
+                    .map(|g| (g.cluster.glyph, Point::new(origin.x + g.x + g.cluster.offset.x, baseline + g.cluster.offset.y)));
+                
+                let smear = style.weight.smear(style.size);
+                let passes = smear.ceil() as usize;
+                for pass in 0..=passes {
+                    let dx = if passes == 0 { 0.0 } else { smear * pass as f32 / passes as f32 };
+                    let glyphs = glyphs().map(|(glyph, p)| (glyph, Point::new(p.x + dx, p.y)));
+                    painter.fill_glyphs(font, glyphs, style.size, &style.color);
+                }
+                
+                // underlines skip whitespace hanging off the end of the line
+                let underlined = run.iter().filter(|g| !g.hanging);
+                let (left, right) = underlined.fold((f32::INFINITY, f32::NEG_INFINITY), |(left, right), g| {
+                    (left.min(g.x), right.max(g.x + g.cluster.advance))
+                });
+                if style.underline && left < right {
+                    let m = style.font.line_metrics(style.size);
+                    let y = baseline + m.underline_offset;
+                    let half = m.underline_thickness.max(1.0) / 2.0;
+                    let rect = Path::rect(
+                        Point::new(origin.x + left, y - half),
+                        Point::new(origin.x + right, y + half),
+                    );
+                    painter.fill_path(&rect, FillRule::NonZero, &style.color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::testfont;
+    
+    // at 10 pixels, most letters are 6 wide, f is 3, spaces are 2.5 and periods 2
+    fn paragraph(text: &str) -> Paragraph {
+        Paragraph::new().with_span(text, TextStyle::new(testfont::font(), 10.0))
+    }
+    
+    fn ranges(layout: &TextLayout) -> Vec<(usize,usize)> {
+        layout.lines().iter().map(|line| (line.range.start, line.range.end)).collect()
+    }
+    
+    #[test]
+    fn wrap() {
+        let layout = paragraph("aaa aaa aaa").with_max_width(40.0).layout();
+        assert_eq!(ranges(&layout), [(0, 8), (8, 11)]);
+        let widths: Vec<f32> = layout.lines().iter().map(|line| line.width).collect();
+        assert_eq!(widths, [38.5, 18.0]);
+        assert_eq!(layout.width(), 38.5);
+        // lines are 11 pixels, with the line gap split above and below the ascent of 8 and descent of 2
+        assert_eq!(layout.lines()[1].top, 11.0);
+        assert_eq!(layout.lines()[1].baseline, 19.5);
+        
+        // a word too long for a line of its own gets broken wherever it has to be
+        assert_eq!(ranges(&paragraph("aaaaaaaaaa").with_max_width(40.0).layout()), [(0, 6), (6, 10)]);
+        assert_eq!(ranges(&paragraph("aaa aaa aaa").with_max_width(40.0).with_wrap(false).layout()), [(0, 11)]);
+    }
+    
+    #[test]
+    fn newlines() {
+        let layout = paragraph("ab\ncd\n").layout();
+        // every line keeps its newline, and the one at the end leaves an empty line after it
+        assert_eq!(ranges(&layout), [(0, 3), (3, 6), (6, 6)]);
+        assert_eq!(layout.lines()[0].width, 12.0);
+        assert_eq!(layout.height(), 33.0);
+        assert_eq!(ranges(&paragraph("").layout()), [(0, 0)]);
+    }
+    
+    #[test]
+    fn ellipsis() {
+        // the ellipsis is three periods, since the font doesn't have one of its own
+        let layout = paragraph("aaaaaaaaaa").with_max_width(30.0).with_wrap(false).with_ellipsis(true).layout();
+        assert_eq!(ranges(&layout), [(0, 4)]);
+        assert_eq!(layout.lines()[0].width, 30.0);
+        
+        let layout = paragraph("aaa aaa aaa").with_max_width(40.0).with_max_lines(1).with_ellipsis(true).layout();
+        assert_eq!(layout.lines().len(), 1);
+        assert!(layout.lines()[0].width <= 40.0);
+        // text that fits is left alone
+        let layout = paragraph("aaa").with_max_width(30.0).with_ellipsis(true).layout();
+        assert_eq!((ranges(&layout), layout.width()), (vec![(0, 3)], 18.0));
+    }
+    
+    #[test]
+    fn justify() {
+        let layout = paragraph("aa aa aa aa\naa aa").with_max_width(30.0).with_align(Align::Justify).layout();
+        assert_eq!(ranges(&layout), [(0, 6), (6, 12), (12, 17)]);
+        let line = &layout.lines()[0];
+        assert_eq!((line.left, line.width), (0.0, 30.0));
+        // the one space takes all the slack, so the second word ends right at the edge
+        let last = line.glyphs.iter().rfind(|g| !g.hanging).unwrap();
+        assert_eq!(last.x + last.cluster.advance, 30.0);
+        // lines ending a paragraph stay as they are
+        for line in &layout.lines()[1..] { assert_eq!((line.left, line.width), (0.0, 26.5)); }
+        
+        let centered = paragraph("aa").with_max_width(30.0).with_align(Align::Center).layout();
+        assert_eq!(centered.lines()[0].left, 9.0);
+    }
+    
+    #[test]
+    fn hit_test_caret() {
+        let text = "ab cd\nef";
+        let layout = paragraph(text).with_max_width(40.0).layout();
+        for index in (0..=text.len()).filter(|i| text.is_char_boundary(*i)) {
+            let (top, height) = layout.caret(index);
+            assert_eq!(layout.hit_test(Point::new(top.x, top.y + height / 2.0)), index, "index {index} at {top:?}");
+        }
+        assert_eq!(layout.caret(3).0, Point::new(14.5, 0.0));
+        assert_eq!(layout.caret(8).0, Point::new(9.0, 11.0));
+        // points past either end of a line go to that end
+        assert_eq!(layout.hit_test(Point::new(-5.0, 5.0)), 0);
+        assert_eq!(layout.hit_test(Point::new(100.0, 5.0)), 5);
+        assert_eq!(layout.hit_test(Point::new(100.0, 100.0)), 8);
+    }
+    
+    #[test]
+    fn fallback() {
+        let fonts = |layout: &TextLayout| -> Vec<(usize,GlyphId)> {
+            layout.lines()[0].glyphs.iter().map(|g| (g.cluster.font, g.cluster.glyph)).collect()
+        };
+        // the font has no hebrew, so alef comes from the fallback
+        let style = TextStyle::new(testfont::font(), 10.0).with_fallback(testfont::fallback());
+        let layout = Paragraph::new().with_span("a\u{5D0}", style).layout();
+        assert_eq!(fonts(&layout), [(0, 2), (1, 1)]);
+        assert_eq!(layout.width(), 12.5);
+        // without one it's drawn as the missing glyph
+        assert_eq!(fonts(&paragraph("a\u{5D0}").layout()), [(0, 2), (0, 0)]);
+    }
+}
@@ -0,0 +1,70 @@
+// big-endian cursor over font data
+// every read returns None instead of running off the end, so parsers can just use ?
+#[derive(Clone,Copy)]
+pub(super) struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+} impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self { Self {data, pos: 0} }
+    
+    pub fn at(data: &'a [u8], pos: usize) -> Self { Self {data, pos} }
+    
+    pub fn pos(&self) -> usize { self.pos }
+    
+    pub fn skip(&mut self, n: usize) -> Option<()> {
+        self.bytes(n).map(|_| ())
+    }
+    
+    pub fn bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+    
+    pub fn u8(&mut self) -> Option<u8> {
+        let b = *self.data.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+    
+    pub fn i8(&mut self) -> Option<i8> { self.u8().map(|b| b as i8) }
+    
+    pub fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+    }
+    
+    pub fn i16(&mut self) -> Option<i16> { self.u16().map(|v| v as i16) }
+    
+    pub fn u24(&mut self) -> Option<u32> {
+        self.bytes(3).map(|b| u32::from_be_bytes([0, b[0], b[1], b[2]]))
+    }
+    
+    pub fn u32(&mut self) -> Option<u32> {
+        self.bytes(4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+    }
+    
+    pub fn i32(&mut self) -> Option<i32> { self.u32().map(|v| v as i32) }
+    
+    // 2.14 fixed point
+    pub fn f2dot14(&mut self) -> Option<f32> { self.i16().map(|v| v as f32 / 16384.0) }
+    
+    pub fn tag(&mut self) -> Option<[u8; 4]> {
+        self.bytes(4).map(|b| [b[0], b[1], b[2], b[3]])
+    }
+    
+    // unsigned int of 1 to 4 bytes, as used by cff
+    pub fn offset(&mut self, size: u8) -> Option<usize> {
+        let v = match size {
+            1 => self.u8()? as u32,
+            2 => self.u16()? as u32,
+            3 => self.u24()?,
+            4 => self.u32()?,
+            _ => return None,
+        };
+        Some(v as usize)
+    }
+}
+
+pub(super) fn u16_at(data: &[u8], pos: usize) -> Option<u16> { Reader::at(data, pos).u16() }
+pub(super) fn i16_at(data: &[u8], pos: usize) -> Option<i16> { Reader::at(data, pos).i16() }
+pub(super) fn u32_at(data: &[u8], pos: usize) -> Option<u32> { Reader::at(data, pos).u32() }
\ No newline at end of file
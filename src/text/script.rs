@@ -0,0 +1,159 @@
+// unicode properties that shaping needs, for the scripts it knows about
+// everything else is treated as left-to-right text that needs no more than ligatures and kerning
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub(super) enum Script {
+    Common, // spaces, digits, punctuation and marks, which take on the script around them
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+    Han,
+    Other,
+} impl Script {
+    pub fn of(c: char) -> Self {
+        use Script::*;
+        match c {
+            _ if is_mark(c) => Common,
+            'A'..='Z' | 'a'..='z' | '\u{AA}' | '\u{BA}' | '\u{C0}'..='\u{24F}' | '\u{1E00}'..='\u{1EFF}' => Latin,
+            '\u{370}'..='\u{3FF}' | '\u{1F00}'..='\u{1FFF}' => Greek,
+            '\u{400}'..='\u{52F}' => Cyrillic,
+            '\u{590}'..='\u{5FF}' | '\u{FB1D}'..='\u{FB4F}' => Hebrew,
+            '\u{600}'..='\u{604}' | '\u{606}'..='\u{60B}' | '\u{60D}'..='\u{61A}' | '\u{61C}'..='\u{61E}' |
+            '\u{620}'..='\u{63F}' | '\u{641}'..='\u{64A}' | '\u{656}'..='\u{66F}' | '\u{671}'..='\u{6DC}' |
+            '\u{6DE}'..='\u{6FF}' | '\u{750}'..='\u{77F}' | '\u{8A0}'..='\u{8FF}' |
+            '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFC}' => Arabic,
+            '\u{900}'..='\u{950}' | '\u{955}'..='\u{963}' | '\u{966}'..='\u{97F}' | '\u{A8E0}'..='\u{A8FF}' => Devanagari,
+            '\u{2E80}'..='\u{2FDF}' | '\u{3005}' | '\u{3007}' | '\u{3021}'..='\u{3029}' | '\u{3038}'..='\u{303B}' |
+            '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}' | '\u{20000}'..='\u{3FFFF}' => Han,
+            '\0'..='\u{24F}' | '\u{2000}'..='\u{2BFF}' | '\u{3000}'..='\u{303F}' | '\u{FE00}'..='\u{FE6F}' |
+            '\u{FF00}'..='\u{FF20}' | '\u{FF3B}'..='\u{FF40}' | '\u{FF5B}'..='\u{FF65}' | '\u{1F000}'..='\u{1FAFF}' => Common,
+            _ => Other,
+        }
+    }
+    
+    // opentype script tags to look for in GSUB and GPOS, best first
+    pub fn tags(self) -> &'static [[u8; 4]] {
+        use Script::*;
+        match self {
+            Latin => const { &[*b"latn"] },
+            Greek => const { &[*b"grek"] },
+            Cyrillic => const { &[*b"cyrl"] },
+            Hebrew => const { &[*b"hebr"] },
+            Arabic => const { &[*b"arab"] },
+            // fonts made for the newer indic shaping model say so with a different tag
+            Devanagari => const { &[*b"dev2", *b"deva"] },
+            Han => const { &[*b"hani"] },
+            Common | Other => &[],
+        }
+    }
+}
+
+// the script of every char of text, with common chars taking on the script of what comes before them,
+// or after them at the start
+pub(super) fn scripts(text: &str) -> Vec<Script> {
+    let mut scripts: Vec<Script> = text.chars().map(Script::of).collect();
+    let first = scripts.iter().copied().find(|s| *s != Script::Common).unwrap_or(Script::Common);
+    let mut previous = first;
+    for script in &mut scripts {
+        if *script == Script::Common { *script = previous; } else { previous = *script; }
+    }
+    scripts
+}
+
+// combining marks, which belong to whatever char they follow
+pub(super) fn is_mark(c: char) -> bool {
+    matches!(c,
+        '\u{300}'..='\u{36F}' | '\u{483}'..='\u{489}' |
+        '\u{591}'..='\u{5BD}' | '\u{5BF}' | '\u{5C1}'..='\u{5C2}' | '\u{5C4}'..='\u{5C5}' | '\u{5C7}' |
+        '\u{610}'..='\u{61A}' | '\u{64B}'..='\u{65F}' | '\u{670}' | '\u{6D6}'..='\u{6DC}' | '\u{6DF}'..='\u{6E4}' |
+        '\u{6E7}'..='\u{6E8}' | '\u{6EA}'..='\u{6ED}' | '\u{8D3}'..='\u{8E1}' | '\u{8E3}'..='\u{8FF}' |
+        '\u{900}'..='\u{903}' | '\u{93A}'..='\u{93C}' | '\u{93E}'..='\u{94F}' | '\u{951}'..='\u{957}' | '\u{962}'..='\u{963}' |
+        '\u{1AB0}'..='\u{1AFF}' | '\u{1DC0}'..='\u{1DFF}' | '\u{200C}'..='\u{200D}' | '\u{20D0}'..='\u{20FF}' |
+        '\u{FE00}'..='\u{FE0F}' | '\u{FE20}'..='\u{FE2F}'
+    )
+}
+
+// formatting chars that aren't drawn unless a font does something special with them
+pub(super) fn is_ignorable(c: char) -> bool {
+    matches!(c,
+        '\u{AD}' | '\u{34F}' | '\u{61C}' | '\u{180B}'..='\u{180F}' | '\u{200B}'..='\u{200F}' |
+        '\u{202A}'..='\u{202E}' | '\u{2060}'..='\u{206F}' | '\u{FE00}'..='\u{FE0F}' | '\u{FEFF}'
+    )
+}
+
+// how an arabic letter connects to its neighbours
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub(super) enum Joining {
+    None,
+    Right,       // only to the letter before it, like alef
+    Dual,        // to letters on both sides, like beh
+    Causing,     // tatweel and zero width joiner, which make their neighbours join to them
+    Transparent, // marks, which letters join through
+}
+
+pub(super) fn joining(c: char) -> Joining {
+    use Joining::*;
+    match c {
+        '\u{640}' | '\u{200D}' => Causing,
+        _ if is_mark(c) => Transparent,
+        '\u{622}'..='\u{625}' | '\u{627}' | '\u{629}' | '\u{62F}'..='\u{632}' | '\u{648}' |
+        '\u{671}'..='\u{673}' | '\u{675}'..='\u{677}' | '\u{688}'..='\u{699}' | '\u{6C0}' | '\u{6C3}'..='\u{6CB}' |
+        '\u{6CD}' | '\u{6CF}' | '\u{6D2}'..='\u{6D3}' | '\u{6D5}' | '\u{6EE}'..='\u{6EF}' |
+        '\u{759}'..='\u{75B}' | '\u{76B}'..='\u{76C}' | '\u{771}' | '\u{773}'..='\u{774}' | '\u{778}'..='\u{779}' => Right,
+        '\u{620}' | '\u{626}' | '\u{628}' | '\u{62A}'..='\u{62E}' | '\u{633}'..='\u{63F}' | '\u{641}'..='\u{647}' |
+        '\u{649}'..='\u{64A}' | '\u{66E}'..='\u{66F}' | '\u{678}'..='\u{687}' | '\u{69A}'..='\u{6BF}' |
+        '\u{6C1}'..='\u{6C2}' | '\u{6CC}' | '\u{6CE}' | '\u{6D0}'..='\u{6D1}' | '\u{6FA}'..='\u{6FC}' | '\u{6FF}' |
+        '\u{750}'..='\u{758}' | '\u{75C}'..='\u{76A}' | '\u{76D}'..='\u{770}' | '\u{772}' | '\u{775}'..='\u{777}' |
+        '\u{77A}'..='\u{77F}' => Dual,
+        _ => None,
+    }
+}
+
+// what part a devanagari char plays in a syllable
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub(super) enum Indic {
+    Consonant,
+    Ra,         // a consonant that turns into reph or rakar next to a halant
+    Vowel,      // independent vowels, which start syllables of their own
+    PreMatra,   // dependent vowel signs drawn before the consonants they follow
+    Matra,      // the rest of them
+    Halant,     // virama, which kills a consonant's inherent vowel
+    Nukta,
+    Modifier,   // anusvara, visarga and such, which end a syllable
+    Zwj,
+    Zwnj,
+    Other,
+}
+
+pub(super) fn indic(c: char) -> Indic {
+    use Indic::*;
+    match c {
+        '\u{930}' => Ra,
+        '\u{915}'..='\u{939}' | '\u{958}'..='\u{95F}' | '\u{978}'..='\u{97F}' => Consonant,
+        '\u{904}'..='\u{914}' | '\u{960}'..='\u{961}' | '\u{972}'..='\u{977}' => Vowel,
+        '\u{93F}' | '\u{94E}' => PreMatra,
+        '\u{93A}'..='\u{93B}' | '\u{93E}' | '\u{940}'..='\u{94C}' | '\u{94F}' | '\u{955}'..='\u{957}' | '\u{962}'..='\u{963}' => Matra,
+        '\u{94D}' => Halant,
+        '\u{93C}' => Nukta,
+        '\u{900}'..='\u{903}' | '\u{951}'..='\u{954}' => Modifier,
+        '\u{200D}' => Zwj,
+        '\u{200C}' => Zwnj,
+        _ => Other,
+    }
+}
+
+// the char drawn in its place in right-to-left text, for chars that point one way or the other
+pub(super) fn mirror(c: char) -> char {
+    const PAIRS: [(char,char); 14] = [
+        ('(', ')'), ('<', '>'), ('[', ']'), ('{', '}'), ('\u{AB}', '\u{BB}'),
+        ('\u{2039}', '\u{203A}'), ('\u{2045}', '\u{2046}'), ('\u{207D}', '\u{207E}'), ('\u{208D}', '\u{208E}'),
+        ('\u{2264}', '\u{2265}'), ('\u{2308}', '\u{2309}'), ('\u{230A}', '\u{230B}'), ('\u{3008}', '\u{3009}'),
+        ('\u{300A}', '\u{300B}'),
+    ];
+    PAIRS.iter()
+        .find_map(|&(a, b)| if c == a { Some(b) } else if c == b { Some(a) } else { None })
+        .unwrap_or(c)
+}
\ No newline at end of file
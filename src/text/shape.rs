@@ -0,0 +1,424 @@
+use std::ops::Range;
+
+use crate::draw::Point;
+
+use super::{Font,GlyphId};
+use super::bidi::{self,Bidi,TextDirection};
+use super::gpos::{self,Attach,Position};
+use super::gsub::Substitution;
+use super::layout::{self,BASE,Gdef,Glyphs,Lookup,MARK};
+use super::linebreak::is_space;
+use super::script::{self,Indic,Joining,Script};
+
+// shaping turns chars into the glyphs a font draws them with: ligatures, the forms arabic letters take depending
+// on their neighbours, devanagari conjuncts, and marks sitting where they belong on the letters they're on
+
+// a glyph placed by shaping
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct ShapedGlyph {
+    pub glyph: GlyphId,
+    pub cluster: usize, // byte offset of the first char of the text this glyph was made from
+    pub advance: f32,   // how far the pen moves after drawing it
+    pub offset: Point,  // where it's drawn relative to the pen, with y pointing down
+}
+
+// which glyphs each feature applies to
+const GLOBAL: u32 = 1 << 0;
+const ISOL: u32 = 1 << 1;
+const FINA: u32 = 1 << 2;
+const MEDI: u32 = 1 << 3;
+const INIT: u32 = 1 << 4;
+const RPHF: u32 = 1 << 5; // devanagari ra and halant that turn into a reph
+const HALF: u32 = 1 << 6; // devanagari consonants before the base, which take half forms
+const POST: u32 = 1 << 7; // devanagari consonants after the base, which take below-base and post-base forms
+
+const GSUB_EXTENSION: u16 = 7;
+const GPOS_EXTENSION: u16 = 9;
+
+// a glyph being shaped, and what shaping knows about it
+#[derive(Clone,Copy,Debug)]
+pub(super) struct Info {
+    pub glyph: GlyphId,
+    pub c: char,          // the char the glyph came from, or the first one for ligatures
+    pub cluster: usize,
+    pub mask: u32,
+    pub class: u16,       // from GDEF, or made up from the char if the font doesn't have it
+    pub ligature: u8,     // id shared by a ligature and marks that were between its components, 0 for neither
+    pub component: u8,    // which component of that ligature a mark went with, from 1
+    pub syllable: u16,
+    pub category: Indic,
+    pub base: bool,       // whether it's the base consonant of its devanagari syllable
+}
+
+// the glyphs a lookup sees, passing over any its flags say to skip
+pub(super) struct View<'a> {
+    pub data: &'a [u8],
+    pub gdef: &'a Gdef,
+    pub lookup: &'a Lookup,
+    pub infos: &'a [Info],
+} impl Glyphs for View<'_> {
+    fn len(&self) -> usize { self.infos.len() }
+    fn glyph(&self, i: usize) -> GlyphId { self.infos[i].glyph }
+    fn skipped(&self, i: usize) -> bool {
+        let info = &self.infos[i];
+        self.gdef.skips(self.data, self.lookup, info.glyph, info.class)
+    }
+}
+
+type Stage = Vec<(u16,u32,Lookup)>;
+
+// which lookups a font applies to a script, worked out once per font and script
+#[derive(Debug,Default)]
+pub(super) struct Plan {
+    basic: Vec<Stage>,        // substitutions, each stage applied to the whole run before the next
+    presentation: Vec<Stage>, // more of them, which devanagari applies after reordering
+    positioning: Stage,
+    kerning: bool,            // whether positioning kerns, or leaves it to the kern table
+} impl Plan {
+    pub fn new(font: &Font, script: Script) -> Self {
+        let data = font.data();
+        let tags = script.tags();
+        let stages = |table: Option<usize>, stages: &[&[([u8; 4],u32)]], extension: u16| -> Vec<Stage> {
+            let Some(table) = table else { return Vec::new() };
+            stages.iter()
+                .map(|features| layout::lookups(data, table, tags, features, extension))
+                .filter(|stage| !stage.is_empty())
+                .collect()
+        };
+        let global = |tag: &[u8; 4]| (*tag, GLOBAL);
+        
+        let (basic, presentation) = match script {
+            Script::Arabic => (stages(font.gsub(), &[
+                &[global(b"ccmp"), global(b"locl")],
+                &[(*b"isol", ISOL)],
+                &[(*b"fina", FINA)],
+                &[(*b"medi", MEDI)],
+                &[(*b"init", INIT)],
+                &[global(b"rlig")],
+                &[global(b"calt")],
+                &[global(b"liga"), global(b"clig"), global(b"mset")],
+            ], GSUB_EXTENSION), Vec::new()),
+            Script::Devanagari => (stages(font.gsub(), &[
+                &[global(b"locl"), global(b"ccmp")],
+                &[global(b"nukt")],
+                &[global(b"akhn")],
+                &[(*b"rphf", RPHF)],
+                &[global(b"rkrf")],
+                &[(*b"blwf", POST)],
+                &[(*b"abvf", POST)],
+                &[(*b"half", HALF)],
+                &[(*b"pstf", POST)],
+                &[global(b"vatu")],
+                &[global(b"cjct")],
+            ], GSUB_EXTENSION), stages(font.gsub(), &[
+                &[global(b"pres"), global(b"abvs"), global(b"blws"), global(b"psts"), global(b"haln")],
+                &[global(b"calt"), global(b"clig")],
+            ], GSUB_EXTENSION)),
+            _ => (stages(font.gsub(), &[
+                &[global(b"ccmp"), global(b"locl")],
+                &[global(b"rlig"), global(b"calt"), global(b"liga"), global(b"clig")],
+            ], GSUB_EXTENSION), Vec::new()),
+        };
+        
+        let positioning = stages(font.gpos(), &[&[
+            global(b"abvm"), global(b"blwm"), global(b"curs"), global(b"dist"),
+            global(b"kern"), global(b"mark"), global(b"mkmk"),
+        ]], GPOS_EXTENSION).pop().unwrap_or_default();
+        let kerning = !stages(font.gpos(), &[&[global(b"kern")]], GPOS_EXTENSION).is_empty();
+        Self {basic, presentation, positioning, kerning}
+    }
+}
+
+// shapes the chars in range of text, which are all in one script and go one way, into glyphs in logical order
+// text outside of range is left alone
+pub(super) fn shape(font: &Font, text: &str, range: Range<usize>, script: Script, rtl: bool, size: f32) -> Vec<ShapedGlyph> {
+    let data = font.data();
+    let gdef = font.gdef();
+    let plan = font.plan(script);
+    let class = |glyph: GlyphId, c: char| {
+        if gdef.has_classes() { gdef.class(data, glyph) } else if script::is_mark(c) { MARK } else { BASE }
+    };
+    
+    let mut infos: Vec<Info> = text[range.clone()].char_indices().map(|(i, c)| {
+        // right-to-left text uses the mirror image of brackets and such, if the font has it
+        let c = if rtl && font.has_glyph(script::mirror(c)) { script::mirror(c) } else { c };
+        // spaces the font doesn't have, like tabs, are drawn as regular spaces
+        let glyph = font.glyph_index(c)
+            .or_else(|| is_space(c).then(|| font.glyph_index(' ')).flatten())
+            .unwrap_or(0);
+        Info {
+            glyph, c,
+            cluster: range.start + i,
+            mask: GLOBAL,
+            class: class(glyph, c),
+            ligature: 0,
+            component: 0,
+            syllable: 0,
+            category: script::indic(c),
+            base: false,
+        }
+    }).collect();
+    // marks go with the char they're on
+    for i in 1..infos.len() {
+        if script::is_mark(infos[i].c) { infos[i].cluster = infos[i - 1].cluster; }
+    }
+    
+    match script {
+        Script::Arabic => join(&mut infos),
+        Script::Devanagari => syllables(&mut infos),
+        _ => {},
+    }
+    let mut substitution = Substitution::new(font);
+    for stage in &plan.basic { substitution.apply(stage, &mut infos); }
+    if script == Script::Devanagari { final_reorder(font, &mut infos); }
+    for stage in &plan.presentation { substitution.apply(stage, &mut infos); }
+    
+    // invisible formatting chars the font didn't do anything with aren't drawn at all
+    infos.retain(|info| !script::is_ignorable(info.c) || (font.glyph_index(info.c) != Some(info.glyph) && info.glyph != 0));
+    // clusters only ever go forward, so reordered glyphs share one cluster, and the first covers the start of the run
+    for i in (1..infos.len()).rev() {
+        infos[i - 1].cluster = infos[i - 1].cluster.min(infos[i].cluster);
+    }
+    if let Some(first) = infos.first_mut() { first.cluster = range.start; }
+    
+    let mut positions: Vec<Position> = infos.iter().map(|info| Position {
+        advance: font.advance_units(info.glyph) as i32,
+        ..Position::default()
+    }).collect();
+    // devanagari's marks take up room, other scripts' sit on what comes before them
+    if script != Script::Devanagari {
+        for (info, position) in infos.iter().zip(&mut positions) {
+            if info.class == MARK { position.advance = 0; }
+        }
+    }
+    gpos::apply(font, &plan.positioning, &infos, &mut positions, rtl);
+    if !plan.kerning {
+        let letters: Vec<usize> = (0..infos.len()).filter(|i| infos[*i].class != MARK).collect();
+        for pair in letters.windows(2) {
+            // the kern table goes by which glyph is on the left
+            let (left, right) = if rtl { (pair[1], pair[0]) } else { (pair[0], pair[1]) };
+            positions[left].advance += font.kerning_units(infos[left].glyph, infos[right].glyph) as i32;
+        }
+    }
+    place(font, &infos, &positions, rtl, size)
+}
+
+// text shaped into glyphs in the order they're drawn, from left to right, as one line
+pub(super) fn shape_text(font: &Font, text: &str, size: f32) -> Vec<ShapedGlyph> {
+    let bidi = Bidi::new(text, TextDirection::Auto);
+    let mut levels = bidi.levels.clone();
+    bidi::line_levels(text.chars(), &mut levels, bidi.base_level(0));
+    let scripts = script::scripts(text);
+    let chars: Vec<(usize,char,(u8,Script))> = text.char_indices()
+        .zip(levels.into_iter().zip(scripts))
+        .map(|((i, c), key)| (i, c, key))
+        .collect();
+    
+    let mut glyphs: Vec<(ShapedGlyph,u8)> = Vec::new();
+    for run in chars.chunk_by(|a, b| a.2 == b.2) {
+        let (start, (level, script)) = (run[0].0, run[0].2);
+        let (last, c, _) = run[run.len() - 1];
+        let shaped = shape(font, text, start..last + c.len_utf8(), script, level % 2 == 1, size);
+        glyphs.extend(shaped.into_iter().map(|g| (g, level)));
+    }
+    let levels: Vec<u8> = glyphs.iter().map(|(_, level)| *level).collect();
+    bidi::visual_order(&levels).into_iter().map(|i| glyphs[i].0).collect()
+}
+
+// which form each arabic letter takes, depending on whether it joins the letters on either side
+fn join(infos: &mut [Info]) {
+    use Joining::*;
+    
+    let mut previous: Option<(usize,Joining)> = Option::None;
+    for i in 0..infos.len() {
+        let joining = script::joining(infos[i].c);
+        if joining == Transparent { continue; }
+        let joins = previous.is_some_and(|(_, j)| matches!(j, Dual | Causing)) && matches!(joining, Right | Dual | Causing);
+        if joins && let Some((p, _)) = previous {
+            let mask = &mut infos[p].mask;
+            if *mask & ISOL != 0 { *mask = *mask & !ISOL | INIT; }
+            if *mask & FINA != 0 { *mask = *mask & !FINA | MEDI; }
+        }
+        if matches!(joining, Right | Dual) {
+            infos[i].mask |= if joins { FINA } else { ISOL };
+        }
+        previous = Some((i, joining));
+    }
+}
+
+// splits devanagari into syllables, works out which parts of each take which forms,
+// and moves vowel signs that are drawn before their consonants to the front
+fn syllables(infos: &mut [Info]) {
+    use Indic::*;
+    
+    let category = |infos: &[Info], k: usize| infos.get(k).map(|info| info.category);
+    let mut start = 0;
+    let mut syllable = 1;
+    while start < infos.len() {
+        // consonants joined by halants, then whatever vowel signs and modifiers follow
+        let mut end = start;
+        match infos[start].category {
+            Consonant | Ra => loop {
+                end += 1;
+                if category(infos, end) == Some(Nukta) { end += 1; }
+                if category(infos, end) != Some(Halant) { break; }
+                end += 1;
+                if matches!(category(infos, end), Some(Zwj | Zwnj)) { end += 1; }
+                if !matches!(category(infos, end), Some(Consonant | Ra)) { break; }
+            },
+            Vowel => {
+                end += 1;
+                if category(infos, end) == Some(Nukta) { end += 1; }
+            },
+            _ => end += 1,
+        }
+        while matches!(category(infos, end), Some(PreMatra | Matra | Nukta | Halant | Modifier | Zwj | Zwnj)) { end += 1; }
+        
+        let infos = &mut infos[start..end];
+        for info in infos.iter_mut() { info.syllable = syllable; }
+        if matches!(infos[0].category, Consonant | Ra) { initial_reorder(infos); }
+        syllable = syllable.wrapping_add(1);
+        start = end;
+    }
+}
+
+fn initial_reorder(syllable: &mut [Info]) {
+    use Indic::*;
+    
+    let consonant = |info: &Info| matches!(info.category, Consonant | Ra);
+    // ra and halant before another consonant become a reph above the syllable
+    let reph = syllable.len() >= 3 && syllable[0].category == Ra && syllable[1].category == Halant && consonant(&syllable[2]);
+    let first = if reph { 2 } else { 0 };
+    let consonants: Vec<usize> = (first..syllable.len()).filter(|k| consonant(&syllable[*k])).collect();
+    let Some(&(mut base)) = consonants.last() else { return };
+    // a final ra after a halant becomes a rakar under the consonant before it, which is then the base
+    if consonants.len() >= 2 && syllable[base].category == Ra && syllable[base - 1].category == Halant {
+        base = consonants[consonants.len() - 2];
+    }
+    
+    if reph {
+        syllable[0].mask |= RPHF;
+        syllable[1].mask |= RPHF;
+    }
+    for info in &mut syllable[first..base] { info.mask |= HALF; }
+    for info in &mut syllable[base + 1..] { info.mask |= POST; }
+    syllable[base].base = true;
+    
+    if let Some(matra) = syllable.iter().position(|info| info.category == PreMatra) && matra > first {
+        syllable[first..=matra].rotate_right(1);
+    }
+    let cluster = syllable.iter().map(|info| info.cluster).min().unwrap_or(0);
+    for info in syllable.iter_mut() { info.cluster = cluster; }
+}
+
+// after the basic forms are in, a formed reph moves to the end of its syllable,
+// and a pre-base matra moves past any halant that didn't form a half form with its consonant
+fn final_reorder(font: &Font, infos: &mut [Info]) {
+    use Indic::*;
+    
+    let ra = font.glyph_index('\u{930}');
+    for syllable in infos.chunk_by_mut(|a, b| a.syllable == b.syllable) {
+        if let Some(matra) = syllable.iter().position(|info| info.category == PreMatra)
+            && let Some(base) = syllable.iter().position(|info| info.base)
+            && let Some(halant) = (matra + 1..base).rev().find(|k| syllable[*k].category == Halant)
+        {
+            syllable[matra..=halant].rotate_left(1);
+        }
+        
+        let first = &syllable[0];
+        let formed = first.category == Ra && first.mask & RPHF != 0 && Some(first.glyph) != ra
+            && syllable.get(1).is_none_or(|info| info.category != Halant);
+        if formed {
+            let end = syllable.iter().rposition(|info| info.category != Modifier).unwrap_or(0);
+            syllable[..=end].rotate_left(1);
+        }
+    }
+}
+
+// glyphs with their attachments resolved, scaled to size
+fn place(font: &Font, infos: &[Info], positions: &[Position], rtl: bool, size: f32) -> Vec<ShapedGlyph> {
+    let scale = size / font.units_per_em() as f32;
+    // where the pen is for each glyph, going left to right, which is backwards through right-to-left text
+    let n = positions.len();
+    let mut pen = vec![0; n];
+    let mut x = 0;
+    for k in 0..n {
+        let i = if rtl { n - 1 - k } else { k };
+        pen[i] = x;
+        x += positions[i].advance;
+    }
+    
+    let mut placed = vec![None; positions.len()];
+    infos.iter().enumerate().map(|(i, info)| {
+        let (x, y) = resolve(i, positions, &pen, &mut placed, 0);
+        ShapedGlyph {
+            glyph: info.glyph,
+            cluster: info.cluster,
+            advance: positions[i].advance as f32 * scale,
+            offset: Point::new((x - pen[i]) as f32 * scale, -y as f32 * scale),
+        }
+    }).collect()
+}
+
+// where glyph i is drawn, in font units from the start of the run, following attachments back to what they're on
+fn resolve(i: usize, positions: &[Position], pen: &[i32], placed: &mut [Option<(i32,i32)>], depth: u8) -> (i32,i32) {
+    const MAX_DEPTH: u8 = 16;
+    if let Some(at) = placed[i] { return at; }
+    let p = &positions[i];
+    let at = match p.attach {
+        Some((parent, Attach::Mark)) if depth < MAX_DEPTH => {
+            let (x, y) = resolve(parent, positions, pen, placed, depth + 1);
+            (x + p.x, y + p.y)
+        },
+        Some((parent, Attach::Cursive)) if depth < MAX_DEPTH => {
+            let (_, y) = resolve(parent, positions, pen, placed, depth + 1);
+            (pen[i] + p.x, y + p.y)
+        },
+        _ => (pen[i] + p.x, p.y),
+    };
+    placed[i] = Some(at);
+    at
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::testfont;
+    
+    fn glyphs(text: &str) -> Vec<(u16,usize)> {
+        testfont::font().shape(text, 10.0).iter().map(|g| (g.glyph, g.cluster)).collect()
+    }
+    
+    #[test]
+    fn ligatures() {
+        assert_eq!(glyphs("fi"), [(6, 0)]);
+        assert_eq!(glyphs("afix"), [(2, 0), (6, 1), (2, 3)]);
+        assert_eq!(glyphs("f i"), [(4, 0), (1, 1), (5, 2)]);
+        let shaped = testfont::font().shape("fi", 10.0);
+        assert_eq!(shaped[0].advance, 5.0);
+    }
+    
+    #[test]
+    fn kerning() {
+        let advances: Vec<f32> = testfont::font().shape("AVA", 10.0).iter().map(|g| g.advance).collect();
+        assert_eq!(advances, [5.2, 6.0, 6.0]);
+    }
+    
+    #[test]
+    fn arabic_forms() {
+        // shaped text comes out left to right, so the first letter, in its initial form, is last
+        assert_eq!(glyphs("\u{628}"), [(10, 0)]);
+        assert_eq!(glyphs("\u{628}\u{628}"), [(13, 2), (11, 0)]);
+        assert_eq!(glyphs("\u{628}\u{628}\u{628}"), [(13, 4), (12, 2), (11, 0)]);
+        // a space breaks the join
+        assert_eq!(glyphs("\u{628}\u{628} \u{628}"), [(10, 5), (1, 4), (13, 2), (11, 0)]);
+    }
+    
+    #[test]
+    fn right_to_left() {
+        // brackets in right-to-left text are mirrored, so they still face what they enclose
+        assert_eq!(glyphs("(\u{628})"), [(14, 3), (10, 1), (15, 0)]);
+        // numbers keep reading left to right
+        assert_eq!(glyphs("\u{628} 12"), [(8, 3), (8, 4), (1, 2), (10, 0)]);
+        assert_eq!(glyphs("a \u{628}\u{628} b"), [(2, 0), (1, 1), (13, 4), (11, 2), (1, 6), (2, 7)]);
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use super::Font;
+
+// tiny truetype fonts for tests, 1000 units per em, with an ascent of 800, a descent of 200 and a line gap of 100
+// FONT's glyphs are all rectangles, apart from V's triangle:
+//   0 .notdef, 500 wide        6 the fi ligature, 500       11 beh initial, 400
+//   1 space, 250               7 hyphen, 300                12 beh medial, 300
+//   2 every other letter, 600  8 every digit, 500           13 beh final, 500
+//   3 V, 600                   9 period, 200                14 (, 300
+//   4 f, 300                   10 arabic beh, 700           15 ), 300
+//   5 i, 200
+// A before V kerns by -80 in its kern table, and its GSUB has liga for latin, and init, medi and fina for arabic
+// FALLBACK only has hebrew alef, glyph 1, which is 650 wide
+pub(super) fn font() -> Arc<Font> { Arc::new(Font::from_bytes(FONT.to_vec()).unwrap()) }
+pub(super) fn fallback() -> Arc<Font> { Arc::new(Font::from_bytes(FALLBACK.to_vec()).unwrap()) }
+
+pub(super) const FONT: &[u8] = &[
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x47, 0x53, 0x55, 0x42, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xAC,
+    0x00, 0x00, 0x00, 0xE4, 0x63, 0x6D, 0x61, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x90, 0x00, 0x00, 0x00, 0xE4, 0x67, 0x6C, 0x79, 0x66,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x74, 0x00, 0x00, 0x01, 0xFA, 0x68, 0x65, 0x61, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0x70,
+    0x00, 0x00, 0x00, 0x36, 0x68, 0x68, 0x65, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xA8, 0x00, 0x00, 0x00, 0x24, 0x68, 0x6D, 0x74, 0x78,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x04, 0xCC, 0x00, 0x00, 0x00, 0x40, 0x6B, 0x65, 0x72, 0x6E, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x0C,
+    0x00, 0x00, 0x00, 0x18, 0x6C, 0x6F, 0x63, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x24, 0x00, 0x00, 0x00, 0x44, 0x6D, 0x61, 0x78, 0x70,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x68, 0x00, 0x00, 0x00, 0x06, 0x70, 0x6F, 0x73, 0x74, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0x70,
+    0x00, 0x00, 0x00, 0x20, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0A, 0x00, 0x46, 0x00, 0x78, 0x00, 0x03, 0x44, 0x46, 0x4C, 0x54, 0x00, 0x14, 0x61, 0x72,
+    0x61, 0x62, 0x00, 0x20, 0x6C, 0x61, 0x74, 0x6E, 0x00, 0x30, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04,
+    0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x03, 0x00, 0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x04, 0x6C, 0x69, 0x67, 0x61, 0x00, 0x1A, 0x69, 0x6E, 0x69, 0x74, 0x00, 0x20, 0x6D, 0x65, 0x64, 0x69, 0x00, 0x26, 0x66, 0x69,
+    0x6E, 0x61, 0x00, 0x2C, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x02, 0x00, 0x00,
+    0x00, 0x01, 0x00, 0x03, 0x00, 0x04, 0x00, 0x0A, 0x00, 0x2A, 0x00, 0x40, 0x00, 0x56, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x01,
+    0x00, 0x12, 0x00, 0x01, 0x00, 0x08, 0x00, 0x01, 0x00, 0x04, 0x00, 0x06, 0x00, 0x02, 0x00, 0x05, 0x00, 0x01, 0x00, 0x01, 0x00, 0x04, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x02, 0x00, 0x08, 0x00, 0x01, 0x00, 0x0B, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0A, 0x00, 0x01, 0x00, 0x00,
+    0x00, 0x01, 0x00, 0x08, 0x00, 0x02, 0x00, 0x08, 0x00, 0x01, 0x00, 0x0C, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0A, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x08, 0x00, 0x02, 0x00, 0x08, 0x00, 0x01, 0x00, 0x0D, 0x00, 0x01, 0x00, 0x01, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x01, 0x00, 0x03, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x0C, 0x00, 0x04, 0x00, 0xD8, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x20, 0x00, 0x29, 0x00, 0x2E,
+    0x00, 0x39, 0x00, 0x5A, 0x00, 0x7A, 0x06, 0x28, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x20, 0x00, 0x28, 0x00, 0x2D, 0x00, 0x30, 0x00, 0x41, 0x00, 0x61,
+    0x06, 0x28, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x10, 0x00, 0x10,
+    0x00, 0x12, 0x00, 0x14, 0x00, 0x26, 0x00, 0x58, 0x00, 0x8A, 0x00, 0x00, 0x00, 0x01, 0x00, 0x0E, 0x00, 0x0F, 0x00, 0x07, 0x00, 0x09, 0x00, 0x08,
+    0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x08, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02,
+    0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02,
+    0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x03, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02,
+    0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x04, 0x00, 0x02, 0x00, 0x02, 0x00, 0x05, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02,
+    0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02, 0x00, 0x02,
+    0x00, 0x02, 0x00, 0x0A, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x01, 0xC2, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32,
+    0x00, 0x00, 0x01, 0x90, 0x00, 0x00, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x64, 0x00, 0x00, 0x01, 0xF4, 0x02, 0xBC,
+    0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x64, 0x00, 0x00, 0x01, 0x90, 0x00, 0x00, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44,
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x02, 0x58, 0x02, 0xBC, 0x00, 0x02, 0x00, 0x00, 0x01, 0x01, 0x01, 0x00, 0x00, 0x02, 0x58, 0xFE, 0xD4, 0x02,
+    0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0xFA, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01,
+    0x00, 0x32, 0x00, 0x00, 0x00, 0xC8, 0x00, 0x00, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0x96,
+    0x01, 0xF4, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x01, 0xF4, 0x00, 0x00,
+    0xFE, 0x0C, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x01, 0xC2, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00,
+    0x01, 0x90, 0x00, 0x00, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x32, 0x00, 0xFA, 0x00, 0xFA, 0x01, 0x5E, 0x00, 0x03,
+    0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0xC8, 0x00, 0x00, 0x00, 0xFA, 0x00, 0x64, 0x00, 0x00, 0xFF, 0x9C, 0x00, 0x01,
+    0x00, 0x32, 0x00, 0x00, 0x01, 0xC2, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x01, 0x90, 0x00, 0x00,
+    0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0x96, 0x00, 0x64, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01,
+    0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, 0x00, 0x00, 0xFF, 0x9C, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00,
+    0x02, 0x8A, 0x01, 0x2C, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x02, 0x58, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2C,
+    0x00, 0x00, 0xFE, 0xD4, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x90, 0x01, 0x2C, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00,
+    0x00, 0x00, 0x01, 0x90, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0xFE, 0xD4, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2C, 0x01, 0x2C,
+    0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0xFE, 0xD4,
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0xC2, 0x01, 0x2C, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0xC2,
+    0x00, 0x00, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0xFE, 0xD4, 0x00, 0x01, 0x00, 0x64, 0x00, 0x00, 0x00, 0xFA, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00,
+    0x01, 0x01, 0x01, 0x01, 0x00, 0x64, 0x00, 0x00, 0x00, 0x96, 0x00, 0x00, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x32,
+    0x00, 0x00, 0x00, 0xC8, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x00, 0x96, 0x00, 0x00, 0x00, 0x00,
+    0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x5F, 0x0F, 0x3C, 0xF5,
+    0x00, 0x00, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x38,
+    0x03, 0xE8, 0x03, 0x20, 0x00, 0x00, 0x00, 0x08, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x03, 0x20, 0xFF, 0x38,
+    0x00, 0x64, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x10, 0x01, 0xF4, 0x00, 0x00, 0x00, 0xFA, 0x00, 0x00, 0x02, 0x58, 0x00, 0x00, 0x02, 0x58, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00,
+    0x00, 0xC8, 0x00, 0x00, 0x01, 0xF4, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0x01, 0xF4, 0x00, 0x00, 0x00, 0xC8, 0x00, 0x00, 0x02, 0xBC, 0x00, 0x00,
+    0x01, 0x90, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0x01, 0xF4, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0x01, 0x2C, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x00, 0x00, 0x14, 0x00, 0x01, 0x00, 0x01, 0x00, 0x06, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x03, 0xFF, 0xB0, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x44, 0x00, 0x00, 0x00, 0x62, 0x00, 0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0xA6,
+    0x00, 0x00, 0x00, 0xC8, 0x00, 0x00, 0x00, 0xEA, 0x00, 0x00, 0x01, 0x0C, 0x00, 0x00, 0x01, 0x2E, 0x00, 0x00, 0x01, 0x50, 0x00, 0x00, 0x01, 0x72,
+    0x00, 0x00, 0x01, 0x94, 0x00, 0x00, 0x01, 0xB6, 0x00, 0x00, 0x01, 0xD8, 0x00, 0x00, 0x01, 0xFA, 0x00, 0x00, 0x50, 0x00, 0x00, 0x10, 0x00, 0x00,
+    0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x9C, 0x00, 0x32, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
+
+pub(super) const FALLBACK: &[u8] = &[
+    0x00, 0x01, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x63, 0x6D, 0x61, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x8C,
+    0x00, 0x00, 0x00, 0x2E, 0x67, 0x6C, 0x79, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xBC, 0x00, 0x00, 0x00, 0x44, 0x68, 0x65, 0x61, 0x64,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x36, 0x68, 0x68, 0x65, 0x61, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x38,
+    0x00, 0x00, 0x00, 0x24, 0x68, 0x6D, 0x74, 0x78, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x5C, 0x00, 0x00, 0x00, 0x08, 0x6C, 0x6F, 0x63, 0x61,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x64, 0x00, 0x00, 0x00, 0x0C, 0x6D, 0x61, 0x78, 0x70, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x70,
+    0x00, 0x00, 0x00, 0x06, 0x70, 0x6F, 0x73, 0x74, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x78, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x01,
+    0x00, 0x03, 0x00, 0x01, 0x00, 0x00, 0x00, 0x0C, 0x00, 0x04, 0x00, 0x22, 0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x05, 0xD0,
+    0xFF, 0xFF, 0x00, 0x00, 0x05, 0xD0, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x01, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x32,
+    0x00, 0x00, 0x01, 0xC2, 0x02, 0xBC, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01, 0x00, 0x32, 0x00, 0x00, 0x01, 0x90, 0x00, 0x00, 0x00, 0x00,
+    0x02, 0xBC, 0x00, 0x00, 0xFD, 0x44, 0x00, 0x01, 0x00, 0x32, 0x00, 0x00, 0x02, 0x58, 0x02, 0x58, 0x00, 0x03, 0x00, 0x00, 0x01, 0x01, 0x01, 0x01,
+    0x00, 0x32, 0x00, 0x00, 0x02, 0x26, 0x00, 0x00, 0x00, 0x00, 0x02, 0x58, 0x00, 0x00, 0xFD, 0xA8, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x5F, 0x0F, 0x3C, 0xF5, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0x38, 0x03, 0xE8, 0x03, 0x20, 0x00, 0x00, 0x00, 0x08, 0x00, 0x02, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x01, 0x00, 0x00, 0x03, 0x20, 0xFF, 0x38, 0x00, 0x64, 0x03, 0xE8, 0x00, 0x00, 0x00, 0x00, 0x03, 0xE8, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x01, 0xF4, 0x00, 0x00, 0x02, 0x8A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, 0x44, 0x00, 0x00, 0x50, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xFF, 0x9C, 0x00, 0x32, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+];
@@ -1,19 +1,35 @@
+mod damage;
+mod frames;
+mod tiles;
 mod windows;
 
 #[cfg(feature="windows")]
 use windows as win_sys;
 
+pub use damage::Damage;
+pub use frames::FrameStats;
+pub use tiles::Tiling;
 pub use win_sys::init;
 
+use frames::{FALLBACK_FPS,Frames};
 use win_sys::{InternalSurfaceData,WinHandle};
 
+use std::cell::{Cell,RefCell};
 use std::marker::PhantomPinned;
+use std::ops::{Deref,DerefMut};
 use std::mem::MaybeUninit;
 use std::pin::Pin;
+use std::time::{Duration,Instant};
 
 use crate::{
-    guicell::GuiCell,
-    {CommonEvent,Direction,Distance,Partition,Partitionable,Pixel,PixelIdx,PixelIndexSlice,Size},
+    blend::BlendMode,
+    display::DisplayList,
+    guicell::{self,GuiCell},
+    image::Image,
+    simd,
+    testing::{RecordedEvent,Recording},
+    text::BitmapFont,
+    {CommonEvent,Direction,Distance,MousePos,Partition,Partitionable,Pixel,PixelIdx,PixelIndexSlice,Size},
 };
 
 // assert internals are implemented correctly
@@ -47,6 +63,21 @@ trait InternalWindowTrait: Sized + Drop {
     fn height(&self) -> usize;
     fn size(&self) -> Size;
     
+    // of the client area; the system may not give exactly what's asked for
+    // resizes that happen right away dispatch their own Resize
+    fn set_size(&self, size: Size);
+    
+    // ask the system for a Draw that covers area, in client coordinates
+    fn invalidate(&self, area: PixelIndexSlice);
+    
+    // have the system call Window::run_frame every interval, or as near as it can, until stopped
+    // starting again while started only changes the interval
+    fn start_frames(&self, interval: Duration);
+    fn stop_frames(&self);
+    
+    // in Hz, of the display the window's on, if the system knows it
+    fn refresh_rate(&self) -> Option<f32>;
+    
     // poll system for events
     // do not do event handling here or call handle_event directly
     fn check_events(&self);
@@ -57,17 +88,47 @@ type EventHandler = &'static dyn Fn(&mut dyn GuiCell, &CommonEvent) -> bool;
 pub struct Window {
     handle: Option<WinHandle>,
     event_handler: EventHandler,
-    root: Box<dyn GuiCell>,
+    root: Root,
     surface: Surface,
+    clock: Clock,
+    recording: RefCell<Option<Recording>>,
+    damage: RefCell<Damage>,
+    frames: RefCell<Frames>,
+    retained: Cell<bool>,
+    display: RefCell<Option<DisplayList>>, // the last frame recorded, while retained
     _pin: PhantomPinned,
 } impl Window {
     // returns result on whether the window was successfully created
     // Windows must be pinned for window system
+    // the backend has nothing to say about why creating one failed, hence the ()
+    #[allow(clippy::result_unit_err)]
     pub fn new(
         name: &str, 
         size: Size, 
         root: Box<dyn GuiCell>, 
         event_handler: EventHandler,
+    ) -> Result<Pin<Box<Self>>,()> {
+        Self::create(name, size, Root::Local(root), event_handler)
+    }
+    
+    // a window that redraws in tiles, on several threads at once
+    // root is shared between the threads while drawing, so it has to be Sync
+    #[allow(clippy::result_unit_err)]
+    pub fn new_tiled(
+        name: &str,
+        size: Size,
+        root: Box<dyn GuiCell + Sync>,
+        event_handler: EventHandler,
+        tiling: Tiling,
+    ) -> Result<Pin<Box<Self>>,()> {
+        Self::create(name, size, Root::Tiled(root, tiling), event_handler)
+    }
+    
+    fn create(
+        name: &str,
+        size: Size,
+        root: Root,
+        event_handler: EventHandler,
     ) -> Result<Pin<Box<Self>>,()> {
         // DO NOT MOVE SELF
         let mut boxed: Box<MaybeUninit<Self>> = Box::<Self>::new_uninit();
@@ -82,16 +143,155 @@ pub struct Window {
             (&raw mut (*self_ptr).event_handler).write(event_handler);
             (&raw mut (*self_ptr).root         ).write(root);
             (&raw mut (*self_ptr).surface      ).write(Surface::new((*self_ptr).handle.as_ref()));
+            (&raw mut (*self_ptr).clock        ).write(Clock::Real(Instant::now()));
+            (&raw mut (*self_ptr).recording    ).write(RefCell::new(None));
+            (&raw mut (*self_ptr).damage       ).write(RefCell::new(Damage::new()));
+            (&raw mut (*self_ptr).frames       ).write(RefCell::new(Frames::default()));
+            (&raw mut (*self_ptr).retained     ).write(Cell::new(false));
+            (&raw mut (*self_ptr).display      ).write(RefCell::new(None));
             (&raw mut (*self_ptr)._pin         ).write(PhantomPinned);
         }
         
-        Ok(Box::into_pin(unsafe {boxed.assume_init()}))
+        let window = unsafe {boxed.assume_init()};
+        // nothing's been drawn yet
+        window.invalidate_all();
+        Ok(Box::into_pin(window))
     }
     
     pub fn handle(&self) -> Option<&WinHandle> { self.handle.as_ref() }
     
+    // None for windows that draw on one thread
+    pub fn tiling(&self) -> Option<Tiling> {
+        match self.root {
+            Root::Local(_) => None,
+            Root::Tiled(_, tiling) => Some(tiling),
+        }
+    }
+    
     pub fn is_closed(&self) -> bool { self.handle.is_none() }
     
+    pub fn root(&self) -> &dyn GuiCell { &*self.root }
+    pub fn root_mut(&mut self) -> &mut dyn GuiCell { &mut *self.root }
+    
+    pub fn size(&self) -> Size { self.surface.size() }
+    pub fn set_size(&self, size: Size) {
+        if let Some(handle) = self.handle() { handle.set_size(size); }
+    }
+    
+    // time since the window was created, or as far as a virtual clock has been advanced
+    pub fn now(&self) -> Duration { self.clock.now() }
+    pub fn clock(&self) -> Clock { self.clock }
+    pub fn set_clock(&mut self, clock: Clock) { self.clock = clock; }
+    
+    // keeps every event handled from here on, with when it happened and the window's size, until stopped
+    // starting again throws away what was recorded so far
+    pub fn start_recording(&self) { *self.recording.borrow_mut() = Some(Recording::new()); }
+    pub fn stop_recording(&self) -> Option<Recording> { self.recording.borrow_mut().take() }
+    pub fn is_recording(&self) -> bool { self.recording.borrow().is_some() }
+    
+    // marks area as needing a redraw on the next frame
+    // cells handling an event should use guicell::invalidate instead, which knows where they are
+    pub fn invalidate(&self, area: PixelIndexSlice) {
+        if self.is_closed() { return; }
+        let area = area.intersect(&self.bounds());
+        if area.is_empty() { return; }
+        self.damage.borrow_mut().add(area);
+        self.schedule_frame();
+    }
+    pub fn invalidate_all(&self) { self.invalidate(self.bounds()); }
+    
+    // what's waiting to be redrawn
+    pub fn damage(&self) -> Damage { self.damage.borrow().clone() }
+    
+    // asks for a frame, for when something changed that no invalidation says where
+    // any number of requests and invalidations before the next frame all make that one frame
+    // if nothing's been invalidated by then, the whole window is redrawn
+    pub fn request_redraw(&self) {
+        if self.damage.borrow().is_empty() { self.invalidate_all(); }
+        else { self.schedule_frame(); }
+    }
+    
+    // frames are paced to the display's refresh rate, unless given a rate of their own
+    pub fn target_fps(&self) -> Option<f32> { self.frames.borrow().target_fps }
+    pub fn set_target_fps(&self, fps: Option<f32>) {
+        let ticking = {
+            let mut frames = self.frames.borrow_mut();
+            frames.target_fps = fps.filter(|fps| *fps > 0.0);
+            frames.ticking
+        };
+        if let (true, Some(handle)) = (ticking, self.handle()) { handle.start_frames(self.frame_interval()); }
+    }
+    
+    // time between frames while there's something to draw
+    // it's what's asked of the backend, not a promise: on windows frames come from a SetTimer, which rounds to the
+    //  system tick (about 15.6ms unless something's raised it), can't go under 10ms, and isn't lined up with vblank,
+    //  so at 60hz expect frames every 15 or 16ms, with the odd one doubled up or dropped
+    pub fn frame_interval(&self) -> Duration {
+        let fps = self.target_fps()
+            .or_else(|| self.handle().and_then(|handle| handle.refresh_rate()))
+            .unwrap_or(FALLBACK_FPS);
+        Duration::from_secs_f32(1.0 / fps)
+    }
+    
+    // retained windows record the root into a display list every frame and compare it against the last one's,
+    //  so whatever changed in what cells record gets redrawn whether or not it was invalidated
+    // cells that don't record are drawn off to the side, and only again where they've been invalidated
+    // drawing happens on one thread, even for tiled windows
+    pub fn is_retained(&self) -> bool { self.retained.get() }
+    pub fn set_retained(&self, retained: bool) {
+        self.retained.set(retained);
+        self.display.borrow_mut().take();
+        self.invalidate_all();
+    }
+    
+    // what was drawn last frame, if retained
+    pub fn display_list(&self) -> Option<DisplayList> { self.display.borrow().clone() }
+    
+    pub fn frame_stats(&self) -> FrameStats { self.frames.borrow().stats }
+    pub fn reset_frame_stats(&self) { self.frames.borrow_mut().stats = FrameStats::default(); }
+    
+    // frames only come while they're asked for, so an idle window costs nothing
+    fn schedule_frame(&self) {
+        let Some(handle) = self.handle() else { return };
+        let start = {
+            let mut frames = self.frames.borrow_mut();
+            frames.scheduled = true;
+            let start = !frames.ticking;
+            if start {
+                frames.ticking = true;
+                // so the first Tick after being idle doesn't count the time spent idle
+                frames.last_tick = self.now();
+            }
+            start
+        };
+        if start { handle.start_frames(self.frame_interval()); }
+    }
+    
+    // called by the backend every frame interval once a frame's been scheduled
+    // tells cells how long it's been with a Tick, then has the system send a Draw for whatever needs it
+    // cells that invalidate on a Tick get another frame, which is all animating takes
+    pub fn run_frame(&mut self, internal: crate::private::Internal) {
+        let Some(handle) = self.handle() else { return };
+        let now = self.now();
+        let since = {
+            let mut frames = self.frames.borrow_mut();
+            if !frames.scheduled {
+                // nothing happened since the last frame, so stop until something does
+                frames.ticking = false;
+                handle.stop_frames();
+                return;
+            }
+            frames.scheduled = false;
+            now.saturating_sub(std::mem::replace(&mut frames.last_tick, now))
+        };
+        self.handle_event(CommonEvent::Tick(since), internal);
+        if let Some(handle) = self.handle() {
+            for area in self.damage.borrow().areas() { handle.invalidate(*area); }
+        }
+    }
+    
+    fn bounds(&self) -> PixelIndexSlice { PixelIndexSlice {offset: PixelIdx::new(0,0), size: self.size()} }
+    
     // free all resources relating to this window and mark it as invalid
     pub fn close(&mut self) {
         if self.is_closed() { return; }
@@ -112,11 +312,54 @@ pub struct Window {
         }
     }
     
+    // redraws whatever's been invalidated, each area through a slice clipped to it, then presents
+    // cells outside of every area aren't drawn at all, and the rest of the surface keeps the last frame
     pub fn draw(&mut self) {
         if self.is_closed() { return; }
         
-        self.root.draw(self.surface.slice_mut());
+        let start = Instant::now();
+        let mut damage = std::mem::take(&mut *self.damage.borrow_mut());
+        match &self.root {
+            _ if self.retained.get() => {
+                // cells that can't record are only drawn again where they've been invalidated
+                let list = match &*self.display.borrow() {
+                    Some(previous) => {
+                        let list = DisplayList::record_since(&*self.root, self.size(), previous, &damage);
+                        for area in list.diff(previous).areas() { damage.add(*area); }
+                        list
+                    },
+                    None => {
+                        damage.add(self.bounds());
+                        DisplayList::record(&*self.root, self.size())
+                    },
+                };
+                for area in damage.areas() {
+                    let area = area.intersect(&self.bounds());
+                    if area.is_empty() { continue; }
+                    list.rasterize(self.surface.slice_mut(), area);
+                }
+                *self.display.borrow_mut() = Some(list);
+            },
+            Root::Local(root) => for area in damage.areas() {
+                let area = area.intersect(&self.bounds());
+                if area.is_empty() { continue; }
+                root.draw(&mut self.surface.slice().clipped(area));
+            },
+            Root::Tiled(root, tiling) => tiling.draw_areas(&**root, self.surface.slice_mut(), damage.areas()),
+        }
         self.surface.commit(self.handle.as_ref().unwrap());
+        
+        let now = self.now();
+        let mut frames = self.frames.borrow_mut();
+        let interval = frames.last_draw.replace(now).map(|last| now.saturating_sub(last));
+        frames.stats.record(start.elapsed(), interval);
+    }
+    
+    // a copy of the last frame drawn, straight from the surface, so it's the same on every platform
+    // closed windows have nothing to show, and give an empty image
+    pub fn capture(&self) -> Image {
+        if self.is_closed() { return Image::default(); }
+        self.surface.slice().capture()
     }
     
     pub fn handle_common_event(&mut self, e: CommonEvent, internal: crate::private::Internal) {
@@ -128,15 +371,27 @@ pub struct Window {
         if self.is_closed() { return }
         
         self.mandatory_event_prefix(&e);
-        if (self.event_handler)(&mut *self.root, &e) {
-            self.default_event_handler(&e);
+        if let Some(recording) = self.recording.borrow_mut().as_mut() {
+            recording.push(RecordedEvent {time: self.now(), size: self.size(), event: e});
         }
+        // handlers changing the root themselves should say what changed with guicell::invalidate
+        let damage = guicell::collect_damage(self.size(), || {
+            if (self.event_handler)(&mut *self.root, &e) {
+                self.default_event_handler(&e);
+            }
+        });
+        for area in damage.areas() { self.invalidate(*area); }
         self.mandatory_event_postfix(&e);
     }
     
     fn mandatory_event_prefix(&mut self, e: &CommonEvent) {
         match e {
-            CommonEvent::Resize => { let _ = self.surface.update_size(self.handle.as_ref()); },
+            // a new surface starts out blank
+            CommonEvent::Resize => {
+                let before = self.size();
+                let _ = self.surface.update_size(self.handle.as_ref());
+                if self.size() != before { self.invalidate_all(); }
+            },
             _ => (),
         }
     }
@@ -153,7 +408,10 @@ pub struct Window {
             CommonEvent::Close => self.close(),
             CommonEvent::Draw  => self.draw(),
             
-            _ => ()
+            _ => {
+                let bounds = self.bounds();
+                guicell::forward_to(&mut *self.root, e, bounds.size, MousePos::default(), bounds);
+            }
         }
     }
 } impl Drop for Window {
@@ -162,6 +420,47 @@ pub struct Window {
     }
 }
 
+// a window's root cell, and how it's drawn
+enum Root {
+    Local(Box<dyn GuiCell>),
+    Tiled(Box<dyn GuiCell + Sync>, Tiling),
+} impl Deref for Root {
+    type Target = dyn GuiCell;
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Root::Local(root) => &**root,
+            Root::Tiled(root, _) => &**root,
+        }
+    }
+} impl DerefMut for Root {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Root::Local(root) => &mut **root,
+            Root::Tiled(root, _) => &mut **root,
+        }
+    }
+}
+
+// where a window's sense of time comes from
+// tests swap in a virtual clock, so that what they see doesn't depend on how fast they run
+#[derive(Clone,Copy,Debug)]
+pub enum Clock {
+    Real(Instant),     // time since this instant
+    Virtual(Duration), // only moves when advanced
+} impl Clock {
+    pub fn now(&self) -> Duration {
+        match self {
+            Clock::Real(start) => start.elapsed(),
+            Clock::Virtual(now) => *now,
+        }
+    }
+    
+    // real clocks move on their own
+    pub fn advance(&mut self, by: Duration) {
+        if let Clock::Virtual(now) = self { *now += by; }
+    }
+}
+
 // must be implemented for surface to work
 trait InternalSurfaceTrait {
     fn new(handle: Option<&WinHandle>) -> Self;
@@ -200,7 +499,7 @@ struct Surface {
             Ok(())
         }
     }
-    
+
 } impl Drop for Surface {
     fn drop(&mut self) {
         self.deallocate();
@@ -210,21 +509,27 @@ struct Surface {
 // invalid after Surface.resize
 // never return an owned instance of a SurfaceSlice to prevent users from holding onto a 
 //  dead slice. 
+// a slice's logical area (origin + size) can extend past the pixels it may actually write to 
+//  (domain), e.g. when scrolled; writes outside of the domain are silently dropped
 pub struct SurfaceSlice {
-    domain: PixelIndexSlice,
+    domain: PixelIndexSlice, // in root coordinates, always within the logical area
+    origin: (isize,isize),   // root coordinates of this slice's (0,0)
+    size: Size,
     root: *mut Pixel,
     root_size: Size,
 } impl SurfaceSlice {
     
     pub fn new(root: *mut Pixel, size: Size) -> Self {
         Self {
-            domain: PixelIndexSlice {offset: PixelIdx::new(0,0), size: size},
-            root: root,
+            domain: PixelIndexSlice {offset: PixelIdx::new(0,0), size},
+            origin: (0,0),
+            size,
+            root,
             root_size: size,
         }
     }
     
-    // idx *must* be in-bounds
+    // idx is in root coordinates and *must* be in-bounds
     pub unsafe fn get_pixel_unchecked(&self, idx: PixelIdx) -> *mut Pixel {
         unsafe {
             self.root.add(idx.y * self.root_size.width() + idx.x)
@@ -234,54 +539,199 @@ pub struct SurfaceSlice {
         unsafe {*self.get_pixel_unchecked(idx) = pixel;}
     }
     
-    pub fn get_pixel(&self,mut idx: PixelIdx) -> Pixel {
-        idx += self.domain.offset;
-        if !self.domain.contains(idx) { return Pixel::default(); }
-        // ^ asserts that below call is safe (idx within domain)
-        unsafe {*self.get_pixel_unchecked(idx)}
+    // local coordinates -> root coordinates, if the pixel is writable
+    fn to_root(&self, idx: PixelIdx) -> Option<PixelIdx> {
+        let x = self.origin.0 + idx.x as isize;
+        let y = self.origin.1 + idx.y as isize;
+        if x < 0 || y < 0 { return None; }
+        let idx = PixelIdx::new(x as usize, y as usize);
+        if self.domain.contains(idx) { Some(idx) } else { None }
+    }
+    
+    pub fn get_pixel(&self, idx: PixelIdx) -> Pixel {
+        match self.to_root(idx) {
+            // ^ asserts that below call is safe (idx within domain)
+            Some(idx) => unsafe {*self.get_pixel_unchecked(idx)},
+            None => Pixel::default(),
+        }
+    }
+    
+    pub fn set_pixel(&mut self, idx: PixelIdx, pixel: Pixel) {
+        if let Some(idx) = self.to_root(idx) {
+            // ^ asserts that below call is safe (idx within domain)
+            unsafe {self.set_pixel_unchecked(idx,pixel);}
+        }
+    }
+    
+    // draw pixel onto whatever is already there
+    pub fn blend_pixel(&mut self, idx: PixelIdx, pixel: Pixel, mode: BlendMode) {
+        if let Some(idx) = self.to_root(idx) {
+            // ^ asserts that below calls are safe (idx within domain)
+            unsafe {
+                let dst = self.get_pixel_unchecked(idx);
+                *dst = pixel.blend(*dst, mode);
+            }
+        }
+    }
+    
+    // text in the built-in font, with the top-left corner of its first line at `at`
+    // returns the size it took up
+    pub fn draw_text(&mut self, text: &str, at: PixelIdx, color: Pixel) -> Size {
+        BitmapFont::builtin().draw(self, text, at, color)
+    }
+    
+    // logical size, which may be larger than what is visible
+    pub fn size(&self) -> Size { self.size }
+    
+    // the part of this slice that can actually be drawn to, in local coordinates
+    // drawing outside of it is allowed, but wasted effort
+    pub fn visible_area(&self) -> PixelIndexSlice {
+        if self.domain.size == Size::ZERO { return PixelIndexSlice::default(); }
+        PixelIndexSlice {
+            offset: PixelIdx::new(
+                (self.domain.offset.x as isize - self.origin.0) as usize,
+                (self.domain.offset.y as isize - self.origin.1) as usize,
+            ),
+            size: self.domain.size,
+        }
     }
     
-    pub fn set_pixel(&mut self,mut idx: PixelIdx, pixel: Pixel) {
-        idx += self.domain.offset;
-        if !self.domain.contains(idx) { return; }
-        // ^ asserts that below call is safe (idx within domain)
-        unsafe {self.set_pixel_unchecked(idx,pixel);}
+    // the same slice, only able to draw within area, in local coordinates
+    pub fn clipped(&self, area: PixelIndexSlice) -> Self {
+        let left   = (self.origin.0 + area.offset.x as isize).max(0) as usize;
+        let top    = (self.origin.1 + area.offset.y as isize).max(0) as usize;
+        let right  = (self.origin.0 + (area.offset.x + area.size.width ) as isize).max(0) as usize;
+        let bottom = (self.origin.1 + (area.offset.y + area.size.height) as isize).max(0) as usize;
+        let area = PixelIndexSlice {
+            offset: PixelIdx::new(left, top),
+            size: Size {width: right.saturating_sub(left), height: bottom.saturating_sub(top)},
+        };
+        Self {
+            domain: self.domain.intersect(&area),
+            origin: self.origin,
+            size: self.size,
+            root: self.root,
+            root_size: self.root_size,
+        }
     }
     
-    pub fn size(&self) -> Size { self.domain.size() }
+    // whether any of this slice can be drawn to
+    pub fn is_visible(&self) -> bool { !self.domain.is_empty() }
     
+    // visible part of row y, as (local x of its first pixel,pixels)
+    pub fn row(&self, y: usize) -> Option<(usize,&[Pixel])> {
+        let (x, start, len) = self.row_ptr(y)?;
+        Some((x, unsafe { std::slice::from_raw_parts(start, len) }))
+    }
+    pub fn row_mut(&mut self, y: usize) -> Option<(usize,&mut [Pixel])> {
+        let (x, start, len) = self.row_ptr(y)?;
+        Some((x, unsafe { std::slice::from_raw_parts_mut(start, len) }))
+    }
+    
+    // a copy of the whole logical area, with whatever isn't visible left as Pixel::default()
+    pub fn capture(&self) -> Image {
+        self.capture_area(PixelIndexSlice {offset: PixelIdx::new(0, 0), size: self.size})
+    }
+    
+    // a copy of area, in local coordinates; like get_pixel, anything not visible comes out as Pixel::default()
+    pub fn capture_area(&self, area: PixelIndexSlice) -> Image {
+        let mut image = Image::new(area.size);
+        let width = area.size.width;
+        for y in 0..area.size.height {
+            let Some((x, row)) = self.row(area.offset.y + y) else { continue };
+            let start = area.offset.x.max(x);
+            let end = (area.offset.x + width).min(x + row.len());
+            if start >= end { continue; }
+            let to = y * width + start - area.offset.x;
+            simd::copy(&mut image.pixels_mut()[to..to + end - start], &row[start - x..end - x]);
+        }
+        image
+    }
+    
+    // area of src, in its coordinates, copied to here with its top-left at to
+    // only what's visible in both gets copied; src can be this slice, or another on the same surface, overlapping or not
+    pub fn copy_from(&mut self, src: &SurfaceSlice, area: PixelIndexSlice, to: PixelIdx) {
+        // moving down within one surface, the rows below have to go first, before they're copied over
+        let downwards = self.root == src.root && self.origin.1 + to.y as isize > src.origin.1 + area.offset.y as isize;
+        let rows: Box<dyn Iterator<Item = usize>> = if downwards { Box::new((0..area.size.height).rev()) } else { Box::new(0..area.size.height) };
+        for j in rows {
+            let (Some((sx, from, from_len)), Some((dx, into, into_len))) = (src.row_ptr(area.offset.y + j), self.row_ptr(to.y + j)) else { continue };
+            // the columns of area, counted from its left edge, that both rows have
+            let start = sx.saturating_sub(area.offset.x).max(dx.saturating_sub(to.x));
+            let end = area.size.width
+                .min((sx + from_len).saturating_sub(area.offset.x))
+                .min((dx + into_len).saturating_sub(to.x));
+            if start >= end { continue; }
+            // ^ asserts both ranges are within their rows
+            unsafe {
+                std::ptr::copy(from.add(area.offset.x + start - sx), into.add(to.x + start - dx), end - start);
+            }
+        }
+    }
+    
+    fn row_ptr(&self, y: usize) -> Option<(usize,*mut Pixel,usize)> {
+        let visible = self.visible_area();
+        if visible.size.width == 0 || y < visible.offset.y || y >= visible.offset.y + visible.size.height {
+            return None;
+        }
+        let root_y = (self.origin.1 + y as isize) as usize;
+        // ^ within domain, so the whole visible row is in-bounds
+        let start = unsafe { self.get_pixel_unchecked(PixelIdx::new(self.domain.offset.x, root_y)) };
+        Some((visible.offset.x, start, visible.size.width))
+    }
+    
+    // a slice with the given logical area, relative to this slice's (0,0)
+    // it can only write to pixels this slice can write to
+    pub(crate) fn child(&self, origin: (isize,isize), size: Size) -> Self {
+        let origin = (self.origin.0 + origin.0, self.origin.1 + origin.1);
+        
+        // logical area clamped to the root, since domains can't be negative
+        let left   = origin.0.max(0) as usize;
+        let top    = origin.1.max(0) as usize;
+        let right  = (origin.0 + size.width  as isize).max(0) as usize;
+        let bottom = (origin.1 + size.height as isize).max(0) as usize;
+        let area = PixelIndexSlice {
+            offset: PixelIdx::new(left, top),
+            size: Size {width: right.saturating_sub(left), height: bottom.saturating_sub(top)},
+        };
+        
+        Self {
+            domain: self.domain.intersect(&area),
+            origin,
+            size,
+            root: self.root,
+            root_size: self.root_size,
+        }
+    }
+    
+    // view of a larger virtual area of `size`, scrolled so that `offset` of it lies at this slice's (0,0)
+    // only the part overlapping this slice is visible
+    pub fn scrolled(&self, offset: PixelIdx, size: Size) -> Self {
+        self.child((-(offset.x as isize), -(offset.y as isize)), size)
+    }
+
 } impl Default for SurfaceSlice {
     fn default() -> Self {
         Self {
             domain: PixelIndexSlice::default(),
+            origin: (0,0),
+            size: Size::default(),
             root: std::ptr::null_mut(),
             root_size: Size::default(),
         }
     }
 } impl From<&Surface> for SurfaceSlice {
     fn from(surface: &Surface) -> Self {
-        let size = surface.size();
-        Self {
-            domain: PixelIndexSlice {offset: PixelIdx::new(0,0), size: size},
-            root: surface.root(),
-            root_size: size,
-        }
+        Self::new(surface.root(), surface.size())
     }
 } impl Partitionable for SurfaceSlice {
     fn partition(&self, dir: Direction, dst: Distance) -> Partition<Self> {
-        let domain_partition = self.domain.partition(dir,dst);
+        let local = PixelIndexSlice {offset: PixelIdx::new(0,0), size: self.size};
+        let (a, b) = local.partition(dir,dst).children;
         Partition {
             children: (
-                Self {
-                    root: self.root,
-                    root_size: self.root_size,
-                    domain: domain_partition.children.0,
-                },
-                Self {
-                    root: self.root,
-                    root_size: self.root_size,
-                    domain: domain_partition.children.1,
-                }
+                self.child((a.offset.x as isize, a.offset.y as isize), a.size),
+                self.child((b.offset.x as isize, b.offset.y as isize), b.size),
             ),
             direction: dir,
             distance: dst,
@@ -0,0 +1,143 @@
+use crate::PixelIndexSlice;
+
+// past this many separate areas, they're replaced by their bounding box,
+// since each one costs a walk through the whole cell tree when redrawing
+const MAX_AREAS: usize = 8;
+
+// the parts of a window that need redrawing, in window coordinates
+// overlapping areas are merged as they're added
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Damage {
+    areas: Vec<PixelIndexSlice>,
+} impl Damage {
+    pub fn new() -> Self { Self::default() }
+    
+    pub fn add(&mut self, area: PixelIndexSlice) {
+        if area.is_empty() { return; }
+        // absorbing one area can make it overlap another, so keep going until nothing does
+        let mut area = area;
+        while let Some(i) = self.areas.iter().position(|other| !other.intersect(&area).is_empty()) {
+            area = area.union(&self.areas.swap_remove(i));
+        }
+        self.areas.push(area);
+        if self.areas.len() > MAX_AREAS {
+            self.areas = vec![self.bounds()];
+        }
+    }
+    
+    pub fn areas(&self) -> &[PixelIndexSlice] { &self.areas }
+    pub fn is_empty(&self) -> bool { self.areas.is_empty() }
+    pub fn clear(&mut self) { self.areas.clear(); }
+    
+    pub fn bounds(&self) -> PixelIndexSlice {
+        self.areas.iter().fold(PixelIndexSlice::default(), |bounds, area| bounds.union(area))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use crate::{
+        CommonEvent,MouseButton,MousePos,Pixel,PixelIdx,Size,
+        guicell::{self,GuiCell,SolidCell},
+        testing::driver,
+        window::SurfaceSlice,
+    };
+    
+    fn rect(x: usize, y: usize, width: usize, height: usize) -> PixelIndexSlice {
+        PixelIndexSlice {offset: PixelIdx::new(x, y), size: Size {width, height}}
+    }
+    
+    fn damage(areas: &[PixelIndexSlice]) -> Damage {
+        let mut damage = Damage::new();
+        for area in areas { damage.add(*area); }
+        damage
+    }
+    
+    #[test]
+    fn merging() {
+        assert!(damage(&[rect(3, 3, 0, 5), rect(3, 3, 5, 0)]).is_empty());
+        assert_eq!(damage(&[rect(0, 0, 4, 4), rect(2, 2, 4, 4)]).areas(), [rect(0, 0, 6, 6)]);
+        // touching isn't overlapping
+        assert_eq!(damage(&[rect(0, 0, 4, 4), rect(4, 0, 4, 4)]).areas(), [rect(0, 0, 4, 4), rect(4, 0, 4, 4)]);
+        // one area bridging two others
+        assert_eq!(damage(&[rect(0, 0, 2, 2), rect(4, 0, 2, 2), rect(1, 0, 4, 1)]).areas(), [rect(0, 0, 6, 2)]);
+        // absorbing the first one grows it into the second, which it didn't overlap by itself
+        assert_eq!(damage(&[rect(0, 0, 2, 4), rect(3, 3, 2, 2), rect(1, 0, 3, 1)]).areas(), [rect(0, 0, 5, 5)]);
+    }
+    
+    #[test]
+    fn too_many_areas() {
+        let areas: Vec<_> = (0..MAX_AREAS).map(|i| rect(i * 2, i, 1, 1)).collect();
+        let mut damage = damage(&areas);
+        assert_eq!(damage.areas(), areas);
+        assert_eq!(damage.bounds(), rect(0, 0, MAX_AREAS * 2 - 1, MAX_AREAS));
+        
+        damage.add(rect(30, 20, 1, 1));
+        assert_eq!(damage.areas(), [rect(0, 0, 31, 21)]);
+        damage.clear();
+        assert!(damage.is_empty());
+        assert_eq!(damage.bounds(), PixelIndexSlice::default());
+    }
+    
+    // draws in whatever color it's been given; a click invalidates the 4x4 pixels under it,
+    //  and letting go just says something changed, without saying where
+    struct Clicky(Rc<Cell<Pixel>>);
+    impl GuiCell for Clicky {
+        fn draw(&self, surface: &mut SurfaceSlice) { SolidCell::new(self.0.get()).draw(surface); }
+        fn handle_event(&mut self, e: &CommonEvent, _size: Size) -> bool {
+            match e {
+                CommonEvent::MouseDown(_, pos) => {
+                    let pos = pos.to_idx().unwrap();
+                    guicell::invalidate(rect(pos.x, pos.y, 4, 4));
+                    true
+                },
+                CommonEvent::MouseUp(..) => true,
+                _ => false,
+            }
+        }
+    }
+    
+    #[test]
+    fn windows() {
+        let color = Rc::new(Cell::new(Pixel::WHITE));
+        let mut driver = driver(Size {width: 200, height: 150}, Clicky(color.clone()));
+        let Size {width, height} = driver.size();
+        let all = rect(0, 0, width, height);
+        
+        // nothing's been drawn yet, and drawing is what clears it
+        assert_eq!(driver.window().damage().areas(), [all]);
+        driver.frame();
+        assert!(driver.window().damage().is_empty());
+        
+        let window = driver.window();
+        window.invalidate(rect(10, 10, 4, 4));
+        window.invalidate(rect(12, 12, 4, 4));
+        window.invalidate(rect(40, 10, 2, 2));
+        // cut down to the window, or dropped if it's entirely off of it
+        window.invalidate(rect(width - 2, 20, 10, 3));
+        window.invalidate(rect(width, 0, 5, 5));
+        assert_eq!(window.damage().areas(), [rect(10, 10, 6, 6), rect(40, 10, 2, 2), rect(width - 2, 20, 2, 3)]);
+        
+        // only what was invalidated is drawn again
+        color.set(Pixel::BLACK);
+        let frame = driver.frame();
+        let black = |x, y| frame.get_pixel(PixelIdx::new(x, y)).r == 0;
+        assert!(black(10, 10) && black(15, 15) && black(41, 11) && black(width - 1, 22));
+        assert!(!black(9, 10) && !black(16, 16) && !black(12, 9) && !black(42, 10) && !black(width - 3, 20) && !black(width - 1, 23));
+        assert_eq!(frame.pixels().iter().filter(|p| p.r == 0).count(), 36 + 4 + 6);
+        
+        // cells say where they changed, or it's everything they cover
+        driver.send(CommonEvent::MouseDown(MouseButton::Left, MousePos::new(20, 30)));
+        driver.send(CommonEvent::MouseDown(MouseButton::Left, MousePos::new(22, 31)));
+        assert_eq!(driver.window().damage().areas(), [rect(20, 30, 6, 5)]);
+        driver.release(MouseButton::Left);
+        assert_eq!(driver.window().damage().areas(), [all]);
+        
+        driver.window().invalidate_all();
+        driver.frame();
+        assert!(driver.window().damage().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+// rate frames are paced to when the display's is unknown
+pub(super) const FALLBACK_FPS: f32 = 60.0;
+
+// how much each new frame counts towards the averages
+const SMOOTHING: f32 = 0.1;
+
+// timings of a window's frames, for profiling
+// draw times are wall-clock time spent in Window::draw; intervals go by the window's clock
+#[derive(Clone,Copy,Debug,Default,PartialEq)]
+pub struct FrameStats {
+    pub frames: u64,
+    pub last_draw: Duration,
+    pub average_draw: Duration,
+    pub worst_draw: Duration,
+    pub last_interval: Duration, // between the last two frames
+    pub average_interval: Duration,
+} impl FrameStats {
+    // frames per second, going by the average interval
+    pub fn fps(&self) -> f32 {
+        if self.average_interval.is_zero() { return 0.0; }
+        1.0 / self.average_interval.as_secs_f32()
+    }
+    
+    pub(super) fn record(&mut self, draw: Duration, interval: Option<Duration>) {
+        let average = |average: Duration, new: Duration| if self.frames == 0 { new } else { average.mul_f32(1.0 - SMOOTHING) + new.mul_f32(SMOOTHING) };
+        self.average_draw = average(self.average_draw, draw);
+        self.last_draw = draw;
+        self.worst_draw = self.worst_draw.max(draw);
+        if let Some(interval) = interval {
+            self.average_interval = if self.average_interval.is_zero() { interval } else { average(self.average_interval, interval) };
+            self.last_interval = interval;
+        }
+        self.frames += 1;
+    }
+}
+
+// whether a frame is on its way, and when the last ones happened
+#[derive(Clone,Copy,Debug,Default)]
+pub(super) struct Frames {
+    pub scheduled: bool, // a frame's been asked for since the last one
+    pub ticking: bool,   // the backend's sending frames
+    pub target_fps: Option<f32>, // None follows the display
+    pub last_tick: Duration,
+    pub last_draw: Option<Duration>,
+    pub stats: FrameStats,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use crate::{
+        CommonEvent,PixelIdx,PixelIndexSlice,Size,
+        guicell::GuiCell,
+        private::Internal,
+        testing::driver,
+        window::SurfaceSlice,
+    };
+    
+    fn ms(ms: u64) -> Duration { Duration::from_millis(ms) }
+    
+    fn near(a: Duration, b: Duration) -> bool { a.abs_diff(b) < Duration::from_micros(1) }
+    
+    #[test]
+    fn stats() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.fps(), 0.0);
+        
+        // the first frame has nothing to be an interval from
+        stats.record(ms(2), None);
+        assert_eq!((stats.frames, stats.average_draw, stats.worst_draw, stats.average_interval), (1, ms(2), ms(2), Duration::ZERO));
+        
+        // the first interval is the average, then each new one counts for a tenth
+        stats.record(ms(4), Some(ms(20)));
+        assert_eq!((stats.frames, stats.last_draw, stats.worst_draw, stats.average_interval), (2, ms(4), ms(4), ms(20)));
+        assert!(near(stats.average_draw, Duration::from_micros(2200)));
+        assert!((stats.fps() - 50.0).abs() < 1e-3);
+        
+        stats.record(ms(1), Some(ms(10)));
+        assert_eq!((stats.frames, stats.last_draw, stats.worst_draw, stats.last_interval), (3, ms(1), ms(4), ms(10)));
+        assert!(near(stats.average_draw, Duration::from_micros(2080)));
+        assert!(near(stats.average_interval, ms(19)));
+    }
+    
+    // keeps how long each Tick it gets says it's been
+    struct Ticks(Rc<RefCell<Vec<Duration>>>);
+    impl GuiCell for Ticks {
+        fn draw(&self, _surface: &mut SurfaceSlice) {}
+        fn handle_event(&mut self, e: &CommonEvent, _size: Size) -> bool {
+            if let CommonEvent::Tick(since) = e { self.0.borrow_mut().push(*since); }
+            false
+        }
+    }
+    
+    #[test]
+    fn one_frame_per_request() {
+        let ticks = Rc::new(RefCell::new(Vec::new()));
+        let mut driver = driver(Size {width: 200, height: 150}, Ticks(ticks.clone()));
+        let run_frame = |driver: &mut crate::testing::Driver| driver.window_mut().run_frame(Internal);
+        
+        // opening the window asks for its first frame
+        run_frame(&mut driver);
+        driver.frame();
+        assert_eq!(ticks.take(), [Duration::ZERO]);
+        // then nothing's asked for another, so frames stop
+        run_frame(&mut driver);
+        assert!(ticks.borrow().is_empty());
+        
+        driver.advance(ms(100));
+        ticks.take();
+        
+        // any number of requests before the next frame make just the one
+        for _ in 0..5 { driver.window().request_redraw(); }
+        let all = PixelIndexSlice {offset: PixelIdx::new(0, 0), size: driver.size()};
+        assert_eq!(driver.window().damage().areas(), [all]);
+        driver.advance(ms(16));
+        ticks.take();
+        run_frame(&mut driver);
+        run_frame(&mut driver);
+        // and time spent idle before the request doesn't count
+        assert_eq!(ticks.take(), [ms(16)]);
+        
+        driver.frame();
+        assert!(driver.window().damage().is_empty());
+        assert_eq!(driver.window().frame_stats().frames, 2);
+    }
+    
+    #[test]
+    fn stats_follow_the_clock() {
+        let mut driver = driver(Size {width: 200, height: 150}, Ticks(Rc::default()));
+        driver.frame();
+        let stats = driver.window().frame_stats();
+        assert_eq!((stats.frames, stats.last_interval, stats.average_interval), (1, Duration::ZERO, Duration::ZERO));
+        
+        driver.advance(ms(40)).frame();
+        let stats = driver.window().frame_stats();
+        assert_eq!((stats.frames, stats.last_interval, stats.average_interval), (2, ms(40), ms(40)));
+        
+        for _ in 0..3 { driver.advance(ms(10)).frame(); }
+        let stats = driver.window().frame_stats();
+        assert_eq!((stats.frames, stats.last_interval), (5, ms(10)));
+        // 40, then a tenth of the way towards 10, three times over
+        assert!(near(stats.average_interval, Duration::from_micros(31_870)));
+        assert!((stats.fps() - 1000.0 / 31.87).abs() < 0.01);
+        
+        // frames with no time between them are still counted
+        driver.frame();
+        let stats = driver.window().frame_stats();
+        assert_eq!((stats.frames, stats.last_interval), (6, Duration::ZERO));
+        
+        driver.window().reset_frame_stats();
+        assert_eq!(driver.window().frame_stats(), FrameStats::default());
+    }
+}
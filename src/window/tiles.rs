@@ -0,0 +1,177 @@
+use std::sync::atomic::{AtomicUsize,Ordering};
+
+use crate::{
+    guicell::GuiCell,
+    {Direction,Distance,Partitionable,PixelIndexSlice,Size},
+};
+
+use super::SurfaceSlice;
+
+// splitting redraws into tiles, each drawn by whichever worker thread gets to it first
+// every tile redraws the cell through a slice clipped to it, so cells pay for their layout once per tile;
+//  tiles should be big enough that it's the pixels that cost
+// cells are shared between the threads, hence only Sync cells can be drawn this way;
+//  containers are Sync when made with their _sync constructors
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Tiling {
+    tile: Size,
+    threads: usize,
+} impl Tiling {
+    // square tiles, as many threads as the system can run at once
+    pub fn new() -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Self {tile: Size {width: 256, height: 256}, threads}
+    }
+    
+    pub fn with_tile_size(mut self, tile: Size) -> Self {
+        self.tile = Size {width: tile.width.max(1), height: tile.height.max(1)};
+        self
+    }
+    
+    // 1 draws every tile on the calling thread, which is mostly useful for comparing against
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+    
+    pub fn tile_size(&self) -> Size { self.tile }
+    pub fn threads(&self) -> usize { self.threads }
+    
+    // area cut into rows of tiles, left to right and top to bottom; the last in each direction may be smaller
+    pub fn tiles(&self, area: PixelIndexSlice) -> Vec<PixelIndexSlice> {
+        let mut tiles = Vec::new();
+        let mut rows = area;
+        while !rows.is_empty() {
+            let (mut row, rest) = rows.partition(Direction::Vertical, Distance::Pixels(self.tile.height)).children;
+            rows = rest;
+            while !row.is_empty() {
+                let (tile, rest) = row.partition(Direction::Horizontal, Distance::Pixels(self.tile.width)).children;
+                row = rest;
+                tiles.push(tile);
+            }
+        }
+        tiles
+    }
+    
+    // the whole of what's visible of surface
+    pub fn draw(&self, cell: &(dyn GuiCell + Sync), surface: &mut SurfaceSlice) {
+        let visible = surface.visible_area();
+        self.draw_areas(cell, surface, &[visible]);
+    }
+    
+    // areas are in surface's coordinates, and mustn't overlap, else threads would draw over one another
+    pub fn draw_areas(&self, cell: &(dyn GuiCell + Sync), surface: &mut SurfaceSlice, areas: &[PixelIndexSlice]) {
+        let tiles: Vec<_> = areas.iter()
+            .map(|area| area.intersect(&surface.visible_area()))
+            .flat_map(|area| self.tiles(area))
+            .collect();
+        let threads = self.threads.min(tiles.len());
+        if threads <= 1 {
+            for tile in &tiles { cell.draw(&mut surface.clipped(*tile)); }
+            return;
+        }
+        
+        let surface = Shared(surface);
+        let next = AtomicUsize::new(0);
+        let work = || {
+            let surface = &surface;
+            while let Some(tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                cell.draw(&mut surface.0.clipped(*tile));
+            }
+        };
+        std::thread::scope(|scope| {
+            for _ in 1..threads { scope.spawn(work); }
+            // the calling thread is one of the workers
+            work();
+        });
+    }
+} impl Default for Tiling {
+    fn default() -> Self { Self::new() }
+}
+
+// slices only hold a pointer into the surface, and clipping each to its own tile means
+//  no two threads ever write the same pixel
+struct Shared<'a>(&'a SurfaceSlice);
+unsafe impl Sync for Shared<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    
+    use super::*;
+    use crate::{
+        Insets,Pixel,PixelIdx,
+        draw::{Gradient,Paint,Point},
+        guicell::{FlexCell,FlexItem,GridCell,GridChild,ImageCell,ScrollCell,SolidCell,Style,StyledCell,Track},
+        image::Image,
+    };
+    
+    const SIZE: Size = Size {width: 61, height: 47};
+    
+    // every container, holding things that are drawn differently at every pixel
+    fn scene() -> Box<dyn GuiCell + Sync> {
+        let mut image = Image::new(Size {width: 5, height: 3});
+        for (i, p) in image.pixels_mut().iter_mut().enumerate() { *p = Pixel::rgb(i as u8 * 17, 0x80, 0xFF - i as u8 * 9); }
+        let image = Arc::new(image);
+        
+        let mut grid = GridCell::new_sync(vec![Track::Fraction(1.0); 4], vec![Track::Fraction(1.0); 4]);
+        for i in 0..16 {
+            grid.push(GridChild::new_sync(Box::new(SolidCell::new(Pixel::rgb(i * 16, 0xFF - i * 16, 0x40))), i as usize / 4, i as usize % 4));
+        }
+        let grid = grid.with_child(GridChild::new_sync(Box::new(ImageCell::new(image.clone())), 1, 1).with_span(2, 2));
+        let mut scroll = ScrollCell::new_sync(Box::new(grid), Size {width: 90, height: 70}).with_bar_width(5);
+        scroll.scroll_to(PixelIdx::new(13, 21));
+        
+        let style = Style::default()
+            .with_margin(Insets::uniform(2))
+            .with_border(Insets::uniform(3), Pixel::rgb(0x20, 0x20, 0x80))
+            .with_radius(9)
+            .with_padding(Insets::uniform(1))
+            .with_background(Paint::linear(Point::new(0.0, 0.0), Point::new(30.0, 47.0), Gradient::even(&[Pixel::rgb(0xFF, 0, 0), Pixel::rgba(0, 0, 0xFF, 0x80)])));
+        let styled = StyledCell::new_sync(Box::new(ImageCell::new(image)), style);
+        
+        Box::new(FlexCell::row_sync()
+            .with_gap(1)
+            .with_item(FlexItem::new_sync(Box::new(styled)).with_grow(1.0))
+            .with_item(FlexItem::new_sync(Box::new(scroll)).with_grow(1.0)))
+    }
+    
+    fn draw(f: impl FnOnce(&mut SurfaceSlice)) -> Vec<[u8; 4]> {
+        let mut pixels = vec![Pixel::rgb(1, 2, 3); SIZE.width * SIZE.height];
+        f(&mut SurfaceSlice::new(pixels.as_mut_ptr(), SIZE));
+        pixels.iter().map(|p| [p.r, p.g, p.b, p.a]).collect()
+    }
+    
+    #[test]
+    fn tiles() {
+        let tiling = Tiling::new().with_tile_size(Size {width: 7, height: 5});
+        let whole = PixelIndexSlice {offset: PixelIdx::new(0,0), size: Size {width: 15, height: 5}};
+        assert_eq!(tiling.tiles(whole).len(), 3);
+        assert_eq!(tiling.tiles(whole)[2].size, Size {width: 1, height: 5});
+    }
+    
+    #[test]
+    fn same_as_one_thread() {
+        let scene = scene();
+        let expected = draw(|surface| scene.draw(surface));
+        for threads in [1, 4] {
+            let tiling = Tiling::new().with_tile_size(Size {width: 7, height: 5}).with_threads(threads);
+            assert!(draw(|surface| tiling.draw(&*scene, surface)) == expected, "{threads} threads");
+        }
+        
+        // areas only redraw what's in them
+        let areas = [
+            PixelIndexSlice {offset: PixelIdx::new(3, 4), size: Size {width: 20, height: 9}},
+            PixelIndexSlice {offset: PixelIdx::new(30, 20), size: Size {width: 40, height: 40}},
+        ];
+        let tiling = Tiling::new().with_tile_size(Size {width: 7, height: 5}).with_threads(4);
+        let drawn = draw(|surface| tiling.draw_areas(&*scene, surface, &areas));
+        for (i, (drawn, expected)) in drawn.iter().zip(&expected).enumerate() {
+            let idx = PixelIdx::new(i % SIZE.width, i / SIZE.width);
+            match areas.iter().any(|area| area.contains(idx)) {
+                true => assert_eq!(drawn, expected, "{idx:?}"),
+                false => assert_eq!(*drawn, [1, 2, 3, 0xFF], "{idx:?}"),
+            }
+        }
+    }
+}
@@ -24,6 +24,19 @@ impl InternalWindowTrait for WinHandle {
     fn size(&self) -> Size {
         unsafe { win32::get_win_size(self.0) }
     }
+    fn set_size(&self, size: Size) {
+        unsafe { win32::set_win_size(self.0, size) }
+    }
+    fn invalidate(&self, area: PixelIndexSlice) {
+        unsafe { win32::invalidate_rect(self.0, area) }
+    }
+    fn start_frames(&self, interval: std::time::Duration) {
+        unsafe { win32::start_frame_timer(self.0, interval) }
+    }
+    fn stop_frames(&self) {
+        unsafe { win32::stop_frame_timer(self.0) }
+    }
+    fn refresh_rate(&self) -> Option<f32> { win32::get_refresh_rate() }
     fn width (&self) -> usize { self.size().width  }
     fn height(&self) -> usize { self.size().height }
     
@@ -116,7 +129,7 @@ impl InternalSurfaceTrait for Surface {
             );
         }
     }
-    
+
 }
 
 
@@ -6,23 +6,31 @@ use windows::{
         Foundation::*,
         Graphics::Gdi::*,
         System::LibraryLoader::*,
+        System::SystemServices::{MK_LBUTTON,MK_MBUTTON,MK_RBUTTON},
+        UI::Input::KeyboardAndMouse::{ReleaseCapture,SetCapture},
         UI::WindowsAndMessaging::*,
     }
 };
 
 use crate::*;
+use crate::keys::Key;
 use crate::window::Window;
 
 use std::result::Result as Result;
+use std::time::Duration;
 
 // wrappers around windows functions for windows.rs
 
 pub const CLASS_NAME: PCWSTR = w!("mz-gui");
 
+// id of the timer frames are paced by
+const FRAME_TIMER: usize = 1;
+
 // events we'd like the library to handle - for when the system default is not adequete
 #[derive(Copy,Clone,Debug)]
 enum InternalEvent {
     WindowResize,
+    Frame,
 }
 #[derive(Copy,Clone,Debug)]
 enum WndProcEvent {
@@ -45,6 +53,34 @@ const fn translate_message(msg: u32, wparam: usize, lparam: isize) -> WndProcEve
         WM_CLOSE    => Common(Close),
         WM_PAINT    => Common(Draw),
         WM_SIZE     => Common(Resize),
+        WM_TIMER if wparam == FRAME_TIMER => Internal(Frame),
+        
+        WM_MOUSEMOVE    => Common(MouseMove(mouse_pos(lparam))),
+        WM_LBUTTONDOWN  => Common(MouseDown(MouseButton::Left,   mouse_pos(lparam))),
+        WM_RBUTTONDOWN  => Common(MouseDown(MouseButton::Right,  mouse_pos(lparam))),
+        WM_MBUTTONDOWN  => Common(MouseDown(MouseButton::Middle, mouse_pos(lparam))),
+        WM_LBUTTONUP    => Common(MouseUp  (MouseButton::Left,   mouse_pos(lparam))),
+        WM_RBUTTONUP    => Common(MouseUp  (MouseButton::Right,  mouse_pos(lparam))),
+        WM_MBUTTONUP    => Common(MouseUp  (MouseButton::Middle, mouse_pos(lparam))),
+        // wheel positions are in screen coordinates; wnd_proc converts them
+        WM_MOUSEWHEEL   => Common(MouseWheel(mouse_pos(lparam), 0.0, wheel_notches(wparam))),
+        WM_MOUSEHWHEEL  => Common(MouseWheel(mouse_pos(lparam), wheel_notches(wparam), 0.0)),
+        
+        // WM_SYSKEY* are left to the system so that alt+f4 and friends keep working
+        WM_KEYDOWN => match translate_key(wparam) {
+            Some(key) => Common(KeyDown(key)),
+            None => Unknown,
+        },
+        WM_KEYUP => match translate_key(wparam) {
+            Some(key) => Common(KeyUp(key)),
+            None => Unknown,
+        },
+        // from TranslateMessage; characters outside the bmp come as two surrogates, which are dropped for now
+        // control characters come from keys like enter and backspace, which KeyDown already covers
+        WM_CHAR => match char::from_u32(wparam as u32) {
+            Some(c) if wparam >= 0x20 && wparam != 0x7F => Common(Text(c)),
+            _ => Unknown,
+        },
         
         /* WM_WINDOWPOSCHANGING => {
             let winpos: WINDOWPOS = unsafe {*(lparam as *const WINDOWPOS)};
@@ -81,6 +117,81 @@ const fn translate_message(msg: u32, wparam: usize, lparam: isize) -> WndProcEve
     }
 }
 
+// signed 16-bit x,y packed into lparam
+const fn mouse_pos(lparam: isize) -> MousePos {
+    MousePos {
+        x: (lparam & 0xFFFF) as i16 as isize,
+        y: ((lparam >> 16) & 0xFFFF) as i16 as isize,
+    }
+}
+
+// high word of wparam, in multiples of WHEEL_DELTA (120)
+const fn wheel_notches(wparam: usize) -> f32 {
+    ((wparam >> 16) & 0xFFFF) as i16 as f32 / 120.0
+}
+
+// virtual-key code -> Key, ignoring modifiers
+// shifted symbols (e.g. Key::Exclamation) have no key code of their own
+const fn translate_key(vk: usize) -> Option<Key> {
+    use Key::*;
+    
+    const LETTERS: [Key; 26] = [A,B,C,D,E,F,G,H,I,J,K,L,M,N,O,P,Q,R,S,T,U,V,W,X,Y,Z];
+    const DIGITS: [Key; 10] = [N0,N1,N2,N3,N4,N5,N6,N7,N8,N9];
+    const NUMPAD: [Key; 10] = [Np0,Np1,Np2,Np3,Np4,Np5,Np6,Np7,Np8,Np9];
+    const FUNCTION: [Key; 12] = [F1,F2,F3,F4,F5,F6,F7,F8,F9,F10,F11,F12];
+    
+    Some(match vk {
+        0x41..=0x5A => LETTERS[vk - 0x41],
+        0x30..=0x39 => DIGITS[vk - 0x30],
+        0x60..=0x69 => NUMPAD[vk - 0x60],
+        0x70..=0x7B => FUNCTION[vk - 0x70],
+        
+        0x08 => Backspace,
+        0x09 => Tab,
+        0x0D => Enter,
+        0x10 | 0xA0 => LShift,
+        0xA1 => RShift,
+        0x11 | 0xA2 => LCtrl,
+        0xA3 => RCtrl,
+        0x12 | 0xA4 => LAlt,
+        0xA5 => RAlt,
+        0x14 => CapsLock,
+        0x1B => Esc,
+        0x20 => Space,
+        0x21 => PageUp,
+        0x22 => PageDown,
+        0x23 => End,
+        0x24 => Home,
+        0x25 => LeftArrow,
+        0x26 => UpArrow,
+        0x27 => RightArrow,
+        0x28 => DownArrow,
+        0x2C => PrintScreen,
+        0x2D => Insert,
+        0x2E => Delete,
+        0x5B | 0x5C => SysKey,
+        0x5D => Menu,
+        0x6A => NpMul,
+        0x6B => NpPlus,
+        0x6D => NpMinus,
+        0x6E => NpDecimal,
+        0x6F => NpDiv,
+        0x90 => NumLock,
+        0xBA => Semicolon,
+        0xBB => Equals,
+        0xBC => Comma,
+        0xBD => Hyphen,
+        0xBE => Period,
+        0xBF => Slash,
+        0xC0 => Grave,
+        0xDB => BeginSqrBracket,
+        0xDC => Backslash,
+        0xDD => EndSqrBracket,
+        0xDE => Apostrophe,
+        _ => return None,
+    })
+}
+
 pub fn get_hinstance() -> Result<HINSTANCE,()> {
     match unsafe {GetModuleHandleW(None)} {
         Ok(hinst) => Ok(hinst.into()),
@@ -155,7 +266,7 @@ pub fn create_window(name: &str, width: i32, height: i32, owner: *mut Window) ->
     };
     
     let _ = unsafe { SetWindowLongPtrW(hwnd, GWLP_USERDATA, owner as isize) };
-        
+    
     let _ = unsafe { ShowWindow(hwnd, SW_SHOW) };
     if !<BOOL as Into<bool>>::into(unsafe { UpdateWindow(hwnd) }) {
         eprintln!("Windows Syscall Error - UpdateWindow fail");
@@ -253,12 +364,57 @@ pub unsafe fn get_win_size(hwnd: HWND) -> Size {
     unsafe {get_client_rect(hwnd).size}
 }
 
+// size is the client area's; the window around it is larger by its frame
+pub unsafe fn set_win_size(hwnd: HWND, size: Size) {
+    let mut rect = RECT {left: 0, top: 0, right: size.width as i32, bottom: size.height as i32};
+    unsafe {
+        let style = WINDOW_STYLE(GetWindowLongW(hwnd, GWL_STYLE) as u32);
+        let _ = AdjustWindowRect(&mut rect, style, false);
+        let _ = SetWindowPos(
+            hwnd, None, 0, 0, rect.right - rect.left, rect.bottom - rect.top,
+            SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+}
+
+// WM_TIMERs every interval until stopped; they're low priority, so a busy window drops frames rather than queueing them
+// intervals are only as fine as the system tick and start no lower than USER_TIMER_MINIMUM; see Window::frame_interval
+pub unsafe fn start_frame_timer(hwnd: HWND, interval: Duration) {
+    let ms = interval.as_millis().clamp(USER_TIMER_MINIMUM as u128, u32::MAX as u128) as u32;
+    unsafe { SetTimer(Some(hwnd), FRAME_TIMER, ms, None); }
+}
+
+pub unsafe fn stop_frame_timer(hwnd: HWND) {
+    unsafe { let _ = KillTimer(Some(hwnd), FRAME_TIMER); }
+}
+
+// of the primary display; 0 and 1 both mean the hardware's default, which isn't known
+pub fn get_refresh_rate() -> Option<f32> {
+    let mut mode = DEVMODEW {dmSize: size_of::<DEVMODEW>() as u16, ..Default::default()};
+    let found = unsafe { EnumDisplaySettingsW(PCWSTR::null(), ENUM_CURRENT_SETTINGS, &mut mode) }.as_bool();
+    (found && mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency as f32)
+}
+
+// adds area to the window's update region, for a WM_PAINT to cover
+pub unsafe fn invalidate_rect(hwnd: HWND, area: PixelIndexSlice) {
+    let rect = RECT {
+        left: area.offset.x as i32,
+        top: area.offset.y as i32,
+        right: (area.offset.x + area.size.width) as i32,
+        bottom: (area.offset.y + area.size.height) as i32,
+    };
+    unsafe { let _ = InvalidateRect(Some(hwnd), Some(&rect), false); }
+}
+
 pub unsafe fn blit_dib(hwnd: HWND, hdib: HBITMAP, size: Size, src_dc: HDC) {
     let mut ps = PAINTSTRUCT::default();
     unsafe {
         let old = SelectObject(src_dc,hdib.into());
         let dst_dc = BeginPaint(hwnd, &mut ps);
-        let _ = BitBlt(dst_dc, 0, 0, size.width as i32, size.height as i32, Some(src_dc), 0, 0, SRCCOPY);
+        // only the update region needs copying; everything else on screen is already current
+        let RECT {left, top, right, bottom} = ps.rcPaint;
+        let (right, bottom) = (right.min(size.width as i32), bottom.min(size.height as i32));
+        let _ = BitBlt(dst_dc, left, top, right - left, bottom - top, Some(src_dc), left, top, SRCCOPY);
         let _ = SelectObject(src_dc, old);
         let _ = EndPaint(hwnd, &ps);
     }
@@ -283,11 +439,19 @@ extern "system" fn wnd_proc(
     // don't use WM_SIZE
     // GetWindowRect + GetClientRect upon WM_WINDOWPOSCHANGING?
     let translation = translate_message(msg,wparam.0,lparam.0);
-    if let WndProcEvent::Unknown = translation {} else {
-        println!("{:?}",translation);
-    }
     match translation {
-        WndProcEvent::Common(common) => unsafe {
+        WndProcEvent::Common(mut common) => unsafe {
+            match common {
+                CommonEvent::MouseDown(..) => { SetCapture(hwnd); },
+                // the cursor stays captured until every button's up, so drags with two buttons don't get cut short
+                CommonEvent::MouseUp(..) if wparam.0 as u32 & (MK_LBUTTON | MK_RBUTTON | MK_MBUTTON).0 == 0 => { let _ = ReleaseCapture(); },
+                CommonEvent::MouseWheel(pos, dx, dy) => {
+                    let mut point = POINT {x: pos.x as i32, y: pos.y as i32};
+                    let _ = ScreenToClient(hwnd, &mut point);
+                    common = CommonEvent::MouseWheel(MousePos::new(point.x as isize, point.y as isize), dx, dy);
+                },
+                _ => (),
+            }
             let window = get_window(hwnd);
             if window != std::ptr::null_mut() {
                 (*window).handle_event(common, crate::private::Internal);
@@ -295,6 +459,13 @@ extern "system" fn wnd_proc(
             LRESULT(0)
         },
         WndProcEvent::Internal(internal) => match internal {
+            InternalEvent::Frame => unsafe {
+                let window = get_window(hwnd);
+                if window != std::ptr::null_mut() {
+                    (*window).run_frame(crate::private::Internal);
+                }
+                LRESULT(0)
+            },
             _ => unsafe { DefWindowProcW(hwnd,msg,wparam,lparam) },
         }
         WndProcEvent::Unknown => {